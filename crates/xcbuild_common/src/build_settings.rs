@@ -0,0 +1,231 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Result, bail};
+use clap::Args;
+
+/// A single `KEY=VALUE` build setting override passed to xcodebuild, such as
+/// `CODE_SIGNING_ALLOWED=NO`.
+///
+/// Keys must be valid build setting names: an ASCII letter or underscore followed by letters,
+/// digits or underscores. Values are passed through verbatim and may be empty or contain spaces.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::BuildSetting;
+///
+/// let setting: BuildSetting = "SWIFT_ACTIVE_COMPILATION_CONDITIONS=DEBUG CI".parse().unwrap();
+/// assert_eq!(setting.key(), "SWIFT_ACTIVE_COMPILATION_CONDITIONS");
+/// assert_eq!(setting.value(), "DEBUG CI");
+/// assert_eq!(setting.to_string(), "SWIFT_ACTIVE_COMPILATION_CONDITIONS=DEBUG CI");
+///
+/// assert!("CODE SIGNING=NO".parse::<BuildSetting>().is_err());
+/// assert!("DEVELOPMENT_TEAM".parse::<BuildSetting>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildSetting {
+    key: String,
+    value: String,
+}
+
+impl BuildSetting {
+    pub fn new(key: &str, value: &str) -> Result<Self> {
+        if !is_valid_key(key) {
+            bail!(
+                "Invalid build setting name '{}': expected letters, digits and underscores, not starting with a digit",
+                key
+            );
+        }
+
+        Ok(Self {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl FromStr for BuildSetting {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid build setting '{}': expected KEY=VALUE", s))?;
+
+        Self::new(key.trim(), value).map_err(|error| error.to_string())
+    }
+}
+
+impl std::fmt::Display for BuildSetting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.key, self.value)
+    }
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {}
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Build setting overrides shared by the build, test and archive commands: an optional
+/// `.xcconfig` file and individual `KEY=VALUE` settings, which take precedence over it.
+///
+/// Derives `clap::Args` so the CLI can flatten the same `--build-setting` and `--xcconfig`
+/// options into each subcommand.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::{BuildOverrides, BuildSetting};
+///
+/// let overrides = BuildOverrides::new()
+///     .with_xcconfig("Config/CI.xcconfig".to_string())
+///     .with_build_setting(BuildSetting::new("CODE_SIGNING_ALLOWED", "NO").unwrap());
+///
+/// assert_eq!(overrides.xcconfig.as_deref(), Some("Config/CI.xcconfig"));
+/// assert_eq!(overrides.build_settings.len(), 1);
+/// ```
+#[derive(Args, Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildOverrides {
+    /// Build setting passed to xcodebuild, e.g. CODE_SIGNING_ALLOWED=NO (may be specified
+    /// multiple times)
+    #[arg(long = "build-setting", value_name = "KEY=VALUE")]
+    pub build_settings: Vec<BuildSetting>,
+
+    /// .xcconfig file whose settings override the project's
+    #[arg(long)]
+    pub xcconfig: Option<String>,
+}
+
+impl BuildOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_build_setting(mut self, build_setting: BuildSetting) -> Self {
+        self.build_settings.push(build_setting);
+        self
+    }
+
+    pub fn with_build_settings(mut self, build_settings: Vec<BuildSetting>) -> Self {
+        self.build_settings.extend(build_settings);
+        self
+    }
+
+    pub fn with_xcconfig(mut self, xcconfig: String) -> Self {
+        self.xcconfig = Some(xcconfig);
+        self
+    }
+
+    /// Returns the xcodebuild arguments for these overrides: `-xcconfig <file>` followed by the
+    /// `KEY=VALUE` settings. Fails if the xcconfig file does not exist.
+    pub fn arguments(&self) -> Result<Vec<String>> {
+        let mut arguments = Vec::new();
+        if let Some(xcconfig) = &self.xcconfig {
+            if !Path::new(xcconfig).is_file() {
+                bail!("xcconfig file not found: {}", xcconfig);
+            }
+            arguments.extend(["-xcconfig".to_string(), xcconfig.clone()]);
+        }
+        arguments.extend(self.build_settings.iter().map(ToString::to_string));
+
+        Ok(arguments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_setting_parses_key_and_value() {
+        let setting: BuildSetting = "OTHER_SWIFT_FLAGS=-D CI -warnings-as-errors"
+            .parse()
+            .unwrap();
+
+        assert_eq!(setting.key(), "OTHER_SWIFT_FLAGS");
+        assert_eq!(setting.value(), "-D CI -warnings-as-errors");
+    }
+
+    #[test]
+    fn test_build_setting_keeps_equals_signs_in_value() {
+        let setting: BuildSetting = "GCC_PREPROCESSOR_DEFINITIONS=FLAG=1".parse().unwrap();
+
+        assert_eq!(setting.key(), "GCC_PREPROCESSOR_DEFINITIONS");
+        assert_eq!(setting.value(), "FLAG=1");
+    }
+
+    #[test]
+    fn test_build_setting_allows_empty_value() {
+        let setting: BuildSetting = "CODE_SIGN_IDENTITY=".parse().unwrap();
+
+        assert_eq!(setting.value(), "");
+        assert_eq!(setting.to_string(), "CODE_SIGN_IDENTITY=");
+    }
+
+    #[test]
+    fn test_build_setting_rejects_invalid_input() {
+        for input in [
+            "NO_EQUALS",
+            "=NO",
+            "1ST=YES",
+            "CODE-SIGNING=NO",
+            "KEY WITH SPACE=1",
+        ] {
+            assert!(input.parse::<BuildSetting>().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_build_overrides_arguments() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let xcconfig = temp_dir.path().join("CI.xcconfig");
+        std::fs::write(&xcconfig, "CODE_SIGNING_ALLOWED = NO\n").unwrap();
+        let xcconfig = xcconfig.to_string_lossy().to_string();
+
+        let arguments = BuildOverrides::new()
+            .with_xcconfig(xcconfig.clone())
+            .with_build_settings(vec![
+                BuildSetting::new("DEVELOPMENT_TEAM", "A1B2C3D4E5").unwrap(),
+                BuildSetting::new("SWIFT_ACTIVE_COMPILATION_CONDITIONS", "DEBUG CI").unwrap(),
+            ])
+            .arguments()
+            .unwrap();
+
+        assert_eq!(
+            arguments,
+            [
+                "-xcconfig".to_string(),
+                xcconfig,
+                "DEVELOPMENT_TEAM=A1B2C3D4E5".to_string(),
+                "SWIFT_ACTIVE_COMPILATION_CONDITIONS=DEBUG CI".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_overrides_missing_xcconfig() {
+        let result = BuildOverrides::new()
+            .with_xcconfig("does/not/exist.xcconfig".to_string())
+            .arguments();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "xcconfig file not found: does/not/exist.xcconfig"
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use std::str::FromStr;
+
+/// Platform names accepted by xcodebuild destinations.
+pub const DESTINATION_PLATFORMS: &[&str] = &[
+    "iOS",
+    "iOS Simulator",
+    "macOS",
+    "tvOS",
+    "tvOS Simulator",
+    "watchOS",
+    "watchOS Simulator",
+    "visionOS",
+    "visionOS Simulator",
+    "DriverKit",
+];
+
+/// Keys accepted in the `key=value,...` destination syntax, in rendering order.
+const DESTINATION_KEYS: &[&str] = &["platform", "name", "OS", "id", "arch", "variant"];
+
+/// An xcodebuild `-destination` specifier.
+///
+/// Parses the `key=value,...` syntax understood by xcodebuild, optionally prefixed with
+/// `generic/` for destinations that build for any device of a platform. Keys are matched
+/// case-insensitively and platforms are checked against [`DESTINATION_PLATFORMS`], so typos are
+/// reported before xcodebuild runs. For compatibility with older invocations a leading bare
+/// platform (`iOS Simulator,name=iPhone 15 Pro`) is read as `platform=`.
+///
+/// Destinations render in a canonical key order: platform, name, OS, id, arch, variant.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::Destination;
+///
+/// let destination: Destination = "platform=iOS Simulator,name=iPhone 16,OS=18.2".parse().unwrap();
+/// assert_eq!(destination.platform.as_deref(), Some("iOS Simulator"));
+/// assert_eq!(destination.os.as_deref(), Some("18.2"));
+/// assert_eq!(destination.to_string(), "platform=iOS Simulator,name=iPhone 16,OS=18.2");
+///
+/// let generic = Destination::new().with_platform("iOS").with_generic(true);
+/// assert_eq!(generic.to_string(), "generic/platform=iOS");
+///
+/// assert!("platform=iOS,device=iPhone".parse::<Destination>().is_err());
+/// assert!("platform=iOS Simulater".parse::<Destination>().is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Destination {
+    pub platform: Option<String>,
+    pub name: Option<String>,
+    pub os: Option<String>,
+    pub id: Option<String>,
+    pub arch: Option<String>,
+    pub variant: Option<String>,
+    pub generic: bool,
+}
+
+impl Destination {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_platform(mut self, platform: &str) -> Self {
+        self.platform = Some(platform.to_string());
+        self
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub fn with_os(mut self, os: &str) -> Self {
+        self.os = Some(os.to_string());
+        self
+    }
+
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    pub fn with_arch(mut self, arch: &str) -> Self {
+        self.arch = Some(arch.to_string());
+        self
+    }
+
+    pub fn with_variant(mut self, variant: &str) -> Self {
+        self.variant = Some(variant.to_string());
+        self
+    }
+
+    pub fn with_generic(mut self, generic: bool) -> Self {
+        self.generic = generic;
+        self
+    }
+
+    fn field_mut(&mut self, key: &str) -> &mut Option<String> {
+        match key {
+            "platform" => &mut self.platform,
+            "name" => &mut self.name,
+            "OS" => &mut self.os,
+            "id" => &mut self.id,
+            "arch" => &mut self.arch,
+            _ => &mut self.variant,
+        }
+    }
+
+    fn fields(&self) -> [(&'static str, &Option<String>); 6] {
+        [
+            ("platform", &self.platform),
+            ("name", &self.name),
+            ("OS", &self.os),
+            ("id", &self.id),
+            ("arch", &self.arch),
+            ("variant", &self.variant),
+        ]
+    }
+}
+
+impl FromStr for Destination {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let (generic, specifier) = match trimmed.strip_prefix("generic/") {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+        if specifier.is_empty() {
+            return Err(String::from("destination must not be empty"));
+        }
+
+        let mut destination = Destination::new().with_generic(generic);
+        for (index, component) in specifier.split(',').map(str::trim).enumerate() {
+            let (key, value) = match component.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None if index == 0 && canonical_platform(component).is_some() => {
+                    ("platform", component)
+                }
+                None => {
+                    return Err(format!(
+                        "invalid destination component '{}' in '{}': expected key=value",
+                        component, s
+                    ));
+                }
+            };
+
+            let key = DESTINATION_KEYS
+                .iter()
+                .find(|known| known.eq_ignore_ascii_case(key))
+                .ok_or_else(|| {
+                    format!(
+                        "unknown destination key '{}' in '{}'; expected one of {}",
+                        key,
+                        s,
+                        DESTINATION_KEYS.join(", ")
+                    )
+                })?;
+            if value.is_empty() {
+                return Err(format!("destination key '{}' has no value in '{}'", key, s));
+            }
+            let value = if *key == "platform" {
+                canonical_platform(value).ok_or_else(|| {
+                    format!(
+                        "unknown destination platform '{}'; expected one of {}",
+                        value,
+                        DESTINATION_PLATFORMS.join(", ")
+                    )
+                })?
+            } else {
+                value
+            };
+
+            let field = destination.field_mut(key);
+            if field.is_some() {
+                return Err(format!(
+                    "destination key '{}' is specified more than once in '{}'",
+                    key, s
+                ));
+            }
+            *field = Some(value.to_string());
+        }
+
+        if destination.platform.is_none() && destination.id.is_none() {
+            return Err(format!(
+                "destination '{}' must specify a platform or an id",
+                s
+            ));
+        }
+        if generic && (destination.name.is_some() || destination.id.is_some()) {
+            return Err(format!(
+                "generic destination '{}' cannot name a specific device",
+                s
+            ));
+        }
+
+        Ok(destination)
+    }
+}
+
+impl std::fmt::Display for Destination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.generic {
+            write!(f, "generic/")?;
+        }
+        let components: Vec<String> = self
+            .fields()
+            .into_iter()
+            .filter_map(|(key, value)| value.as_ref().map(|value| format!("{}={}", key, value)))
+            .collect();
+
+        write!(f, "{}", components.join(","))
+    }
+}
+
+fn canonical_platform(platform: &str) -> Option<&'static str> {
+    DESTINATION_PLATFORMS
+        .iter()
+        .find(|known| known.eq_ignore_ascii_case(platform))
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_all_keys_in_canonical_order() {
+        let destination: Destination =
+            "variant=Designed for iPad,arch=arm64,id=ABC-123,os=latest,NAME=My Mac,platform=macos"
+                .parse()
+                .unwrap();
+
+        assert_eq!(
+            destination,
+            Destination::new()
+                .with_platform("macOS")
+                .with_name("My Mac")
+                .with_os("latest")
+                .with_id("ABC-123")
+                .with_arch("arm64")
+                .with_variant("Designed for iPad")
+        );
+        assert_eq!(
+            destination.to_string(),
+            "platform=macOS,name=My Mac,OS=latest,id=ABC-123,arch=arm64,variant=Designed for iPad"
+        );
+    }
+
+    #[test]
+    fn test_parse_generic_destination() {
+        let destination: Destination = "generic/platform=watchOS Simulator".parse().unwrap();
+
+        assert!(destination.generic);
+        assert_eq!(destination.platform.as_deref(), Some("watchOS Simulator"));
+        assert_eq!(
+            destination.to_string(),
+            "generic/platform=watchOS Simulator"
+        );
+    }
+
+    #[test]
+    fn test_parse_bare_platform_shorthand() {
+        let destination: Destination = "iOS Simulator,name=iPhone 15 Pro".parse().unwrap();
+
+        assert_eq!(
+            destination.to_string(),
+            "platform=iOS Simulator,name=iPhone 15 Pro"
+        );
+    }
+
+    #[test]
+    fn test_parse_id_only_destination() {
+        let destination: Destination = "id=00008110-001A2B3C4D5E".parse().unwrap();
+
+        assert_eq!(destination.platform, None);
+        assert_eq!(destination.to_string(), "id=00008110-001A2B3C4D5E");
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_destinations() {
+        let cases = [
+            ("", "must not be empty"),
+            (
+                "platform=iOS,device=iPhone",
+                "unknown destination key 'device'",
+            ),
+            (
+                "platform=iOS Simulater",
+                "unknown destination platform 'iOS Simulater'",
+            ),
+            ("platform=iOS,name=", "has no value"),
+            ("platform=iOS,platform=macOS", "more than once"),
+            ("name=iPhone 15", "must specify a platform or an id"),
+            ("platform=iOS,iPhone 15", "expected key=value"),
+            (
+                "generic/platform=iOS,name=iPhone 15",
+                "cannot name a specific device",
+            ),
+            ("Generic/iOS", "expected key=value"),
+        ];
+
+        for (input, expected) in cases {
+            let error = input.parse::<Destination>().unwrap_err();
+            assert!(error.contains(expected), "{}: {}", input, error);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use std::process::Output;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::Invocation;
+
+/// Number of trailing output lines kept on a [`CommandError`].
+pub const OUTPUT_TAIL_LINES: usize = 20;
+
+/// Output fragments that identify a code signing or provisioning failure in xcodebuild output.
+const SIGNING_FAILURE_PATTERNS: &[&str] = &[
+    "Code Signing Error",
+    "code signing is required",
+    "No signing certificate",
+    "No profiles for",
+    "requires a provisioning profile",
+    "requires a development team",
+    "Provisioning profile",
+    "errSecInternalComponent",
+];
+
+/// The class of failure an external command ended with.
+///
+/// The CLI maps each kind to a distinct process exit code so CI scripts can tell a missing
+/// toolchain apart from a broken build, failing tests or a signing problem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CommandErrorKind {
+    /// The program could not be found on `PATH`.
+    ToolMissing,
+    /// xcodebuild failed to build, archive or export.
+    BuildFailed,
+    /// xcodebuild ran the tests and they failed.
+    TestsFailed,
+    /// Code signing, provisioning or keychain setup failed.
+    SigningFailed,
+    /// The command ran longer than the configured timeout and was stopped.
+    TimedOut,
+    /// xctools received SIGINT or SIGTERM and stopped the command.
+    Interrupted,
+    /// Any other non-zero exit.
+    Failed,
+}
+
+/// Error returned when an external command cannot be started or exits unsuccessfully.
+///
+/// Carries everything needed to diagnose the failure without re-running it: the action that
+/// was attempted, the exit code, the rendered (redacted) command line and the last
+/// [`OUTPUT_TAIL_LINES`] lines of stdout/stderr.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::{CommandError, CommandErrorKind, Invocation};
+///
+/// let invocation = Invocation::new("xcodebuild").with_args(["build", "-scheme", "MyApp"]);
+/// let error = CommandError::new(CommandErrorKind::BuildFailed, "build", &invocation)
+///     .with_exit_code(Some(65))
+///     .with_output("Compiling...\n** BUILD FAILED **\n", "");
+///
+/// assert_eq!(error.exit_code, Some(65));
+/// assert_eq!(error.invocation, "xcodebuild build -scheme MyApp");
+/// assert_eq!(error.output_tail, ["Compiling...", "** BUILD FAILED **"]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandError {
+    pub kind: CommandErrorKind,
+    pub action: String,
+    pub exit_code: Option<i32>,
+    pub invocation: String,
+    pub output_tail: Vec<String>,
+    /// The timeout that was exceeded, for [`CommandErrorKind::TimedOut`].
+    pub timeout: Option<Duration>,
+}
+
+impl CommandError {
+    pub fn new(kind: CommandErrorKind, action: &str, invocation: &Invocation) -> Self {
+        Self {
+            kind,
+            action: action.to_string(),
+            exit_code: None,
+            invocation: invocation.to_string(),
+            output_tail: Vec::new(),
+            timeout: None,
+        }
+    }
+
+    /// Builds the error for work stopped by SIGINT or SIGTERM while no command was running, which
+    /// therefore has no command line.
+    pub fn interrupted(action: &str) -> Self {
+        Self {
+            kind: CommandErrorKind::Interrupted,
+            action: action.to_string(),
+            exit_code: None,
+            invocation: String::new(),
+            output_tail: Vec::new(),
+            timeout: None,
+        }
+    }
+
+    pub fn tool_missing(invocation: &Invocation) -> Self {
+        Self::new(
+            CommandErrorKind::ToolMissing,
+            invocation.program(),
+            invocation,
+        )
+    }
+
+    /// Builds an error for a process that ran to completion with a non-zero exit status.
+    pub fn from_output(
+        kind: CommandErrorKind,
+        action: &str,
+        invocation: &Invocation,
+        output: &Output,
+    ) -> Self {
+        Self::new(kind, action, invocation)
+            .with_exit_code(output.status.code())
+            .with_output(
+                &String::from_utf8_lossy(&output.stdout),
+                &String::from_utf8_lossy(&output.stderr),
+            )
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_exit_code(mut self, exit_code: Option<i32>) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+
+    /// Keeps the last [`OUTPUT_TAIL_LINES`] non-blank lines of stdout followed by stderr.
+    pub fn with_output(mut self, stdout: &str, stderr: &str) -> Self {
+        let lines: Vec<String> = stdout
+            .lines()
+            .chain(stderr.lines())
+            .filter(|line| !line.trim().is_empty())
+            .map(String::from)
+            .collect();
+        let skip = lines.len().saturating_sub(OUTPUT_TAIL_LINES);
+        self.output_tail = lines.into_iter().skip(skip).collect();
+        self
+    }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.kind == CommandErrorKind::ToolMissing {
+            return write!(
+                f,
+                "{} was not found; make sure Xcode is installed and selected with `xcode-select --switch`",
+                self.action
+            );
+        }
+
+        match (self.kind, self.timeout, self.exit_code) {
+            (CommandErrorKind::TimedOut, Some(timeout), _) => write!(
+                f,
+                "{} timed out after {}s and was stopped",
+                self.action,
+                timeout.as_secs_f64()
+            )?,
+            (CommandErrorKind::Interrupted, _, _) => write!(f, "{} was interrupted", self.action)?,
+            (_, _, Some(code)) => write!(f, "{} failed with exit code {}", self.action, code)?,
+            (_, _, None) => write!(f, "{} was terminated by a signal", self.action)?,
+        }
+        if !self.invocation.is_empty() {
+            write!(f, "\nCommand: {}", self.invocation)?;
+        }
+        if !self.output_tail.is_empty() {
+            write!(f, "\nLast {} lines of output:", self.output_tail.len())?;
+            for line in &self.output_tail {
+                write!(f, "\n    {}", line)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Returns `true` when xcodebuild output shows that the failure was caused by code signing or
+/// provisioning rather than by the sources or tests themselves.
+pub fn is_signing_failure(output: &str) -> bool {
+    SIGNING_FAILURE_PATTERNS
+        .iter()
+        .any(|pattern| output.contains(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_output_keeps_last_lines_of_stdout_then_stderr() {
+        let stdout: String = (1..=30).map(|n| format!("line {}\n", n)).collect();
+        let error = CommandError::new(
+            CommandErrorKind::BuildFailed,
+            "build",
+            &Invocation::new("xcodebuild"),
+        )
+        .with_output(&stdout, "error: boom\n");
+
+        assert_eq!(error.output_tail.len(), OUTPUT_TAIL_LINES);
+        assert_eq!(error.output_tail.first().unwrap(), "line 12");
+        assert_eq!(error.output_tail.last().unwrap(), "error: boom");
+    }
+
+    #[test]
+    fn test_display_includes_exit_code_command_and_output() {
+        let error = CommandError::new(
+            CommandErrorKind::TestsFailed,
+            "test",
+            &Invocation::new("xcodebuild").with_args(["test", "-scheme", "My App"]),
+        )
+        .with_exit_code(Some(65))
+        .with_output("** TEST FAILED **", "");
+
+        assert_eq!(
+            error.to_string(),
+            "test failed with exit code 65\nCommand: xcodebuild test -scheme 'My App'\nLast 1 lines of output:\n    ** TEST FAILED **"
+        );
+    }
+
+    #[test]
+    fn test_display_redacts_secret_arguments() {
+        let invocation = Invocation::new("security")
+            .with_args(["unlock-keychain", "-p"])
+            .with_secret_arg("hunter2")
+            .with_arg("xctools-signing.keychain");
+        let error = CommandError::new(CommandErrorKind::SigningFailed, "security", &invocation)
+            .with_exit_code(Some(51));
+
+        assert!(!error.to_string().contains("hunter2"));
+    }
+
+    #[test]
+    fn test_display_timed_out() {
+        let error = CommandError::new(
+            CommandErrorKind::TimedOut,
+            "xcodebuild",
+            &Invocation::new("xcodebuild").with_arg("test"),
+        )
+        .with_timeout(Duration::from_secs(90))
+        .with_output("Testing started", "");
+
+        assert_eq!(
+            error.to_string(),
+            "xcodebuild timed out after 90s and was stopped\nCommand: xcodebuild test\n\
+             Last 1 lines of output:\n    Testing started"
+        );
+    }
+
+    #[test]
+    fn test_display_interrupted() {
+        let error = CommandError::new(
+            CommandErrorKind::Interrupted,
+            "xcrun",
+            &Invocation::new("xcrun").with_args(["notarytool", "submit"]),
+        );
+
+        assert_eq!(
+            error.to_string(),
+            "xcrun was interrupted\nCommand: xcrun notarytool submit"
+        );
+    }
+
+    #[test]
+    fn test_display_interrupted_between_commands() {
+        let error = CommandError::interrupted("upload");
+
+        assert_eq!(error.kind, CommandErrorKind::Interrupted);
+        assert_eq!(error.to_string(), "upload was interrupted");
+    }
+
+    #[test]
+    fn test_display_tool_missing() {
+        let error = CommandError::tool_missing(&Invocation::new("xcodebuild"));
+
+        assert_eq!(error.kind, CommandErrorKind::ToolMissing);
+        assert!(error.to_string().starts_with("xcodebuild was not found"));
+    }
+
+    #[test]
+    fn test_is_signing_failure() {
+        assert!(is_signing_failure(
+            "error: No signing certificate \"iOS Distribution\" found"
+        ));
+        assert!(is_signing_failure(
+            "Code Signing Error: No profiles for 'com.example.app' were found"
+        ));
+        assert!(!is_signing_failure(
+            "error: cannot find 'Foo' in scope\n** BUILD FAILED **"
+        ));
+    }
+}
@@ -0,0 +1,783 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use clap::ValueEnum;
+
+/// How xcodebuild output is shown on the terminal. The log file always receives the raw output.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Formatter {
+    /// Condensed, colourised output: compile and link steps, diagnostics, test results and a
+    /// final summary
+    #[default]
+    Pretty,
+    /// xcodebuild's output exactly as it was printed
+    Raw,
+    /// Only errors, warnings, test failures and the final result
+    Quiet,
+}
+
+impl std::fmt::Display for Formatter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Formatter::Pretty => "pretty",
+            Formatter::Raw => "raw",
+            Formatter::Quiet => "quiet",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Source position of a diagnostic. Test assertion failures carry a line but no column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: u32,
+    pub column: Option<u32>,
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A single line of xcodebuild output, classified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XcodebuildEvent {
+    Compile {
+        file: String,
+    },
+    Link {
+        product: String,
+    },
+    Diagnostic {
+        severity: Severity,
+        location: Option<Location>,
+        message: String,
+    },
+    TestSuiteStarted {
+        name: String,
+    },
+    TestCaseStarted {
+        suite: String,
+        name: String,
+    },
+    TestCasePassed {
+        suite: String,
+        name: String,
+        duration: Option<String>,
+    },
+    TestCaseFailed {
+        suite: String,
+        name: String,
+        duration: Option<String>,
+    },
+    /// A `** BUILD SUCCEEDED **`-style banner.
+    Result {
+        banner: String,
+        succeeded: bool,
+    },
+    Other,
+}
+
+/// Classifies one line of xcodebuild output.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::{Location, Severity, XcodebuildEvent, parse_xcodebuild_line};
+///
+/// assert_eq!(
+///     parse_xcodebuild_line("/src/App/View.swift:12:5: error: cannot find 'x' in scope"),
+///     XcodebuildEvent::Diagnostic {
+///         severity: Severity::Error,
+///         location: Some(Location { file: "/src/App/View.swift".to_string(), line: 12, column: Some(5) }),
+///         message: "cannot find 'x' in scope".to_string(),
+///     }
+/// );
+/// ```
+pub fn parse_xcodebuild_line(line: &str) -> XcodebuildEvent {
+    let line = line.trim_end_matches(['\r', '\n']);
+
+    if let Some(event) = parse_result_banner(line) {
+        return event;
+    }
+    if let Some(event) = parse_compile(line) {
+        return event;
+    }
+    if let Some(event) = parse_link(line) {
+        return event;
+    }
+    if let Some(event) = parse_test_case(line) {
+        return event;
+    }
+    if let Some(event) = parse_test_suite(line) {
+        return event;
+    }
+    if let Some(event) = parse_diagnostic(line) {
+        return event;
+    }
+
+    XcodebuildEvent::Other
+}
+
+/// Collects the distinct warnings in xcodebuild output, as `file:line:column: message` when the
+/// warning has a location.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::xcodebuild_warnings;
+///
+/// let output = "CompileSwift normal arm64 /src/App.swift\n\
+///               /src/App.swift:3:9: warning: initialization of 'x' was never used\n\
+///               /src/App.swift:3:9: warning: initialization of 'x' was never used\n\
+///               warning: Run script build phase 'Lint' will be run during every build\n";
+///
+/// assert_eq!(
+///     xcodebuild_warnings(output),
+///     [
+///         "/src/App.swift:3:9: initialization of 'x' was never used",
+///         "Run script build phase 'Lint' will be run during every build",
+///     ]
+/// );
+/// ```
+pub fn xcodebuild_warnings(output: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    output
+        .lines()
+        .filter_map(|line| match parse_xcodebuild_line(line) {
+            XcodebuildEvent::Diagnostic {
+                severity: Severity::Warning,
+                location,
+                message,
+            } => Some(match location {
+                Some(location) => format!("{}: {}", location, message),
+                None => message,
+            }),
+            _ => None,
+        })
+        .filter(|warning| seen.insert(warning.clone()))
+        .collect()
+}
+
+fn parse_result_banner(line: &str) -> Option<XcodebuildEvent> {
+    let inner = line.trim().strip_prefix("** ")?.strip_suffix(" **")?;
+    let succeeded = if inner.ends_with("SUCCEEDED") {
+        true
+    } else if inner.ends_with("FAILED") || inner.ends_with("INTERRUPTED") {
+        false
+    } else {
+        return None;
+    };
+
+    Some(XcodebuildEvent::Result {
+        banner: line.trim().to_string(),
+        succeeded,
+    })
+}
+
+/// Build steps start at the beginning of the line; indented copies appear in the
+/// "The following build commands failed" recap and must not be counted twice.
+fn parse_compile(line: &str) -> Option<XcodebuildEvent> {
+    if line.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let words = split_escaped_words(line);
+    let file = match words.first()?.as_str() {
+        // CompileSwift normal arm64 /path/File.swift (in target ...)
+        "CompileSwift" | "SwiftCompile" => words.get(3)?,
+        // CompileC /path/File.o /path/File.m normal arm64 objective-c ...
+        "CompileC" => words.get(2)?,
+        _ => return None,
+    };
+    // Whole-module and batch steps list no single source file.
+    if !file.starts_with('/') || file.ends_with(".o") {
+        return None;
+    }
+
+    Some(XcodebuildEvent::Compile {
+        file: file_name(file),
+    })
+}
+
+fn parse_link(line: &str) -> Option<XcodebuildEvent> {
+    if !line.starts_with("Ld ") {
+        return None;
+    }
+
+    let product = split_escaped_words(line).into_iter().nth(1)?;
+    Some(XcodebuildEvent::Link {
+        product: file_name(&product),
+    })
+}
+
+/// Splits a build step line on whitespace, keeping backslash-escaped spaces inside words.
+fn split_escaped_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => word.extend(chars.next()),
+            c if c.is_whitespace() => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+fn parse_test_suite(line: &str) -> Option<XcodebuildEvent> {
+    let rest = line.strip_prefix("Test Suite '")?;
+    let (name, status) = rest.split_once('\'')?;
+    if !status.trim_start().starts_with("started") {
+        return None;
+    }
+    // Aggregate suites only add noise above the class-level suites.
+    if name == "All tests" || name == "Selected tests" || name.ends_with(".xctest") {
+        return None;
+    }
+
+    Some(XcodebuildEvent::TestSuiteStarted {
+        name: name.to_string(),
+    })
+}
+
+fn parse_test_case(line: &str) -> Option<XcodebuildEvent> {
+    let rest = line
+        .strip_prefix("Test Case '")
+        .or_else(|| line.strip_prefix("Test case '"))?;
+    let (identifier, status) = rest.split_once('\'')?;
+    let (suite, name) = split_test_identifier(identifier)?;
+    let status = status.trim_start();
+    let duration = status
+        .rfind('(')
+        .and_then(|start| {
+            status[start + 1..]
+                .strip_suffix(").")
+                .or(status[start + 1..].strip_suffix(')'))
+        })
+        .map(String::from);
+
+    if status.starts_with("started") {
+        Some(XcodebuildEvent::TestCaseStarted { suite, name })
+    } else if status.starts_with("passed") {
+        Some(XcodebuildEvent::TestCasePassed {
+            suite,
+            name,
+            duration,
+        })
+    } else if status.starts_with("failed") {
+        Some(XcodebuildEvent::TestCaseFailed {
+            suite,
+            name,
+            duration,
+        })
+    } else {
+        None
+    }
+}
+
+/// Splits `-[Module.Suite testName]` or `Suite.testName()` into suite and test name.
+fn split_test_identifier(identifier: &str) -> Option<(String, String)> {
+    if let Some(inner) = identifier
+        .strip_prefix("-[")
+        .and_then(|inner| inner.strip_suffix(']'))
+    {
+        let (suite, name) = inner.split_once(' ')?;
+        return Some((suite.to_string(), name.to_string()));
+    }
+
+    let (suite, name) = identifier.rsplit_once('.')?;
+    Some((suite.to_string(), name.to_string()))
+}
+
+fn parse_diagnostic(line: &str) -> Option<XcodebuildEvent> {
+    let trimmed = line.trim_start();
+    for (marker, severity) in [
+        ("fatal error: ", Severity::Error),
+        ("error: ", Severity::Error),
+        ("warning: ", Severity::Warning),
+    ] {
+        // `error: message` or `xcodebuild: error: message` without a location.
+        if let Some(message) = trimmed.strip_prefix(marker).or_else(|| {
+            trimmed
+                .strip_prefix("xcodebuild: ")
+                .and_then(|rest| rest.strip_prefix(marker))
+        }) {
+            return Some(XcodebuildEvent::Diagnostic {
+                severity,
+                location: None,
+                message: message.to_string(),
+            });
+        }
+
+        let separator = format!(": {}", marker);
+        if let Some((location, message)) = trimmed.split_once(separator.as_str()) {
+            let location = parse_location(location)?;
+            return Some(XcodebuildEvent::Diagnostic {
+                severity,
+                location: Some(location),
+                message: message.to_string(),
+            });
+        }
+    }
+
+    None
+}
+
+/// Parses `file:line:column` or `file:line`.
+fn parse_location(location: &str) -> Option<Location> {
+    let (rest, last) = location.rsplit_once(':')?;
+    let last: u32 = last.parse().ok()?;
+    if let Some((file, line)) = rest.rsplit_once(':')
+        && let Ok(line) = line.parse()
+    {
+        return Some(Location {
+            file: file.to_string(),
+            line,
+            column: Some(last),
+        });
+    }
+
+    Some(Location {
+        file: rest.to_string(),
+        line: last,
+        column: None,
+    })
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+const RED: &str = "31";
+const GREEN: &str = "32";
+const YELLOW: &str = "33";
+const BOLD: &str = "1";
+const DIM: &str = "2";
+
+/// Renders xcodebuild output line by line according to a [`Formatter`], keeping the counts
+/// needed for the closing summary.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::{Formatter, XcodebuildFormatter};
+///
+/// let mut formatter = XcodebuildFormatter::new(Formatter::Pretty, false);
+/// let lines = [
+///     "CompileSwift normal arm64 /src/App/ContentView.swift (in target 'App' from project 'App')",
+///     "/src/App/ContentView.swift:3:9: warning: variable 'x' was never used",
+///     "** BUILD SUCCEEDED **",
+/// ];
+/// let rendered: Vec<String> = lines.iter().filter_map(|line| formatter.format_line(line)).collect();
+///
+/// assert_eq!(
+///     rendered,
+///     [
+///         "▸ Compiling ContentView.swift",
+///         "⚠ /src/App/ContentView.swift:3:9: variable 'x' was never used",
+///         "** BUILD SUCCEEDED **",
+///     ]
+/// );
+/// assert_eq!(formatter.summary().unwrap(), "1 file compiled, 1 warning");
+/// ```
+#[derive(Debug)]
+pub struct XcodebuildFormatter {
+    formatter: Formatter,
+    colored: bool,
+    seen_diagnostics: HashSet<String>,
+    compiled: usize,
+    warnings: usize,
+    errors: usize,
+    tests_passed: usize,
+    tests_failed: usize,
+}
+
+impl XcodebuildFormatter {
+    pub fn new(formatter: Formatter, colored: bool) -> Self {
+        Self {
+            formatter,
+            colored,
+            seen_diagnostics: HashSet::new(),
+            compiled: 0,
+            warnings: 0,
+            errors: 0,
+            tests_passed: 0,
+            tests_failed: 0,
+        }
+    }
+
+    /// Returns the text to show for `line`, without a trailing newline, or `None` to hide it.
+    pub fn format_line(&mut self, line: &str) -> Option<String> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if self.formatter == Formatter::Raw {
+            return Some(line.to_string());
+        }
+
+        let quiet = self.formatter == Formatter::Quiet;
+        match parse_xcodebuild_line(line) {
+            XcodebuildEvent::Compile { file } => {
+                self.compiled += 1;
+                (!quiet).then(|| format!("{} Compiling {}", self.paint("▸", DIM), file))
+            }
+            XcodebuildEvent::Link { product } => {
+                (!quiet).then(|| format!("{} Linking {}", self.paint("▸", DIM), product))
+            }
+            XcodebuildEvent::Diagnostic {
+                severity,
+                location,
+                message,
+            } => {
+                // xcodebuild repeats diagnostics, e.g. once per architecture.
+                let text = match location {
+                    Some(location) => format!("{}: {}", location, message),
+                    None => message,
+                };
+                if !self.seen_diagnostics.insert(text.clone()) {
+                    return None;
+                }
+
+                Some(match severity {
+                    Severity::Error => {
+                        self.errors += 1;
+                        format!("{} {}", self.paint("✖", RED), self.paint(&text, RED))
+                    }
+                    Severity::Warning => {
+                        self.warnings += 1;
+                        format!("{} {}", self.paint("⚠", YELLOW), text)
+                    }
+                })
+            }
+            XcodebuildEvent::TestSuiteStarted { name } => (!quiet).then(|| self.paint(&name, BOLD)),
+            XcodebuildEvent::TestCaseStarted { .. } => None,
+            XcodebuildEvent::TestCasePassed { name, duration, .. } => {
+                self.tests_passed += 1;
+                (!quiet).then(|| {
+                    format!(
+                        "    {} {}{}",
+                        self.paint("✔", GREEN),
+                        name,
+                        format_duration(duration)
+                    )
+                })
+            }
+            XcodebuildEvent::TestCaseFailed {
+                suite,
+                name,
+                duration,
+            } => {
+                self.tests_failed += 1;
+                let name = if quiet {
+                    format!("{}.{}", suite, name)
+                } else {
+                    name
+                };
+                Some(format!(
+                    "    {} {}{}",
+                    self.paint("✖", RED),
+                    self.paint(&name, RED),
+                    format_duration(duration)
+                ))
+            }
+            XcodebuildEvent::Result { banner, succeeded } => {
+                let color = if succeeded { GREEN } else { RED };
+                Some(self.paint(&banner, &format!("{};{}", BOLD, color)))
+            }
+            XcodebuildEvent::Other => None,
+        }
+    }
+
+    /// Returns a one-line summary of what was seen, or `None` for raw output or when nothing
+    /// was recognised.
+    pub fn summary(&self) -> Option<String> {
+        if self.formatter == Formatter::Raw {
+            return None;
+        }
+
+        let parts: Vec<String> = [
+            (self.compiled, "file compiled", "files compiled"),
+            (self.warnings, "warning", "warnings"),
+            (self.errors, "error", "errors"),
+            (self.tests_passed, "test passed", "tests passed"),
+            (self.tests_failed, "test failed", "tests failed"),
+        ]
+        .into_iter()
+        .filter(|(count, _, _)| *count > 0)
+        .map(|(count, singular, plural)| {
+            format!("{} {}", count, if count == 1 { singular } else { plural })
+        })
+        .collect();
+
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+
+    fn paint(&self, text: &str, code: &str) -> String {
+        if self.colored {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+fn format_duration(duration: Option<String>) -> String {
+    duration
+        .map(|duration| format!(" ({})", duration))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(formatter: Formatter, log: &str) -> (Vec<String>, Option<String>) {
+        let mut formatter = XcodebuildFormatter::new(formatter, false);
+        let lines = log
+            .lines()
+            .filter_map(|line| formatter.format_line(line))
+            .collect();
+
+        (lines, formatter.summary())
+    }
+
+    #[test]
+    fn test_parse_compile_steps() {
+        assert_eq!(
+            parse_xcodebuild_line(
+                "SwiftCompile normal arm64 /src/My\\ App/Model.swift (in target 'App' from project 'App')"
+            ),
+            XcodebuildEvent::Compile {
+                file: "Model.swift".to_string()
+            }
+        );
+        assert_eq!(
+            parse_xcodebuild_line(
+                "CompileC /build/Objects/Legacy.o /src/App/Legacy.m normal arm64 objective-c com.apple.compilers.llvm.clang.1_0.compiler (in target 'App' from project 'App')"
+            ),
+            XcodebuildEvent::Compile {
+                file: "Legacy.m".to_string()
+            }
+        );
+        assert_eq!(
+            parse_xcodebuild_line(
+                "SwiftCompile normal arm64 Compiling\\ Model.swift,\\ View.swift (in target 'App' from project 'App')"
+            ),
+            XcodebuildEvent::Other
+        );
+    }
+
+    #[test]
+    fn test_parse_link_step() {
+        assert_eq!(
+            parse_xcodebuild_line(
+                "Ld /build/Products/Debug-iphonesimulator/App.app/App normal (in target 'App' from project 'App')"
+            ),
+            XcodebuildEvent::Link {
+                product: "App".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_diagnostics() {
+        assert_eq!(
+            parse_xcodebuild_line("/src/App/Legacy.m:7:1: warning: implicit declaration"),
+            XcodebuildEvent::Diagnostic {
+                severity: Severity::Warning,
+                location: Some(Location {
+                    file: "/src/App/Legacy.m".to_string(),
+                    line: 7,
+                    column: Some(1),
+                }),
+                message: "implicit declaration".to_string(),
+            }
+        );
+        assert_eq!(
+            parse_xcodebuild_line(
+                "/src/AppTests/AppTests.swift:20: error: -[AppTests.AppTests testSum] : XCTAssertEqual failed"
+            ),
+            XcodebuildEvent::Diagnostic {
+                severity: Severity::Error,
+                location: Some(Location {
+                    file: "/src/AppTests/AppTests.swift".to_string(),
+                    line: 20,
+                    column: None,
+                }),
+                message: "-[AppTests.AppTests testSum] : XCTAssertEqual failed".to_string(),
+            }
+        );
+        assert_eq!(
+            parse_xcodebuild_line(
+                "xcodebuild: error: The project named \"App\" does not contain a scheme named \"Nope\"."
+            ),
+            XcodebuildEvent::Diagnostic {
+                severity: Severity::Error,
+                location: None,
+                message: "The project named \"App\" does not contain a scheme named \"Nope\"."
+                    .to_string(),
+            }
+        );
+        assert_eq!(
+            parse_xcodebuild_line("note: Using new build system"),
+            XcodebuildEvent::Other
+        );
+    }
+
+    #[test]
+    fn test_parse_test_cases() {
+        assert_eq!(
+            parse_xcodebuild_line(
+                "Test Case '-[AppTests.AppTests testSum]' passed (0.004 seconds)."
+            ),
+            XcodebuildEvent::TestCasePassed {
+                suite: "AppTests.AppTests".to_string(),
+                name: "testSum".to_string(),
+                duration: Some("0.004 seconds".to_string()),
+            }
+        );
+        assert_eq!(
+            parse_xcodebuild_line(
+                "Test case 'AppTests.testSum()' failed on 'iPhone 16 - AppTests (1234)' (0.010 seconds)"
+            ),
+            XcodebuildEvent::TestCaseFailed {
+                suite: "AppTests".to_string(),
+                name: "testSum()".to_string(),
+                duration: Some("0.010 seconds".to_string()),
+            }
+        );
+        assert_eq!(
+            parse_xcodebuild_line("Test Case '-[AppTests.AppTests testSum]' started."),
+            XcodebuildEvent::TestCaseStarted {
+                suite: "AppTests.AppTests".to_string(),
+                name: "testSum".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_result_banners() {
+        assert_eq!(
+            parse_xcodebuild_line("** ARCHIVE SUCCEEDED **"),
+            XcodebuildEvent::Result {
+                banner: "** ARCHIVE SUCCEEDED **".to_string(),
+                succeeded: true,
+            }
+        );
+        assert_eq!(
+            parse_xcodebuild_line("** TEST BUILD FAILED **"),
+            XcodebuildEvent::Result {
+                banner: "** TEST BUILD FAILED **".to_string(),
+                succeeded: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_pretty_build_failed_fixture() {
+        let (lines, summary) = render(
+            Formatter::Pretty,
+            include_str!("../tests/fixtures/build_failed.log"),
+        );
+
+        assert_eq!(
+            lines,
+            [
+                "▸ Compiling AppDelegate.swift",
+                "⚠ /Users/dev/App/App/AppDelegate.swift:14:13: initialization of immutable value 'unused' was never used; consider replacing with assignment to '_' or removing it",
+                "▸ Compiling ContentView.swift",
+                "✖ /Users/dev/App/App/ContentView.swift:21:16: cannot find 'titel' in scope",
+                "▸ Compiling Legacy.m",
+                "** BUILD FAILED **",
+            ]
+        );
+        assert_eq!(summary.unwrap(), "3 files compiled, 1 warning, 1 error");
+    }
+
+    #[test]
+    fn test_pretty_test_failed_fixture() {
+        let (lines, summary) = render(
+            Formatter::Pretty,
+            include_str!("../tests/fixtures/test_failed.log"),
+        );
+
+        assert_eq!(
+            lines,
+            [
+                "▸ Compiling Calculator.swift",
+                "▸ Linking App",
+                "▸ Compiling CalculatorTests.swift",
+                "CalculatorTests",
+                "    ✔ testAddition (0.001 seconds)",
+                "✖ /Users/dev/App/AppTests/CalculatorTests.swift:18: -[AppTests.CalculatorTests testDivision] : XCTAssertEqual failed: (\"2.0\") is not equal to (\"2.5\")",
+                "    ✖ testDivision (0.003 seconds)",
+                "    ✔ testSubtraction (0.001 seconds)",
+                "** TEST FAILED **",
+            ]
+        );
+        assert_eq!(
+            summary.unwrap(),
+            "2 files compiled, 1 error, 2 tests passed, 1 test failed"
+        );
+    }
+
+    #[test]
+    fn test_quiet_test_failed_fixture() {
+        let (lines, _) = render(
+            Formatter::Quiet,
+            include_str!("../tests/fixtures/test_failed.log"),
+        );
+
+        assert_eq!(
+            lines,
+            [
+                "✖ /Users/dev/App/AppTests/CalculatorTests.swift:18: -[AppTests.CalculatorTests testDivision] : XCTAssertEqual failed: (\"2.0\") is not equal to (\"2.5\")",
+                "    ✖ AppTests.CalculatorTests.testDivision (0.003 seconds)",
+                "** TEST FAILED **",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_raw_keeps_every_line() {
+        let log = include_str!("../tests/fixtures/build_failed.log");
+        let (lines, summary) = render(Formatter::Raw, log);
+
+        assert_eq!(lines, log.lines().collect::<Vec<_>>());
+        assert_eq!(summary, None);
+    }
+
+    #[test]
+    fn test_colored_output_wraps_text_in_ansi_codes() {
+        let mut formatter = XcodebuildFormatter::new(Formatter::Pretty, true);
+
+        assert_eq!(
+            formatter.format_line("** BUILD SUCCEEDED **").unwrap(),
+            "\x1b[1;32m** BUILD SUCCEEDED **\x1b[0m"
+        );
+    }
+}
@@ -0,0 +1,370 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+
+use anyhow::{Context, Result};
+
+use crate::{command_runner, supervisor};
+
+const REDACTED: &str = "********";
+
+/// A single external program invocation: the program, its argument vector, extra environment
+/// variables and an optional working directory.
+///
+/// Invocations are executed directly through `std::process::Command` without an intermediate
+/// shell, so arguments containing spaces, quotes, `$` or `!` reach the program verbatim.
+///
+/// Arguments added with [`Invocation::with_secret_arg`] are passed to the program unchanged but
+/// are redacted whenever the invocation is rendered with `Display`, e.g. in error messages.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::Invocation;
+///
+/// let invocation = Invocation::new("xcrun")
+///     .with_args(["stapler", "staple", "My App.dmg"]);
+///
+/// assert_eq!(invocation.program(), "xcrun");
+/// assert_eq!(invocation.args(), ["stapler", "staple", "My App.dmg"]);
+/// assert_eq!(invocation.to_string(), "xcrun stapler staple 'My App.dmg'");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    program: String,
+    args: Vec<String>,
+    secret_arg_indices: Vec<usize>,
+    envs: Vec<(String, String)>,
+    current_dir: Option<PathBuf>,
+}
+
+impl Invocation {
+    pub fn new<S: Into<String>>(program: S) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            secret_arg_indices: Vec::new(),
+            envs: Vec::new(),
+            current_dir: None,
+        }
+    }
+
+    pub fn with_arg<S: Into<String>>(mut self, arg: S) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Appends an argument that must never be shown, such as a password.
+    pub fn with_secret_arg<S: Into<String>>(mut self, arg: S) -> Self {
+        self.secret_arg_indices.push(self.args.len());
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn with_env<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.envs.push((key.into(), value.into()));
+        self
+    }
+
+    /// Returns a copy with `key` set before the invocation's own environment variables.
+    pub(crate) fn prepend_env<K: Into<String>, V: Into<String>>(&self, key: K, value: V) -> Self {
+        let mut invocation = self.clone();
+        invocation.envs.insert(0, (key.into(), value.into()));
+        invocation
+    }
+
+    pub fn with_current_dir<P: Into<PathBuf>>(mut self, current_dir: P) -> Self {
+        self.current_dir = Some(current_dir.into());
+        self
+    }
+
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    pub fn envs(&self) -> &[(String, String)] {
+        &self.envs
+    }
+
+    pub fn current_dir(&self) -> Option<&Path> {
+        self.current_dir.as_deref()
+    }
+
+    /// Builds a `std::process::Command` for this invocation. Callers may further configure
+    /// stdio before spawning it.
+    ///
+    /// The developer directory configured with [`crate::set_developer_dir`] is passed as
+    /// `DEVELOPER_DIR` unless the invocation sets that variable itself.
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        if let Some(developer_dir) = crate::developer_dir() {
+            command.env("DEVELOPER_DIR", developer_dir);
+        }
+        for (key, value) in &self.envs {
+            command.env(key, value);
+        }
+        if let Some(current_dir) = &self.current_dir {
+            command.current_dir(current_dir);
+        }
+
+        command
+    }
+}
+
+impl std::fmt::Display for Invocation {
+    /// Renders the invocation as a copy-pasteable shell command line, quoting any argument
+    /// that the shell would otherwise split or interpret.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (key, value) in &self.envs {
+            write!(f, "{}={} ", key, shell_quote(value))?;
+        }
+        write!(f, "{}", shell_quote(&self.program))?;
+        for (index, arg) in self.args.iter().enumerate() {
+            if self.secret_arg_indices.contains(&index) {
+                write!(f, " {}", shell_quote(REDACTED))?;
+            } else {
+                write!(f, " {}", shell_quote(arg))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    let is_safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c));
+    if is_safe {
+        return value.to_string();
+    }
+
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Spawns the invocation and waits for it to finish, returning its raw output.
+///
+/// The invocation is executed through the installed [`crate::CommandRunner`], so under
+/// `--dry-run` it is only printed. The exit status is not inspected; callers decide whether a
+/// non-zero exit is an error.
+///
+/// # Arguments
+///
+/// * `invocation` - The program, arguments, environment and working directory to run
+///
+/// # Returns
+///
+/// Returns `Ok(Output)` with the captured stdout and stderr once the process has exited, or
+/// `Err` if it could not be spawned. A program that is not installed yields a
+/// [`crate::CommandError`] of kind [`crate::CommandErrorKind::ToolMissing`].
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::{CommandError, CommandErrorKind, Invocation, run_invocation};
+///
+/// let result = run_invocation(&Invocation::new("xctools-nonexistent-program-12345"));
+/// let error = result.unwrap_err();
+/// let command_error = error.downcast_ref::<CommandError>().unwrap();
+/// assert_eq!(command_error.kind, CommandErrorKind::ToolMissing);
+/// ```
+pub fn run_invocation(invocation: &Invocation) -> Result<Output> {
+    command_runner().run(invocation)
+}
+
+/// Runs the invocation as a child process; the implementation behind [`crate::ProcessRunner`].
+///
+/// The child is supervised like a streamed one: it is stopped once the configured timeout
+/// expires and receives the SIGINT/SIGTERM sent to xctools.
+pub(crate) fn run_process(invocation: &Invocation) -> Result<Output> {
+    let mut command = invocation.command();
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = supervisor::spawn(&mut command, invocation)?;
+
+    let stdout_thread = spawn_reader(child.stdout.take().expect("stdout is piped"));
+    let stderr_thread = spawn_reader(child.stderr.take().expect("stderr is piped"));
+    let status = supervisor::wait(&mut child)
+        .context(format!("Failed to wait for {}", invocation.program()))?;
+    let stdout = stdout_thread
+        .join()
+        .expect("stdout reader panicked")
+        .context("Failed to read stdout")?;
+    let stderr = stderr_thread
+        .join()
+        .expect("stderr reader panicked")
+        .context("Failed to read stderr")?;
+
+    match status {
+        Ok(status) => Ok(Output {
+            status,
+            stdout,
+            stderr,
+        }),
+        Err(termination) => {
+            Err(supervisor::termination_error(termination, invocation, &stdout, &stderr).into())
+        }
+    }
+}
+
+fn spawn_reader<R: Read + Send + 'static>(
+    mut reader: R,
+) -> thread::JoinHandle<std::io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer)?;
+        Ok(buffer)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CommandError;
+
+    #[test]
+    fn test_invocation_keeps_arguments_separate() {
+        let invocation = Invocation::new("security")
+            .with_arg("unlock-keychain")
+            .with_args(["-p", "pa$$ word!", "my keychain"]);
+
+        assert_eq!(invocation.program(), "security");
+        assert_eq!(
+            invocation.args(),
+            ["unlock-keychain", "-p", "pa$$ word!", "my keychain"]
+        );
+    }
+
+    #[test]
+    fn test_invocation_display_quotes_unsafe_arguments() {
+        let invocation = Invocation::new("xcodebuild").with_args([
+            "-scheme",
+            "My \"Fancy\" App",
+            "-destination",
+            "platform=iOS Simulator,name=iPhone 15 Pro",
+            "-archivePath",
+            "it's.xcarchive",
+        ]);
+
+        assert_eq!(
+            invocation.to_string(),
+            "xcodebuild -scheme 'My \"Fancy\" App' -destination 'platform=iOS Simulator,name=iPhone 15 Pro' -archivePath 'it'\\''s.xcarchive'"
+        );
+    }
+
+    #[test]
+    fn test_invocation_display_includes_env() {
+        let invocation = Invocation::new("xcodebuild").with_arg("-version").with_env(
+            "DEVELOPER_DIR",
+            "/Applications/Xcode 16.app/Contents/Developer",
+        );
+
+        assert_eq!(
+            invocation.to_string(),
+            "DEVELOPER_DIR='/Applications/Xcode 16.app/Contents/Developer' xcodebuild -version"
+        );
+    }
+
+    #[test]
+    fn test_invocation_display_redacts_secret_arguments() {
+        let invocation = Invocation::new("xcrun")
+            .with_args(["altool", "-u", "developer@example.com", "-p"])
+            .with_secret_arg("pa$$word!");
+
+        assert_eq!(invocation.args()[4], "pa$$word!");
+        assert_eq!(
+            invocation.to_string(),
+            "xcrun altool -u developer@example.com -p '********'"
+        );
+    }
+
+    #[test]
+    fn test_invocation_display_quotes_empty_argument() {
+        let invocation = Invocation::new("echo").with_arg("");
+
+        assert_eq!(invocation.to_string(), "echo ''");
+    }
+
+    #[test]
+    fn test_invocation_command_passes_arguments_verbatim() {
+        let output = Invocation::new("echo")
+            .with_args(["$HOME", "a  b", "'quoted'"])
+            .command()
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "$HOME a  b 'quoted'\n"
+        );
+    }
+
+    #[test]
+    fn test_invocation_command_applies_env() {
+        let output = Invocation::new("printenv")
+            .with_arg("XCTOOLS_TEST")
+            .with_env("XCTOOLS_TEST", "value with spaces")
+            .command()
+            .output()
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "value with spaces\n"
+        );
+    }
+
+    #[test]
+    fn test_invocation_command_applies_current_dir() {
+        let tmp = std::env::temp_dir();
+        let mut command = Invocation::new("pwd").with_current_dir(&tmp).command();
+        let output = command.output().unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+
+        assert_eq!(
+            std::fs::canonicalize(stdout.trim()).unwrap(),
+            std::fs::canonicalize(&tmp).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_run_invocation_missing_program() {
+        let result = run_invocation(&Invocation::new("xctools-nonexistent-program-12345"));
+        let error = result.unwrap_err();
+        let command_error = error.downcast_ref::<CommandError>().unwrap();
+
+        assert_eq!(command_error.kind, crate::CommandErrorKind::ToolMissing);
+        assert!(
+            error
+                .to_string()
+                .contains("xctools-nonexistent-program-12345 was not found")
+        );
+    }
+
+    #[test]
+    fn test_run_invocation_captures_output() {
+        let output = run_invocation(&Invocation::new("echo").with_arg("captured")).unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "captured\n");
+    }
+}
@@ -1,20 +1,78 @@
-use std::process::Command;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 use clap::ValueEnum;
 
+mod build_settings;
+mod destination;
+mod error;
+mod formatter;
+mod invocation;
+mod packages;
+mod project;
+mod project_list;
+mod report;
+mod retry;
+mod runner;
+mod simulators;
+mod stream;
+mod supervisor;
+mod test_selection;
+mod xcode;
+
+pub use build_settings::{BuildOverrides, BuildSetting};
+pub use destination::{DESTINATION_PLATFORMS, Destination};
+pub use error::{CommandError, CommandErrorKind, OUTPUT_TAIL_LINES, is_signing_failure};
+pub use formatter::{
+    Formatter, Location, Severity, XcodebuildEvent, XcodebuildFormatter, parse_xcodebuild_line,
+    xcodebuild_warnings,
+};
+pub use invocation::{Invocation, run_invocation};
+pub use packages::{PackageResolution, ResolvedPackage};
+pub use project::{parse_project_configurations, project_configurations, workspace_projects};
+pub use project_list::ProjectList;
+pub use report::{
+    CommandResult, OutputFormat, REPORT_SCHEMA_VERSION, RenderedResult, Report, ReportError,
+    ReportStatus, output_format, set_output_format,
+};
+pub use retry::{RetryPolicy, is_retryable, retry_policy, set_retry_policy};
+pub use runner::{
+    CommandRunner, DryRunRunner, ProcessRunner, command_runner, run_query, set_command_runner,
+};
+pub use simulators::{
+    Simulator, SimulatorList, SimulatorRuntime, SimulatorSelector, list_simulators,
+};
+pub use stream::{
+    DEFAULT_LOG_DIRECTORY, StreamOptions, log_file_for, set_log_file, set_xcodebuild_formatter,
+    stream_invocation, xcodebuild_formatter,
+};
+pub use supervisor::{
+    TERMINATION_GRACE_PERIOD, check_interrupted, command_timeout, install_signal_handlers,
+    interrupted, parse_duration, set_command_timeout,
+};
+pub use test_selection::{TestIdentifier, TestSelection};
+pub use xcode::{
+    XCODE_SEARCH_PATH_ENV, XcodeInstallation, XcodeSelector, developer_dir, find_xcodes,
+    find_xcodes_in, set_developer_dir,
+};
+
 /// Data Transfer Object for xcodebuild command parameters
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct XcodebuildParams {
     pub action: XcodebuildCommandAction,
     pub scheme: Option<String>,
-    pub destination: Option<String>,
+    pub destinations: Vec<Destination>,
     pub configuration: Option<Configuration>,
     pub target: Option<BuildTarget>,
     pub sdk: Option<SDK>,
     pub archive_path: Option<String>,
     pub export_path: Option<String>,
     pub export_options: Option<String>,
+    pub derived_data_path: Option<String>,
+    pub xctestrun_path: Option<String>,
+    pub result_bundle_path: Option<String>,
+    pub test_selection: TestSelection,
+    pub overrides: BuildOverrides,
 }
 
 impl XcodebuildParams {
@@ -22,44 +80,63 @@ impl XcodebuildParams {
         Self {
             action,
             scheme: None,
-            destination: None,
+            destinations: Vec::new(),
             configuration: None,
             target: None,
             sdk: None,
             archive_path: None,
             export_path: None,
             export_options: None,
+            derived_data_path: None,
+            xctestrun_path: None,
+            result_bundle_path: None,
+            test_selection: TestSelection::new(),
+            overrides: BuildOverrides::new(),
         }
     }
 
-    fn make_xcodebuild_command(&self) -> anyhow::Result<String> {
-        let mut command = format!("xcodebuild {}", self.action.command_string());
+    /// Builds the `xcodebuild` invocation described by these parameters without running it.
+    pub fn make_xcodebuild_invocation(&self) -> anyhow::Result<Invocation> {
+        let mut invocation = Invocation::new("xcodebuild").with_arg(self.action.command_string());
         if let Some(target) = &self.target {
-            command += &format!(" {}", target.project_or_workspace_argument()?)
+            invocation = invocation.with_args(target.project_or_workspace_arguments()?);
         }
         if let Some(scheme) = &self.scheme {
-            command += &format!(" -scheme {}", scheme);
+            invocation = invocation.with_args(["-scheme", scheme]);
         }
-        if let Some(destination) = &self.destination {
-            command += &format!(" -destination '{}'", destination);
+        for destination in &self.destinations {
+            invocation =
+                invocation.with_args(["-destination".to_string(), destination.to_string()]);
         }
         if let Some(configuration) = &self.configuration {
-            command += &format!(" -configuration {}", configuration.command_string());
+            invocation = invocation
+                .with_args(["-configuration".to_string(), configuration.command_string()]);
         }
         if let Some(archive_path) = &self.archive_path {
-            command += &format!(" -archivePath {}", archive_path);
+            invocation = invocation.with_args(["-archivePath", archive_path]);
         }
         if let Some(sdk) = &self.sdk {
-            command += &format!(" -sdk {}", sdk.command_string());
+            invocation = invocation.with_args(["-sdk".to_string(), sdk.command_string()]);
+        }
+        if let Some(derived_data_path) = &self.derived_data_path {
+            invocation = invocation.with_args(["-derivedDataPath", derived_data_path]);
+        }
+        if let Some(xctestrun_path) = &self.xctestrun_path {
+            invocation = invocation.with_args(["-xctestrun", xctestrun_path]);
+        }
+        if let Some(result_bundle_path) = &self.result_bundle_path {
+            invocation = invocation.with_args(["-resultBundlePath", result_bundle_path]);
         }
+        invocation = invocation.with_args(self.test_selection.arguments()?);
         if let Some(export_path) = &self.export_path {
-            command += &format!(" -exportPath {}", export_path);
+            invocation = invocation.with_args(["-exportPath", export_path]);
         }
         if let Some(export_options) = &self.export_options {
-            command += &format!(" -exportOptionsPlist {}", export_options);
+            invocation = invocation.with_args(["-exportOptionsPlist", export_options]);
         }
+        invocation = invocation.with_args(self.overrides.arguments()?);
 
-        Ok(command)
+        Ok(invocation)
     }
 
     pub fn with_export_options(mut self, export_options: String) -> Self {
@@ -77,8 +154,13 @@ impl XcodebuildParams {
         self
     }
 
-    pub fn with_destination(mut self, destination: String) -> Self {
-        self.destination = Some(destination);
+    pub fn with_destination(mut self, destination: Destination) -> Self {
+        self.destinations.push(destination);
+        self
+    }
+
+    pub fn with_destinations(mut self, destinations: Vec<Destination>) -> Self {
+        self.destinations.extend(destinations);
         self
     }
 
@@ -101,25 +183,114 @@ impl XcodebuildParams {
         self.archive_path = Some(archive_path);
         self
     }
+
+    pub fn with_derived_data_path(mut self, derived_data_path: String) -> Self {
+        self.derived_data_path = Some(derived_data_path);
+        self
+    }
+
+    pub fn with_xctestrun_path(mut self, xctestrun_path: String) -> Self {
+        self.xctestrun_path = Some(xctestrun_path);
+        self
+    }
+
+    pub fn with_result_bundle_path(mut self, result_bundle_path: String) -> Self {
+        self.result_bundle_path = Some(result_bundle_path);
+        self
+    }
+
+    pub fn with_test_selection(mut self, test_selection: TestSelection) -> Self {
+        self.test_selection = test_selection;
+        self
+    }
+
+    pub fn with_overrides(mut self, overrides: BuildOverrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
 }
 
+/// Runs xcodebuild with the given parameters and returns its stdout.
+///
+/// Output is streamed to the terminal as it is produced, rendered with the configured
+/// [`Formatter`] (see [`set_xcodebuild_formatter`]), and appended raw to the run's log file (see
+/// [`log_file_for`]); the captured stdout is still returned for parsing.
+///
+/// A non-zero exit is reported as a [`CommandError`] whose kind reflects the action: failing
+/// tests yield [`CommandErrorKind::TestsFailed`], code signing problems
+/// [`CommandErrorKind::SigningFailed`] and everything else [`CommandErrorKind::BuildFailed`].
 pub fn run_xcodebuild_command(params: &XcodebuildParams) -> Result<String> {
-    let command = params.make_xcodebuild_command()?;
-    let output = Command::new("zsh")
-        .arg("-c")
-        .arg(command)
-        .spawn()
-        .context(format!("Failed to run {}", params.action.command_string()))?
-        .wait_with_output()
-        .context(format!("Failed to run {}", params.action.command_string()))?;
+    let invocation = params.make_xcodebuild_invocation()?;
+    let options = StreamOptions::new()
+        .with_log_file(log_file_for(&params.action.command_string()))
+        .with_formatter(xcodebuild_formatter());
+    let output = stream_invocation(&invocation, &options)?;
+    if !output.status.success() {
+        let kind = xcodebuild_failure_kind(&params.action, &output);
+        let error: anyhow::Error =
+            CommandError::from_output(kind, &params.action.command_string(), &invocation, &output)
+                .into();
+        return Err(match unknown_scheme_hint(params, &output) {
+            Some(hint) => error.context(hint),
+            None => error,
+        });
+    }
 
     String::from_utf8(output.stdout).context("Failed to decode output")
 }
 
-#[derive(Debug, PartialEq)]
+/// When xcodebuild rejected the scheme, lists the project's schemes and returns an error message
+/// suggesting the closest one. Returns `None` if listing fails or the scheme does exist.
+fn unknown_scheme_hint(params: &XcodebuildParams, output: &std::process::Output) -> Option<String> {
+    let (Some(scheme), Some(target)) = (&params.scheme, &params.target) else {
+        return None;
+    };
+    let combined = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    if !combined.contains("does not contain a scheme named") {
+        return None;
+    }
+
+    let error = target.list().ok()?.check_scheme(scheme).err()?;
+    Some(error.to_string())
+}
+
+fn xcodebuild_failure_kind(
+    action: &XcodebuildCommandAction,
+    output: &std::process::Output,
+) -> CommandErrorKind {
+    let combined = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    if is_signing_failure(&combined) {
+        return CommandErrorKind::SigningFailed;
+    }
+
+    match action {
+        XcodebuildCommandAction::Test | XcodebuildCommandAction::TestWithoutBuilding
+            if !combined.contains("** TEST BUILD FAILED **")
+                && !combined.contains("Testing cancelled because the build failed") =>
+        {
+            CommandErrorKind::TestsFailed
+        }
+        _ => CommandErrorKind::BuildFailed,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum XcodebuildCommandAction {
     Build,
     Test,
+    /// Builds the scheme's tests and writes an `.xctestrun` file describing how to run them.
+    BuildForTesting,
+    /// Runs tests built by [`XcodebuildCommandAction::BuildForTesting`], usually from an
+    /// `.xctestrun` file.
+    TestWithoutBuilding,
     Archive,
     ExportArchive,
 }
@@ -129,16 +300,39 @@ impl XcodebuildCommandAction {
         match self {
             XcodebuildCommandAction::Build => String::from("build"),
             XcodebuildCommandAction::Test => String::from("test"),
+            XcodebuildCommandAction::BuildForTesting => String::from("build-for-testing"),
+            XcodebuildCommandAction::TestWithoutBuilding => String::from("test-without-building"),
             XcodebuildCommandAction::Archive => String::from("archive"),
             XcodebuildCommandAction::ExportArchive => String::from("-exportArchive"),
         }
     }
 }
 
-#[derive(ValueEnum, Clone, Debug)]
+/// An Xcode build configuration.
+///
+/// `Debug` and `Release` are matched case-insensitively; any other name, such as `Staging` or
+/// `App Store`, is passed to xcodebuild verbatim. Use [`Configuration::validate`] with
+/// [`BuildTarget::configurations`] to check a name against the project before building.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::Configuration;
+///
+/// assert_eq!("release".parse::<Configuration>().unwrap(), Configuration::Release);
+/// assert_eq!(
+///     "Staging".parse::<Configuration>().unwrap(),
+///     Configuration::Custom("Staging".to_string())
+/// );
+/// assert_eq!(Configuration::Custom("App Store".to_string()).command_string(), "App Store");
+/// assert!("".parse::<Configuration>().is_err());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub enum Configuration {
+    #[default]
     Debug,
     Release,
+    Custom(String),
 }
 
 impl Configuration {
@@ -146,13 +340,42 @@ impl Configuration {
         match self {
             Configuration::Debug => String::from("Debug"),
             Configuration::Release => String::from("Release"),
+            Configuration::Custom(name) => name.clone(),
+        }
+    }
+
+    /// Fails unless this configuration is one of `available`, listing the declared
+    /// configurations in the error.
+    pub fn validate(&self, available: &[String]) -> Result<()> {
+        let name = self.command_string();
+        if available.contains(&name) {
+            return Ok(());
         }
+
+        anyhow::bail!(
+            "Configuration '{}' is not declared in the project; available configurations: {}",
+            name,
+            available.join(", ")
+        )
     }
 }
 
-impl Default for Configuration {
-    fn default() -> Self {
-        Self::Debug
+impl std::str::FromStr for Configuration {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let name = s.trim();
+        if name.is_empty() {
+            return Err(String::from("configuration name must not be empty"));
+        }
+
+        if name.eq_ignore_ascii_case("debug") {
+            Ok(Configuration::Debug)
+        } else if name.eq_ignore_ascii_case("release") {
+            Ok(Configuration::Release)
+        } else {
+            Ok(Configuration::Custom(name.to_string()))
+        }
     }
 }
 
@@ -161,31 +384,175 @@ impl std::fmt::Display for Configuration {
         match self {
             Configuration::Debug => write!(f, "debug"),
             Configuration::Release => write!(f, "release"),
+            Configuration::Custom(name) => write!(f, "{}", name),
         }
     }
 }
 
-#[derive(ValueEnum, Clone, Debug)]
+/// An Apple platform SDK passed to xcodebuild with `-sdk`.
+///
+/// Parses the SDK names reported by `xcodebuild -showsdks`, case-insensitively, optionally
+/// followed by a version to pin a specific SDK (for example `iphoneos18.2`).
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::SDK;
+///
+/// assert_eq!("appletvos".parse::<SDK>().unwrap(), SDK::Appletvos);
+/// assert_eq!(
+///     "iphoneos18.2".parse::<SDK>().unwrap(),
+///     SDK::Versioned(Box::new(SDK::Iphoneos), "18.2".to_string())
+/// );
+/// assert_eq!("xrsimulator2.2".parse::<SDK>().unwrap().command_string(), "xrsimulator2.2");
+/// assert_eq!(
+///     SDK::Watchsimulator.generic_destination().to_string(),
+///     "generic/platform=watchOS Simulator"
+/// );
+/// assert!("android".parse::<SDK>().is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum SDK {
     Iphoneos,
+    Iphonesimulator,
     Macosx,
+    Appletvos,
+    Appletvsimulator,
+    Watchos,
+    Watchsimulator,
+    Xros,
+    Xrsimulator,
+    /// A specific version of one of the other SDKs, such as `iphoneos18.2`.
+    Versioned(Box<SDK>, String),
 }
 
 impl SDK {
+    /// Every unversioned SDK, in the order they are listed in help and error messages.
+    pub const ALL: [SDK; 9] = [
+        SDK::Iphoneos,
+        SDK::Iphonesimulator,
+        SDK::Macosx,
+        SDK::Appletvos,
+        SDK::Appletvsimulator,
+        SDK::Watchos,
+        SDK::Watchsimulator,
+        SDK::Xros,
+        SDK::Xrsimulator,
+    ];
+
     pub fn command_string(&self) -> String {
         match self {
             SDK::Iphoneos => String::from("iphoneos"),
+            SDK::Iphonesimulator => String::from("iphonesimulator"),
             SDK::Macosx => String::from("macosx"),
+            SDK::Appletvos => String::from("appletvos"),
+            SDK::Appletvsimulator => String::from("appletvsimulator"),
+            SDK::Watchos => String::from("watchos"),
+            SDK::Watchsimulator => String::from("watchsimulator"),
+            SDK::Xros => String::from("xros"),
+            SDK::Xrsimulator => String::from("xrsimulator"),
+            SDK::Versioned(sdk, version) => format!("{}{}", sdk.command_string(), version),
+        }
+    }
+
+    /// Returns the SDK without its version.
+    pub fn base(&self) -> &SDK {
+        match self {
+            SDK::Versioned(sdk, _) => sdk.base(),
+            sdk => sdk,
+        }
+    }
+
+    /// Returns the pinned SDK version, if any.
+    pub fn version(&self) -> Option<&str> {
+        match self {
+            SDK::Versioned(_, version) => Some(version),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` for the simulator SDKs.
+    pub fn is_simulator(&self) -> bool {
+        matches!(
+            self.base(),
+            SDK::Iphonesimulator | SDK::Appletvsimulator | SDK::Watchsimulator | SDK::Xrsimulator
+        )
+    }
+
+    /// Returns the xcodebuild platform name for this SDK, as used in destinations.
+    pub fn platform(&self) -> &'static str {
+        match self.base() {
+            SDK::Iphoneos => "iOS",
+            SDK::Iphonesimulator => "iOS Simulator",
+            SDK::Macosx => "macOS",
+            SDK::Appletvos => "tvOS",
+            SDK::Appletvsimulator => "tvOS Simulator",
+            SDK::Watchos => "watchOS",
+            SDK::Watchsimulator => "watchOS Simulator",
+            SDK::Xros => "visionOS",
+            SDK::Xrsimulator => "visionOS Simulator",
+            SDK::Versioned(..) => unreachable!("base() never returns a versioned SDK"),
+        }
+    }
+
+    /// Returns the generic destination used to build or archive for this SDK when no
+    /// `--destination` is given, e.g. `generic/platform=iOS`.
+    pub fn generic_destination(&self) -> Destination {
+        Destination::new()
+            .with_platform(self.platform())
+            .with_generic(true)
+    }
+
+    /// Returns the destination used to run tests for this SDK when no `--destination` is given.
+    ///
+    /// Tests need a concrete device, so this names only the platform and lets xcodebuild pick
+    /// the first matching simulator, connected device or, for `macosx`, this Mac.
+    pub fn test_destination(&self) -> Destination {
+        Destination::new().with_platform(self.platform())
+    }
+}
+
+impl std::str::FromStr for SDK {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let lowercased = s.trim().to_ascii_lowercase();
+        let version_start = lowercased
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(lowercased.len());
+        let (name, version) = lowercased.split_at(version_start);
+
+        let sdk = SDK::ALL
+            .into_iter()
+            .find(|sdk| sdk.command_string() == name)
+            .ok_or_else(|| {
+                format!(
+                    "unknown SDK '{}'; expected one of {}, optionally followed by a version (e.g. iphoneos18.2)",
+                    s,
+                    SDK::ALL.map(|sdk| sdk.command_string()).join(", ")
+                )
+            })?;
+
+        if version.is_empty() {
+            return Ok(sdk);
         }
+        if version
+            .split('.')
+            .any(|part| part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()))
+        {
+            return Err(format!(
+                "invalid SDK version '{}' in '{}'; expected numbers separated by dots (e.g. iphoneos18.2)",
+                version, s
+            ));
+        }
+
+        Ok(SDK::Versioned(Box::new(sdk), version.to_string()))
     }
 }
 
 impl std::fmt::Display for SDK {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            SDK::Iphoneos => write!(f, "iphoneos"),
-            SDK::Macosx => write!(f, "macosx"),
-        }
+        write!(f, "{}", self.command_string())
     }
 }
 
@@ -213,7 +580,7 @@ impl std::fmt::Display for UploadTarget {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BuildTarget {
     project: Option<String>,
     workspace: Option<String>,
@@ -227,6 +594,74 @@ impl BuildTarget {
         }
     }
 
+    /// Finds the workspace or project in the current directory; see [`BuildTarget::discover_in`].
+    pub fn discover() -> Result<Self> {
+        Self::discover_in(Path::new("."))
+    }
+
+    /// Finds the `.xcworkspace` or `.xcodeproj` at the top level of `dir`.
+    ///
+    /// A single workspace wins over any projects next to it, since the workspace is what
+    /// references them. Without a workspace, a single project is used. Only `dir` itself is
+    /// searched, so the `project.xcworkspace` Xcode keeps inside every `.xcodeproj` is never
+    /// picked up. Several workspaces, or several projects and no workspace, are reported as
+    /// ambiguous so the caller can pass `--workspace` or `--project` explicitly.
+    ///
+    /// Paths are relative to `dir`, or bare names when `dir` is `.`.
+    pub fn discover_in(dir: &Path) -> Result<Self> {
+        let mut workspaces = Vec::new();
+        let mut projects = Vec::new();
+        let entries = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read directory {}", dir.display()))?;
+        for entry in entries {
+            let entry =
+                entry.with_context(|| format!("Failed to read directory {}", dir.display()))?;
+            if !entry.path().is_dir() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = if dir == Path::new(".") {
+                name.clone()
+            } else {
+                dir.join(&name).to_string_lossy().into_owned()
+            };
+            if name.ends_with(".xcworkspace") {
+                workspaces.push(path);
+            } else if name.ends_with(".xcodeproj") {
+                projects.push(path);
+            }
+        }
+        workspaces.sort();
+        projects.sort();
+
+        match (workspaces.as_slice(), projects.as_slice()) {
+            ([workspace], _) => Ok(Self::new(None, Some(workspace))),
+            ([], [project]) => Ok(Self::new(Some(project), None)),
+            ([], []) => anyhow::bail!(
+                "No .xcworkspace or .xcodeproj found in {}; pass --workspace or --project",
+                dir.display()
+            ),
+            ([], projects) => anyhow::bail!(
+                "Found several projects in {} ({}); pass --project to choose one",
+                dir.display(),
+                projects.join(", ")
+            ),
+            (workspaces, _) => anyhow::bail!(
+                "Found several workspaces in {} ({}); pass --workspace to choose one",
+                dir.display(),
+                workspaces.join(", ")
+            ),
+        }
+    }
+
+    pub fn project(&self) -> Option<&str> {
+        self.project.as_deref()
+    }
+
+    pub fn workspace(&self) -> Option<&str> {
+        self.workspace.as_deref()
+    }
+
     pub fn project_or_workspace_string(&self) -> Result<String> {
         if let Some(project) = &self.project {
             return Ok(project.clone());
@@ -239,23 +674,131 @@ impl BuildTarget {
         anyhow::bail!("Neither project nor workspace is specified")
     }
 
-    pub fn project_or_workspace_argument(&self) -> Result<String> {
+    pub fn project_or_workspace_arguments(&self) -> Result<Vec<String>> {
         if let Some(project) = &self.project {
-            return Ok(format!("-project {}", project));
+            return Ok(vec![String::from("-project"), project.clone()]);
         }
 
         if let Some(workspace) = &self.workspace {
-            return Ok(format!("-workspace {}", workspace));
+            return Ok(vec![String::from("-workspace"), workspace.clone()]);
         }
 
         anyhow::bail!("Neither project nor workspace is specified")
     }
+
+    /// Returns the build configurations declared by the project, or by every project referenced
+    /// from the workspace.
+    pub fn configurations(&self) -> Result<Vec<String>> {
+        let projects = match (&self.project, &self.workspace) {
+            (Some(project), _) => vec![PathBuf::from(project)],
+            (None, Some(workspace)) => workspace_projects(Path::new(workspace))?,
+            (None, None) => anyhow::bail!("Neither project nor workspace is specified"),
+        };
+
+        let mut configurations: Vec<String> = Vec::new();
+        for project in projects {
+            for configuration in project_configurations(&project)? {
+                if !configurations.contains(&configuration) {
+                    configurations.push(configuration);
+                }
+            }
+        }
+
+        Ok(configurations)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn discovery_dir(bundles: &[&str]) -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for bundle in bundles {
+            std::fs::create_dir_all(temp_dir.path().join(bundle)).unwrap();
+        }
+        temp_dir
+    }
+
+    #[test]
+    fn test_discover_prefers_workspace_over_projects() {
+        let temp_dir = discovery_dir(&[
+            "MyApp.xcodeproj/project.xcworkspace",
+            "MyApp.xcworkspace",
+            "Pods/Pods.xcodeproj",
+        ]);
+
+        let target = BuildTarget::discover_in(temp_dir.path()).unwrap();
+
+        assert_eq!(target.project(), None);
+        assert_eq!(
+            target.workspace(),
+            Some(&*temp_dir.path().join("MyApp.xcworkspace").to_string_lossy())
+        );
+    }
+
+    #[test]
+    fn test_discover_ignores_workspace_nested_in_project() {
+        let temp_dir = discovery_dir(&["MyApp.xcodeproj/project.xcworkspace"]);
+
+        let target = BuildTarget::discover_in(temp_dir.path()).unwrap();
+
+        assert_eq!(target.workspace(), None);
+        assert_eq!(
+            target.project(),
+            Some(&*temp_dir.path().join("MyApp.xcodeproj").to_string_lossy())
+        );
+    }
+
+    #[test]
+    fn test_discover_ignores_files_with_bundle_extensions() {
+        let temp_dir = discovery_dir(&["MyApp.xcodeproj"]);
+        std::fs::write(temp_dir.path().join("Notes.xcworkspace"), "").unwrap();
+
+        let target = BuildTarget::discover_in(temp_dir.path()).unwrap();
+
+        assert!(target.project().is_some());
+        assert_eq!(target.workspace(), None);
+    }
+
+    #[test]
+    fn test_discover_reports_ambiguity_and_absence() {
+        let cases: [(&[&str], &str); 3] = [
+            (
+                &["App.xcodeproj", "Kit.xcodeproj"],
+                "Found several projects in",
+            ),
+            (
+                &["App.xcworkspace", "Other.xcworkspace", "App.xcodeproj"],
+                "Found several workspaces in",
+            ),
+            (&["Sources"], "No .xcworkspace or .xcodeproj found in"),
+        ];
+
+        for (bundles, expected) in cases {
+            let temp_dir = discovery_dir(bundles);
+            let error = BuildTarget::discover_in(temp_dir.path())
+                .unwrap_err()
+                .to_string();
+            assert!(error.contains(expected), "{:?}: {}", bundles, error);
+        }
+    }
+
+    #[test]
+    fn test_discover_lists_ambiguous_candidates_in_order() {
+        let temp_dir = discovery_dir(&["Kit.xcodeproj", "App.xcodeproj"]);
+
+        let error = BuildTarget::discover_in(temp_dir.path())
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.ends_with(&format!(
+            "({}, {}); pass --project to choose one",
+            temp_dir.path().join("App.xcodeproj").display(),
+            temp_dir.path().join("Kit.xcodeproj").display()
+        )));
+    }
+
     #[test]
     fn test_build_target_with_project() {
         let target = BuildTarget::new(Some(&"TestProject.xcodeproj".to_string()), None);
@@ -265,8 +808,8 @@ mod tests {
             "TestProject.xcodeproj"
         );
         assert_eq!(
-            target.project_or_workspace_argument().unwrap(),
-            "-project TestProject.xcodeproj"
+            target.project_or_workspace_arguments().unwrap(),
+            ["-project", "TestProject.xcodeproj"]
         );
     }
 
@@ -279,8 +822,8 @@ mod tests {
             "TestWorkspace.xcworkspace"
         );
         assert_eq!(
-            target.project_or_workspace_argument().unwrap(),
-            "-workspace TestWorkspace.xcworkspace"
+            target.project_or_workspace_arguments().unwrap(),
+            ["-workspace", "TestWorkspace.xcworkspace"]
         );
     }
 
@@ -289,7 +832,7 @@ mod tests {
         let target = BuildTarget::new(None, None);
 
         assert!(target.project_or_workspace_string().is_err());
-        assert!(target.project_or_workspace_argument().is_err());
+        assert!(target.project_or_workspace_arguments().is_err());
     }
 
     #[test]
@@ -309,14 +852,14 @@ mod tests {
         let target = BuildTarget::new(Some(&"TestProject.xcodeproj".to_string()), None);
         let params = &XcodebuildParams::new(XcodebuildCommandAction::Build)
             .with_scheme("TestScheme".to_string())
-            .with_destination("iOS Simulator,name=iPhone 15 Pro".to_string())
+            .with_destination("platform=iOS Simulator,name=iPhone 15 Pro".parse().unwrap())
             .with_configuration(Configuration::Debug)
             .with_target(target);
-        let command = params.make_xcodebuild_command().unwrap();
+        let command = params.make_xcodebuild_invocation().unwrap().to_string();
 
         assert_eq!(
             command,
-            "xcodebuild build -project TestProject.xcodeproj -scheme TestScheme -destination 'iOS Simulator,name=iPhone 15 Pro' -configuration Debug"
+            "xcodebuild build -project TestProject.xcodeproj -scheme TestScheme -destination 'platform=iOS Simulator,name=iPhone 15 Pro' -configuration Debug"
         );
     }
 
@@ -325,6 +868,14 @@ mod tests {
         assert_eq!(XcodebuildCommandAction::Build.command_string(), "build");
         assert_eq!(XcodebuildCommandAction::Test.command_string(), "test");
         assert_eq!(XcodebuildCommandAction::Archive.command_string(), "archive");
+        assert_eq!(
+            XcodebuildCommandAction::BuildForTesting.command_string(),
+            "build-for-testing"
+        );
+        assert_eq!(
+            XcodebuildCommandAction::TestWithoutBuilding.command_string(),
+            "test-without-building"
+        );
     }
 
     #[test]
@@ -339,6 +890,77 @@ mod tests {
         assert_eq!(SDK::Macosx.to_string(), "macosx");
     }
 
+    #[test]
+    fn test_sdk_parses_every_sdk_name() {
+        for sdk in SDK::ALL {
+            assert_eq!(sdk.command_string().parse::<SDK>().unwrap(), sdk);
+            assert_eq!(
+                sdk.command_string().to_uppercase().parse::<SDK>().unwrap(),
+                sdk
+            );
+        }
+    }
+
+    #[test]
+    fn test_sdk_parses_versioned_sdks() {
+        let sdk: SDK = "iphonesimulator18.2".parse().unwrap();
+
+        assert_eq!(sdk.base(), &SDK::Iphonesimulator);
+        assert_eq!(sdk.version(), Some("18.2"));
+        assert!(sdk.is_simulator());
+        assert_eq!(sdk.command_string(), "iphonesimulator18.2");
+        assert_eq!(sdk.to_string(), "iphonesimulator18.2");
+        assert_eq!(
+            sdk.generic_destination().to_string(),
+            "generic/platform=iOS Simulator"
+        );
+    }
+
+    #[test]
+    fn test_sdk_rejects_unknown_names_and_bad_versions() {
+        for input in [
+            "invalid",
+            "ios",
+            "iphoneos18.",
+            "iphoneos18..2",
+            "macosx15a",
+            "",
+        ] {
+            assert!(input.parse::<SDK>().is_err(), "{}", input);
+        }
+        assert!(
+            "tvos"
+                .parse::<SDK>()
+                .unwrap_err()
+                .contains("expected one of iphoneos, iphonesimulator, macosx")
+        );
+    }
+
+    #[test]
+    fn test_sdk_default_destinations() {
+        let expected = [
+            (SDK::Iphoneos, "generic/platform=iOS", "platform=iOS"),
+            (SDK::Macosx, "generic/platform=macOS", "platform=macOS"),
+            (
+                SDK::Appletvsimulator,
+                "generic/platform=tvOS Simulator",
+                "platform=tvOS Simulator",
+            ),
+            (SDK::Watchos, "generic/platform=watchOS", "platform=watchOS"),
+            (SDK::Xros, "generic/platform=visionOS", "platform=visionOS"),
+            (
+                SDK::Xrsimulator,
+                "generic/platform=visionOS Simulator",
+                "platform=visionOS Simulator",
+            ),
+        ];
+
+        for (sdk, generic, test) in expected {
+            assert_eq!(sdk.generic_destination().to_string(), generic);
+            assert_eq!(sdk.test_destination().to_string(), test);
+        }
+    }
+
     #[test]
     fn test_configuration_default() {
         let default_config: Configuration = Default::default();
@@ -346,19 +968,77 @@ mod tests {
         assert_eq!(default_config.to_string(), "debug");
     }
 
+    #[test]
+    fn test_configuration_parses_built_ins_case_insensitively() {
+        for input in ["debug", "Debug", "DEBUG"] {
+            assert_eq!(
+                input.parse::<Configuration>().unwrap(),
+                Configuration::Debug
+            );
+        }
+        for input in ["release", "Release", "RELEASE"] {
+            assert_eq!(
+                input.parse::<Configuration>().unwrap(),
+                Configuration::Release
+            );
+        }
+    }
+
+    #[test]
+    fn test_configuration_keeps_custom_names_verbatim() {
+        let configuration: Configuration = "App Store".parse().unwrap();
+
+        assert_eq!(
+            configuration,
+            Configuration::Custom("App Store".to_string())
+        );
+        assert_eq!(configuration.command_string(), "App Store");
+        assert_eq!(configuration.to_string(), "App Store");
+        assert!("  ".parse::<Configuration>().is_err());
+    }
+
+    #[test]
+    fn test_configuration_validate() {
+        let available = vec!["Debug".to_string(), "Staging".to_string()];
+
+        assert!(Configuration::Debug.validate(&available).is_ok());
+        assert!(
+            Configuration::Custom("Staging".to_string())
+                .validate(&available)
+                .is_ok()
+        );
+        assert_eq!(
+            Configuration::Release
+                .validate(&available)
+                .unwrap_err()
+                .to_string(),
+            "Configuration 'Release' is not declared in the project; available configurations: Debug, Staging"
+        );
+    }
+
+    #[test]
+    fn test_build_target_configurations_from_project() {
+        let target = BuildTarget::new(
+            Some(&"../xctools_cli/TestXcodeApp/TestXcodeApp.xcodeproj".to_string()),
+            None,
+        );
+
+        assert_eq!(target.configurations().unwrap(), ["Debug", "Release"]);
+    }
+
     #[test]
     fn test_test_command() {
         let target = BuildTarget::new(Some(&"TestProject.xcodeproj".to_string()), None);
         let params = XcodebuildParams::new(XcodebuildCommandAction::Test)
             .with_scheme("TestScheme".to_string())
-            .with_destination("iOS Simulator,name=iPhone 15 Pro".to_string())
+            .with_destination("platform=iOS Simulator,name=iPhone 15 Pro".parse().unwrap())
             .with_configuration(Configuration::Release)
             .with_target(target);
-        let command = params.make_xcodebuild_command().unwrap();
+        let command = params.make_xcodebuild_invocation().unwrap().to_string();
 
         assert_eq!(
             command,
-            "xcodebuild test -project TestProject.xcodeproj -scheme TestScheme -destination 'iOS Simulator,name=iPhone 15 Pro' -configuration Release"
+            "xcodebuild test -project TestProject.xcodeproj -scheme TestScheme -destination 'platform=iOS Simulator,name=iPhone 15 Pro' -configuration Release"
         );
     }
 
@@ -367,15 +1047,15 @@ mod tests {
         let target = BuildTarget::new(Some(&"TestProject.xcodeproj".to_string()), None);
         let params = XcodebuildParams::new(XcodebuildCommandAction::Archive)
             .with_scheme("TestScheme".to_string())
-            .with_destination("iOS Simulator,name=iPhone 15 Pro".to_string())
+            .with_destination("platform=iOS Simulator,name=iPhone 15 Pro".parse().unwrap())
             .with_configuration(Configuration::Release)
             .with_target(target)
             .with_archive_path("/path/to/archive.xcarchive".to_string());
-        let command = params.make_xcodebuild_command().unwrap();
+        let command = params.make_xcodebuild_invocation().unwrap().to_string();
 
         assert_eq!(
             command,
-            "xcodebuild archive -project TestProject.xcodeproj -scheme TestScheme -destination 'iOS Simulator,name=iPhone 15 Pro' -configuration Release -archivePath /path/to/archive.xcarchive"
+            "xcodebuild archive -project TestProject.xcodeproj -scheme TestScheme -destination 'platform=iOS Simulator,name=iPhone 15 Pro' -configuration Release -archivePath /path/to/archive.xcarchive"
         );
     }
 
@@ -384,15 +1064,15 @@ mod tests {
         let target = BuildTarget::new(Some(&"TestProject.xcodeproj".to_string()), None);
         let params = XcodebuildParams::new(XcodebuildCommandAction::Build)
             .with_scheme("TestScheme".to_string())
-            .with_destination("iOS Simulator,name=iPhone 15 Pro".to_string())
+            .with_destination("platform=iOS Simulator,name=iPhone 15 Pro".parse().unwrap())
             .with_configuration(Configuration::Debug)
             .with_target(target)
             .with_sdk(SDK::Iphoneos);
-        let command = params.make_xcodebuild_command().unwrap();
+        let command = params.make_xcodebuild_invocation().unwrap().to_string();
 
         assert_eq!(
             command,
-            "xcodebuild build -project TestProject.xcodeproj -scheme TestScheme -destination 'iOS Simulator,name=iPhone 15 Pro' -configuration Debug -sdk iphoneos"
+            "xcodebuild build -project TestProject.xcodeproj -scheme TestScheme -destination 'platform=iOS Simulator,name=iPhone 15 Pro' -configuration Debug -sdk iphoneos"
         );
     }
 
@@ -401,14 +1081,14 @@ mod tests {
         let target = BuildTarget::new(None, Some(&"TestWorkspace.xcworkspace".to_string()));
         let params = XcodebuildParams::new(XcodebuildCommandAction::Build)
             .with_scheme("TestScheme".to_string())
-            .with_destination("iOS Simulator,name=iPhone 15 Pro".to_string())
+            .with_destination("platform=iOS Simulator,name=iPhone 15 Pro".parse().unwrap())
             .with_configuration(Configuration::Debug)
             .with_target(target);
-        let command = params.make_xcodebuild_command().unwrap();
+        let command = params.make_xcodebuild_invocation().unwrap().to_string();
 
         assert_eq!(
             command,
-            "xcodebuild build -workspace TestWorkspace.xcworkspace -scheme TestScheme -destination 'iOS Simulator,name=iPhone 15 Pro' -configuration Debug"
+            "xcodebuild build -workspace TestWorkspace.xcworkspace -scheme TestScheme -destination 'platform=iOS Simulator,name=iPhone 15 Pro' -configuration Debug"
         );
     }
 
@@ -417,16 +1097,16 @@ mod tests {
         let target = BuildTarget::new(None, Some(&"TestWorkspace.xcworkspace".to_string()));
         let params = XcodebuildParams::new(XcodebuildCommandAction::Archive)
             .with_scheme("TestScheme".to_string())
-            .with_destination("Generic/iOS".to_string())
+            .with_destination("generic/platform=iOS".parse().unwrap())
             .with_configuration(Configuration::Release)
             .with_target(target)
             .with_sdk(SDK::Macosx)
             .with_archive_path("/tmp/MyApp.xcarchive".to_string());
-        let command = params.make_xcodebuild_command().unwrap();
+        let command = params.make_xcodebuild_invocation().unwrap().to_string();
 
         assert_eq!(
             command,
-            "xcodebuild archive -workspace TestWorkspace.xcworkspace -scheme TestScheme -destination 'Generic/iOS' -configuration Release -archivePath /tmp/MyApp.xcarchive -sdk macosx"
+            "xcodebuild archive -workspace TestWorkspace.xcworkspace -scheme TestScheme -destination generic/platform=iOS -configuration Release -archivePath /tmp/MyApp.xcarchive -sdk macosx"
         );
     }
 
@@ -435,10 +1115,10 @@ mod tests {
         let target = BuildTarget::new(None, None);
         let params = XcodebuildParams::new(XcodebuildCommandAction::Build)
             .with_scheme("TestScheme".to_string())
-            .with_destination("iOS Simulator,name=iPhone 15 Pro".to_string())
+            .with_destination("platform=iOS Simulator,name=iPhone 15 Pro".parse().unwrap())
             .with_configuration(Configuration::Debug)
             .with_target(target);
-        let result = params.make_xcodebuild_command();
+        let result = params.make_xcodebuild_invocation();
 
         assert!(result.is_err());
         assert!(
@@ -462,8 +1142,8 @@ mod tests {
             "TestProject.xcodeproj"
         );
         assert_eq!(
-            target.project_or_workspace_argument().unwrap(),
-            "-project TestProject.xcodeproj"
+            target.project_or_workspace_arguments().unwrap(),
+            ["-project", "TestProject.xcodeproj"]
         );
     }
 
@@ -472,7 +1152,7 @@ mod tests {
         let params = XcodebuildParams::new(XcodebuildCommandAction::ExportArchive)
             .with_archive_path("/path/to/archive.xcarchive".to_string())
             .with_export_path("/path/to/export".to_string());
-        let command = params.make_xcodebuild_command().unwrap();
+        let command = params.make_xcodebuild_invocation().unwrap().to_string();
 
         assert_eq!(
             command,
@@ -485,7 +1165,7 @@ mod tests {
         let params = XcodebuildParams::new(XcodebuildCommandAction::ExportArchive)
             .with_archive_path("/path/to/archive.xcarchive".to_string())
             .with_export_options("/path/to/ExportOptions.plist".to_string());
-        let command = params.make_xcodebuild_command().unwrap();
+        let command = params.make_xcodebuild_invocation().unwrap().to_string();
 
         assert_eq!(
             command,
@@ -499,7 +1179,7 @@ mod tests {
             .with_archive_path("/path/to/archive.xcarchive".to_string())
             .with_export_path("/path/to/export".to_string())
             .with_export_options("/path/to/ExportOptions.plist".to_string());
-        let command = params.make_xcodebuild_command().unwrap();
+        let command = params.make_xcodebuild_invocation().unwrap().to_string();
 
         assert_eq!(
             command,
@@ -507,6 +1187,80 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_xcodebuild_invocation_with_multiple_destinations() {
+        let target = BuildTarget::new(Some(&"MyApp.xcodeproj".to_string()), None);
+        let params = XcodebuildParams::new(XcodebuildCommandAction::Test)
+            .with_scheme("MyApp".to_string())
+            .with_destinations(vec![
+                Destination::new()
+                    .with_platform("iOS Simulator")
+                    .with_name("iPhone 16"),
+                "platform=macOS,arch=arm64".parse().unwrap(),
+            ])
+            .with_target(target);
+        let command = params.make_xcodebuild_invocation().unwrap().to_string();
+
+        assert_eq!(
+            command,
+            "xcodebuild test -project MyApp.xcodeproj -scheme MyApp -destination 'platform=iOS Simulator,name=iPhone 16' -destination platform=macOS,arch=arm64"
+        );
+    }
+
+    #[test]
+    fn test_xcodebuild_invocation_with_result_bundle_path() {
+        let target = BuildTarget::new(Some(&"MyApp.xcodeproj".to_string()), None);
+        let params = XcodebuildParams::new(XcodebuildCommandAction::Test)
+            .with_scheme("MyApp".to_string())
+            .with_configuration(Configuration::Debug)
+            .with_target(target)
+            .with_result_bundle_path("results/My App.xcresult".to_string());
+        let command = params.make_xcodebuild_invocation().unwrap().to_string();
+
+        assert_eq!(
+            command,
+            "xcodebuild test -project MyApp.xcodeproj -scheme MyApp -configuration Debug -resultBundlePath 'results/My App.xcresult'"
+        );
+    }
+
+    #[test]
+    fn test_xcodebuild_invocation_for_xctestrun() {
+        let params = XcodebuildParams::new(XcodebuildCommandAction::TestWithoutBuilding)
+            .with_destination("platform=macOS".parse().unwrap())
+            .with_xctestrun_path("products/MyApp_macosx15.2-arm64.xctestrun".to_string())
+            .with_result_bundle_path("MyApp.xcresult".to_string());
+        let command = params.make_xcodebuild_invocation().unwrap().to_string();
+
+        assert_eq!(
+            command,
+            "xcodebuild test-without-building -destination platform=macOS -xctestrun products/MyApp_macosx15.2-arm64.xctestrun -resultBundlePath MyApp.xcresult"
+        );
+    }
+
+    #[test]
+    fn test_xcodebuild_invocation_with_test_selection() {
+        let target = BuildTarget::new(Some(&"MyApp.xcodeproj".to_string()), None);
+        let params = XcodebuildParams::new(XcodebuildCommandAction::Test)
+            .with_scheme("MyApp".to_string())
+            .with_target(target)
+            .with_result_bundle_path("MyApp.xcresult".to_string())
+            .with_test_selection(
+                TestSelection::new()
+                    .with_test_plan("Smoke Tests".to_string())
+                    .with_only_testing("MyAppTests/LoginTests".parse().unwrap()),
+            )
+            .with_overrides(
+                BuildOverrides::new()
+                    .with_build_setting(BuildSetting::new("CODE_SIGNING_ALLOWED", "NO").unwrap()),
+            );
+        let command = params.make_xcodebuild_invocation().unwrap().to_string();
+
+        assert_eq!(
+            command,
+            "xcodebuild test -project MyApp.xcodeproj -scheme MyApp -resultBundlePath MyApp.xcresult -testPlan 'Smoke Tests' -only-testing:MyAppTests/LoginTests CODE_SIGNING_ALLOWED=NO"
+        );
+    }
+
     #[test]
     fn test_export_archive_action_string() {
         assert_eq!(
@@ -514,4 +1268,126 @@ mod tests {
             "-exportArchive"
         );
     }
+
+    #[test]
+    fn test_xcodebuild_invocation_passes_values_as_single_arguments() {
+        let target = BuildTarget::new(Some(&"My Project/My App.xcodeproj".to_string()), None);
+        let params = XcodebuildParams::new(XcodebuildCommandAction::Archive)
+            .with_scheme("My \"Quoted\" Scheme".to_string())
+            .with_destination("platform=iOS Simulator,name=iPhone 15 Pro".parse().unwrap())
+            .with_target(target)
+            .with_archive_path("build/My App $1.xcarchive".to_string());
+        let invocation = params.make_xcodebuild_invocation().unwrap();
+
+        assert_eq!(invocation.program(), "xcodebuild");
+        assert_eq!(
+            invocation.args(),
+            [
+                "archive",
+                "-project",
+                "My Project/My App.xcodeproj",
+                "-scheme",
+                "My \"Quoted\" Scheme",
+                "-destination",
+                "platform=iOS Simulator,name=iPhone 15 Pro",
+                "-archivePath",
+                "build/My App $1.xcarchive",
+            ]
+        );
+    }
+
+    fn failed_output(stdout: &str) -> std::process::Output {
+        use std::os::unix::process::ExitStatusExt;
+
+        std::process::Output {
+            status: std::process::ExitStatus::from_raw(65 << 8),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_xcodebuild_failure_kind() {
+        assert_eq!(
+            xcodebuild_failure_kind(
+                &XcodebuildCommandAction::Build,
+                &failed_output("error: cannot find 'Foo' in scope\n** BUILD FAILED **")
+            ),
+            CommandErrorKind::BuildFailed
+        );
+        assert_eq!(
+            xcodebuild_failure_kind(
+                &XcodebuildCommandAction::Test,
+                &failed_output("Test Suite 'All tests' failed\n** TEST FAILED **")
+            ),
+            CommandErrorKind::TestsFailed
+        );
+        assert_eq!(
+            xcodebuild_failure_kind(
+                &XcodebuildCommandAction::Test,
+                &failed_output("Testing cancelled because the build failed.\n** TEST FAILED **")
+            ),
+            CommandErrorKind::BuildFailed
+        );
+        assert_eq!(
+            xcodebuild_failure_kind(
+                &XcodebuildCommandAction::BuildForTesting,
+                &failed_output("error: cannot find 'Foo' in scope\n** TEST BUILD FAILED **")
+            ),
+            CommandErrorKind::BuildFailed
+        );
+        assert_eq!(
+            xcodebuild_failure_kind(
+                &XcodebuildCommandAction::TestWithoutBuilding,
+                &failed_output("Test Suite 'All tests' failed\n** TEST EXECUTE FAILED **")
+            ),
+            CommandErrorKind::TestsFailed
+        );
+        assert_eq!(
+            xcodebuild_failure_kind(
+                &XcodebuildCommandAction::Archive,
+                &failed_output("error: No signing certificate \"iOS Distribution\" found")
+            ),
+            CommandErrorKind::SigningFailed
+        );
+    }
+
+    #[test]
+    fn test_xcodebuild_invocation_appends_build_settings_last() {
+        let target = BuildTarget::new(Some(&"MyApp.xcodeproj".to_string()), None);
+        let overrides = BuildOverrides::new()
+            .with_build_setting("CODE_SIGNING_ALLOWED=NO".parse().unwrap())
+            .with_build_setting(
+                "SWIFT_ACTIVE_COMPILATION_CONDITIONS=DEBUG CI"
+                    .parse()
+                    .unwrap(),
+            );
+        let params = XcodebuildParams::new(XcodebuildCommandAction::Build)
+            .with_scheme("MyApp".to_string())
+            .with_target(target)
+            .with_configuration(Configuration::Release)
+            .with_overrides(overrides);
+
+        assert_eq!(
+            params.make_xcodebuild_invocation().unwrap().to_string(),
+            "xcodebuild build -project MyApp.xcodeproj -scheme MyApp -configuration Release \
+             CODE_SIGNING_ALLOWED=NO 'SWIFT_ACTIVE_COMPILATION_CONDITIONS=DEBUG CI'"
+        );
+    }
+
+    #[test]
+    fn test_xcodebuild_invocation_fails_for_missing_xcconfig() {
+        let target = BuildTarget::new(Some(&"MyApp.xcodeproj".to_string()), None);
+        let params = XcodebuildParams::new(XcodebuildCommandAction::Build)
+            .with_target(target)
+            .with_overrides(BuildOverrides::new().with_xcconfig("Missing.xcconfig".to_string()));
+
+        assert!(
+            params
+                .make_xcodebuild_invocation()
+                .unwrap_err()
+                .to_string()
+                .contains("xcconfig file not found: Missing.xcconfig")
+        );
+    }
 }
@@ -0,0 +1,164 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{
+    BuildTarget, CommandError, CommandErrorKind, CommandResult, Invocation, StreamOptions,
+    log_file_for, retry_policy, stream_invocation,
+};
+
+/// The action reported in errors and retry messages for package resolution.
+const RESOLVE_ACTION: &str = "xcodebuild -resolvePackageDependencies";
+
+/// The Swift packages reported by `xcodebuild -resolvePackageDependencies`.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::PackageResolution;
+///
+/// let resolution = PackageResolution::parse(
+///     "Resolved source packages:\n  \
+///        Alamofire: https://github.com/Alamofire/Alamofire.git @ 5.9.1\n",
+/// );
+///
+/// assert_eq!(resolution.packages[0].name, "Alamofire");
+/// assert_eq!(resolution.packages[0].version, "5.9.1");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PackageResolution {
+    pub packages: Vec<ResolvedPackage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResolvedPackage {
+    pub name: String,
+    pub url: String,
+    /// A version such as `5.9.1`, or the branch or revision the package is pinned to.
+    pub version: String,
+}
+
+impl PackageResolution {
+    /// Reads the `Resolved source packages:` section of xcodebuild's output. Output without
+    /// that section, such as a dry run's, yields no packages.
+    pub fn parse(output: &str) -> Self {
+        let packages = output
+            .lines()
+            .skip_while(|line| line.trim() != "Resolved source packages:")
+            .skip(1)
+            .take_while(|line| !line.trim().is_empty())
+            .filter_map(|line| {
+                let (name, rest) = line.trim().split_once(": ")?;
+                let (url, version) = rest.rsplit_once(" @ ")?;
+                Some(ResolvedPackage {
+                    name: name.to_string(),
+                    url: url.to_string(),
+                    version: version.to_string(),
+                })
+            })
+            .collect();
+
+        Self { packages }
+    }
+}
+
+impl std::fmt::Display for PackageResolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Resolved {} package(s)", self.packages.len())?;
+        for package in &self.packages {
+            writeln!(
+                f,
+                "  {} {} ({})",
+                package.name, package.version, package.url
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CommandResult for PackageResolution {}
+
+impl BuildTarget {
+    /// Resolves the Swift package dependencies of the project or workspace with
+    /// `xcodebuild -resolvePackageDependencies`.
+    ///
+    /// Fetching packages is the step of a build most exposed to network failures, so it is
+    /// retried with [`retry_policy`] when git or the package registry reports a transient error.
+    /// Output is streamed to the terminal and appended to the run's log file.
+    pub fn resolve_package_dependencies(&self) -> Result<PackageResolution> {
+        let invocation = self.resolve_package_dependencies_invocation()?;
+        let log_file = log_file_for("resolve-packages");
+
+        retry_policy()
+            .run(RESOLVE_ACTION, || {
+                let output = stream_invocation(
+                    &invocation,
+                    &StreamOptions::new().with_log_file(log_file.clone()),
+                )?;
+                if !output.status.success() {
+                    return Err(CommandError::from_output(
+                        CommandErrorKind::Failed,
+                        RESOLVE_ACTION,
+                        &invocation,
+                        &output,
+                    )
+                    .into());
+                }
+
+                String::from_utf8(output.stdout).context("Failed to decode xcodebuild output")
+            })
+            .map(|output| PackageResolution::parse(&output))
+    }
+
+    fn resolve_package_dependencies_invocation(&self) -> Result<Invocation> {
+        Ok(Invocation::new("xcodebuild")
+            .with_arg("-resolvePackageDependencies")
+            .with_args(self.project_or_workspace_arguments()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_package_resolution() {
+        let output = "Command line invocation:\n    xcodebuild -resolvePackageDependencies\n\n\
+                      Resolve Package Graph\n\n\
+                      Resolved source packages:\n  \
+                      Alamofire: https://github.com/Alamofire/Alamofire.git @ 5.9.1\n  \
+                      SnapshotKit: git@github.com:acme/snapshot-kit.git @ main\n\n\
+                      resolved source packages: Alamofire, SnapshotKit\n";
+
+        assert_eq!(
+            PackageResolution::parse(output).packages,
+            [
+                ResolvedPackage {
+                    name: String::from("Alamofire"),
+                    url: String::from("https://github.com/Alamofire/Alamofire.git"),
+                    version: String::from("5.9.1"),
+                },
+                ResolvedPackage {
+                    name: String::from("SnapshotKit"),
+                    url: String::from("git@github.com:acme/snapshot-kit.git"),
+                    version: String::from("main"),
+                },
+            ]
+        );
+        assert!(PackageResolution::parse("").packages.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_package_dependencies_invocation() {
+        let workspace = String::from("My App.xcworkspace");
+        let target = BuildTarget::new(None, Some(&workspace));
+
+        assert_eq!(
+            target
+                .resolve_package_dependencies_invocation()
+                .unwrap()
+                .to_string(),
+            "xcodebuild -resolvePackageDependencies -workspace 'My App.xcworkspace'"
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Returns the build configuration names declared in an `.xcodeproj` bundle, in declaration
+/// order and without duplicates.
+///
+/// Reads `<project>/project.pbxproj` and collects the `name` of every `XCBuildConfiguration`,
+/// so configurations that only exist on a target are included as well.
+pub fn project_configurations(project: &Path) -> Result<Vec<String>> {
+    let pbxproj = project.join("project.pbxproj");
+    let contents = std::fs::read_to_string(&pbxproj)
+        .with_context(|| format!("Failed to read {}", pbxproj.display()))?;
+
+    Ok(parse_project_configurations(&contents))
+}
+
+/// Extracts the configuration names from the contents of a `project.pbxproj` file.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::parse_project_configurations;
+///
+/// let pbxproj = r#"
+/// /* Begin XCBuildConfiguration section */
+///         1A /* Debug */ = {
+///             isa = XCBuildConfiguration;
+///             buildSettings = {
+///                 PRODUCT_NAME = "$(TARGET_NAME)";
+///             };
+///             name = Debug;
+///         };
+///         1B /* App Store */ = {
+///             isa = XCBuildConfiguration;
+///             buildSettings = {
+///             };
+///             name = "App Store";
+///         };
+/// /* End XCBuildConfiguration section */
+/// "#;
+///
+/// assert_eq!(parse_project_configurations(pbxproj), ["Debug", "App Store"]);
+/// ```
+pub fn parse_project_configurations(pbxproj: &str) -> Vec<String> {
+    let mut configurations: Vec<String> = Vec::new();
+    let mut in_section = false;
+
+    for line in pbxproj.lines().map(str::trim) {
+        if line == "/* Begin XCBuildConfiguration section */" {
+            in_section = true;
+        } else if line == "/* End XCBuildConfiguration section */" {
+            in_section = false;
+        } else if in_section
+            && let Some(name) = line
+                .strip_prefix("name = ")
+                .and_then(|rest| rest.strip_suffix(';'))
+        {
+            let name = unquote(name);
+            if !configurations.contains(&name) {
+                configurations.push(name);
+            }
+        }
+    }
+
+    configurations
+}
+
+/// Returns the `.xcodeproj` bundles referenced by an `.xcworkspace`, resolved relative to the
+/// workspace's directory. References to projects that do not exist on disk are skipped.
+pub fn workspace_projects(workspace: &Path) -> Result<Vec<PathBuf>> {
+    let contents_path = workspace.join("contents.xcworkspacedata");
+    let contents = std::fs::read_to_string(&contents_path)
+        .with_context(|| format!("Failed to read {}", contents_path.display()))?;
+    let base = workspace.parent().unwrap_or_else(|| Path::new(""));
+
+    Ok(contents
+        .split("location = \"")
+        .skip(1)
+        .filter_map(|rest| rest.split('"').next())
+        .filter_map(|location| {
+            location
+                .strip_prefix("group:")
+                .or_else(|| location.strip_prefix("container:"))
+                .or_else(|| location.strip_prefix("absolute:"))
+        })
+        .filter(|location| location.ends_with(".xcodeproj"))
+        .map(|location| base.join(location))
+        .filter(|project| project.is_dir())
+        .collect())
+}
+
+fn unquote(value: &str) -> String {
+    match value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        Some(quoted) => quoted.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE_PROJECT: &str = "../xctools_cli/TestXcodeApp/TestXcodeApp.xcodeproj";
+
+    #[test]
+    fn test_project_configurations_reads_fixture_project() {
+        let configurations = project_configurations(Path::new(FIXTURE_PROJECT)).unwrap();
+
+        assert_eq!(configurations, ["Debug", "Release"]);
+    }
+
+    #[test]
+    fn test_project_configurations_missing_project() {
+        let error = project_configurations(Path::new("Missing.xcodeproj")).unwrap_err();
+
+        assert!(
+            error
+                .to_string()
+                .contains("Failed to read Missing.xcodeproj/project.pbxproj")
+        );
+    }
+
+    #[test]
+    fn test_parse_project_configurations_ignores_names_outside_build_configurations() {
+        let pbxproj = "\
+/* Begin PBXGroup section */
+\t\t1A = {
+\t\t\tisa = PBXGroup;
+\t\t\tname = Products;
+\t\t};
+/* End PBXGroup section */
+/* Begin XCBuildConfiguration section */
+\t\t2A /* Staging */ = {
+\t\t\tisa = XCBuildConfiguration;
+\t\t\tname = Staging;
+\t\t};
+\t\t2B /* Staging */ = {
+\t\t\tisa = XCBuildConfiguration;
+\t\t\tname = Staging;
+\t\t};
+\t\t2C /* Beta */ = {
+\t\t\tisa = XCBuildConfiguration;
+\t\t\tname = Beta;
+\t\t};
+/* End XCBuildConfiguration section */
+";
+
+        assert_eq!(parse_project_configurations(pbxproj), ["Staging", "Beta"]);
+    }
+
+    #[test]
+    fn test_workspace_projects_resolves_group_references() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = temp_dir.path().join("MyApp.xcworkspace");
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("App/MyApp.xcodeproj")).unwrap();
+        std::fs::write(
+            workspace.join("contents.xcworkspacedata"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Workspace
+   version = "1.0">
+   <FileRef
+      location = "group:App/MyApp.xcodeproj">
+   </FileRef>
+   <FileRef
+      location = "group:Pods/Pods.xcodeproj">
+   </FileRef>
+   <FileRef
+      location = "group:README.md">
+   </FileRef>
+</Workspace>
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            workspace_projects(&workspace).unwrap(),
+            [temp_dir.path().join("App/MyApp.xcodeproj")]
+        );
+    }
+}
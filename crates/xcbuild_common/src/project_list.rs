@@ -0,0 +1,218 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{BuildTarget, CommandError, CommandErrorKind};
+
+/// The schemes, targets and configurations reported by `xcodebuild -list -json`.
+///
+/// Workspaces only report their schemes, so `targets` and `configurations` are empty for them.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::ProjectList;
+///
+/// let list = ProjectList::parse(r#"{
+///   "project": {
+///     "configurations": ["Debug", "Release"],
+///     "name": "MyApp",
+///     "schemes": ["MyApp", "MyAppTests"],
+///     "targets": ["MyApp", "MyAppTests"]
+///   }
+/// }"#).unwrap();
+///
+/// assert_eq!(list.name, "MyApp");
+/// assert_eq!(list.schemes, ["MyApp", "MyAppTests"]);
+/// assert_eq!(list.suggest_scheme("myap"), Some("MyApp"));
+/// assert!(list.check_scheme("MyAppTest").is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ProjectList {
+    pub name: String,
+    #[serde(default)]
+    pub schemes: Vec<String>,
+    #[serde(default)]
+    pub targets: Vec<String>,
+    #[serde(default)]
+    pub configurations: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct XcodebuildList {
+    project: Option<ProjectList>,
+    workspace: Option<ProjectList>,
+}
+
+impl ProjectList {
+    /// Parses the output of `xcodebuild -list -json` for a project or a workspace. Anything
+    /// xcodebuild prints before the JSON document, such as package resolution messages, is
+    /// ignored.
+    pub fn parse(json: &str) -> Result<Self> {
+        let start = json.find('{').unwrap_or(0);
+        let list: XcodebuildList = serde_json::from_str(&json[start..])
+            .context("Failed to parse `xcodebuild -list -json` output")?;
+
+        list.project
+            .or(list.workspace)
+            .context("`xcodebuild -list -json` output has neither a project nor a workspace")
+    }
+
+    /// Fails with a "did you mean" hint when `scheme` is not one of the listed schemes.
+    pub fn check_scheme(&self, scheme: &str) -> Result<()> {
+        if self.schemes.iter().any(|known| known == scheme) {
+            return Ok(());
+        }
+
+        let suggestion = self
+            .suggest_scheme(scheme)
+            .map(|suggestion| format!(" (did you mean '{}'?)", suggestion))
+            .unwrap_or_default();
+        anyhow::bail!(
+            "Scheme '{}' not found in {}{}; available schemes: {}",
+            scheme,
+            self.name,
+            suggestion,
+            self.schemes.join(", ")
+        )
+    }
+
+    /// Returns the listed scheme closest to `scheme`: a case-insensitive match, or otherwise the
+    /// scheme with the smallest edit distance, provided it is close enough to be a likely typo.
+    pub fn suggest_scheme(&self, scheme: &str) -> Option<&str> {
+        let wanted = scheme.to_lowercase();
+        self.schemes
+            .iter()
+            .map(|known| (edit_distance(&wanted, &known.to_lowercase()), known))
+            .filter(|(distance, known)| *distance <= (known.chars().count() / 3).max(2))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, known)| known.as_str())
+    }
+}
+
+impl BuildTarget {
+    /// Lists the schemes, targets and configurations of the project or workspace with
+    /// `xcodebuild -list -json`.
+    ///
+    /// Listing has no side effects, so it runs even in dry-run mode.
+    pub fn list(&self) -> Result<ProjectList> {
+        let invocation = crate::Invocation::new("xcodebuild")
+            .with_args(["-list", "-json"])
+            .with_args(self.project_or_workspace_arguments()?);
+        let output = crate::runner::run_query(&invocation)?;
+        if !output.status.success() {
+            return Err(CommandError::from_output(
+                CommandErrorKind::Failed,
+                "xcodebuild -list",
+                &invocation,
+                &output,
+            )
+            .into());
+        }
+
+        ProjectList::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+/// Levenshtein distance between two strings, counted in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECT_LIST: &str = include_str!("../tests/fixtures/xcodebuild_list_project.json");
+    const WORKSPACE_LIST: &str = include_str!("../tests/fixtures/xcodebuild_list_workspace.json");
+
+    #[test]
+    fn test_parse_project_list() {
+        let list = ProjectList::parse(PROJECT_LIST).unwrap();
+
+        assert_eq!(
+            list,
+            ProjectList {
+                name: String::from("TestXcodeApp"),
+                schemes: vec![String::from("TestXcodeApp")],
+                targets: vec![
+                    String::from("TestXcodeApp"),
+                    String::from("TestXcodeAppTests"),
+                    String::from("TestXcodeAppUITests"),
+                ],
+                configurations: vec![String::from("Debug"), String::from("Release")],
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_workspace_list_skips_leading_output() {
+        let list = ProjectList::parse(WORKSPACE_LIST).unwrap();
+
+        assert_eq!(list.name, "MyApp");
+        assert_eq!(
+            list.schemes,
+            ["MyApp", "MyApp Staging", "MyAppKit", "Alamofire"]
+        );
+        assert!(list.targets.is_empty());
+        assert!(list.configurations.is_empty());
+    }
+
+    #[test]
+    fn test_parse_rejects_unexpected_output() {
+        let error = ProjectList::parse("{\"other\": {}}").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("neither a project nor a workspace")
+        );
+
+        let error = ProjectList::parse("xcodebuild: error: nope").unwrap_err();
+        assert!(error.to_string().contains("Failed to parse"));
+    }
+
+    #[test]
+    fn test_suggest_scheme() {
+        let list = ProjectList::parse(WORKSPACE_LIST).unwrap();
+
+        assert_eq!(list.suggest_scheme("myapp"), Some("MyApp"));
+        assert_eq!(list.suggest_scheme("MyAppKt"), Some("MyAppKit"));
+        assert_eq!(list.suggest_scheme("MyApp Stagin"), Some("MyApp Staging"));
+        assert_eq!(list.suggest_scheme("Networking"), None);
+    }
+
+    #[test]
+    fn test_check_scheme() {
+        let list = ProjectList::parse(WORKSPACE_LIST).unwrap();
+
+        assert!(list.check_scheme("MyAppKit").is_ok());
+        assert_eq!(
+            list.check_scheme("MyAp").unwrap_err().to_string(),
+            "Scheme 'MyAp' not found in MyApp (did you mean 'MyApp'?); available schemes: \
+             MyApp, MyApp Staging, MyAppKit, Alamofire"
+        );
+        assert_eq!(
+            list.check_scheme("Networking").unwrap_err().to_string(),
+            "Scheme 'Networking' not found in MyApp; available schemes: \
+             MyApp, MyApp Staging, MyAppKit, Alamofire"
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("MyApp", "MyApp"), 0);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
@@ -0,0 +1,313 @@
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{CommandError, CommandErrorKind};
+
+/// Version of the JSON document printed by `--output-format json`. It is bumped whenever a field is
+/// removed or changes meaning; new fields may be added without a bump.
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+
+static OUTPUT_FORMAT: Mutex<OutputFormat> = Mutex::new(OutputFormat::Text);
+
+/// How xctools reports the outcome of a command on stdout.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable text; tool output is streamed to stdout
+    #[default]
+    Text,
+    /// A single versioned JSON document; tool output is streamed to stderr instead
+    Json,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Sets the output format. In [`OutputFormat::Json`] mode stdout is reserved for the report, so
+/// streamed tool output and dry-run command lines go to stderr.
+pub fn set_output_format(format: OutputFormat) {
+    *OUTPUT_FORMAT
+        .lock()
+        .unwrap_or_else(|error| error.into_inner()) = format;
+}
+
+/// Returns the format configured with [`set_output_format`].
+pub fn output_format() -> OutputFormat {
+    *OUTPUT_FORMAT
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+}
+
+/// The typed outcome of an xctools command: rendered with [`fmt::Display`] in text mode and
+/// serialized as the `result` of a [`Report`] in JSON mode.
+pub trait CommandResult: Serialize + fmt::Display {
+    /// Warnings reported by the tools the command ran, e.g. compiler warnings.
+    fn warnings(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Why the command failed even though it produced a result, e.g. a failed `doctor` check.
+    /// The result is reported all the same, and xctools exits with a non-zero code.
+    fn failure(&self) -> Option<String> {
+        None
+    }
+}
+
+/// A [`CommandResult`] rendered both ways, so that commands returning different result types
+/// can be reported uniformly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedResult {
+    pub text: String,
+    pub json: serde_json::Value,
+    pub warnings: Vec<String>,
+    pub failure: Option<String>,
+}
+
+impl RenderedResult {
+    pub fn new<T: CommandResult>(result: T) -> Result<Self> {
+        Ok(Self {
+            text: result.to_string(),
+            json: serde_json::to_value(&result).context("Failed to serialize command result")?,
+            warnings: result.warnings(),
+            failure: result.failure(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReportStatus {
+    Succeeded,
+    Failed,
+}
+
+/// The JSON document printed by `--output-format json`, for successful and failed commands alike.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use xcbuild_common::{CommandError, CommandErrorKind, Invocation, Report};
+///
+/// let error = CommandError::new(
+///     CommandErrorKind::BuildFailed,
+///     "build",
+///     &Invocation::new("xcodebuild").with_arg("build"),
+/// )
+/// .with_exit_code(Some(65));
+/// let report = Report::failed("build", Duration::from_millis(1500), &error.into());
+/// let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+///
+/// assert_eq!(json["schemaVersion"], 1);
+/// assert_eq!(json["status"], "failed");
+/// assert_eq!(json["durationSeconds"], 1.5);
+/// assert_eq!(json["error"]["kind"], "buildFailed");
+/// assert_eq!(json["error"]["exitCode"], 65);
+/// assert_eq!(json["result"], serde_json::Value::Null);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub schema_version: u32,
+    /// The subcommand, e.g. `build` or `simulator boot`.
+    pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub xctools_version: Option<String>,
+    pub status: ReportStatus,
+    pub duration_seconds: f64,
+    /// The command's [`CommandResult`], or `null` when it failed.
+    pub result: serde_json::Value,
+    pub warnings: Vec<String>,
+    pub error: Option<ReportError>,
+}
+
+/// Why a command failed. The fields other than `message` are only set when an external tool
+/// failed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportError {
+    pub message: String,
+    pub kind: Option<CommandErrorKind>,
+    /// The redacted command line of the tool that failed.
+    pub invocation: Option<String>,
+    /// The tool's exit code, which differs from the exit code of xctools itself.
+    pub exit_code: Option<i32>,
+    pub output_tail: Vec<String>,
+}
+
+impl Report {
+    pub fn succeeded(command: &str, duration: Duration, result: RenderedResult) -> Self {
+        Self {
+            schema_version: REPORT_SCHEMA_VERSION,
+            command: command.to_string(),
+            xctools_version: None,
+            status: ReportStatus::Succeeded,
+            duration_seconds: seconds(duration),
+            result: result.json,
+            warnings: result.warnings,
+            error: None,
+        }
+    }
+
+    pub fn failed(command: &str, duration: Duration, error: &anyhow::Error) -> Self {
+        let command_error = error.downcast_ref::<CommandError>();
+        Self {
+            schema_version: REPORT_SCHEMA_VERSION,
+            command: command.to_string(),
+            xctools_version: None,
+            status: ReportStatus::Failed,
+            duration_seconds: seconds(duration),
+            result: serde_json::Value::Null,
+            warnings: Vec::new(),
+            error: Some(ReportError {
+                message: format!("{:#}", error),
+                kind: command_error.map(|command_error| command_error.kind),
+                invocation: command_error.map(|command_error| command_error.invocation.clone()),
+                exit_code: command_error.and_then(|command_error| command_error.exit_code),
+                output_tail: command_error
+                    .map(|command_error| command_error.output_tail.clone())
+                    .unwrap_or_default(),
+            }),
+        }
+    }
+
+    /// Adds the result of a command that failed although it produced one; see
+    /// [`CommandResult::failure`].
+    pub fn with_result(mut self, result: RenderedResult) -> Self {
+        self.result = result.json;
+        self.warnings = result.warnings;
+        self
+    }
+
+    pub fn with_xctools_version(mut self, version: &str) -> Self {
+        self.xctools_version = Some(version.to_string());
+        self
+    }
+
+    /// Renders the report as pretty-printed JSON followed by a newline.
+    pub fn to_json(&self) -> Result<String> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize report")?;
+
+        Ok(format!("{}\n", json))
+    }
+}
+
+/// Durations are reported in seconds, rounded to milliseconds.
+fn seconds(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1000.0).round() / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Bumped {
+        build_number: u32,
+        #[serde(skip)]
+        warnings: Vec<String>,
+    }
+
+    impl fmt::Display for Bumped {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            writeln!(f, "Build number set to: {}", self.build_number)
+        }
+    }
+
+    impl CommandResult for Bumped {
+        fn warnings(&self) -> Vec<String> {
+            self.warnings.clone()
+        }
+    }
+
+    #[test]
+    fn test_rendered_result() {
+        let rendered = RenderedResult::new(Bumped {
+            build_number: 42,
+            warnings: vec![String::from("deprecated")],
+        })
+        .unwrap();
+
+        assert_eq!(rendered.text, "Build number set to: 42\n");
+        assert_eq!(rendered.json, serde_json::json!({ "buildNumber": 42 }));
+        assert_eq!(rendered.warnings, ["deprecated"]);
+        assert_eq!(rendered.failure, None);
+    }
+
+    #[test]
+    fn test_succeeded_report() {
+        let rendered = RenderedResult::new(Bumped {
+            build_number: 7,
+            warnings: vec![String::from("App.swift:3:1: unused variable")],
+        })
+        .unwrap();
+
+        let report = Report::succeeded("bump-version", Duration::from_micros(2_345_678), rendered)
+            .with_xctools_version("0.1.0");
+
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "schemaVersion": 1,
+                "command": "bump-version",
+                "xctoolsVersion": "0.1.0",
+                "status": "succeeded",
+                "durationSeconds": 2.346,
+                "result": { "buildNumber": 7 },
+                "warnings": ["App.swift:3:1: unused variable"],
+                "error": null
+            })
+        );
+    }
+
+    #[test]
+    fn test_failed_report_without_command_error() {
+        let error = anyhow::anyhow!("No project.pbxproj found");
+
+        let report = Report::failed("bump-version", Duration::ZERO, &error);
+
+        assert_eq!(
+            report.error,
+            Some(ReportError {
+                message: String::from("No project.pbxproj found"),
+                kind: None,
+                invocation: None,
+                exit_code: None,
+                output_tail: Vec::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_failed_report_with_result() {
+        let rendered = RenderedResult::new(Bumped {
+            build_number: 7,
+            warnings: Vec::new(),
+        })
+        .unwrap();
+
+        let report = Report::failed("doctor", Duration::ZERO, &anyhow::anyhow!("1 check failed"))
+            .with_result(rendered);
+
+        assert_eq!(report.status, ReportStatus::Failed);
+        assert_eq!(report.result, serde_json::json!({ "buildNumber": 7 }));
+        assert_eq!(report.error.unwrap().message, "1 check failed");
+    }
+
+    #[test]
+    fn test_output_format_display() {
+        assert_eq!(OutputFormat::default().to_string(), "text");
+        assert_eq!(OutputFormat::Json.to_string(), "json");
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::{CommandError, CommandErrorKind, supervisor};
+
+/// Output fragments, matched case-insensitively, that identify a transient network or service
+/// failure worth retrying.
+const RETRYABLE_PATTERNS: &[&str] = &[
+    "timed out",
+    "network connection was lost",
+    "not connected to the internet",
+    "could not connect to the server",
+    "connection reset",
+    "connection refused",
+    "nsurlerrordomain",
+    "service unavailable",
+    "bad gateway",
+    "gateway timeout",
+    "internal server error",
+    "could not resolve host",
+    "the remote end hung up unexpectedly",
+    "early eof",
+];
+
+/// Markers that are followed by an HTTP status code in altool, notarytool and git output.
+const HTTP_STATUS_MARKERS: &[&str] = &["status code", "http/1.1 ", "http/2 ", "statuscode="];
+
+static RETRY_POLICY: Mutex<RetryPolicy> = Mutex::new(RetryPolicy::new(3));
+
+/// How network-bound steps such as uploads, notarization and package resolution are retried:
+/// up to `max_attempts` runs, waiting an exponentially growing, jittered delay between them.
+///
+/// Only failures classified by [`is_retryable`] are retried; a rejected password or an invalid
+/// package fails on the first attempt.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use xcbuild_common::RetryPolicy;
+///
+/// let policy = RetryPolicy::new(4)
+///     .with_initial_delay(Duration::from_secs(2))
+///     .with_max_delay(Duration::from_secs(5));
+///
+/// // Each delay is jittered between half and all of 2s, 4s, then 5s (capped).
+/// let third = policy.delay_before_attempt(4);
+/// assert!(third >= Duration::from_millis(2500) && third <= Duration::from_secs(5));
+///
+/// let mut attempts = 0;
+/// let result = RetryPolicy::new(3)
+///     .with_initial_delay(Duration::ZERO)
+///     .run("lookup", || {
+///         attempts += 1;
+///         Ok(attempts)
+///     });
+/// assert_eq!(result.unwrap(), 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// A policy making up to `max_attempts` attempts, starting with a 5 second delay that
+    /// doubles up to one minute.
+    pub const fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            initial_delay: Duration::from_secs(5),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+
+    pub fn with_initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// How long to wait before `attempt` (counted from 1): the initial delay doubled for every
+    /// earlier retry and capped at the maximum, then jittered to between half and all of it so
+    /// parallel CI jobs do not retry in lockstep.
+    pub fn delay_before_attempt(&self, attempt: u32) -> Duration {
+        let retries = attempt.saturating_sub(2).min(31);
+        let delay = self
+            .initial_delay
+            .saturating_mul(1 << retries)
+            .min(self.max_delay);
+
+        delay / 2 + delay.mul_f64(random_fraction() / 2.0)
+    }
+
+    /// Runs `operation` until it succeeds, fails with an error that is not retryable, or has
+    /// been attempted `max_attempts` times. Every failed attempt is reported on stderr.
+    pub fn run<T>(&self, action: &str, mut operation: impl FnMut() -> Result<T>) -> Result<T> {
+        let max_attempts = self.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            let error = match operation() {
+                Ok(value) => {
+                    if attempt > 1 {
+                        eprintln!(
+                            "{} succeeded on attempt {} of {}",
+                            action, attempt, max_attempts
+                        );
+                    }
+                    return Ok(value);
+                }
+                Err(error) => error,
+            };
+
+            let Some(reason) = retryable_reason(&error) else {
+                return Err(error);
+            };
+            if attempt >= max_attempts {
+                if max_attempts == 1 {
+                    return Err(error);
+                }
+                return Err(
+                    error.context(format!("{} failed after {} attempts", action, max_attempts))
+                );
+            }
+
+            attempt += 1;
+            let delay = self.delay_before_attempt(attempt);
+            eprintln!(
+                "{} failed on attempt {} of {} ({}); retrying in {:.1}s",
+                action,
+                attempt - 1,
+                max_attempts,
+                reason,
+                delay.as_secs_f64()
+            );
+            if !supervisor::sleep_unless_interrupted(delay) {
+                return Err(error.context(format!("Interrupted before retrying {}", action)));
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts with the default delays.
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+/// Sets the policy used by [`retry_policy`], e.g. from `xctools --max-attempts`.
+pub fn set_retry_policy(policy: RetryPolicy) {
+    *RETRY_POLICY
+        .lock()
+        .unwrap_or_else(|error| error.into_inner()) = policy;
+}
+
+/// Returns the policy network-bound steps are retried with; 3 attempts unless changed with
+/// [`set_retry_policy`].
+pub fn retry_policy() -> RetryPolicy {
+    *RETRY_POLICY
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+}
+
+/// Whether `error` is a transient failure worth retrying: a [`CommandError`] of kind
+/// [`CommandErrorKind::Failed`] whose output mentions a network problem or an HTTP 5xx status.
+///
+/// Timeouts set with `--timeout`, interrupts and missing tools are never retried.
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    retryable_reason(error).is_some()
+}
+
+/// The output line that made `error` retryable.
+fn retryable_reason(error: &anyhow::Error) -> Option<String> {
+    let command_error = error.downcast_ref::<CommandError>()?;
+    if command_error.kind != CommandErrorKind::Failed {
+        return None;
+    }
+
+    command_error
+        .output_tail
+        .iter()
+        .find(|line| is_retryable_line(line))
+        .map(|line| line.trim().to_string())
+}
+
+fn is_retryable_line(line: &str) -> bool {
+    let line = line.to_lowercase();
+    RETRYABLE_PATTERNS
+        .iter()
+        .any(|pattern| line.contains(pattern))
+        || HTTP_STATUS_MARKERS.iter().any(|marker| {
+            line.match_indices(marker).any(|(index, _)| {
+                let status: String = line[index + marker.len()..]
+                    .trim_start_matches([':', ' '])
+                    .chars()
+                    .take_while(char::is_ascii_digit)
+                    .collect();
+                status.len() == 3 && status.starts_with('5')
+            })
+        })
+}
+
+/// A random number in `[0, 1)`, good enough for spreading out retries.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Invocation;
+
+    fn failure(output: &str) -> anyhow::Error {
+        CommandError::new(
+            CommandErrorKind::Failed,
+            "xcrun altool --upload-app",
+            &Invocation::new("xcrun").with_args(["altool", "--upload-app"]),
+        )
+        .with_exit_code(Some(1))
+        .with_output("", output)
+        .into()
+    }
+
+    fn immediate(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy::new(max_attempts).with_initial_delay(Duration::ZERO)
+    }
+
+    #[test]
+    fn test_is_retryable_matches_network_failures() {
+        for output in [
+            "Error: The request timed out.",
+            "*** Error: The network connection was lost. (-1005)",
+            "Error Domain=NSURLErrorDomain Code=-1009",
+            "Server returned HTTP status code: 503",
+            "HTTP/1.1 502 Bad Gateway",
+            "fatal: unable to access 'https://github.com/a/b.git/': Could not resolve host: github.com",
+        ] {
+            assert!(is_retryable(&failure(output)), "{}", output);
+        }
+    }
+
+    #[test]
+    fn test_is_retryable_rejects_other_failures() {
+        for output in [
+            "Error: Unable to upload archive. Invalid username and password (401)",
+            "Server returned HTTP status code: 404",
+            "Error: Package validation failed",
+        ] {
+            assert!(!is_retryable(&failure(output)), "{}", output);
+        }
+
+        let invocation = Invocation::new("xcrun");
+        let timed_out = CommandError::new(CommandErrorKind::TimedOut, "xcrun", &invocation)
+            .with_output("", "The request timed out.");
+        assert!(!is_retryable(&timed_out.into()));
+        assert!(!is_retryable(
+            &CommandError::tool_missing(&invocation).into()
+        ));
+        assert!(!is_retryable(&anyhow::anyhow!(
+            "network connection was lost"
+        )));
+    }
+
+    #[test]
+    fn test_run_retries_retryable_failures() {
+        let mut attempts = 0;
+
+        let result = immediate(3).run("upload", || {
+            attempts += 1;
+            if attempts < 3 {
+                Err(failure("The network connection was lost."))
+            } else {
+                Ok("uploaded")
+            }
+        });
+
+        assert_eq!(result.unwrap(), "uploaded");
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_run_gives_up_after_max_attempts() {
+        let mut attempts = 0;
+
+        let error = immediate(2)
+            .run("upload", || -> Result<()> {
+                attempts += 1;
+                Err(failure("HTTP status code: 500"))
+            })
+            .unwrap_err();
+
+        assert_eq!(attempts, 2);
+        assert_eq!(error.to_string(), "upload failed after 2 attempts");
+        assert!(error.downcast_ref::<CommandError>().is_some());
+    }
+
+    #[test]
+    fn test_run_does_not_retry_other_failures() {
+        let mut attempts = 0;
+
+        let error = immediate(3)
+            .run("upload", || -> Result<()> {
+                attempts += 1;
+                Err(failure("Invalid username and password"))
+            })
+            .unwrap_err();
+
+        assert_eq!(attempts, 1);
+        assert!(error.downcast_ref::<CommandError>().is_some());
+    }
+
+    #[test]
+    fn test_delay_grows_exponentially_up_to_max() {
+        let policy = RetryPolicy::new(10)
+            .with_initial_delay(Duration::from_secs(4))
+            .with_max_delay(Duration::from_secs(20));
+
+        for (attempt, full) in [(2, 4), (3, 8), (4, 16), (5, 20), (10, 20)] {
+            let delay = policy.delay_before_attempt(attempt);
+            let full = Duration::from_secs(full);
+            assert!(delay >= full / 2 && delay <= full, "{:?}", delay);
+        }
+    }
+}
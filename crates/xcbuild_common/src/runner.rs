@@ -0,0 +1,232 @@
+use std::io::Write;
+use std::process::{ExitStatus, Output};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+
+use crate::{Invocation, StreamOptions, invocation, stream};
+
+/// Executes [`Invocation`]s on behalf of every xctools crate.
+///
+/// [`crate::run_invocation`] and [`crate::stream_invocation`] delegate to the runner installed
+/// for the whole process with [`set_command_runner`], which defaults to [`ProcessRunner`].
+/// Swapping in a [`DryRunRunner`] lets the CLI show exactly which commands would run without
+/// touching the machine, and lets tests observe invocations without Xcode installed.
+pub trait CommandRunner: Send + Sync {
+    /// Runs the invocation to completion and returns its captured output.
+    fn run(&self, invocation: &Invocation) -> Result<Output>;
+
+    /// Runs the invocation while streaming its output to the terminal and log file described by
+    /// `options`, returning the captured output once it exits.
+    fn stream(&self, invocation: &Invocation, options: &StreamOptions) -> Result<Output>;
+
+    /// Returns `true` when invocations are only reported and side effects must be skipped.
+    fn is_dry_run(&self) -> bool {
+        false
+    }
+}
+
+/// Runs invocations as child processes. This is the default runner.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProcessRunner;
+
+impl CommandRunner for ProcessRunner {
+    fn run(&self, invocation: &Invocation) -> Result<Output> {
+        invocation::run_process(invocation)
+    }
+
+    fn stream(&self, invocation: &Invocation, options: &StreamOptions) -> Result<Output> {
+        stream::stream_process(invocation, options)
+    }
+}
+
+/// Prints each invocation as a copy-pasteable command line, with secrets redacted, instead of
+/// running it. Every invocation reports a successful exit with empty output.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+/// use xcbuild_common::{CommandRunner, DryRunRunner, Invocation};
+///
+/// let printed = Arc::new(Mutex::new(Vec::new()));
+/// let runner = DryRunRunner::with_writer(SharedWriter(printed.clone()));
+/// let output = runner
+///     .run(&Invocation::new("xcrun").with_args(["stapler", "staple", "My App.dmg"]))
+///     .unwrap();
+///
+/// assert!(output.status.success());
+/// assert_eq!(
+///     String::from_utf8(printed.lock().unwrap().clone()).unwrap(),
+///     "xcrun stapler staple 'My App.dmg'\n"
+/// );
+/// # struct SharedWriter(Arc<Mutex<Vec<u8>>>);
+/// # impl std::io::Write for SharedWriter {
+/// #     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> { self.0.lock().unwrap().write(buf) }
+/// #     fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+/// # }
+/// ```
+pub struct DryRunRunner {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl DryRunRunner {
+    /// Creates a dry-run runner that prints to stdout.
+    pub fn new() -> Self {
+        Self::with_writer(std::io::stdout())
+    }
+
+    pub fn with_writer<W: Write + Send + 'static>(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+
+    fn report(&self, invocation: &Invocation) -> Result<Output> {
+        let mut writer = self
+            .writer
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        let invocation =
+            crate::xcode::with_developer_dir(invocation, crate::developer_dir().as_deref());
+        writeln!(writer, "{}", invocation).context("Failed to print dry-run invocation")?;
+        writer
+            .flush()
+            .context("Failed to print dry-run invocation")?;
+
+        Ok(Output {
+            status: ExitStatus::default(),
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        })
+    }
+}
+
+impl Default for DryRunRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandRunner for DryRunRunner {
+    fn run(&self, invocation: &Invocation) -> Result<Output> {
+        self.report(invocation)
+    }
+
+    fn stream(&self, invocation: &Invocation, _options: &StreamOptions) -> Result<Output> {
+        self.report(invocation)
+    }
+
+    fn is_dry_run(&self) -> bool {
+        true
+    }
+}
+
+/// The runner installed with [`set_command_runner`]; `None` runs [`ProcessRunner`].
+static COMMAND_RUNNER: Mutex<Option<Arc<dyn CommandRunner>>> = Mutex::new(None);
+
+/// Installs the runner used by [`crate::run_invocation`] and [`crate::stream_invocation`] for
+/// the whole process, including work done on other threads.
+pub fn set_command_runner(runner: Arc<dyn CommandRunner>) {
+    *COMMAND_RUNNER
+        .lock()
+        .unwrap_or_else(|error| error.into_inner()) = Some(runner);
+}
+
+/// Returns the runner installed with [`set_command_runner`].
+pub fn command_runner() -> Arc<dyn CommandRunner> {
+    COMMAND_RUNNER
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+        .clone()
+        .unwrap_or_else(|| Arc::new(ProcessRunner))
+}
+
+/// Runs a read-only query such as `xcrun simctl list` and returns its captured output.
+///
+/// Queries have no side effects, so they run as real processes even in dry-run mode, where their
+/// results are needed to print the invocations that would follow.
+pub fn run_query(invocation: &Invocation) -> Result<Output> {
+    let runner = command_runner();
+    if runner.is_dry_run() {
+        ProcessRunner.run(invocation)
+    } else {
+        runner.run(invocation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run_invocation;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_default_runner_is_process_runner() {
+        assert!(!command_runner().is_dry_run());
+        let output = run_invocation(&Invocation::new("echo").with_arg("real")).unwrap();
+
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "real\n");
+    }
+
+    #[test]
+    fn test_dry_run_runner_reports_redacted_invocations_without_running_them() {
+        let printed = SharedBuffer::default();
+        let runner = DryRunRunner::with_writer(printed.clone());
+        let temp_dir = tempfile::tempdir().unwrap();
+        let marker = temp_dir.path().join("marker");
+        let log_file = temp_dir.path().join("run.log");
+
+        // The runner is used directly: installing it would switch every test of this process,
+        // running on other threads, to dry-run mode.
+        let output = runner
+            .run(&Invocation::new("touch").with_arg(marker.to_string_lossy().to_string()))
+            .unwrap();
+        runner
+            .stream(
+                &Invocation::new("xcrun")
+                    .with_args(["altool", "-p"])
+                    .with_secret_arg("hunter2"),
+                &StreamOptions::new().with_log_file(log_file.clone()),
+            )
+            .unwrap();
+
+        assert!(output.status.success());
+        assert!(output.stdout.is_empty());
+        assert!(!marker.exists());
+        assert!(!log_file.exists());
+        assert_eq!(
+            String::from_utf8(printed.0.lock().unwrap().clone()).unwrap(),
+            format!(
+                "touch {}\nxcrun altool -p '********'\n",
+                marker.to_string_lossy()
+            )
+        );
+    }
+
+    #[test]
+    fn test_dry_run_runner_reports_missing_programs_as_successful() {
+        let runner = DryRunRunner::with_writer(std::io::sink());
+
+        assert!(runner.is_dry_run());
+        assert!(
+            runner
+                .run(&Invocation::new("xctools-nonexistent-program-12345"))
+                .unwrap()
+                .status
+                .success()
+        );
+    }
+}
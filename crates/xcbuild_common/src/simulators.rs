@@ -0,0 +1,656 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{CommandError, CommandErrorKind, Destination, Invocation};
+
+/// A simulator runtime reported by `xcrun simctl list`, such as iOS 18.2.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatorRuntime {
+    pub identifier: String,
+    pub name: String,
+    /// Platform name (`iOS`, `tvOS`, `watchOS`, `visionOS`). Older Xcodes omit it from the JSON,
+    /// in which case it is taken from the runtime name.
+    #[serde(default)]
+    pub platform: String,
+    pub version: String,
+    #[serde(default)]
+    pub is_available: bool,
+}
+
+/// A simulator device reported by `xcrun simctl list`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Simulator {
+    pub udid: String,
+    pub name: String,
+    /// `Shutdown`, `Booted`, `Creating`, ...
+    pub state: String,
+    #[serde(default)]
+    pub is_available: bool,
+    #[serde(default)]
+    pub device_type_identifier: Option<String>,
+    /// Identifier of the [`SimulatorRuntime`] the device runs.
+    #[serde(default)]
+    pub runtime_identifier: String,
+}
+
+impl Simulator {
+    pub fn is_booted(&self) -> bool {
+        self.state == "Booted"
+    }
+
+    /// Returns the destination naming this device by UDID on `runtime`'s simulator platform.
+    pub fn destination(&self, runtime: &SimulatorRuntime) -> Destination {
+        Destination::new()
+            .with_platform(&format!("{} Simulator", runtime.platform))
+            .with_id(&self.udid)
+    }
+}
+
+#[derive(Deserialize)]
+struct SimctlList {
+    #[serde(default)]
+    runtimes: Vec<SimulatorRuntime>,
+    #[serde(default)]
+    devices: BTreeMap<String, Vec<Simulator>>,
+}
+
+/// The runtimes and devices known to `simctl`.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::{SimulatorList, SimulatorSelector};
+///
+/// let json = r#"{
+///   "runtimes": [
+///     { "identifier": "com.apple.CoreSimulator.SimRuntime.iOS-18-2", "name": "iOS 18.2",
+///       "platform": "iOS", "version": "18.2", "isAvailable": true }
+///   ],
+///   "devices": {
+///     "com.apple.CoreSimulator.SimRuntime.iOS-18-2": [
+///       { "udid": "3C8D2F01", "name": "iPhone 16", "state": "Shutdown", "isAvailable": true }
+///     ]
+///   }
+/// }"#;
+///
+/// let simulators = SimulatorList::parse(json).unwrap();
+/// let selector: SimulatorSelector = "iPhone latest".parse().unwrap();
+/// let destination = simulators.resolve_destination(&selector).unwrap();
+///
+/// assert_eq!(destination.to_string(), "platform=iOS Simulator,id=3C8D2F01");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimulatorList {
+    pub runtimes: Vec<SimulatorRuntime>,
+    /// Devices grouped by runtime, in the order of [`SimulatorList::runtimes`].
+    pub devices: Vec<Simulator>,
+}
+
+impl SimulatorList {
+    /// Parses the output of `xcrun simctl list --json`.
+    pub fn parse(json: &str) -> Result<Self> {
+        let list: SimctlList = serde_json::from_str(json)
+            .context("Failed to parse `xcrun simctl list --json` output")?;
+        let mut devices_by_runtime = list.devices;
+
+        let runtimes: Vec<SimulatorRuntime> = list
+            .runtimes
+            .into_iter()
+            .map(|mut runtime| {
+                if runtime.platform.is_empty() {
+                    runtime.platform = runtime
+                        .name
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string();
+                }
+                runtime
+            })
+            .collect();
+        let devices = runtimes
+            .iter()
+            .flat_map(|runtime| {
+                devices_by_runtime
+                    .remove(&runtime.identifier)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|mut device| {
+                        device.runtime_identifier = runtime.identifier.clone();
+                        device
+                    })
+            })
+            .collect();
+
+        Ok(Self { runtimes, devices })
+    }
+
+    pub fn runtime(&self, identifier: &str) -> Option<&SimulatorRuntime> {
+        self.runtimes
+            .iter()
+            .find(|runtime| runtime.identifier == identifier)
+    }
+
+    /// Returns the available devices together with their (available) runtimes.
+    pub fn available_devices(&self) -> impl Iterator<Item = (&Simulator, &SimulatorRuntime)> {
+        self.devices.iter().filter_map(|device| {
+            let runtime = self.runtime(&device.runtime_identifier)?;
+            (device.is_available && runtime.is_available).then_some((device, runtime))
+        })
+    }
+
+    /// Picks the device matching `selector`.
+    ///
+    /// Among the matching devices the one on the newest runtime wins; ties prefer an exact name
+    /// match, then a booted device, then the order `simctl` lists them in.
+    pub fn select(&self, selector: &SimulatorSelector) -> Result<(&Simulator, &SimulatorRuntime)> {
+        let mut best: Option<(&Simulator, &SimulatorRuntime)> = None;
+        for (device, runtime) in self.available_devices() {
+            if !selector.matches(device, runtime) {
+                continue;
+            }
+            let is_better = match best {
+                None => true,
+                Some((best_device, best_runtime)) => {
+                    selection_rank(selector, device, runtime)
+                        > selection_rank(selector, best_device, best_runtime)
+                }
+            };
+            if is_better {
+                best = Some((device, runtime));
+            }
+        }
+
+        best.ok_or_else(|| {
+            let available: Vec<String> = self
+                .available_devices()
+                .map(|(device, runtime)| format!("{} ({})", device.name, runtime.name))
+                .collect();
+            anyhow::anyhow!(
+                "No available simulator matches '{}'; available simulators: {}",
+                selector,
+                if available.is_empty() {
+                    String::from("none")
+                } else {
+                    available.join(", ")
+                }
+            )
+        })
+    }
+
+    /// Resolves `selector` to a destination naming the selected device by UDID.
+    pub fn resolve_destination(&self, selector: &SimulatorSelector) -> Result<Destination> {
+        let (device, runtime) = self.select(selector)?;
+
+        Ok(device.destination(runtime))
+    }
+
+    /// Finds a device by UDID or by exact (case-insensitive) name, including unavailable devices.
+    /// Fails if a name matches more than one device.
+    pub fn find_device(&self, device: &str) -> Result<&Simulator> {
+        if let Some(found) = self
+            .devices
+            .iter()
+            .find(|simulator| simulator.udid.eq_ignore_ascii_case(device))
+        {
+            return Ok(found);
+        }
+
+        let matches: Vec<&Simulator> = self
+            .devices
+            .iter()
+            .filter(|simulator| simulator.name.eq_ignore_ascii_case(device))
+            .collect();
+        match matches.as_slice() {
+            [found] => Ok(found),
+            [] => anyhow::bail!("No simulator is named or identified by '{}'", device),
+            _ => anyhow::bail!(
+                "Simulator name '{}' is ambiguous; use one of these UDIDs instead: {}",
+                device,
+                matches
+                    .iter()
+                    .map(|simulator| {
+                        let runtime = self
+                            .runtime(&simulator.runtime_identifier)
+                            .map_or("unknown runtime", |runtime| runtime.name.as_str());
+                        format!("{} ({})", simulator.udid, runtime)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    /// Finds an available runtime by identifier, by name (`iOS 18.2`) or by platform and
+    /// version prefix (`iOS 18`, `iOS latest`), preferring the newest match.
+    pub fn find_runtime(&self, runtime: &str) -> Result<&SimulatorRuntime> {
+        let available = || self.runtimes.iter().filter(|runtime| runtime.is_available);
+        if let Some(found) = available().find(|candidate| {
+            candidate.identifier == runtime || candidate.name.eq_ignore_ascii_case(runtime)
+        }) {
+            return Ok(found);
+        }
+
+        let mut words = runtime.split_whitespace();
+        let platform = words.next().unwrap_or_default();
+        let version = strip_latest(words.next().unwrap_or_default());
+        available()
+            .filter(|candidate| {
+                candidate.platform.eq_ignore_ascii_case(platform)
+                    && (version.is_empty()
+                        || candidate.version == version
+                        || candidate.version.starts_with(&format!("{}.", version)))
+            })
+            .max_by_key(|candidate| version_key(&candidate.version))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No available simulator runtime matches '{}'; available runtimes: {}",
+                    runtime,
+                    available()
+                        .map(|runtime| runtime.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+fn selection_rank(
+    selector: &SimulatorSelector,
+    device: &Simulator,
+    runtime: &SimulatorRuntime,
+) -> (Vec<u32>, bool, bool) {
+    (
+        version_key(&runtime.version),
+        device.name.eq_ignore_ascii_case(&selector.device),
+        device.is_booted(),
+    )
+}
+
+fn version_key(version: &str) -> Vec<u32> {
+    version
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Lists the installed simulator runtimes and devices with `xcrun simctl list --json`.
+///
+/// Listing simulators has no side effects, so it runs even in dry-run mode, where the resolved
+/// destinations are needed to print the xcodebuild invocation.
+pub fn list_simulators() -> Result<SimulatorList> {
+    let invocation = Invocation::new("xcrun").with_args(["simctl", "list", "--json"]);
+    let output = crate::runner::run_query(&invocation)?;
+    if !output.status.success() {
+        return Err(CommandError::from_output(
+            CommandErrorKind::Failed,
+            "xcrun simctl list",
+            &invocation,
+            &output,
+        )
+        .into());
+    }
+
+    SimulatorList::parse(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// A human-friendly simulator query such as `iPhone latest`, `iPad, iOS 17` or
+/// `iPhone 16 Pro, iOS 18.2`.
+///
+/// The part before the comma is matched against device names case-insensitively, as an exact
+/// name or a prefix (`iPhone` matches `iPhone 16 Pro`). The optional part after the comma
+/// restricts the runtime by platform and/or version prefix (`iOS 17` matches iOS 17.5); when it is
+/// omitted or `latest`, the newest runtime with a matching device is used.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::SimulatorSelector;
+///
+/// let selector: SimulatorSelector = "iPad, iOS 17".parse().unwrap();
+/// assert_eq!(selector.device, "iPad");
+/// assert_eq!(selector.platform.as_deref(), Some("iOS"));
+/// assert_eq!(selector.version.as_deref(), Some("17"));
+///
+/// let latest: SimulatorSelector = "iPhone latest".parse().unwrap();
+/// assert_eq!(latest.device, "iPhone");
+/// assert_eq!(latest.version, None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatorSelector {
+    pub device: String,
+    pub platform: Option<String>,
+    /// Runtime version prefix; `None` selects the latest runtime.
+    pub version: Option<String>,
+}
+
+impl SimulatorSelector {
+    pub fn matches(&self, device: &Simulator, runtime: &SimulatorRuntime) -> bool {
+        let name = device.name.to_ascii_lowercase();
+        let pattern = self.device.to_ascii_lowercase();
+        let name_matches = name == pattern || name.starts_with(&format!("{} ", pattern));
+        let platform_matches = self
+            .platform
+            .as_ref()
+            .is_none_or(|platform| platform.eq_ignore_ascii_case(&runtime.platform));
+        let version_matches = self.version.as_ref().is_none_or(|version| {
+            runtime.version == *version || runtime.version.starts_with(&format!("{}.", version))
+        });
+
+        name_matches && platform_matches && version_matches
+    }
+}
+
+impl FromStr for SimulatorSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parts = s.split(',').map(str::trim);
+        let device_part = parts.next().unwrap_or_default();
+        let runtime_part = parts.next();
+        if parts.next().is_some() {
+            return Err(format!(
+                "invalid simulator selector '{}': expected '<device>[, <runtime>]', e.g. 'iPad, iOS 17'",
+                s
+            ));
+        }
+
+        let device = strip_latest(device_part).trim();
+        if device.is_empty() {
+            return Err(format!(
+                "invalid simulator selector '{}': expected a device name such as 'iPhone'",
+                s
+            ));
+        }
+
+        let mut selector = SimulatorSelector {
+            device: device.to_string(),
+            platform: None,
+            version: None,
+        };
+        let Some(runtime) = runtime_part else {
+            return Ok(selector);
+        };
+
+        for word in strip_latest(runtime).split_whitespace() {
+            if word.chars().all(|c| c.is_ascii_digit() || c == '.') {
+                if selector.version.is_some() || word.starts_with('.') || word.ends_with('.') {
+                    return Err(format!(
+                        "invalid runtime '{}' in simulator selector",
+                        runtime
+                    ));
+                }
+                selector.version = Some(word.to_string());
+            } else if selector.platform.is_none() && selector.version.is_none() {
+                selector.platform = Some(word.to_string());
+            } else {
+                return Err(format!(
+                    "invalid runtime '{}' in simulator selector: expected e.g. 'iOS 17' or 'latest'",
+                    runtime
+                ));
+            }
+        }
+
+        Ok(selector)
+    }
+}
+
+impl std::fmt::Display for SimulatorSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, ", self.device)?;
+        if let Some(platform) = &self.platform {
+            write!(f, "{} ", platform)?;
+        }
+        write!(f, "{}", self.version.as_deref().unwrap_or("latest"))
+    }
+}
+
+/// Strips a trailing `latest` word, matched case-insensitively.
+fn strip_latest(value: &str) -> &str {
+    let trimmed = value.trim_end();
+    let split = trimmed.len().saturating_sub("latest".len());
+    match trimmed.split_at_checked(split) {
+        Some((rest, last))
+            if last.eq_ignore_ascii_case("latest")
+                && (rest.is_empty() || rest.ends_with(char::is_whitespace)) =>
+        {
+            rest
+        }
+        _ => trimmed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../tests/fixtures/simctl_list.json");
+
+    fn fixture() -> SimulatorList {
+        SimulatorList::parse(FIXTURE).unwrap()
+    }
+
+    fn select(selector: &str) -> Result<(Simulator, SimulatorRuntime)> {
+        let simulators = fixture();
+        let (device, runtime) = simulators.select(&selector.parse().unwrap())?;
+        Ok((device.clone(), runtime.clone()))
+    }
+
+    #[test]
+    fn test_parse_fixture_runtimes_and_devices() {
+        let simulators = fixture();
+
+        assert_eq!(simulators.runtimes.len(), 5);
+        assert_eq!(simulators.devices.len(), 8);
+        assert_eq!(
+            simulators.runtimes[1],
+            SimulatorRuntime {
+                identifier: "com.apple.CoreSimulator.SimRuntime.iOS-18-2".to_string(),
+                name: "iOS 18.2".to_string(),
+                platform: "iOS".to_string(),
+                version: "18.2".to_string(),
+                is_available: true,
+            }
+        );
+        let booted = simulators
+            .devices
+            .iter()
+            .find(|device| device.is_booted())
+            .unwrap();
+        assert_eq!(booted.name, "iPhone 16 Pro");
+        assert_eq!(
+            booted.runtime_identifier,
+            "com.apple.CoreSimulator.SimRuntime.iOS-18-2"
+        );
+    }
+
+    #[test]
+    fn test_available_devices_skip_unavailable_runtimes() {
+        let simulators = fixture();
+        let names: Vec<&str> = simulators
+            .available_devices()
+            .map(|(device, _)| device.name.as_str())
+            .collect();
+
+        assert_eq!(names.len(), 7);
+        assert!(!names.contains(&"iPhone 14"));
+    }
+
+    #[test]
+    fn test_parse_derives_platform_from_runtime_name() {
+        let simulators = SimulatorList::parse(
+            r#"{"runtimes": [{"identifier": "com.apple.CoreSimulator.SimRuntime.xrOS-1-0",
+                "name": "visionOS 1.0", "version": "1.0", "isAvailable": true}], "devices": {}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(simulators.runtimes[0].platform, "visionOS");
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_json() {
+        let error = SimulatorList::parse("not json").unwrap_err();
+
+        assert!(error.to_string().contains("Failed to parse"));
+    }
+
+    #[test]
+    fn test_select_latest_prefers_newest_runtime_then_booted_device() {
+        let (device, runtime) = select("iPhone latest").unwrap();
+
+        assert_eq!(device.name, "iPhone 16 Pro");
+        assert_eq!(runtime.version, "18.2");
+    }
+
+    #[test]
+    fn test_select_with_runtime_version_prefix() {
+        let (device, runtime) = select("iPad, iOS 17").unwrap();
+
+        assert_eq!(device.name, "iPad Air 11-inch (M2)");
+        assert_eq!(runtime.name, "iOS 17.5");
+    }
+
+    #[test]
+    fn test_select_prefers_exact_name() {
+        let (device, _) = select("iPhone 16").unwrap();
+
+        assert_eq!(device.name, "iPhone 16");
+    }
+
+    #[test]
+    fn test_select_other_platforms() {
+        assert_eq!(
+            select("Apple TV").unwrap().0.udid,
+            "70C16345-DE99-4C7F-B608-ADBECFDE0107"
+        );
+        assert_eq!(
+            select("apple watch, watchOS latest").unwrap().1.platform,
+            "watchOS"
+        );
+    }
+
+    #[test]
+    fn test_select_reports_available_devices_when_nothing_matches() {
+        let error = select("iPhone, iOS 16").unwrap_err().to_string();
+
+        assert!(error.starts_with("No available simulator matches 'iPhone, iOS 16'"));
+        assert!(error.contains("iPhone 16 Pro (iOS 18.2)"));
+        assert!(!error.contains("iPhone 14"));
+    }
+
+    #[test]
+    fn test_resolve_destination_uses_udid() {
+        let destination = fixture()
+            .resolve_destination(&"iPad Pro".parse().unwrap())
+            .unwrap();
+
+        assert_eq!(
+            destination.to_string(),
+            "platform=iOS Simulator,id=5EAF4123-BC77-4A5D-94E6-8B9CADBECF05"
+        );
+    }
+
+    #[test]
+    fn test_find_device_by_udid_or_name() {
+        let simulators = fixture();
+
+        assert_eq!(
+            simulators
+                .find_device("4d9e3012-ab66-4f4c-83d5-7a8b9cadbe04")
+                .unwrap()
+                .name,
+            "iPhone 16 Pro"
+        );
+        assert_eq!(
+            simulators
+                .find_device("apple tv 4k (3rd generation)")
+                .unwrap()
+                .udid,
+            "70C16345-DE99-4C7F-B608-ADBECFDE0107"
+        );
+        assert_eq!(
+            simulators.find_device("iPhone 14").unwrap().udid,
+            "6FB05234-CD88-4B6E-A5F7-9CADBECFD006"
+        );
+        assert!(
+            simulators
+                .find_device("iPhone")
+                .unwrap_err()
+                .to_string()
+                .contains("No simulator is named or identified by 'iPhone'")
+        );
+    }
+
+    #[test]
+    fn test_find_device_rejects_ambiguous_names() {
+        let mut simulators = fixture();
+        let mut duplicate = simulators.devices[0].clone();
+        duplicate.udid = "DUPLICATE".to_string();
+        simulators.devices.push(duplicate);
+
+        let error = simulators
+            .find_device("iPhone 15 Pro")
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("ambiguous"));
+        assert!(error.contains("1F9A6C52-5A43-4C36-9E0B-0C1D3B6A7E01 (iOS 17.5)"));
+        assert!(error.contains("DUPLICATE (iOS 17.5)"));
+    }
+
+    #[test]
+    fn test_find_runtime() {
+        let simulators = fixture();
+
+        for (input, expected) in [
+            ("com.apple.CoreSimulator.SimRuntime.iOS-17-5", "iOS 17.5"),
+            ("ios 18.2", "iOS 18.2"),
+            ("iOS", "iOS 18.2"),
+            ("iOS latest", "iOS 18.2"),
+            ("iOS 17", "iOS 17.5"),
+            ("watchOS 11", "watchOS 11.2"),
+        ] {
+            assert_eq!(simulators.find_runtime(input).unwrap().name, expected);
+        }
+        let error = simulators.find_runtime("iOS 16").unwrap_err().to_string();
+        assert!(error.contains("available runtimes: iOS 17.5, iOS 18.2, tvOS 18.2, watchOS 11.2"));
+    }
+
+    #[test]
+    fn test_selector_parsing() {
+        let cases = [
+            ("iPhone latest", ("iPhone", None, None)),
+            ("iPhone 16 Pro", ("iPhone 16 Pro", None, None)),
+            ("iPad, iOS 17", ("iPad", Some("iOS"), Some("17"))),
+            ("iPad, 17.5", ("iPad", None, Some("17.5"))),
+            ("Apple TV, latest", ("Apple TV", None, None)),
+            ("Apple TV, tvOS LATEST", ("Apple TV", Some("tvOS"), None)),
+        ];
+
+        for (input, (device, platform, version)) in cases {
+            let selector: SimulatorSelector = input.parse().unwrap();
+            assert_eq!(selector.device, device, "{}", input);
+            assert_eq!(selector.platform.as_deref(), platform, "{}", input);
+            assert_eq!(selector.version.as_deref(), version, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_selector_rejects_invalid_input() {
+        for input in [
+            "",
+            "latest",
+            ", iOS 17",
+            "iPad, iOS 17, extra",
+            "iPad, 17 18",
+            "iPad, 17.",
+        ] {
+            assert!(input.parse::<SimulatorSelector>().is_err(), "{}", input);
+        }
+    }
+}
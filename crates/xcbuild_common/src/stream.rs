@@ -0,0 +1,506 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    Formatter, Invocation, OutputFormat, XcodebuildFormatter, command_runner, output_format,
+    supervisor,
+};
+
+/// Directory, relative to the working directory, that run logs are written to by default.
+pub const DEFAULT_LOG_DIRECTORY: &str = ".xctools/logs";
+
+/// Log file explicitly requested for this process, e.g. through `xctools --log-file`.
+static LOG_FILE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Formatter applied to xcodebuild output, e.g. through `xctools --formatter`.
+static XCODEBUILD_FORMATTER: Mutex<Formatter> = Mutex::new(Formatter::Pretty);
+
+/// Where streamed output goes besides the captured output returned to the caller.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::{Formatter, StreamOptions};
+///
+/// let options = StreamOptions::new()
+///     .with_log_file(".xctools/logs/build.log".into())
+///     .with_formatter(Formatter::Quiet);
+///
+/// assert_eq!(options.formatter, Formatter::Quiet);
+/// assert_eq!(StreamOptions::new().formatter, Formatter::Raw);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamOptions {
+    /// File the raw output is appended to; parent directories are created as needed.
+    pub log_file: Option<PathBuf>,
+    /// How output is rendered on the terminal. Defaults to [`Formatter::Raw`] because only
+    /// xcodebuild output is understood by the other formatters.
+    pub formatter: Formatter,
+}
+
+impl StreamOptions {
+    pub fn new() -> Self {
+        Self {
+            log_file: None,
+            formatter: Formatter::Raw,
+        }
+    }
+
+    pub fn with_log_file(mut self, log_file: PathBuf) -> Self {
+        self.log_file = Some(log_file);
+        self
+    }
+
+    pub fn with_formatter(mut self, formatter: Formatter) -> Self {
+        self.formatter = formatter;
+        self
+    }
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sets the formatter used for xcodebuild output on the terminal.
+pub fn set_xcodebuild_formatter(formatter: Formatter) {
+    *XCODEBUILD_FORMATTER
+        .lock()
+        .unwrap_or_else(|error| error.into_inner()) = formatter;
+}
+
+/// Returns the formatter configured with [`set_xcodebuild_formatter`], [`Formatter::Pretty`]
+/// by default.
+pub fn xcodebuild_formatter() -> Formatter {
+    *XCODEBUILD_FORMATTER
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+}
+
+/// Sets the file that every streamed invocation appends its output to. Passing `None` restores
+/// the default of one log file per run under [`DEFAULT_LOG_DIRECTORY`].
+pub fn set_log_file(log_file: Option<PathBuf>) {
+    *LOG_FILE.lock().unwrap_or_else(|error| error.into_inner()) = log_file;
+}
+
+/// Returns the log file a streamed run of `action` should write to: the file configured with
+/// [`set_log_file`], or `.xctools/logs/<action>-<timestamp>.log`.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::log_file_for;
+///
+/// let log_file = log_file_for("archive");
+/// assert!(log_file.starts_with(".xctools/logs"));
+/// assert!(log_file.file_name().unwrap().to_string_lossy().starts_with("archive-"));
+/// ```
+pub fn log_file_for(action: &str) -> PathBuf {
+    if let Some(log_file) = LOG_FILE
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+        .clone()
+    {
+        return log_file;
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let action = action.trim_start_matches('-');
+    Path::new(DEFAULT_LOG_DIRECTORY).join(format!(
+        "{}-{}-{}.log",
+        action,
+        timestamp,
+        std::process::id()
+    ))
+}
+
+/// Spawns the invocation and streams its stdout and stderr line by line to the terminal while
+/// also appending them to the log file in `options`.
+///
+/// Long-running commands such as `xcodebuild archive` show progress as it happens instead of
+/// only once the process exits. Terminal output is rendered with `options.formatter`, followed
+/// by its summary; the log file always receives the raw lines. The full output is still
+/// captured and returned so callers can parse it or build a [`crate::CommandError`] from it.
+/// Like [`crate::run_invocation`], the invocation goes through the current
+/// [`crate::CommandRunner`] and the exit status is not inspected.
+///
+/// # Arguments
+///
+/// * `invocation` - The program, arguments, environment and working directory to run
+/// * `options` - Log file and terminal formatter to use
+///
+/// # Returns
+///
+/// Returns `Ok(Output)` with the captured stdout and stderr once the process has exited, or
+/// `Err` if the log file could not be opened or the process could not be spawned.
+pub fn stream_invocation(invocation: &Invocation, options: &StreamOptions) -> Result<Output> {
+    command_runner().stream(invocation, options)
+}
+
+/// Streams a child process to the real terminal; the implementation behind
+/// [`crate::ProcessRunner`].
+pub(crate) fn stream_process(invocation: &Invocation, options: &StreamOptions) -> Result<Output> {
+    // In JSON mode stdout is reserved for the report.
+    let stdout_sink: Sink = match output_format() {
+        OutputFormat::Text => Box::new(std::io::stdout()),
+        OutputFormat::Json => Box::new(std::io::stderr()),
+    };
+    let colored = match output_format() {
+        OutputFormat::Text => std::io::stdout().is_terminal(),
+        OutputFormat::Json => std::io::stderr().is_terminal(),
+    } && std::env::var_os("NO_COLOR").is_none();
+    tee_invocation(
+        invocation,
+        options,
+        colored,
+        stdout_sink,
+        Box::new(std::io::stderr()),
+    )
+}
+
+type Sink = Box<dyn Write + Send>;
+type SharedLog = Option<Arc<Mutex<File>>>;
+type SharedFormatter = Option<Arc<Mutex<XcodebuildFormatter>>>;
+
+fn tee_invocation(
+    invocation: &Invocation,
+    options: &StreamOptions,
+    colored: bool,
+    stdout_sink: Sink,
+    stderr_sink: Sink,
+) -> Result<Output> {
+    let mut command = invocation.command();
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = supervisor::spawn(&mut command, invocation)?;
+
+    // The log is opened only once the program is known to exist so a missing toolchain does
+    // not leave empty log files behind.
+    let log = match options
+        .log_file
+        .as_deref()
+        .map(|path| start_log(path, invocation))
+        .transpose()
+    {
+        Ok(log) => log.map(|file| Arc::new(Mutex::new(file))),
+        Err(error) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(error);
+        }
+    };
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let formatter = (options.formatter != Formatter::Raw).then(|| {
+        Arc::new(Mutex::new(XcodebuildFormatter::new(
+            options.formatter,
+            colored,
+        )))
+    });
+    let stdout_thread = spawn_tee(stdout, stdout_sink, log.clone(), formatter.clone());
+    let stderr_thread = spawn_tee(stderr, stderr_sink, log, formatter.clone());
+
+    let status = supervisor::wait(&mut child)
+        .context(format!("Failed to wait for {}", invocation.program()))?;
+    let (stdout, mut stdout_sink) = stdout_thread
+        .join()
+        .expect("stdout reader panicked")
+        .context("Failed to read stdout")?;
+    let (stderr, _) = stderr_thread
+        .join()
+        .expect("stderr reader panicked")
+        .context("Failed to read stderr")?;
+
+    let summary = formatter.and_then(|formatter| {
+        formatter
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .summary()
+    });
+    if let Some(summary) = summary {
+        let _ = writeln!(stdout_sink, "{}", summary).and_then(|_| stdout_sink.flush());
+    }
+
+    match status {
+        Ok(status) => Ok(Output {
+            status,
+            stdout,
+            stderr,
+        }),
+        Err(termination) => {
+            Err(supervisor::termination_error(termination, invocation, &stdout, &stderr).into())
+        }
+    }
+}
+
+/// Opens `path` for appending and writes the (redacted) command line as a header.
+fn start_log(path: &Path, invocation: &Invocation) -> Result<File> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).context(format!(
+            "Failed to create log directory: {}",
+            parent.display()
+        ))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context(format!("Failed to open log file: {}", path.display()))?;
+    writeln!(file, "$ {}", invocation)
+        .context(format!("Failed to write log file: {}", path.display()))?;
+
+    Ok(file)
+}
+
+/// Copies `reader` line by line to `sink`, rendered through the shared formatter if there is
+/// one, and to the shared log, returning everything read along with the sink.
+///
+/// The pipe is always drained to the end so the child never blocks on a full pipe: errors
+/// writing to the terminal (e.g. a closed pipe) are ignored, and a failed log write stops
+/// logging and is reported once the stream ends.
+fn spawn_tee<R: Read + Send + 'static>(
+    reader: R,
+    mut sink: Sink,
+    log: SharedLog,
+    formatter: SharedFormatter,
+) -> thread::JoinHandle<std::io::Result<(Vec<u8>, Sink)>> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut captured = Vec::new();
+        let mut line = Vec::new();
+        let mut log_error = None;
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+
+            let rendered = match &formatter {
+                Some(formatter) => formatter
+                    .lock()
+                    .unwrap_or_else(|error| error.into_inner())
+                    .format_line(&String::from_utf8_lossy(&line))
+                    .map(|text| format!("{}\n", text).into_bytes()),
+                None => Some(line.clone()),
+            };
+            if let Some(rendered) = rendered {
+                let _ = sink.write_all(&rendered).and_then(|_| sink.flush());
+            }
+            if let Some(log) = log.as_ref().filter(|_| log_error.is_none()) {
+                let mut file = log.lock().unwrap_or_else(|error| error.into_inner());
+                if let Err(error) = file.write_all(&line) {
+                    log_error = Some(error);
+                }
+            }
+            captured.extend_from_slice(&line);
+        }
+
+        match log_error {
+            Some(error) => Err(error),
+            None => Ok((captured, sink)),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CommandError;
+    use crate::CommandErrorKind;
+
+    /// A `Write` sink whose contents can be inspected after it has been moved into a thread.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    fn shell(script: &str) -> Invocation {
+        Invocation::new("sh").with_args(["-c", script])
+    }
+
+    #[test]
+    fn test_tee_invocation_captures_and_forwards_both_streams() {
+        let terminal_out = SharedBuffer::default();
+        let terminal_err = SharedBuffer::default();
+        let output = tee_invocation(
+            &shell("echo one; echo oops >&2; printf 'two'"),
+            &StreamOptions::new(),
+            false,
+            Box::new(terminal_out.clone()),
+            Box::new(terminal_err.clone()),
+        )
+        .unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "one\ntwo");
+        assert_eq!(String::from_utf8(output.stderr).unwrap(), "oops\n");
+        assert_eq!(terminal_out.contents(), "one\ntwo");
+        assert_eq!(terminal_err.contents(), "oops\n");
+    }
+
+    #[test]
+    fn test_tee_invocation_appends_to_log_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_file = temp_dir.path().join("nested").join("run.log");
+
+        for message in ["first", "second"] {
+            tee_invocation(
+                &Invocation::new("echo").with_arg(message),
+                &StreamOptions::new().with_log_file(log_file.clone()),
+                false,
+                Box::new(std::io::sink()),
+                Box::new(std::io::sink()),
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            fs::read_to_string(&log_file).unwrap(),
+            "$ echo first\nfirst\n$ echo second\nsecond\n"
+        );
+    }
+
+    #[test]
+    fn test_tee_invocation_redacts_secrets_in_log_header() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_file = temp_dir.path().join("run.log");
+        tee_invocation(
+            &Invocation::new("true").with_secret_arg("hunter2"),
+            &StreamOptions::new().with_log_file(log_file.clone()),
+            false,
+            Box::new(std::io::sink()),
+            Box::new(std::io::sink()),
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&log_file).unwrap(),
+            "$ true '********'\n"
+        );
+    }
+
+    #[test]
+    fn test_tee_invocation_returns_failed_status() {
+        let output = tee_invocation(
+            &shell("echo '** BUILD FAILED **'; exit 65"),
+            &StreamOptions::new(),
+            false,
+            Box::new(std::io::sink()),
+            Box::new(std::io::sink()),
+        )
+        .unwrap();
+
+        assert_eq!(output.status.code(), Some(65));
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "** BUILD FAILED **\n"
+        );
+    }
+
+    #[test]
+    fn test_tee_invocation_formats_terminal_output_but_logs_raw_lines() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_file = temp_dir.path().join("run.log");
+        let terminal_out = SharedBuffer::default();
+        let script = "echo 'CompileSwift normal arm64 /src/App/App.swift (in target)'; \
+                      echo '    cd /src/App'; \
+                      echo '** BUILD SUCCEEDED **'";
+        let output = tee_invocation(
+            &shell(script),
+            &StreamOptions::new()
+                .with_log_file(log_file.clone())
+                .with_formatter(Formatter::Pretty),
+            false,
+            Box::new(terminal_out.clone()),
+            Box::new(std::io::sink()),
+        )
+        .unwrap();
+
+        assert_eq!(
+            terminal_out.contents(),
+            "▸ Compiling App.swift\n** BUILD SUCCEEDED **\n1 file compiled\n"
+        );
+        assert!(
+            String::from_utf8(output.stdout)
+                .unwrap()
+                .contains("    cd /src/App\n")
+        );
+        assert!(
+            fs::read_to_string(&log_file)
+                .unwrap()
+                .contains("    cd /src/App\n")
+        );
+    }
+
+    #[test]
+    fn test_stream_invocation_missing_program() {
+        let error = stream_invocation(
+            &Invocation::new("xctools-nonexistent-program-12345"),
+            &StreamOptions::new(),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<CommandError>().unwrap().kind,
+            CommandErrorKind::ToolMissing
+        );
+    }
+
+    #[test]
+    fn test_stream_invocation_missing_program_does_not_create_log() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_file = temp_dir.path().join("run.log");
+        let result = stream_invocation(
+            &Invocation::new("xctools-nonexistent-program-12345"),
+            &StreamOptions::new().with_log_file(log_file.clone()),
+        );
+
+        assert!(result.is_err());
+        assert!(!log_file.exists());
+    }
+
+    #[test]
+    fn test_log_file_for_strips_leading_dashes() {
+        let log_file = log_file_for("-exportArchive");
+
+        assert!(
+            log_file
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("exportArchive-")
+        );
+    }
+}
@@ -0,0 +1,333 @@
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus};
+use std::sync::Mutex;
+use std::sync::Once;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{CommandError, CommandErrorKind, Invocation};
+
+/// How long a child process group gets to exit after SIGINT/SIGTERM before it is sent SIGKILL.
+pub const TERMINATION_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// How often a running child is checked for exit, timeout and pending signals.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Timeout applied to every child process, e.g. through `xctools --timeout`.
+static COMMAND_TIMEOUT: Mutex<Option<Duration>> = Mutex::new(None);
+
+/// The last SIGINT or SIGTERM received by this process, or 0.
+static RECEIVED_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+static INSTALL_SIGNAL_HANDLERS: Once = Once::new();
+
+/// Sets the maximum time each child process may run before it is terminated. `None` lets
+/// children run for as long as they need.
+pub fn set_command_timeout(timeout: Option<Duration>) {
+    *COMMAND_TIMEOUT
+        .lock()
+        .unwrap_or_else(|error| error.into_inner()) = timeout;
+}
+
+/// Returns the timeout configured with [`set_command_timeout`].
+pub fn command_timeout() -> Option<Duration> {
+    *COMMAND_TIMEOUT
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+}
+
+/// Parses a duration such as `90`, `90s`, `15m` or `2h`; a bare number is in seconds.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use xcbuild_common::parse_duration;
+///
+/// assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+/// assert_eq!(parse_duration("15m"), Ok(Duration::from_secs(900)));
+/// assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+/// assert!(parse_duration("0s").is_err());
+/// assert!(parse_duration("soon").is_err());
+/// ```
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let trimmed = s.trim();
+    let (number, multiplier) = match trimmed.char_indices().last() {
+        Some((index, 's')) => (&trimmed[..index], 1),
+        Some((index, 'm')) => (&trimmed[..index], 60),
+        Some((index, 'h')) => (&trimmed[..index], 60 * 60),
+        _ => (trimmed, 1),
+    };
+    let seconds = number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| {
+            format!(
+                "invalid duration '{}': expected a number of seconds or a value such as 90s, 15m \
+                 or 2h",
+                s
+            )
+        })?;
+    if seconds == 0 {
+        return Err(format!(
+            "invalid duration '{}': must be greater than zero",
+            s
+        ));
+    }
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Why a supervised child was stopped before it exited on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Termination {
+    /// The child ran longer than the configured timeout.
+    TimedOut(Duration),
+    /// xctools received SIGINT or SIGTERM and forwarded it to the child.
+    Interrupted,
+}
+
+/// Catches SIGINT and SIGTERM so that they are recorded instead of ending xctools right away.
+///
+/// The CLI calls this at startup: a signal received while a child runs is forwarded to it by
+/// [`wait`], and one received between tools is reported by [`check_interrupted`] and stops any
+/// further child from starting. Calling it again has no effect.
+pub fn install_signal_handlers() {
+    INSTALL_SIGNAL_HANDLERS.call_once(|| {
+        let handler = handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        // SAFETY: the handler only stores to an atomic, which is async-signal-safe.
+        unsafe {
+            libc::signal(libc::SIGINT, handler);
+            libc::signal(libc::SIGTERM, handler);
+        }
+    });
+}
+
+/// Returns `true` once xctools has received SIGINT or SIGTERM.
+pub fn interrupted() -> bool {
+    RECEIVED_SIGNAL.load(Ordering::SeqCst) != 0
+}
+
+/// Fails with [`CommandErrorKind::Interrupted`] once xctools has received SIGINT or SIGTERM, so
+/// that work between tools, such as the next step of a pipeline, stops at its boundary.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::check_interrupted;
+///
+/// assert!(check_interrupted("upload").is_ok());
+/// ```
+pub fn check_interrupted(action: &str) -> Result<(), CommandError> {
+    if interrupted() {
+        return Err(CommandError::interrupted(action));
+    }
+
+    Ok(())
+}
+
+/// Spawns `command` in its own process group, so that signals can be forwarded to it and to
+/// everything it starts. Nothing is started once xctools has been interrupted.
+pub(crate) fn spawn(command: &mut Command, invocation: &Invocation) -> anyhow::Result<Child> {
+    install_signal_handlers();
+    if interrupted() {
+        return Err(CommandError::new(
+            CommandErrorKind::Interrupted,
+            invocation.program(),
+            invocation,
+        )
+        .into());
+    }
+
+    match command.process_group(0).spawn() {
+        Ok(child) => Ok(child),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            Err(CommandError::tool_missing(invocation).into())
+        }
+        Err(error) => {
+            Err(anyhow::Error::new(error)
+                .context(format!("Failed to run {}", invocation.program())))
+        }
+    }
+}
+
+extern "C" fn handle_signal(signal: libc::c_int) {
+    RECEIVED_SIGNAL.store(signal, Ordering::SeqCst);
+}
+
+/// Waits for a child started with [`spawn`], applying the configured timeout and forwarding
+/// SIGINT/SIGTERM received by xctools to the child's process group. A child that is still running
+/// [`TERMINATION_GRACE_PERIOD`] after being asked to stop is killed with SIGKILL.
+pub(crate) fn wait(child: &mut Child) -> io::Result<Result<ExitStatus, Termination>> {
+    wait_with(child, command_timeout(), TERMINATION_GRACE_PERIOD)
+}
+
+fn wait_with(
+    child: &mut Child,
+    timeout: Option<Duration>,
+    grace_period: Duration,
+) -> io::Result<Result<ExitStatus, Termination>> {
+    let started = Instant::now();
+    let mut termination = None;
+    let mut stop_requested_at: Option<Instant> = None;
+    let mut forwarded_signal = 0;
+    let mut killed = false;
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(match termination {
+                Some(termination) => {
+                    // Helpers the child started may outlive it and keep its pipes open.
+                    signal_group(child, libc::SIGKILL);
+                    Err(termination)
+                }
+                None => Ok(status),
+            });
+        }
+
+        let received = RECEIVED_SIGNAL.load(Ordering::SeqCst);
+        if received != 0 && received != forwarded_signal {
+            if stop_requested_at.is_some() {
+                // A second Ctrl-C, or SIGTERM after SIGINT, means "stop now".
+                signal_group(child, libc::SIGKILL);
+                killed = true;
+            } else {
+                signal_group(child, received);
+                stop_requested_at = Some(Instant::now());
+            }
+            forwarded_signal = received;
+            termination.get_or_insert(Termination::Interrupted);
+        }
+
+        if let Some(timeout) = timeout
+            && stop_requested_at.is_none()
+            && started.elapsed() >= timeout
+        {
+            signal_group(child, libc::SIGTERM);
+            stop_requested_at = Some(Instant::now());
+            termination = Some(Termination::TimedOut(timeout));
+        }
+
+        if let Some(requested_at) = stop_requested_at
+            && !killed
+            && requested_at.elapsed() >= grace_period
+        {
+            signal_group(child, libc::SIGKILL);
+            killed = true;
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Sleeps for `duration`, returning early with `false` if xctools receives SIGINT or SIGTERM
+/// meanwhile.
+pub(crate) fn sleep_unless_interrupted(duration: Duration) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < duration {
+        if interrupted() {
+            return false;
+        }
+        std::thread::sleep(POLL_INTERVAL.min(duration - started.elapsed()));
+    }
+
+    !interrupted()
+}
+
+/// Builds the error reported for a child stopped by [`wait`], keeping the output it produced.
+pub(crate) fn termination_error(
+    termination: Termination,
+    invocation: &Invocation,
+    stdout: &[u8],
+    stderr: &[u8],
+) -> CommandError {
+    let error = match termination {
+        Termination::TimedOut(timeout) => {
+            CommandError::new(CommandErrorKind::TimedOut, invocation.program(), invocation)
+                .with_timeout(timeout)
+        }
+        Termination::Interrupted => CommandError::new(
+            CommandErrorKind::Interrupted,
+            invocation.program(),
+            invocation,
+        ),
+    };
+
+    error.with_output(
+        &String::from_utf8_lossy(stdout),
+        &String::from_utf8_lossy(stderr),
+    )
+}
+
+fn signal_group(child: &Child, signal: libc::c_int) {
+    // The child leads its own process group (see `spawn`), so its pid is the group id.
+    // SAFETY: kill has no memory-safety preconditions; failures (e.g. the group is gone) are
+    // deliberately ignored.
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), signal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_shell(script: &str) -> Child {
+        let invocation = Invocation::new("sh").with_args(["-c", script]);
+        spawn(&mut invocation.command(), &invocation).unwrap()
+    }
+
+    #[test]
+    fn test_wait_returns_exit_status() {
+        let mut child = spawn_shell("exit 3");
+
+        let status = wait_with(&mut child, Some(Duration::from_secs(10)), grace())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[test]
+    fn test_wait_terminates_child_after_timeout() {
+        let started = Instant::now();
+        let mut child = spawn_shell("sleep 10");
+
+        let result = wait_with(&mut child, Some(Duration::from_millis(200)), grace()).unwrap();
+
+        assert_eq!(
+            result,
+            Err(Termination::TimedOut(Duration::from_millis(200)))
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_wait_kills_child_that_ignores_sigterm() {
+        let started = Instant::now();
+        let mut child = spawn_shell("trap '' TERM; sleep 10");
+
+        let result = wait_with(
+            &mut child,
+            Some(Duration::from_millis(200)),
+            Duration::from_millis(300),
+        )
+        .unwrap();
+
+        assert!(matches!(result, Err(Termination::TimedOut(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_parse_duration_rejects_invalid_values() {
+        for invalid in ["", "s", "1.5m", "-3", "10d", "0"] {
+            assert!(parse_duration(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    fn grace() -> Duration {
+        Duration::from_secs(1)
+    }
+}
@@ -0,0 +1,345 @@
+use std::str::FromStr;
+
+use anyhow::{Result, bail};
+use clap::Args;
+
+/// A test, test class or test target to run or skip, written the way xcodebuild's
+/// `-only-testing` and `-skip-testing` expect it: `Target`, `Target/Class` or
+/// `Target/Class/method`.
+///
+/// Each component must be non-empty and must not start or end with whitespace. Swift Testing
+/// functions keep their parentheses, e.g. `MyAppTests/LoginTests/loginSucceeds()`.
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::TestIdentifier;
+///
+/// let identifier: TestIdentifier = "MyAppTests/LoginTests/testLogin".parse().unwrap();
+/// assert_eq!(identifier.target(), "MyAppTests");
+/// assert_eq!(identifier.class(), Some("LoginTests"));
+/// assert_eq!(identifier.method(), Some("testLogin"));
+///
+/// assert!("MyAppTests//testLogin".parse::<TestIdentifier>().is_err());
+/// assert!("MyAppTests/LoginTests/testLogin/extra".parse::<TestIdentifier>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestIdentifier {
+    target: String,
+    class: Option<String>,
+    method: Option<String>,
+}
+
+impl TestIdentifier {
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    pub fn method(&self) -> Option<&str> {
+        self.method.as_deref()
+    }
+}
+
+impl FromStr for TestIdentifier {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid test identifier '{}': expected Target, Target/Class or Target/Class/method",
+                s
+            )
+        };
+        let components: Vec<&str> = s.split('/').collect();
+        if components.len() > 3
+            || components
+                .iter()
+                .any(|component| component.is_empty() || component.trim() != *component)
+        {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            target: components[0].to_string(),
+            class: components.get(1).map(ToString::to_string),
+            method: components.get(2).map(ToString::to_string),
+        })
+    }
+}
+
+impl std::fmt::Display for TestIdentifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.target)?;
+        if let Some(class) = &self.class {
+            write!(f, "/{}", class)?;
+        }
+        if let Some(method) = &self.method {
+            write!(f, "/{}", method)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Which tests the test command runs: a test plan other than the scheme's default, tests to run
+/// or skip, and test plan configurations to run or skip. Without any of them xcodebuild runs
+/// the whole scheme.
+///
+/// Derives `clap::Args` so the CLI can flatten the options into the test command, like
+/// [`BuildOverrides`](crate::BuildOverrides).
+///
+/// # Examples
+///
+/// ```rust
+/// use xcbuild_common::TestSelection;
+///
+/// let selection = TestSelection::new()
+///     .with_test_plan("Smoke".to_string())
+///     .with_only_testing("MyAppTests/LoginTests".parse().unwrap())
+///     .with_skip_testing("MyAppTests/LoginTests/testSlowLogin".parse().unwrap());
+///
+/// assert_eq!(
+///     selection.arguments().unwrap(),
+///     [
+///         "-testPlan",
+///         "Smoke",
+///         "-only-testing:MyAppTests/LoginTests",
+///         "-skip-testing:MyAppTests/LoginTests/testSlowLogin",
+///     ]
+/// );
+/// ```
+#[derive(Args, Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestSelection {
+    /// Test plan to run instead of the scheme's default one
+    #[arg(long, value_name = "NAME")]
+    pub test_plan: Option<String>,
+
+    /// Run only this test target, class or test, e.g. MyAppTests/LoginTests/testLogin (may be
+    /// specified multiple times)
+    #[arg(long, value_name = "TARGET[/CLASS[/METHOD]]")]
+    pub only_testing: Vec<TestIdentifier>,
+
+    /// Skip this test target, class or test (may be specified multiple times)
+    #[arg(long, value_name = "TARGET[/CLASS[/METHOD]]")]
+    pub skip_testing: Vec<TestIdentifier>,
+
+    /// Run only this configuration of the test plan (may be specified multiple times)
+    #[arg(long = "only-test-configuration", value_name = "NAME")]
+    pub only_test_configurations: Vec<String>,
+
+    /// Skip this configuration of the test plan (may be specified multiple times)
+    #[arg(long = "skip-test-configuration", value_name = "NAME")]
+    pub skip_test_configurations: Vec<String>,
+}
+
+impl TestSelection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_test_plan(mut self, test_plan: String) -> Self {
+        self.test_plan = Some(test_plan);
+        self
+    }
+
+    pub fn with_only_testing(mut self, identifier: TestIdentifier) -> Self {
+        self.only_testing.push(identifier);
+        self
+    }
+
+    pub fn with_skip_testing(mut self, identifier: TestIdentifier) -> Self {
+        self.skip_testing.push(identifier);
+        self
+    }
+
+    pub fn with_only_test_configuration(mut self, configuration: String) -> Self {
+        self.only_test_configurations.push(configuration);
+        self
+    }
+
+    pub fn with_skip_test_configuration(mut self, configuration: String) -> Self {
+        self.skip_test_configurations.push(configuration);
+        self
+    }
+
+    /// Whether the selection narrows down the tests the scheme runs.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns the xcodebuild arguments for this selection. Fails if a name is empty, or if a
+    /// test or test configuration is both selected and skipped.
+    pub fn arguments(&self) -> Result<Vec<String>> {
+        if let Some(test_plan) = &self.test_plan
+            && test_plan.trim().is_empty()
+        {
+            bail!("Test plan name must not be empty");
+        }
+        for configuration in self
+            .only_test_configurations
+            .iter()
+            .chain(&self.skip_test_configurations)
+        {
+            if configuration.trim().is_empty() {
+                bail!("Test configuration name must not be empty");
+            }
+        }
+        if let Some(identifier) = self
+            .only_testing
+            .iter()
+            .find(|identifier| self.skip_testing.contains(identifier))
+        {
+            bail!(
+                "'{}' is passed to both --only-testing and --skip-testing",
+                identifier
+            );
+        }
+        if let Some(configuration) = self
+            .only_test_configurations
+            .iter()
+            .find(|configuration| self.skip_test_configurations.contains(configuration))
+        {
+            bail!(
+                "Test configuration '{}' is passed to both --only-test-configuration and \
+                 --skip-test-configuration",
+                configuration
+            );
+        }
+
+        let mut arguments = Vec::new();
+        if let Some(test_plan) = &self.test_plan {
+            arguments.extend(["-testPlan".to_string(), test_plan.clone()]);
+        }
+        arguments.extend(
+            self.only_testing
+                .iter()
+                .map(|identifier| format!("-only-testing:{}", identifier)),
+        );
+        arguments.extend(
+            self.skip_testing
+                .iter()
+                .map(|identifier| format!("-skip-testing:{}", identifier)),
+        );
+        for configuration in &self.only_test_configurations {
+            arguments.extend([
+                "-only-test-configuration".to_string(),
+                configuration.clone(),
+            ]);
+        }
+        for configuration in &self.skip_test_configurations {
+            arguments.extend([
+                "-skip-test-configuration".to_string(),
+                configuration.clone(),
+            ]);
+        }
+
+        Ok(arguments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier_parses_target_class_and_method() {
+        let target: TestIdentifier = "MyAppTests".parse().unwrap();
+        assert_eq!(target.target(), "MyAppTests");
+        assert_eq!(target.class(), None);
+        assert_eq!(target.method(), None);
+
+        let class: TestIdentifier = "MyAppTests/LoginTests".parse().unwrap();
+        assert_eq!(class.class(), Some("LoginTests"));
+        assert_eq!(class.method(), None);
+
+        let method: TestIdentifier = "My App Tests/LoginTests/loginSucceeds()".parse().unwrap();
+        assert_eq!(method.target(), "My App Tests");
+        assert_eq!(method.method(), Some("loginSucceeds()"));
+        assert_eq!(
+            method.to_string(),
+            "My App Tests/LoginTests/loginSucceeds()"
+        );
+    }
+
+    #[test]
+    fn test_identifier_rejects_invalid_input() {
+        for input in [
+            "",
+            "/LoginTests",
+            "MyAppTests/",
+            "MyAppTests//testLogin",
+            "MyAppTests/LoginTests/testLogin/extra",
+            " MyAppTests/LoginTests",
+            "MyAppTests/LoginTests ",
+        ] {
+            assert!(input.parse::<TestIdentifier>().is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_selection_arguments() {
+        let selection = TestSelection::new()
+            .with_test_plan("Nightly".to_string())
+            .with_only_testing("MyAppTests".parse().unwrap())
+            .with_only_testing("MyAppUITests/CheckoutUITests".parse().unwrap())
+            .with_skip_testing("MyAppTests/LoginTests/testSlowLogin".parse().unwrap())
+            .with_only_test_configuration("English".to_string())
+            .with_skip_test_configuration("German".to_string());
+
+        assert_eq!(
+            selection.arguments().unwrap(),
+            [
+                "-testPlan",
+                "Nightly",
+                "-only-testing:MyAppTests",
+                "-only-testing:MyAppUITests/CheckoutUITests",
+                "-skip-testing:MyAppTests/LoginTests/testSlowLogin",
+                "-only-test-configuration",
+                "English",
+                "-skip-test-configuration",
+                "German",
+            ]
+        );
+        assert!(!selection.is_empty());
+        assert!(TestSelection::new().is_empty());
+        assert!(TestSelection::new().arguments().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_selection_rejects_conflicting_and_empty_values() {
+        let identifier: TestIdentifier = "MyAppTests/LoginTests".parse().unwrap();
+        let error = TestSelection::new()
+            .with_only_testing(identifier.clone())
+            .with_skip_testing(identifier)
+            .arguments()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "'MyAppTests/LoginTests' is passed to both --only-testing and --skip-testing"
+        );
+
+        let error = TestSelection::new()
+            .with_only_test_configuration("English".to_string())
+            .with_skip_test_configuration("English".to_string())
+            .arguments()
+            .unwrap_err();
+        assert!(error.to_string().contains("Test configuration 'English'"));
+
+        assert!(
+            TestSelection::new()
+                .with_test_plan(" ".to_string())
+                .arguments()
+                .is_err()
+        );
+        assert!(
+            TestSelection::new()
+                .with_skip_test_configuration(String::new())
+                .arguments()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,413 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::Invocation;
+
+/// Environment variable listing extra directories, separated by `:`, that are searched for
+/// Xcode bundles before `/Applications` and `~/Applications`.
+pub const XCODE_SEARCH_PATH_ENV: &str = "XCTOOLS_XCODE_SEARCH_PATH";
+
+/// Developer directory selected for this process, e.g. through `xctools --xcode`.
+static DEVELOPER_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Sets the `DEVELOPER_DIR` passed to every child process. Passing `None` leaves the variable
+/// untouched, so the Xcode chosen with `xcode-select` is used.
+pub fn set_developer_dir(developer_dir: Option<PathBuf>) {
+    *DEVELOPER_DIR
+        .lock()
+        .unwrap_or_else(|error| error.into_inner()) = developer_dir;
+}
+
+/// Returns the developer directory configured with [`set_developer_dir`].
+pub fn developer_dir() -> Option<PathBuf> {
+    DEVELOPER_DIR
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+        .clone()
+}
+
+/// Returns `invocation` with `DEVELOPER_DIR` set to `developer_dir`, unless the invocation
+/// already sets the variable itself.
+pub(crate) fn with_developer_dir(
+    invocation: &Invocation,
+    developer_dir: Option<&Path>,
+) -> Invocation {
+    match developer_dir {
+        Some(developer_dir)
+            if !invocation
+                .envs()
+                .iter()
+                .any(|(key, _)| key == "DEVELOPER_DIR") =>
+        {
+            invocation.prepend_env("DEVELOPER_DIR", developer_dir.to_string_lossy())
+        }
+        _ => invocation.clone(),
+    }
+}
+
+/// An installed Xcode bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct XcodeInstallation {
+    /// Path to the `.app` bundle.
+    pub path: PathBuf,
+    /// Marketing version, e.g. `16.2`.
+    pub version: String,
+    /// Build number, e.g. `16C5032a`, when the bundle reports one.
+    pub build: Option<String>,
+}
+
+impl XcodeInstallation {
+    /// Reads the version of the Xcode bundle at `path` from `Contents/version.plist`, falling back
+    /// to `Contents/Info.plist`.
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = path.join("Contents");
+        let (version, build) = [
+            ("version.plist", "ProductBuildVersion"),
+            ("Info.plist", "DTXcodeBuild"),
+        ]
+        .iter()
+        .filter_map(|(file, build_key)| {
+            let plist = std::fs::read_to_string(contents.join(file)).ok()?;
+            let version = plist_string(&plist, "CFBundleShortVersionString")?;
+            Some((version, plist_string(&plist, build_key)))
+        })
+        .next()
+        .with_context(|| {
+            format!(
+                "{} is not an Xcode bundle: no version found in Contents/version.plist or \
+                 Contents/Info.plist",
+                path.display()
+            )
+        })?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            version,
+            build,
+        })
+    }
+
+    /// Returns the `Contents/Developer` directory used as `DEVELOPER_DIR`.
+    pub fn developer_dir(&self) -> PathBuf {
+        self.path.join("Contents/Developer")
+    }
+}
+
+/// Finds the Xcode bundles (`Xcode*.app`) in the directories listed in
+/// [`XCODE_SEARCH_PATH_ENV`], `/Applications` and `~/Applications`, newest version first.
+/// Bundles whose version cannot be read are skipped.
+pub fn find_xcodes() -> Result<Vec<XcodeInstallation>> {
+    let mut directories: Vec<PathBuf> = std::env::var_os(XCODE_SEARCH_PATH_ENV)
+        .map(|paths| std::env::split_paths(&paths).collect())
+        .unwrap_or_default();
+    directories.push(PathBuf::from("/Applications"));
+    if let Some(home) = std::env::var_os("HOME") {
+        directories.push(Path::new(&home).join("Applications"));
+    }
+
+    find_xcodes_in(&directories)
+}
+
+/// Finds the Xcode bundles directly inside `directories`, newest version first. Directories that
+/// do not exist or are listed more than once are ignored.
+pub fn find_xcodes_in(directories: &[PathBuf]) -> Result<Vec<XcodeInstallation>> {
+    let mut installations = Vec::new();
+    for (index, directory) in directories.iter().enumerate() {
+        if directories[..index].contains(directory) {
+            continue;
+        }
+        let Ok(entries) = std::fs::read_dir(directory) else {
+            continue;
+        };
+        let mut bundles: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy())
+                    .is_some_and(|name| name.starts_with("Xcode") && name.ends_with(".app"))
+            })
+            .collect();
+        bundles.sort();
+        installations.extend(
+            bundles
+                .iter()
+                .filter_map(|bundle| XcodeInstallation::read(bundle).ok()),
+        );
+    }
+    installations.sort_by_key(|installation| std::cmp::Reverse(version_key(&installation.version)));
+
+    Ok(installations)
+}
+
+/// The value of `--xcode`: a version such as `16` or `16.2`, or a path to an Xcode bundle.
+///
+/// A version matches the newest installed Xcode whose version equals it or starts with it
+/// (`16` matches 16.2). A path may name the `.app` bundle or its `Contents/Developer` directory.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::path::PathBuf;
+/// use xcbuild_common::XcodeSelector;
+///
+/// assert_eq!("16.2".parse(), Ok(XcodeSelector::Version(String::from("16.2"))));
+/// assert_eq!(
+///     "/Applications/Xcode-16.2.app/Contents/Developer".parse(),
+///     Ok(XcodeSelector::Path(PathBuf::from("/Applications/Xcode-16.2.app")))
+/// );
+/// assert!("latest".parse::<XcodeSelector>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XcodeSelector {
+    Version(String),
+    Path(PathBuf),
+}
+
+impl XcodeSelector {
+    /// Returns the installed Xcode this selector refers to.
+    pub fn resolve(&self) -> Result<XcodeInstallation> {
+        match self {
+            XcodeSelector::Path(path) => XcodeInstallation::read(path),
+            XcodeSelector::Version(_) => self.select(&find_xcodes()?).cloned(),
+        }
+    }
+
+    /// Picks the installation matching this selector from `installations`, which are expected
+    /// newest first.
+    pub fn select<'a>(
+        &self,
+        installations: &'a [XcodeInstallation],
+    ) -> Result<&'a XcodeInstallation> {
+        let found = match self {
+            XcodeSelector::Version(version) => installations.iter().find(|installation| {
+                installation.version == *version
+                    || installation.version.starts_with(&format!("{}.", version))
+            }),
+            XcodeSelector::Path(path) => installations
+                .iter()
+                .find(|installation| installation.path == *path),
+        };
+
+        found.with_context(|| {
+            let installed = if installations.is_empty() {
+                String::from("none")
+            } else {
+                installations
+                    .iter()
+                    .map(|installation| {
+                        format!("{} ({})", installation.version, installation.path.display())
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            format!(
+                "No installed Xcode matches '{}'; installed Xcodes: {}",
+                self, installed
+            )
+        })
+    }
+}
+
+impl FromStr for XcodeSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        if trimmed.contains('/') || trimmed.ends_with(".app") {
+            let path = Path::new(trimmed);
+            let bundle = if path.ends_with("Contents/Developer") {
+                path.parent().and_then(Path::parent).unwrap_or(path)
+            } else {
+                path
+            };
+            return Ok(XcodeSelector::Path(bundle.to_path_buf()));
+        }
+
+        let is_version = !trimmed.is_empty()
+            && trimmed
+                .split('.')
+                .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+        if !is_version {
+            return Err(format!(
+                "invalid Xcode '{}': expected a version such as 16 or 16.2, or a path to an \
+                 Xcode bundle",
+                s
+            ));
+        }
+
+        Ok(XcodeSelector::Version(trimmed.to_string()))
+    }
+}
+
+impl std::fmt::Display for XcodeSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XcodeSelector::Version(version) => write!(f, "{}", version),
+            XcodeSelector::Path(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Returns the `<string>` value following `<key>key</key>` in an XML property list.
+fn plist_string(plist: &str, key: &str) -> Option<String> {
+    let after_key = plist.split_once(&format!("<key>{}</key>", key))?.1;
+    let value = after_key.trim_start().strip_prefix("<string>")?;
+    let value = value.split_once("</string>")?.0;
+
+    Some(value.trim().to_string())
+}
+
+fn version_key(version: &str) -> Vec<u32> {
+    version
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_xcode(dir: &Path, name: &str, version: &str, build: &str) -> PathBuf {
+        let bundle = dir.join(name);
+        std::fs::create_dir_all(bundle.join("Contents/Developer")).unwrap();
+        std::fs::write(
+            bundle.join("Contents/version.plist"),
+            format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\">\n<dict>\n\
+                 \t<key>CFBundleShortVersionString</key>\n\t<string>{}</string>\n\
+                 \t<key>ProductBuildVersion</key>\n\t<string>{}</string>\n</dict>\n</plist>\n",
+                version, build
+            ),
+        )
+        .unwrap();
+        bundle
+    }
+
+    #[test]
+    fn test_read_falls_back_to_info_plist() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bundle = temp_dir.path().join("Xcode.app");
+        std::fs::create_dir_all(bundle.join("Contents")).unwrap();
+        std::fs::write(
+            bundle.join("Contents/Info.plist"),
+            "<dict>\n<key>CFBundleShortVersionString</key>\n<string>15.4</string>\n\
+             <key>DTXcodeBuild</key>\n<string>15F31d</string>\n</dict>",
+        )
+        .unwrap();
+
+        let installation = XcodeInstallation::read(&bundle).unwrap();
+
+        assert_eq!(installation.version, "15.4");
+        assert_eq!(installation.build.as_deref(), Some("15F31d"));
+        assert_eq!(
+            installation.developer_dir(),
+            bundle.join("Contents/Developer")
+        );
+    }
+
+    #[test]
+    fn test_read_rejects_non_xcode_bundle() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let error = XcodeInstallation::read(temp_dir.path()).unwrap_err();
+
+        assert!(error.to_string().contains("is not an Xcode bundle"));
+    }
+
+    #[test]
+    fn test_find_xcodes_in_sorts_newest_first_and_skips_other_bundles() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_xcode(temp_dir.path(), "Xcode-15.4.app", "15.4", "15F31d");
+        write_xcode(temp_dir.path(), "Xcode.app", "16.2", "16C5032a");
+        write_xcode(temp_dir.path(), "Xcode-16.0.app", "16.0", "16A242d");
+        write_xcode(temp_dir.path(), "Simulator.app", "16.0", "1");
+        std::fs::create_dir_all(temp_dir.path().join("Xcode-broken.app")).unwrap();
+
+        let installations = find_xcodes_in(&[
+            temp_dir.path().to_path_buf(),
+            temp_dir.path().join("missing"),
+            temp_dir.path().to_path_buf(),
+        ])
+        .unwrap();
+
+        let versions: Vec<&str> = installations
+            .iter()
+            .map(|installation| installation.version.as_str())
+            .collect();
+        assert_eq!(versions, ["16.2", "16.0", "15.4"]);
+    }
+
+    #[test]
+    fn test_select_by_version_prefix() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_xcode(temp_dir.path(), "Xcode-15.4.app", "15.4", "15F31d");
+        write_xcode(temp_dir.path(), "Xcode-16.0.app", "16.0", "16A242d");
+        write_xcode(temp_dir.path(), "Xcode-16.2.app", "16.2", "16C5032a");
+        let installations = find_xcodes_in(&[temp_dir.path().to_path_buf()]).unwrap();
+
+        let select = |selector: &str| {
+            selector
+                .parse::<XcodeSelector>()
+                .unwrap()
+                .select(&installations)
+                .map(|installation| installation.version.clone())
+        };
+
+        assert_eq!(select("16").unwrap(), "16.2");
+        assert_eq!(select("16.0").unwrap(), "16.0");
+        assert_eq!(select("15").unwrap(), "15.4");
+        assert!(
+            select("1")
+                .unwrap_err()
+                .to_string()
+                .starts_with("No installed Xcode matches '1'; installed Xcodes: 16.2 (")
+        );
+        assert!(select("16.2.1").is_err());
+    }
+
+    #[test]
+    fn test_parse_selector() {
+        assert_eq!(
+            "Xcode-beta.app".parse(),
+            Ok(XcodeSelector::Path(PathBuf::from("Xcode-beta.app")))
+        );
+        assert_eq!(
+            " 16.2 ".parse(),
+            Ok(XcodeSelector::Version(String::from("16.2")))
+        );
+        for invalid in ["", "16.", "v16", "16.x"] {
+            let error = invalid.parse::<XcodeSelector>().unwrap_err();
+            assert!(error.contains("expected a version"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_with_developer_dir() {
+        let invocation = Invocation::new("xcodebuild").with_arg("-version");
+
+        assert_eq!(
+            with_developer_dir(&invocation, None).to_string(),
+            "xcodebuild -version"
+        );
+        assert_eq!(
+            with_developer_dir(
+                &invocation,
+                Some(Path::new("/Applications/Xcode 16.app/Contents/Developer"))
+            )
+            .to_string(),
+            "DEVELOPER_DIR='/Applications/Xcode 16.app/Contents/Developer' xcodebuild -version"
+        );
+
+        let explicit = invocation.with_env("DEVELOPER_DIR", "/Custom");
+        assert_eq!(
+            with_developer_dir(&explicit, Some(Path::new("/Other"))).to_string(),
+            "DEVELOPER_DIR=/Custom xcodebuild -version"
+        );
+    }
+}
@@ -3,10 +3,9 @@ use glob::glob;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
-    ffi::OsStr,
     path::PathBuf,
-    process::{Command, Stdio},
 };
+use xcbuild_common::{CommandResult, Invocation, run_invocation};
 
 /// Generates acknowledgements file for Swift Package Manager dependencies and git contributors.
 ///
@@ -24,8 +23,8 @@ use std::{
 ///
 /// # Returns
 ///
-/// Returns `Ok(String)` with a success message indicating where the acknowledgements file was written,
-/// or `Err` if the operation fails (e.g., DerivedData not found, file write permissions, etc.).
+/// Returns `Ok(AcknowledgementsResult)` with the path the acknowledgements file was written to and
+/// how many packages and contributors it lists, or `Err` if the operation fails (e.g., DerivedData not found, file write permissions, etc.).
 ///
 /// # Examples
 ///
@@ -85,20 +84,44 @@ use std::{
 /// - The app must have been built at least once to generate DerivedData
 /// - Git repository must exist for contributor analysis
 /// - Write permissions for the output location
-pub fn acknowledgements(app_name: &String, output: &String) -> Result<String> {
-    let packages = get_packages_acknowledgements(&app_name)?;
+pub fn acknowledgements(app_name: &str, output: &str) -> Result<AcknowledgementsResult> {
+    let packages = get_packages_acknowledgements(app_name)?;
     let contributors = get_contributors_list();
     let acknowledgements = Acknowledgements::new(&packages, &contributors);
     let final_output_path = make_final_output_path(output);
     write_acknowledgements(&acknowledgements, &final_output_path)?;
-    let stdout = format!(
-        "✅ Acknowledgements written to: {}",
-        final_output_path.display()
-    );
 
-    Ok(stdout)
+    Ok(AcknowledgementsResult {
+        output_path: final_output_path,
+        packages: packages.len(),
+        contributors: contributors.len(),
+    })
+}
+
+/// The outcome of a successful [`acknowledgements`] run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcknowledgementsResult {
+    /// The JSON file the acknowledgements were written to.
+    pub output_path: PathBuf,
+    /// Number of Swift packages acknowledged.
+    pub packages: usize,
+    /// Number of git contributors acknowledged.
+    pub contributors: usize,
+}
+
+impl std::fmt::Display for AcknowledgementsResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "✅ Acknowledgements written to: {}",
+            self.output_path.display()
+        )
+    }
 }
 
+impl CommandResult for AcknowledgementsResult {}
+
 #[derive(Debug, Serialize)]
 struct Acknowledgements {
     packages: Vec<PackageAcknowledgement>,
@@ -106,10 +129,10 @@ struct Acknowledgements {
 }
 
 impl Acknowledgements {
-    fn new(packages: &Vec<PackageAcknowledgement>, contributors: &Vec<Contributor>) -> Self {
+    fn new(packages: &[PackageAcknowledgement], contributors: &[Contributor]) -> Self {
         Self {
-            packages: packages.clone(),
-            contributors: contributors.clone(),
+            packages: packages.to_vec(),
+            contributors: contributors.to_vec(),
         }
     }
 }
@@ -122,9 +145,9 @@ struct Contributor {
 }
 
 impl Contributor {
-    fn new(name: &String, email: Option<&String>, contributions: &i64) -> Self {
+    fn new(name: &str, email: Option<&String>, contributions: &i64) -> Self {
         Self {
-            name: name.clone(),
+            name: name.to_string(),
             email: email.cloned(),
             contributions: *contributions,
         }
@@ -136,7 +159,7 @@ impl Contributor {
 
     fn first_name(&self) -> Option<&str> {
         let parts = self.name_parts();
-        if parts.len() == 0 {
+        if parts.is_empty() {
             return None;
         }
 
@@ -186,25 +209,24 @@ struct PackageAcknowledgement {
 }
 
 impl PackageAcknowledgement {
-    fn new(name: &String, license: Option<&String>, author: &String, url: &String) -> Self {
+    fn new(name: &str, license: Option<&String>, author: &str, url: &str) -> Self {
         Self {
-            name: name.clone(),
+            name: name.to_string(),
             license: license.cloned(),
-            author: author.clone(),
-            url: url.clone(),
+            author: author.to_string(),
+            url: url.to_string(),
         }
     }
 }
 
-fn make_final_output_path(output: &String) -> PathBuf {
+fn make_final_output_path(output: &str) -> PathBuf {
     let output_path = PathBuf::from(output);
-    let final_output_path = if output_path.is_dir() {
+
+    if output_path.is_dir() {
         output_path.join("acknowledgements.json")
     } else {
         output_path
-    };
-
-    final_output_path
+    }
 }
 
 fn write_acknowledgements(
@@ -213,7 +235,7 @@ fn write_acknowledgements(
 ) -> Result<()> {
     let json_content = serde_json::to_string_pretty(&acknowledgements)
         .context("Failed to serialize acknowledgements to JSON")?;
-    std::fs::write(&output_path, &json_content).context(format!(
+    std::fs::write(output_path, &json_content).context(format!(
         "Failed to write acknowledgements to file: {}",
         output_path.display()
     ))?;
@@ -222,7 +244,9 @@ fn write_acknowledgements(
 }
 
 fn get_contributors_list() -> Vec<Contributor> {
-    let output = match run_zsh_command(&"git --no-pager log \"--pretty=format:%an <%ae>\"") {
+    let invocation =
+        Invocation::new("git").with_args(["--no-pager", "log", "--pretty=format:%an <%ae>"]);
+    let output = match run_command(&invocation) {
         None => return Vec::new(),
         Some(output) => output,
     };
@@ -251,19 +275,19 @@ fn get_contributors_list() -> Vec<Contributor> {
 
                 name.clone()
             });
-            let contributor = Contributor::new(&longest_name, Some(&email), &(names.len() as i64));
+            let contributor = Contributor::new(&longest_name, Some(email), &(names.len() as i64));
             acc.push(contributor);
 
             acc
         },
     );
     let mut contributors = merge_contributors_with_similar_names(&aggregated_contributors);
-    contributors.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    contributors.sort_by_key(|a| a.name.to_lowercase());
 
     contributors.iter().map(|c| c.without_email()).collect()
 }
 
-fn merge_contributors_with_similar_names(contributors: &Vec<Contributor>) -> Vec<Contributor> {
+fn merge_contributors_with_similar_names(contributors: &[Contributor]) -> Vec<Contributor> {
     contributors.iter().fold(
         Vec::<Contributor>::new(),
         |mut merged_contributors, contributor| {
@@ -317,21 +341,18 @@ fn merge_contributors_with_similar_names(contributors: &Vec<Contributor>) -> Vec
     )
 }
 
-fn patch_contributor_name(name: &String) -> String {
+fn patch_contributor_name(name: &str) -> String {
     // TODO: Make this extendable somehow!
     if name == "kamaal111" || name == "Kamaal" {
         String::from("Kamaal Farah")
     } else {
-        name.clone()
+        name.to_string()
     }
 }
 
 /// Extract name from format "Name <email@domain.com>"
 fn extract_name_out_of_contributors_line(line: &str) -> Option<String> {
-    let end = match line.find('<') {
-        None => return None,
-        Some(end) => end,
-    };
+    let end = line.find('<')?;
 
     let name = line[..end].trim().to_string();
     if name.is_empty() {
@@ -343,14 +364,8 @@ fn extract_name_out_of_contributors_line(line: &str) -> Option<String> {
 
 /// Extract email from format "Name <email@domain.com>"
 fn extract_email_out_of_contributors_line(line: &str) -> Option<String> {
-    let start = match line.find('<') {
-        None => return None,
-        Some(start) => start,
-    };
-    let end = match line.find('>') {
-        None => return None,
-        Some(end) => end,
-    };
+    let start = line.find('<')?;
+    let end = line.find('>')?;
     if end <= start {
         return None;
     }
@@ -360,7 +375,7 @@ fn extract_email_out_of_contributors_line(line: &str) -> Option<String> {
     Some(email)
 }
 
-fn get_packages_acknowledgements(app_name: &String) -> Result<Vec<PackageAcknowledgement>> {
+fn get_packages_acknowledgements(app_name: &str) -> Result<Vec<PackageAcknowledgement>> {
     let packages_directory = find_derived_data_for_app(app_name)?.join("SourcePackages");
     let packages_licenses = get_packages_licenses(&packages_directory.join("checkouts"))?;
     let packages_urls = get_packages_urls(&packages_directory.join("workspace-state.json"))?;
@@ -383,7 +398,7 @@ fn make_packages_acknowledgements(
                 url_parts[url_parts.len() - 2].to_string()
             } else {
                 // Fallback for URLs with insufficient parts
-                url_parts.get(0).unwrap_or(&"unknown").to_string()
+                url_parts.first().unwrap_or(&"unknown").to_string()
             };
             let entry = PackageAcknowledgement::new(name, license, &author, url);
             acc.push(entry);
@@ -479,7 +494,7 @@ fn get_packages_licenses(packages_directory: &PathBuf) -> Result<HashMap<String,
     Ok(licenses)
 }
 
-fn find_derived_data_for_app(app_name: &String) -> Result<PathBuf> {
+fn find_derived_data_for_app(app_name: &str) -> Result<PathBuf> {
     let xcode_derived_data_base_display = get_xcode_derived_data_base()?;
     let glob_pattern = format!(
         "{}-*",
@@ -537,21 +552,8 @@ fn get_default_derived_data_base() -> Result<PathBuf> {
     Ok(result)
 }
 
-fn run_zsh_command<S>(command: &S) -> Option<String>
-where
-    S: AsRef<OsStr>,
-{
-    let child = match Command::new("zsh")
-        .arg("-c")
-        .arg(command)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .spawn()
-    {
-        Err(_error) => return None,
-        Ok(child) => child,
-    };
-    let output = match child.wait_with_output() {
+fn run_command(invocation: &Invocation) -> Option<String> {
+    let output = match run_invocation(invocation) {
         Err(_error) => return None,
         Ok(output) => output,
     };
@@ -564,8 +566,12 @@ where
 }
 
 fn get_user_configured_derived_data_base() -> Option<PathBuf> {
-    let stdout_string =
-        run_zsh_command(&"defaults read com.apple.dt.Xcode IDECustomDerivedDataLocation")?;
+    let invocation = Invocation::new("defaults").with_args([
+        "read",
+        "com.apple.dt.Xcode",
+        "IDECustomDerivedDataLocation",
+    ]);
+    let stdout_string = run_command(&invocation)?;
     let trimmed_stdout_string = stdout_string.trim();
     if trimmed_stdout_string.is_empty() {
         return None;
@@ -727,18 +733,9 @@ mod tests {
 
     #[test]
     fn test_patch_contributor_name() {
-        assert_eq!(
-            patch_contributor_name(&"kamaal111".to_string()),
-            "Kamaal Farah"
-        );
-        assert_eq!(
-            patch_contributor_name(&"Kamaal".to_string()),
-            "Kamaal Farah"
-        );
-        assert_eq!(
-            patch_contributor_name(&"Other Name".to_string()),
-            "Other Name"
-        );
+        assert_eq!(patch_contributor_name("kamaal111"), "Kamaal Farah");
+        assert_eq!(patch_contributor_name("Kamaal"), "Kamaal Farah");
+        assert_eq!(patch_contributor_name("Other Name"), "Other Name");
     }
 
     #[test]
@@ -968,10 +965,7 @@ mod tests {
 
         // Since we can't easily mock the git commands and derived data discovery,
         // this test focuses on the JSON serialization and file writing parts
-        let _result = acknowledgements(
-            &"TestApp".to_string(),
-            &output_path.to_string_lossy().to_string(),
-        );
+        let _result = acknowledgements("TestApp", output_path.to_string_lossy().as_ref());
 
         // The function might fail due to DerivedData discovery, but we're testing the structure
         // In a real scenario, this would require more complex mocking
@@ -1115,15 +1109,15 @@ mod tests {
     }
 
     #[test]
-    fn test_run_zsh_command_success() {
-        let result = run_zsh_command(&"echo 'test'");
+    fn test_run_command_success() {
+        let result = run_command(&Invocation::new("echo").with_arg("test"));
         assert!(result.is_some());
         assert_eq!(result.unwrap().trim(), "test");
     }
 
     #[test]
-    fn test_run_zsh_command_failure() {
-        let result = run_zsh_command(&"nonexistentcommand12345");
+    fn test_run_command_failure() {
+        let result = run_command(&Invocation::new("nonexistentcommand12345"));
         // The command should fail but the function returns None on error
         // This tests the error handling path
         assert!(result.is_none() || result.unwrap().is_empty());
@@ -1255,10 +1249,10 @@ mod tests {
     #[test]
     fn test_package_acknowledgement_methods() {
         let package = PackageAcknowledgement::new(
-            &"TestPackage".to_string(),
+            "TestPackage",
             Some(&"MIT License".to_string()),
-            &"TestAuthor".to_string(),
-            &"https://github.com/testauthor/testpackage".to_string(),
+            "TestAuthor",
+            "https://github.com/testauthor/testpackage",
         );
 
         assert_eq!(package.name, "TestPackage");
@@ -1268,10 +1262,10 @@ mod tests {
 
         // Test with None license
         let package_no_license = PackageAcknowledgement::new(
-            &"TestPackage2".to_string(),
+            "TestPackage2",
             None,
-            &"TestAuthor2".to_string(),
-            &"https://github.com/testauthor2/testpackage2".to_string(),
+            "TestAuthor2",
+            "https://github.com/testauthor2/testpackage2",
         );
 
         assert_eq!(package_no_license.license, None);
@@ -1334,7 +1328,7 @@ mod tests {
         std::fs::create_dir_all(&app2_dir).unwrap();
 
         // Test finding derived data for app
-        let result = find_derived_data_for_app(&"TestApp".to_string());
+        let result = find_derived_data_for_app("TestApp");
 
         // Should find one of the directories (the most recently modified)
         assert!(result.is_ok() || result.is_err()); // Either finds it or doesn't due to timing
@@ -1,9 +1,46 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
+use serde::Serialize;
 use xcbuild_common::{
-    BuildTarget, Configuration, SDK, XcodebuildCommandAction, XcodebuildParams,
-    run_xcodebuild_command,
+    BuildOverrides, BuildTarget, CommandResult, Configuration, Destination, SDK,
+    XcodebuildCommandAction, XcodebuildParams, run_xcodebuild_command, xcodebuild_warnings,
 };
 
+/// The outcome of a successful [`archive`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveResult {
+    pub scheme: String,
+    /// The configuration name passed to xcodebuild, e.g. `Release`.
+    pub configuration: String,
+    pub sdk: String,
+    pub destinations: Vec<String>,
+    /// The .xcarchive bundle that was created.
+    pub archive_path: PathBuf,
+    /// Warnings found in xcodebuild's output; reported at the top level of the JSON report.
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+impl std::fmt::Display for ArchiveResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Archived scheme {} ({}) to {}",
+            self.scheme,
+            self.configuration,
+            self.archive_path.display()
+        )
+    }
+}
+
+impl CommandResult for ArchiveResult {
+    fn warnings(&self) -> Vec<String> {
+        self.warnings.clone()
+    }
+}
+
 /// Creates an archive for an Xcode project or workspace using the `xcodebuild` command-line tool.
 ///
 /// This function constructs and executes an `xcodebuild archive` command with the specified parameters
@@ -14,7 +51,8 @@ use xcbuild_common::{
 /// # Arguments
 ///
 /// * `scheme` - The Xcode scheme name to archive (e.g., "MyApp", "MyApp Release")
-/// * `destination` - The archive destination specifying the target platform:
+/// * `destinations` - One or more archive destinations specifying the target platform,
+///   each passed to xcodebuild with `-destination`:
 ///   - Generic iOS: "generic/platform=iOS"
 ///   - Generic macOS: "generic/platform=macOS"
 /// * `configuration` - The build configuration to use (Debug or Release)
@@ -26,28 +64,32 @@ use xcbuild_common::{
 ///   `workspace` must be provided, but not both.
 /// * `workspace` - Optional path to the Xcode workspace file (.xcworkspace). Either this or
 ///   `project` must be provided, but not both.
+/// * `overrides` - Build setting overrides (`KEY=VALUE` pairs and an optional `.xcconfig` file)
+///   appended to the xcodebuild command
 ///
 /// # Returns
 ///
-/// Returns `Ok(String)` containing the stdout from the xcodebuild archive command on success,
-/// or `Err` if the archive process fails or if neither project nor workspace is specified.
+/// Returns `Ok(ArchiveResult)` with the path of the created archive and the warnings xcodebuild
+/// reported, or `Err` if the archive process fails or if neither project nor workspace is
+/// specified.
 ///
 /// # Examples
 ///
 /// ## Archiving parameter validation - neither project nor workspace:
 /// ```rust
 /// use xctools_archive::archive;
-/// use xcbuild_common::{Configuration, SDK};
+/// use xcbuild_common::{BuildOverrides, Configuration, SDK};
 ///
 /// // This should fail because neither project nor workspace is specified
 /// let result = archive(
 ///     &"MyApp".to_string(),
-///     &"generic/platform=iOS".to_string(),
+///     &["generic/platform=iOS".parse().unwrap()],
 ///     &Configuration::Release,
 ///     &SDK::Iphoneos,
 ///     &"MyApp.xcarchive".to_string(),
 ///     &None,
 ///     &None,
+///     &BuildOverrides::new(),
 /// );
 /// assert!(result.is_err());
 /// let error_msg = result.unwrap_err().to_string();
@@ -72,18 +114,19 @@ use xcbuild_common::{
 /// ## Archiving with project parameter (will attempt to create archive):
 /// ```rust,no_run
 /// use xctools_archive::archive;
-/// use xcbuild_common::{Configuration, SDK};
+/// use xcbuild_common::{BuildOverrides, Configuration, SDK};
 ///
 /// // This example shows the function signature but doesn't run
 /// // because it would try to execute xcodebuild archive with a non-existent project
 /// let result = archive(
 ///     &"MyApp".to_string(),
-///     &"generic/platform=iOS".to_string(),
+///     &["generic/platform=iOS".parse().unwrap()],
 ///     &Configuration::Release,
 ///     &SDK::Iphoneos,
 ///     &"build/MyApp.xcarchive".to_string(),
 ///     &Some("MyApp.xcodeproj".to_string()),
 ///     &None,
+///     &BuildOverrides::new(),
 /// );
 /// // In a real scenario with a valid project, this would either succeed or
 /// // fail based on the actual build results
@@ -92,18 +135,19 @@ use xcbuild_common::{
 /// ## Archiving with workspace parameter (will attempt to create archive):
 /// ```rust,no_run
 /// use xctools_archive::archive;
-/// use xcbuild_common::{Configuration, SDK};
+/// use xcbuild_common::{BuildOverrides, Configuration, SDK};
 ///
 /// // This example shows the function signature but doesn't run
 /// // because it would try to execute xcodebuild archive with a non-existent workspace
 /// let result = archive(
 ///     &"MyApp".to_string(),
-///     &"generic/platform=macOS".to_string(),
+///     &["generic/platform=macOS".parse().unwrap()],
 ///     &Configuration::Release,
 ///     &SDK::Macosx,
 ///     &"archives/MyApp.xcarchive".to_string(),
 ///     &None,
 ///     &Some("MyApp.xcworkspace".to_string()),
+///     &BuildOverrides::new(),
 /// );
 /// // In a real scenario with a valid workspace, this would either succeed or
 /// // fail based on the actual build results
@@ -164,26 +208,36 @@ use xcbuild_common::{
 /// - Sufficient disk space for the archive output
 /// - Write permissions for the output directory
 /// - The SDK must match the target platform
+#[allow(clippy::too_many_arguments)]
 pub fn archive(
-    scheme: &String,
-    destination: &String,
+    scheme: &str,
+    destinations: &[Destination],
     configuration: &Configuration,
     sdk: &SDK,
-    output: &String,
+    output: &str,
     project: &Option<String>,
     workspace: &Option<String>,
-) -> Result<String> {
+    overrides: &BuildOverrides,
+) -> Result<ArchiveResult> {
     let target = BuildTarget::new(project.as_ref(), workspace.as_ref());
     let params = XcodebuildParams::new(XcodebuildCommandAction::Archive)
-        .with_scheme(scheme.clone())
-        .with_destination(destination.clone())
+        .with_scheme(scheme.to_string())
+        .with_destinations(destinations.to_vec())
         .with_configuration(configuration.clone())
         .with_target(target)
+        .with_overrides(overrides.clone())
         .with_sdk(sdk.clone())
-        .with_archive_path(output.clone());
-    let output = run_xcodebuild_command(&params)?;
-
-    Ok(output)
+        .with_archive_path(output.to_string());
+    let xcodebuild_output = run_xcodebuild_command(&params)?;
+
+    Ok(ArchiveResult {
+        scheme: scheme.to_string(),
+        configuration: configuration.command_string(),
+        sdk: sdk.command_string(),
+        destinations: destinations.iter().map(ToString::to_string).collect(),
+        archive_path: PathBuf::from(output),
+        warnings: xcodebuild_warnings(&xcodebuild_output),
+    })
 }
 
 #[cfg(test)]
@@ -196,13 +250,14 @@ mod tests {
         // Test that archive function properly validates parameters
         // This will fail because neither project nor workspace is specified
         let result = archive(
-            &"MyApp".to_string(),
-            &"generic/platform=iOS".to_string(),
+            "MyApp",
+            &["generic/platform=iOS".parse().unwrap()],
             &Configuration::Release,
             &SDK::Iphoneos,
-            &"MyApp.xcarchive".to_string(),
+            "MyApp.xcarchive",
             &None,
             &None,
+            &BuildOverrides::new(),
         );
 
         assert!(result.is_err());
@@ -215,13 +270,14 @@ mod tests {
         // Test that archive function properly validates parameters
         // This will fail because neither project nor workspace is specified
         let result = archive(
-            &"MyApp".to_string(),
-            &"generic/platform=macOS".to_string(),
+            "MyApp",
+            &["generic/platform=macOS".parse().unwrap()],
             &Configuration::Debug,
             &SDK::Macosx,
-            &"MyApp.xcarchive".to_string(),
+            "MyApp.xcarchive",
             &None,
             &None,
+            &BuildOverrides::new(),
         );
 
         assert!(result.is_err());
@@ -233,13 +289,14 @@ mod tests {
     fn test_archive_parameter_validation_debug_configuration() {
         // Test archive with Debug configuration
         let result = archive(
-            &"TestScheme".to_string(),
-            &"generic/platform=iOS".to_string(),
+            "TestScheme",
+            &["generic/platform=iOS".parse().unwrap()],
             &Configuration::Debug,
             &SDK::Iphoneos,
-            &"TestApp-Debug.xcarchive".to_string(),
+            "TestApp-Debug.xcarchive",
             &None,
             &None,
+            &BuildOverrides::new(),
         );
 
         assert!(result.is_err());
@@ -255,13 +312,14 @@ mod tests {
     fn test_archive_parameter_validation_release_configuration() {
         // Test archive with Release configuration
         let result = archive(
-            &"TestScheme".to_string(),
-            &"generic/platform=macOS".to_string(),
+            "TestScheme",
+            &["generic/platform=macOS".parse().unwrap()],
             &Configuration::Release,
             &SDK::Macosx,
-            &"TestApp-Release.xcarchive".to_string(),
+            "TestApp-Release.xcarchive",
             &None,
             &None,
+            &BuildOverrides::new(),
         );
 
         assert!(result.is_err());
@@ -277,13 +335,14 @@ mod tests {
     fn test_archive_with_custom_output_path() {
         // Test archive with custom output path
         let result = archive(
-            &"MyTestApp".to_string(),
-            &"generic/platform=iOS".to_string(),
+            "MyTestApp",
+            &["generic/platform=iOS".parse().unwrap()],
             &Configuration::Release,
             &SDK::Iphoneos,
-            &"/tmp/build/archives/MyTestApp-v1.0.0.xcarchive".to_string(),
+            "/tmp/build/archives/MyTestApp-v1.0.0.xcarchive",
             &None,
             &None,
+            &BuildOverrides::new(),
         );
 
         assert!(result.is_err());
@@ -302,13 +361,14 @@ mod tests {
 
         for destination in destinations {
             let result = archive(
-                &"TestApp".to_string(),
-                &destination.to_string(),
+                "TestApp",
+                &[destination.parse().unwrap()],
                 &Configuration::Release,
                 &SDK::Iphoneos,
-                &"TestApp.xcarchive".to_string(),
+                "TestApp.xcarchive",
                 &None,
                 &None,
+                &BuildOverrides::new(),
             );
 
             assert!(result.is_err());
@@ -328,13 +388,14 @@ mod tests {
 
         for destination in destinations {
             let result = archive(
-                &"TestApp".to_string(),
-                &destination.to_string(),
+                "TestApp",
+                &[destination.parse().unwrap()],
                 &Configuration::Release,
                 &SDK::Macosx,
-                &"TestApp.xcarchive".to_string(),
+                "TestApp.xcarchive",
                 &None,
                 &None,
+                &BuildOverrides::new(),
             );
 
             assert!(result.is_err());
@@ -359,13 +420,14 @@ mod tests {
 
         for scheme in schemes {
             let result = archive(
-                &scheme.to_string(),
-                &"generic/platform=iOS".to_string(),
+                scheme,
+                &["generic/platform=iOS".parse().unwrap()],
                 &Configuration::Release,
                 &SDK::Iphoneos,
-                &"Archive.xcarchive".to_string(),
+                "Archive.xcarchive",
                 &None,
                 &None,
+                &BuildOverrides::new(),
             );
 
             assert!(result.is_err());
@@ -392,13 +454,14 @@ mod tests {
 
         for output in outputs {
             let result = archive(
-                &"TestApp".to_string(),
-                &"generic/platform=iOS".to_string(),
+                "TestApp",
+                &["generic/platform=iOS".parse().unwrap()],
                 &Configuration::Release,
                 &SDK::Iphoneos,
-                &output.to_string(),
+                output,
                 &None,
                 &None,
+                &BuildOverrides::new(),
             );
 
             assert!(result.is_err());
@@ -415,23 +478,25 @@ mod tests {
     fn test_archive_configuration_enum_values() {
         // Test Configuration enum usage in archive function
         let debug_result = archive(
-            &"TestApp".to_string(),
-            &"generic/platform=iOS".to_string(),
+            "TestApp",
+            &["generic/platform=iOS".parse().unwrap()],
             &Configuration::Debug,
             &SDK::Iphoneos,
-            &"TestApp-Debug.xcarchive".to_string(),
+            "TestApp-Debug.xcarchive",
             &None,
             &None,
+            &BuildOverrides::new(),
         );
 
         let release_result = archive(
-            &"TestApp".to_string(),
-            &"generic/platform=iOS".to_string(),
+            "TestApp",
+            &["generic/platform=iOS".parse().unwrap()],
             &Configuration::Release,
             &SDK::Iphoneos,
-            &"TestApp-Release.xcarchive".to_string(),
+            "TestApp-Release.xcarchive",
             &None,
             &None,
+            &BuildOverrides::new(),
         );
 
         // Both should fail with the same error (no project/workspace)
@@ -447,23 +512,25 @@ mod tests {
     fn test_archive_sdk_enum_values() {
         // Test SDK enum usage in archive function
         let ios_result = archive(
-            &"TestApp".to_string(),
-            &"generic/platform=iOS".to_string(),
+            "TestApp",
+            &["generic/platform=iOS".parse().unwrap()],
             &Configuration::Release,
             &SDK::Iphoneos,
-            &"TestApp-iOS.xcarchive".to_string(),
+            "TestApp-iOS.xcarchive",
             &None,
             &None,
+            &BuildOverrides::new(),
         );
 
         let macos_result = archive(
-            &"TestApp".to_string(),
-            &"generic/platform=macOS".to_string(),
+            "TestApp",
+            &["generic/platform=macOS".parse().unwrap()],
             &Configuration::Release,
             &SDK::Macosx,
-            &"TestApp-macOS.xcarchive".to_string(),
+            "TestApp-macOS.xcarchive",
             &None,
             &None,
+            &BuildOverrides::new(),
         );
 
         // Both should fail with the same error (no project/workspace)
@@ -479,7 +546,7 @@ mod tests {
     fn test_archive_function_signature() {
         // Test that the archive function accepts the correct parameter types
         let scheme = "TestScheme".to_string();
-        let destination = "generic/platform=iOS".to_string();
+        let destination: Destination = "generic/platform=iOS".parse().unwrap();
         let configuration = Configuration::Release;
         let sdk = SDK::Iphoneos;
         let output = "TestApp.xcarchive".to_string();
@@ -489,12 +556,13 @@ mod tests {
         // This should compile and demonstrate the correct function signature
         let _result = archive(
             &scheme,
-            &destination,
+            &[destination],
             &configuration,
             &sdk,
             &output,
             &project,
             &workspace,
+            &BuildOverrides::new(),
         );
 
         // We don't assert on the result since it will fail due to missing xcodebuild,
@@ -505,13 +573,14 @@ mod tests {
     fn test_archive_with_both_project_and_workspace_none() {
         // Test the specific error case when both project and workspace are None
         let result = archive(
-            &"TestScheme".to_string(),
-            &"generic/platform=iOS".to_string(),
+            "TestScheme",
+            &["generic/platform=iOS".parse().unwrap()],
             &Configuration::Release,
             &SDK::Iphoneos,
-            &"TestApp.xcarchive".to_string(),
+            "TestApp.xcarchive",
             &None,
             &None,
+            &BuildOverrides::new(),
         );
 
         assert!(result.is_err());
@@ -527,13 +596,14 @@ mod tests {
     fn test_archive_return_type() {
         // Test that archive function returns Result<String>
         let result = archive(
-            &"TestScheme".to_string(),
-            &"generic/platform=iOS".to_string(),
+            "TestScheme",
+            &["generic/platform=iOS".parse().unwrap()],
             &Configuration::Release,
             &SDK::Iphoneos,
-            &"TestApp.xcarchive".to_string(),
+            "TestApp.xcarchive",
             &None,
             &None,
+            &BuildOverrides::new(),
         );
 
         // Verify it's a Result<String> by checking the error type
@@ -544,7 +614,7 @@ mod tests {
             }
             Err(error) => {
                 // Error should be anyhow::Error
-                assert!(error.to_string().len() > 0);
+                assert!(!error.to_string().is_empty());
             }
         }
     }
@@ -1,7 +1,41 @@
+use serde::Serialize;
 use xcbuild_common::{
-    BuildTarget, Configuration, XcodebuildCommandAction, XcodebuildParams, run_xcodebuild_command,
+    BuildOverrides, BuildTarget, CommandResult, Configuration, Destination, SDK,
+    XcodebuildCommandAction, XcodebuildParams, run_xcodebuild_command, xcodebuild_warnings,
 };
 
+/// The outcome of a successful [`build`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildResult {
+    pub scheme: String,
+    /// The configuration name passed to xcodebuild, e.g. `Debug`.
+    pub configuration: String,
+    pub sdk: Option<String>,
+    pub destinations: Vec<String>,
+    /// Warnings found in xcodebuild's output; reported at the top level of the JSON report.
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+impl std::fmt::Display for BuildResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Built scheme {} ({}) with {} warning(s)",
+            self.scheme,
+            self.configuration,
+            self.warnings.len()
+        )
+    }
+}
+
+impl CommandResult for BuildResult {
+    fn warnings(&self) -> Vec<String> {
+        self.warnings.clone()
+    }
+}
+
 /// Builds an Xcode project or workspace using the `xcodebuild` command-line tool.
 ///
 /// This function constructs and executes an `xcodebuild` command with the specified parameters
@@ -11,35 +45,41 @@ use xcbuild_common::{
 /// # Arguments
 ///
 /// * `scheme` - The Xcode scheme name to build (e.g., "MyApp", "MyAppTests")
-/// * `destination` - The build destination specifying the target device or simulator:
-///   - iOS Simulator: "iOS Simulator,name=iPhone 15 Pro"
+/// * `destinations` - One or more build destinations specifying the target device or simulator,
+///   each passed to xcodebuild with `-destination`:
+///   - iOS Simulator: "platform=iOS Simulator,name=iPhone 15 Pro"
 ///   - Generic iOS: "generic/platform=iOS"
 ///   - macOS: "platform=macOS"
 /// * `configuration` - The build configuration to use (Debug or Release)
+/// * `sdk` - Optional SDK passed to xcodebuild with `-sdk` (e.g. `SDK::Iphonesimulator`)
 /// * `project` - Optional path to the Xcode project file (.xcodeproj). Either this or
 ///   `workspace` must be provided, but not both.
 /// * `workspace` - Optional path to the Xcode workspace file (.xcworkspace). Either this or
 ///   `project` must be provided, but not both.
+/// * `overrides` - Build setting overrides (`KEY=VALUE` pairs and an optional `.xcconfig` file)
+///   appended to the xcodebuild command
 ///
 /// # Returns
 ///
-/// Returns `Ok(String)` containing the stdout from the xcodebuild command on success,
-/// or `Err` if the build fails or if neither project nor workspace is specified.
+/// Returns `Ok(BuildResult)` describing what was built, including the warnings xcodebuild
+/// reported, or `Err` if the build fails or if neither project nor workspace is specified.
 ///
 /// # Examples
 ///
 /// ## Testing parameter validation - neither project nor workspace:
 /// ```rust
 /// use xctools_build::build;
-/// use xcbuild_common::Configuration;
+/// use xcbuild_common::{BuildOverrides, Configuration};
 ///
 /// // This should fail because neither project nor workspace is specified
 /// let result = build(
 ///     &"MyApp".to_string(),
-///     &"iOS Simulator,name=iPhone 15 Pro".to_string(),
+///     &["platform=iOS Simulator,name=iPhone 15 Pro".parse().unwrap()],
 ///     &Configuration::Debug,
 ///     &None,
 ///     &None,
+///     &None,
+///     &BuildOverrides::new(),
 /// );
 /// assert!(result.is_err());
 /// let error_msg = result.unwrap_err().to_string();
@@ -60,16 +100,18 @@ use xcbuild_common::{
 /// ## Testing with project parameter (will attempt to build):
 /// ```rust,no_run
 /// use xctools_build::build;
-/// use xcbuild_common::Configuration;
+/// use xcbuild_common::{BuildOverrides, Configuration};
 ///
 /// // This example shows the function signature but doesn't run
 /// // because it would try to execute xcodebuild with a non-existent project
 /// let result = build(
 ///     &"MyApp".to_string(),
-///     &"iOS Simulator,name=iPhone 15 Pro".to_string(),
+///     &["platform=iOS Simulator,name=iPhone 15 Pro".parse().unwrap()],
 ///     &Configuration::Debug,
+///     &None,
 ///     &Some("MyApp.xcodeproj".to_string()),
 ///     &None,
+///     &BuildOverrides::new(),
 /// );
 /// // In a real scenario with a valid project, this would either succeed or
 /// // fail based on the actual build outcome
@@ -78,16 +120,18 @@ use xcbuild_common::{
 /// ## Testing with workspace parameter (will attempt to build):
 /// ```rust,no_run
 /// use xctools_build::build;
-/// use xcbuild_common::Configuration;
+/// use xcbuild_common::{BuildOverrides, Configuration};
 ///
 /// // This example shows the function signature but doesn't run
 /// // because it would try to execute xcodebuild with a non-existent workspace
 /// let result = build(
 ///     &"MyApp".to_string(),
-///     &"generic/platform=iOS".to_string(),
+///     &["generic/platform=iOS".parse().unwrap()],
 ///     &Configuration::Release,
 ///     &None,
+///     &None,
 ///     &Some("MyApp.xcworkspace".to_string()),
+///     &BuildOverrides::new(),
 /// );
 /// // In a real scenario with a valid workspace, this would either succeed or
 /// // fail based on the actual build outcome
@@ -96,7 +140,7 @@ use xcbuild_common::{
 /// ## Using the xctools CLI:
 /// ```bash
 /// # Build with project file and Debug configuration (default)
-/// xctools build --scheme MyApp --destination "iOS Simulator,name=iPhone 15 Pro" --project MyApp.xcodeproj
+/// xctools build --scheme MyApp --destination "platform=iOS Simulator,name=iPhone 15 Pro" --project MyApp.xcodeproj
 ///
 /// # Build with workspace file and Release configuration
 /// xctools build --scheme MyApp --destination "generic/platform=iOS" --workspace MyApp.xcworkspace --configuration release
@@ -109,7 +153,7 @@ use xcbuild_common::{
 ///
 /// The function generates an xcodebuild command in the format:
 /// ```bash
-/// xcodebuild build -project MyApp.xcodeproj -scheme MyApp -destination 'iOS Simulator,name=iPhone 15 Pro' -configuration Debug
+/// xcodebuild build -project MyApp.xcodeproj -scheme MyApp -destination 'platform=iOS Simulator,name=iPhone 15 Pro' -configuration Debug
 /// ```
 ///
 /// # Requirements
@@ -119,21 +163,34 @@ use xcbuild_common::{
 /// - The specified scheme must exist in the project/workspace
 /// - The destination must be valid for the target platform
 pub fn build(
-    scheme: &String,
-    destination: &String,
+    scheme: &str,
+    destinations: &[Destination],
     configuration: &Configuration,
+    sdk: &Option<SDK>,
     project: &Option<String>,
     workspace: &Option<String>,
-) -> anyhow::Result<String> {
+    overrides: &BuildOverrides,
+) -> anyhow::Result<BuildResult> {
     let target = BuildTarget::new(project.as_ref(), workspace.as_ref());
     let params = XcodebuildParams::new(XcodebuildCommandAction::Build)
-        .with_scheme(scheme.clone())
-        .with_destination(destination.clone())
+        .with_scheme(scheme.to_string())
+        .with_destinations(destinations.to_vec())
         .with_configuration(configuration.clone())
-        .with_target(target);
+        .with_target(target)
+        .with_overrides(overrides.clone());
+    let params = match sdk {
+        Some(sdk) => params.with_sdk(sdk.clone()),
+        None => params,
+    };
     let output = run_xcodebuild_command(&params)?;
 
-    Ok(output)
+    Ok(BuildResult {
+        scheme: scheme.to_string(),
+        configuration: configuration.command_string(),
+        sdk: sdk.as_ref().map(SDK::command_string),
+        destinations: destinations.iter().map(ToString::to_string).collect(),
+        warnings: xcodebuild_warnings(&output),
+    })
 }
 
 #[cfg(test)]
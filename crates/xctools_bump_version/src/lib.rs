@@ -1,7 +1,45 @@
 use anyhow::{Result, anyhow, bail};
 use glob::glob;
 use semver::Version;
+use serde::Serialize;
 use std::path::{Path, PathBuf};
+use xcbuild_common::CommandResult;
+
+/// The outcome of a successful [`bump_version`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionBump {
+    /// The `project.pbxproj` file that was updated.
+    pub project_file: PathBuf,
+    /// The new `CURRENT_PROJECT_VERSION`, if it was changed.
+    pub build_number: Option<i32>,
+    /// The new `MARKETING_VERSION`, if it was changed.
+    pub version_number: Option<String>,
+}
+
+impl std::fmt::Display for VersionBump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Successfully updated project.pbxproj at: {}",
+            self.project_file.display()
+        )?;
+        writeln!(
+            f,
+            "Build number set to: {}",
+            self.build_number
+                .map(|number| number.to_string())
+                .unwrap_or(String::from("UNSET"))
+        )?;
+        writeln!(
+            f,
+            "Version number set to: {}",
+            self.version_number.as_deref().unwrap_or("UNSET")
+        )
+    }
+}
+
+impl CommandResult for VersionBump {}
 
 /// Updates version numbers and build numbers in Xcode project files.
 ///
@@ -19,8 +57,7 @@ use std::path::{Path, PathBuf};
 ///
 /// # Returns
 ///
-/// Returns `Ok(String)` with a success message indicating what was updated and where,
-/// or `Err` if the operation fails (e.g., no project.pbxproj found, file read/write errors).
+/// Returns `Ok(VersionBump)` describing what was updated and where, or `Err` if the operation fails (e.g., no project.pbxproj found, file read/write errors).
 ///
 /// # Examples
 ///
@@ -75,7 +112,7 @@ use std::path::{Path, PathBuf};
 /// // Update build number only
 /// let result = bump_version(&Some(42), &None);
 /// match result {
-///     Ok(message) => println!("{}", message),
+///     Ok(bump) => print!("{}", bump),
 ///     Err(e) => eprintln!("Error: {}", e),
 /// }
 ///
@@ -114,7 +151,7 @@ use std::path::{Path, PathBuf};
 pub fn bump_version(
     build_number: &Option<i32>,
     version_number: &Option<Version>,
-) -> Result<String> {
+) -> Result<VersionBump> {
     bump_version_in_path(build_number, version_number, None)
 }
 
@@ -122,15 +159,12 @@ fn bump_version_in_path(
     build_number: &Option<i32>,
     version_number: &Option<Version>,
     search_path: Option<&Path>,
-) -> Result<String> {
+) -> Result<VersionBump> {
     let pbxproj_filepath = match find_first_pbxproj_filepath(search_path) {
         None => bail!("No project.pbxproj found"),
         Some(pbxproj_filepath) => pbxproj_filepath,
     };
-    let content = match read_pbxproj_file(&pbxproj_filepath) {
-        Err(error) => return Err(error),
-        Ok(content) => content,
-    };
+    let content = read_pbxproj_file(&pbxproj_filepath)?;
     let updated_content = content
         .lines()
         .map(|line| replace_pbxproj_line(line, build_number, version_number))
@@ -143,21 +177,13 @@ fn bump_version_in_path(
             e
         )
     });
-    if let Err(error) = write_result {
-        return Err(error);
-    }
+    write_result?;
 
-    Ok(format!(
-        "Successfully updated project.pbxproj at: {}\nBuild number set to: {}\nVersion number set to: {}\n",
-        pbxproj_filepath.display(),
-        build_number
-            .map(|number| number.to_string())
-            .unwrap_or(String::from("UNSET")),
-        version_number
-            .clone()
-            .map(|version| version.to_string())
-            .unwrap_or(String::from("UNSET"))
-    ))
+    Ok(VersionBump {
+        project_file: pbxproj_filepath,
+        build_number: *build_number,
+        version_number: version_number.as_ref().map(ToString::to_string),
+    })
 }
 
 fn replace_pbxproj_line(
@@ -248,8 +274,10 @@ mod tests {
         )
         .unwrap();
 
-        assert!(output.contains("Build number set to: 5"));
-        assert!(output.contains("Version number set to: 2.3.4"));
+        assert_eq!(output.build_number, Some(5));
+        assert_eq!(output.version_number.as_deref(), Some("2.3.4"));
+        assert!(output.to_string().contains("Build number set to: 5"));
+        assert!(output.to_string().contains("Version number set to: 2.3.4"));
 
         let content = std::fs::read_to_string(&filepath).unwrap();
 
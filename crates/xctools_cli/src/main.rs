@@ -1,78 +1,317 @@
-use clap::{ArgGroup, Parser, Subcommand, builder::ValueParser};
-use xcbuild_common::{Configuration, SDK, UploadTarget};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand, builder::ValueParser};
+use serde::Serialize;
+use xcbuild_common::{
+    BuildOverrides, BuildTarget, CommandError, CommandErrorKind, CommandResult, Configuration,
+    Destination, DryRunRunner, Formatter, OutputFormat, RenderedResult, Report, RetryPolicy, SDK,
+    SimulatorSelector, TestSelection, UploadTarget, XcodeInstallation, XcodeSelector, find_xcodes,
+    list_simulators, parse_duration, set_command_runner,
+};
 use xctools_acknowledgements::acknowledgements;
 use xctools_archive::archive;
 use xctools_build::build;
 use xctools_bump_version::bump_version;
+use xctools_config::Config;
+use xctools_doctor::doctor;
 use xctools_export_archive::export_archive;
+use xctools_list::{ListFormat, list};
 use xctools_notarize::notarize;
+use xctools_run::run_pipeline;
 use xctools_setup_signing::setup_signing;
-use xctools_test::test;
+use xctools_simulator::{
+    StatusBarOverrides, boot_simulators, create_simulator, delete_simulators, erase_simulators,
+    set_status_bar,
+};
+use xctools_test::{
+    DEFAULT_FLAKE_HISTORY_PATH, Shard, TestOptions, build_for_testing, flaky_report, test,
+    test_without_building,
+};
 use xctools_upload::upload;
 
+/// Exit code for failures without a more specific code below.
+const EXIT_FAILURE: i32 = 1;
+/// Exit code for invalid arguments or an invalid xctools.toml, matching clap's usage errors.
+const EXIT_INVALID_ARGUMENTS: i32 = 2;
+/// Exit code when a required tool such as xcodebuild or xcrun is not installed.
+const EXIT_TOOL_MISSING: i32 = 3;
+/// Exit code when xcodebuild fails to build, archive or export.
+const EXIT_BUILD_FAILED: i32 = 4;
+/// Exit code when the test run completes with failing tests.
+const EXIT_TESTS_FAILED: i32 = 5;
+/// Exit code when code signing, provisioning or keychain setup fails.
+const EXIT_SIGNING_FAILED: i32 = 6;
+/// Exit code when a tool runs longer than `--timeout` and is stopped.
+const EXIT_TIMED_OUT: i32 = 7;
+/// Exit code when xctools is stopped with Ctrl-C or SIGTERM, following the shell convention.
+const EXIT_INTERRUPTED: i32 = 130;
+
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
+#[command(after_help = "Exit codes:
+  0    success
+  1    general failure
+  2    invalid arguments or xctools.toml
+  3    required tool (xcodebuild, xcrun, security) not found
+  4    build, archive or export failed
+  5    tests failed
+  6    code signing failed
+  7    a tool ran longer than --timeout
+  130  interrupted by Ctrl-C or SIGTERM")]
 struct Args {
     #[command(subcommand)]
     command: Commands,
+
+    /// Print a human-readable result, or a single versioned JSON report (tool output then goes
+    /// to stderr)
+    #[arg(long, global = true, value_name = "FORMAT", default_value_t = OutputFormat::default())]
+    output_format: OutputFormat,
+
+    /// Append the output of xcodebuild, altool and notarytool to this file instead of a new
+    /// file under .xctools/logs/
+    #[arg(long, global = true)]
+    log_file: Option<PathBuf>,
+
+    /// How xcodebuild output is shown; the log file always keeps the raw output
+    #[arg(long, global = true, default_value_t = Formatter::default())]
+    formatter: Formatter,
+
+    /// Print the xcodebuild, xcrun and security commands that would run, without running them
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Xcode to run the tools from: a version such as "16.2" or "16", or the path to an Xcode
+    /// bundle. Sets DEVELOPER_DIR for every command xctools runs
+    #[arg(long, global = true, value_name = "VERSION|PATH")]
+    xcode: Option<XcodeSelector>,
+
+    /// Stop any tool that runs longer than this, e.g. "90s", "30m" or "2h" (bare numbers are
+    /// seconds). The tool gets SIGTERM, then SIGKILL if it is still running 10 seconds later
+    #[arg(long, global = true, value_parser = parse_duration, value_name = "DURATION")]
+    timeout: Option<Duration>,
+
+    /// How many times upload, notarize and resolve-packages run a step that fails with a network
+    /// error or an HTTP 5xx response; 1 disables retrying
+    #[arg(
+        long,
+        global = true,
+        default_value_t = RetryPolicy::default().max_attempts,
+        value_parser = clap::value_parser!(u32).range(1..),
+    )]
+    max_attempts: u32,
+
+    /// Delay before the first retry, doubled for each further retry (up to one minute) and
+    /// jittered
+    #[arg(long, global = true, value_parser = parse_duration, value_name = "DURATION")]
+    retry_delay: Option<Duration>,
+
+    /// Apply the defaults of [profiles.NAME] from xctools.toml on top of the others
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Print which option values came from the command line and which from xctools.toml
+    #[arg(long, global = true)]
+    verbose: bool,
 }
 
+// Parsed once per run, so the size of the largest subcommand does not matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Build Xcode project
     #[command(group(
         ArgGroup::new("target")
-            .required(true)
             .args(["project", "workspace"]),
     ))]
+    #[command(group(
+        ArgGroup::new("platform")
+            .required(true)
+            .multiple(true)
+            .args(["destination", "destination_auto", "sdk"]),
+    ))]
     Build {
         /// The Xcode scheme to build.
         #[arg(short, long)]
         scheme: String,
 
-        /// The build destination (e.g., "iOS Simulator,name=iPhone 15 Pro").
+        /// The build destination (e.g., "platform=iOS Simulator,name=iPhone 15 Pro"); may be
+        /// repeated to build for several destinations. Defaults to the generic destination for
+        /// --sdk
         #[arg(short, long)]
-        destination: String,
+        destination: Vec<Destination>,
+
+        /// Pick an installed simulator instead of naming one, e.g. "iPhone latest" or
+        /// "iPad, iOS 17"; resolved to the device's UDID with `xcrun simctl list`
+        #[arg(long, value_name = "SELECTOR")]
+        destination_auto: Option<SimulatorSelector>,
 
-        /// Configuration - "Debug" or "Release"
+        /// SDK to build against, e.g. "iphonesimulator", "appletvos" or "iphoneos18.2"
+        #[arg(long)]
+        sdk: Option<SDK>,
+
+        /// Build configuration - "debug", "release" or any configuration declared in the project
+        /// (e.g. "Staging")
         #[arg(short, long, default_value_t = Configuration::default())]
         configuration: Configuration,
 
-        /// Xcode project folder (.xcodeproj)
+        /// Fail before running xcodebuild if the configuration is not declared in the project
+        #[arg(long)]
+        validate_configuration: bool,
+
+        /// Xcode project folder (.xcodeproj). Defaults to the workspace or project in the current
+        /// directory
         #[arg(short, long)]
         project: Option<String>,
 
-        /// Xcode workspace file (.xcworkspace)
+        /// Xcode workspace file (.xcworkspace). Defaults to the workspace or project in the
+        /// current directory
         #[arg(short, long)]
         workspace: Option<String>,
+
+        #[command(flatten)]
+        overrides: BuildOverrides,
     },
 
     /// Test Xcode project
     #[command(group(
         ArgGroup::new("target")
-            .required(true)
             .args(["project", "workspace"]),
     ))]
+    #[command(group(
+        ArgGroup::new("platform")
+            .required(true)
+            .multiple(true)
+            .args(["destination", "destination_auto", "sdk"]),
+    ))]
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Test {
+        #[command(subcommand)]
+        command: Option<TestCommands>,
+
         /// The Xcode scheme to build.
-        #[arg(short, long)]
-        scheme: String,
+        #[arg(short, long, required_unless_present = "from_xctestrun")]
+        scheme: Option<String>,
 
-        /// The build destination (e.g., "iOS Simulator,name=iPhone 15 Pro").
+        /// The test destination (e.g., "platform=iOS Simulator,name=iPhone 15 Pro"); may be
+        /// repeated to test on several destinations. Defaults to the first device or simulator
+        /// for --sdk
         #[arg(short, long)]
-        destination: String,
+        destination: Vec<Destination>,
+
+        /// Pick an installed simulator instead of naming one, e.g. "iPhone latest" or
+        /// "iPad, iOS 17"; resolved to the device's UDID with `xcrun simctl list`
+        #[arg(long, value_name = "SELECTOR")]
+        destination_auto: Option<SimulatorSelector>,
 
-        /// Configuration - "Debug" or "Release"
+        /// SDK to build against, e.g. "iphonesimulator", "appletvos" or "iphoneos18.2"
+        #[arg(long)]
+        sdk: Option<SDK>,
+
+        /// Build configuration - "debug", "release" or any configuration declared in the project
+        /// (e.g. "Staging")
         #[arg(short, long, default_value_t = Configuration::default())]
         configuration: Configuration,
 
-        /// Xcode project folder (.xcodeproj)
+        /// Fail before running xcodebuild if the configuration is not declared in the project
+        #[arg(long)]
+        validate_configuration: bool,
+
+        /// Xcode project folder (.xcodeproj). Defaults to the workspace or project in the current
+        /// directory
         #[arg(short, long)]
         project: Option<String>,
 
-        /// Xcode workspace file (.xcworkspace)
+        /// Xcode workspace file (.xcworkspace). Defaults to the workspace or project in the
+        /// current directory
         #[arg(short, long)]
         workspace: Option<String>,
+
+        #[command(flatten)]
+        overrides: BuildOverrides,
+
+        #[command(flatten)]
+        test_selection: TestSelection,
+
+        /// Where xcodebuild writes the result bundle (.xcresult). Defaults to
+        /// .xctools/results/SCHEME.xcresult, which is replaced on every run
+        #[arg(long, value_name = "PATH")]
+        result_bundle_path: Option<PathBuf>,
+
+        /// Write a JUnit XML report of the test results to this path
+        #[arg(long, value_name = "PATH")]
+        junit: Option<PathBuf>,
+
+        /// Write a Markdown summary of the test results to this path, e.g.
+        /// "$GITHUB_STEP_SUMMARY"
+        #[arg(long, value_name = "PATH")]
+        summary: Option<PathBuf>,
+
+        /// Only build the tests (build-for-testing) and package the products with their
+        /// .xctestrun files into --artifact-dir, for --from-xctestrun on other machines
+        #[arg(long, conflicts_with_all = ["result_bundle_path", "junit", "summary"])]
+        build_only: bool,
+
+        /// Where --build-only packages the test products. Defaults to
+        /// .xctools/test-products/SCHEME, which is replaced on every build; any other directory
+        /// must be empty
+        #[arg(long, value_name = "DIR", requires = "build_only")]
+        artifact_dir: Option<PathBuf>,
+
+        /// Run tests built with --build-only (test-without-building) from this .xctestrun file
+        /// instead of building the scheme
+        #[arg(
+            long,
+            value_name = "FILE",
+            conflicts_with_all = [
+                "scheme",
+                "project",
+                "workspace",
+                "sdk",
+                "validate_configuration",
+                "build_settings",
+                "xcconfig",
+                "build_only",
+                "test_plan",
+            ],
+        )]
+        from_xctestrun: Option<PathBuf>,
+
+        /// Run only this shard of the test classes, e.g. 2/5. Every machine splits the classes
+        /// the same way, balanced by the durations in --timings or else by their number
+        #[arg(
+            long,
+            value_name = "INDEX/COUNT",
+            conflicts_with_all = ["only_testing", "build_only"],
+        )]
+        shard: Option<Shard>,
+
+        /// The tests to shard: the JSON of xcodebuild -enumerate-tests or a previous .xcresult.
+        /// Defaults to enumerating the tests of --from-xctestrun
+        #[arg(long, value_name = "PATH", requires = "shard")]
+        shard_tests: Option<PathBuf>,
+
+        /// File the test class durations are recorded in after every run, which balance
+        /// --shard. Every machine must use the same file, e.g. one kept in the repository
+        #[arg(long, value_name = "PATH", conflicts_with = "build_only")]
+        timings: Option<PathBuf>,
+
+        /// Re-run the tests that failed up to N times, each time only those still failing.
+        /// Tests that pass on a retry are reported as flaky and do not fail the run
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = 0,
+            conflicts_with = "build_only"
+        )]
+        retry_failed: u32,
+
+        /// File the flaky and failed tests of runs with --retry-failed are recorded in, for
+        /// `xctools test flaky-report`. Defaults to .xctools/flaky-tests.json
+        #[arg(long, value_name = "PATH", conflicts_with = "build_only")]
+        flake_history: Option<PathBuf>,
     },
 
     /// Bump version of Xcode project
@@ -107,7 +346,6 @@ enum Commands {
     /// Archive Xcode project
     #[command(group(
         ArgGroup::new("target")
-            .required(true)
             .args(["project", "workspace"]),
     ))]
     Archive {
@@ -115,29 +353,41 @@ enum Commands {
         #[arg(long)]
         scheme: String,
 
-        /// The build destination (e.g., "iOS Simulator,name=iPhone 15 Pro").
+        /// The archive destination (e.g., "generic/platform=iOS"). Defaults to the generic
+        /// destination for --sdk
         #[arg(short, long)]
-        destination: String,
+        destination: Vec<Destination>,
 
-        /// SDK to use to perform the archiving - "iphoneos" or "macosx"
+        /// SDK to use to perform the archiving, e.g. "iphoneos", "macosx", "appletvos", "watchos",
+        /// "xros" or a versioned SDK such as "iphoneos18.2"
         #[arg(long)]
         sdk: SDK,
 
-        /// Configuration - "debug" or "release"
+        /// Build configuration - "debug", "release" or any configuration declared in the project
+        /// (e.g. "Staging")
         #[arg(short, long, default_value_t = Configuration::default())]
         configuration: Configuration,
 
+        /// Fail before running xcodebuild if the configuration is not declared in the project
+        #[arg(long)]
+        validate_configuration: bool,
+
         /// Where to output the archive
         #[arg(short, long)]
         output: String,
 
-        /// Xcode project folder (.xcodeproj)
+        /// Xcode project folder (.xcodeproj). Defaults to the workspace or project in the current
+        /// directory
         #[arg(short, long)]
         project: Option<String>,
 
-        /// Xcode workspace file (.xcworkspace)
+        /// Xcode workspace file (.xcworkspace). Defaults to the workspace or project in the
+        /// current directory
         #[arg(short, long)]
         workspace: Option<String>,
+
+        #[command(flatten)]
+        overrides: BuildOverrides,
     },
 
     /// Upload archive to distribution platforms
@@ -160,6 +410,45 @@ enum Commands {
         password: String,
     },
 
+    /// List the schemes, targets and build configurations of a project or workspace
+    #[command(group(
+        ArgGroup::new("target")
+            .args(["project", "workspace"]),
+    ))]
+    List {
+        /// Xcode project folder (.xcodeproj). Defaults to the workspace or project in the current
+        /// directory
+        #[arg(short, long)]
+        project: Option<String>,
+
+        /// Xcode workspace file (.xcworkspace). Defaults to the workspace or project in the
+        /// current directory
+        #[arg(short, long)]
+        workspace: Option<String>,
+
+        /// Print a table or the listing as JSON
+        #[arg(long, default_value_t = ListFormat::default())]
+        format: ListFormat,
+    },
+
+    /// Resolve the Swift package dependencies of a project or workspace, retrying network
+    /// failures
+    #[command(group(
+        ArgGroup::new("target")
+            .args(["project", "workspace"]),
+    ))]
+    ResolvePackages {
+        /// Xcode project folder (.xcodeproj). Defaults to the workspace or project in the current
+        /// directory
+        #[arg(short, long)]
+        project: Option<String>,
+
+        /// Xcode workspace file (.xcworkspace). Defaults to the workspace or project in the
+        /// current directory
+        #[arg(short, long)]
+        workspace: Option<String>,
+    },
+
     /// Export archive to various formats
     #[command()]
     ExportArchive {
@@ -211,64 +500,487 @@ enum Commands {
         #[arg(long)]
         provisioning_profile: Vec<String>,
     },
+
+    /// Check that this machine has the tools, Xcode, simulators and signing setup xctools needs;
+    /// exits with code 1 if any check fails
+    #[command()]
+    Doctor,
+
+    /// Show the Xcode installations xctools can select with --xcode
+    #[command()]
+    Xcode {
+        #[command(subcommand)]
+        command: XcodeCommands,
+    },
+
+    /// Create, boot, erase and delete simulators or override their status bar; prints the devices
+    /// touched as JSON
+    #[command()]
+    Simulator {
+        #[command(subcommand)]
+        command: SimulatorCommands,
+    },
+
+    /// Run a pipeline of xctools commands defined in xctools.toml, stopping at the first step
+    /// that fails
+    #[command()]
+    Run {
+        /// Name of the pipeline, defined as [[pipelines.NAME.steps]] in xctools.toml
+        pipeline: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TestCommands {
+    /// Rank the tests that most often passed only on a retry, from the flake history of runs
+    /// with --retry-failed
+    #[command()]
+    FlakyReport {
+        /// The flake history to read. Defaults to .xctools/flaky-tests.json
+        #[arg(long, value_name = "PATH")]
+        flake_history: Option<PathBuf>,
+
+        /// How many tests to list
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum XcodeCommands {
+    /// List the installed Xcodes, newest first
+    #[command()]
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+enum SimulatorCommands {
+    /// Create a new simulator
+    #[command()]
+    Create {
+        /// Name of the new simulator
+        #[arg(short, long)]
+        name: String,
+
+        /// Device type name or identifier (e.g., "iPhone 16 Pro")
+        #[arg(short, long)]
+        device_type: String,
+
+        /// Runtime identifier, name or platform and version, e.g. "iOS 18.2", "iOS 18" or
+        /// "iOS latest". Defaults to the newest runtime supporting the device type
+        #[arg(short, long)]
+        runtime: Option<String>,
+    },
+
+    /// Boot simulators and wait until they have finished booting
+    #[command()]
+    Boot {
+        /// Simulator UDIDs or names
+        #[arg(required = true)]
+        devices: Vec<String>,
+
+        /// Return as soon as the boot has started
+        #[arg(long)]
+        no_wait: bool,
+    },
+
+    /// Erase the contents and settings of simulators, shutting them down first
+    #[command()]
+    Erase {
+        /// Simulator UDIDs or names
+        #[arg(required = true)]
+        devices: Vec<String>,
+    },
+
+    /// Delete simulators
+    #[command()]
+    Delete {
+        /// Simulator UDIDs or names
+        #[arg(required = true)]
+        devices: Vec<String>,
+    },
+
+    /// Override the status bar of booted simulators, e.g. for screenshots
+    #[command()]
+    StatusBar {
+        /// Simulator UDIDs or names
+        #[arg(required = true)]
+        devices: Vec<String>,
+
+        /// Remove a previous override instead of applying one
+        #[arg(long)]
+        clear: bool,
+
+        #[command(flatten)]
+        overrides: StatusBarOverrides,
+    },
+}
+
+impl Commands {
+    /// The subcommand as typed on the command line, reported in JSON output.
+    fn name(&self) -> String {
+        let name = match self {
+            Commands::Build { .. } => "build",
+            Commands::BumpVersion { .. } => "bump-version",
+            Commands::Acknowledgements { .. } => "acknowledgements",
+            Commands::Test {
+                command: Some(TestCommands::FlakyReport { .. }),
+                ..
+            } => "test flaky-report",
+            Commands::Test { .. } => "test",
+            Commands::Archive { .. } => "archive",
+            Commands::Upload { .. } => "upload",
+            Commands::List { .. } => "list",
+            Commands::ResolvePackages { .. } => "resolve-packages",
+            Commands::ExportArchive { .. } => "export-archive",
+            Commands::Notarize { .. } => "notarize",
+            Commands::SetupSigning { .. } => "setup-signing",
+            Commands::Doctor => "doctor",
+            Commands::Xcode {
+                command: XcodeCommands::List,
+            } => "xcode list",
+            Commands::Simulator { command } => match command {
+                SimulatorCommands::Create { .. } => "simulator create",
+                SimulatorCommands::Boot { .. } => "simulator boot",
+                SimulatorCommands::Erase { .. } => "simulator erase",
+                SimulatorCommands::Delete { .. } => "simulator delete",
+                SimulatorCommands::StatusBar { .. } => "simulator status-bar",
+            },
+            Commands::Run { .. } => "run",
+        };
+
+        name.to_string()
+    }
+
+    /// Whether the command runs external tools, which `--dry-run` prints instead.
+    fn supports_dry_run(&self) -> bool {
+        !matches!(
+            self,
+            Commands::BumpVersion { .. }
+                | Commands::Acknowledgements { .. }
+                | Commands::Xcode { .. }
+        )
+    }
+
+    /// Whether the command streams tool output to the terminal while it runs, in which case its
+    /// result must not be printed a second time once it finishes.
+    fn streams_output(&self) -> bool {
+        matches!(
+            self,
+            Commands::Build { .. }
+                | Commands::Test { command: None, .. }
+                | Commands::Archive { .. }
+                | Commands::ResolvePackages { .. }
+                | Commands::ExportArchive { .. }
+                | Commands::Upload { .. }
+                | Commands::Notarize { .. }
+        )
+    }
 }
 
 fn main() {
-    let args = Args::parse();
-    let output_result: anyhow::Result<String> = match args.command {
+    // Installed before anything runs so that a Ctrl-C between two tools is not lost.
+    xcbuild_common::install_signal_handlers();
+    let args = parse_args();
+    let started = Instant::now();
+    let command_name = args.command.name();
+    let output_format = args.output_format;
+    xcbuild_common::set_output_format(output_format);
+    xcbuild_common::set_log_file(args.log_file);
+    xcbuild_common::set_xcodebuild_formatter(args.formatter);
+    xcbuild_common::set_command_timeout(args.timeout);
+    let mut retry_policy = RetryPolicy::new(args.max_attempts);
+    if let Some(retry_delay) = args.retry_delay {
+        retry_policy = retry_policy.with_initial_delay(retry_delay);
+    }
+    xcbuild_common::set_retry_policy(retry_policy);
+    if args.dry_run {
+        if !args.command.supports_dry_run() {
+            fail(
+                output_format,
+                &command_name,
+                started,
+                &anyhow::anyhow!("--dry-run is only supported by commands that run external tools"),
+                EXIT_FAILURE,
+            );
+        }
+        // In JSON mode stdout is reserved for the report.
+        let runner = match output_format {
+            OutputFormat::Text => DryRunRunner::new(),
+            OutputFormat::Json => DryRunRunner::with_writer(std::io::stderr()),
+        };
+        set_command_runner(Arc::new(runner));
+    }
+    if let Some(xcode) = &args.xcode {
+        match xcode.resolve() {
+            Ok(installation) => {
+                eprintln!(
+                    "Using Xcode {} ({})",
+                    installation.version,
+                    installation.path.display()
+                );
+                xcbuild_common::set_developer_dir(Some(installation.developer_dir()));
+            }
+            Err(error) => fail(
+                output_format,
+                &command_name,
+                started,
+                &error,
+                EXIT_TOOL_MISSING,
+            ),
+        }
+    }
+    let streams_output = args.command.streams_output();
+    let output_result = match args.command {
+        Commands::Run { pipeline } => run(&pipeline, args.profile.as_deref(), args.dry_run),
+        command => execute(command),
+    };
+    // A signal received after the last tool exited still stops xctools with EXIT_INTERRUPTED.
+    let output_result = output_result.and_then(|rendered| {
+        xcbuild_common::check_interrupted("xctools")?;
+        Ok(rendered)
+    });
+    match output_result {
+        Err(error) => fail(
+            output_format,
+            &command_name,
+            started,
+            &error,
+            exit_code_for_error(&error),
+        ),
+        Ok(rendered) => match rendered.failure.clone() {
+            None => match output_format {
+                OutputFormat::Text if streams_output => {}
+                OutputFormat::Text => print!("{}", rendered.text),
+                OutputFormat::Json => print_report(Report::succeeded(
+                    &command_name,
+                    started.elapsed(),
+                    rendered,
+                )),
+            },
+            // The command produced a result but still failed, e.g. a failed doctor check.
+            Some(failure) => {
+                let error = anyhow::anyhow!(failure);
+                match output_format {
+                    OutputFormat::Text => {
+                        print!("{}", rendered.text);
+                        eprintln!("Error: {}", error);
+                    }
+                    OutputFormat::Json => {
+                        eprintln!("Error: {}", error);
+                        print_report(
+                            Report::failed(&command_name, started.elapsed(), &error)
+                                .with_result(rendered),
+                        );
+                    }
+                }
+                std::process::exit(EXIT_FAILURE);
+            }
+        },
+    }
+}
+
+/// Runs every command except `run`, which is handled by [`run`].
+fn execute(command: Commands) -> anyhow::Result<RenderedResult> {
+    match command {
         Commands::Build {
             scheme,
             destination,
+            destination_auto,
+            sdk,
             configuration,
+            validate_configuration,
             project,
             workspace,
-        } => build(&scheme, &destination, &configuration, &project, &workspace),
+            overrides,
+        } => resolve_target(project, workspace).and_then(|(project, workspace)| {
+            check_configuration(validate_configuration, &configuration, &project, &workspace)?;
+            build(
+                &scheme,
+                &resolve_destinations(
+                    destination,
+                    destination_auto,
+                    sdk.as_ref().map(SDK::generic_destination),
+                )?,
+                &configuration,
+                &sdk,
+                &project,
+                &workspace,
+                &overrides,
+            )
+            .and_then(RenderedResult::new)
+        }),
         Commands::BumpVersion {
             build_number,
             version_number,
-        } => bump_version(&build_number, &version_number),
-        Commands::Acknowledgements { app_name, output } => acknowledgements(&app_name, &output),
+        } => bump_version(&build_number, &version_number).and_then(RenderedResult::new),
+        Commands::Acknowledgements { app_name, output } => {
+            acknowledgements(&app_name, &output).and_then(RenderedResult::new)
+        }
+        Commands::Test {
+            command:
+                Some(TestCommands::FlakyReport {
+                    flake_history,
+                    limit,
+                }),
+            ..
+        } => {
+            let flake_history =
+                flake_history.unwrap_or_else(|| PathBuf::from(DEFAULT_FLAKE_HISTORY_PATH));
+            flaky_report(&flake_history, limit).and_then(RenderedResult::new)
+        }
         Commands::Test {
+            command: None,
             scheme,
             destination,
+            destination_auto,
+            sdk,
             configuration,
+            validate_configuration,
             project,
             workspace,
-        } => test(&scheme, &destination, &configuration, &project, &workspace),
+            overrides,
+            test_selection,
+            result_bundle_path,
+            junit,
+            summary,
+            build_only,
+            artifact_dir,
+            from_xctestrun,
+            shard,
+            shard_tests,
+            timings,
+            retry_failed,
+            flake_history,
+        } => {
+            let mut options = TestOptions::new().with_test_selection(test_selection);
+            if let Some(result_bundle_path) = result_bundle_path {
+                options = options.with_result_bundle_path(result_bundle_path);
+            }
+            if let Some(junit) = junit {
+                options = options.with_junit_path(junit);
+            }
+            if let Some(summary) = summary {
+                options = options.with_summary_path(summary);
+            }
+            if let Some(artifact_dir) = artifact_dir {
+                options = options.with_artifact_directory(artifact_dir);
+            }
+            if let Some(shard) = shard {
+                options = options.with_shard(shard);
+            }
+            if let Some(shard_tests) = shard_tests {
+                options = options.with_shard_tests(shard_tests);
+            }
+            if let Some(timings) = timings {
+                options = options.with_timings_path(timings);
+            }
+            options = options.with_retry_failed(retry_failed);
+            if let Some(flake_history) = flake_history {
+                options = options.with_flake_history_path(flake_history);
+            }
+            let Some(scheme) = scheme else {
+                let xctestrun = from_xctestrun.unwrap_or_default();
+                return test_without_building(
+                    &xctestrun,
+                    &resolve_destinations(destination, destination_auto, None)?,
+                    &options,
+                )
+                .and_then(RenderedResult::new);
+            };
+
+            resolve_target(project, workspace).and_then(|(project, workspace)| {
+                check_configuration(validate_configuration, &configuration, &project, &workspace)?;
+                // Tests built once are run on many devices, so build for a generic destination.
+                let sdk_destination = if build_only {
+                    sdk.as_ref().map(SDK::generic_destination)
+                } else {
+                    sdk.as_ref().map(SDK::test_destination)
+                };
+                let destinations =
+                    resolve_destinations(destination, destination_auto, sdk_destination)?;
+                if build_only {
+                    return build_for_testing(
+                        &scheme,
+                        &destinations,
+                        &configuration,
+                        &sdk,
+                        &project,
+                        &workspace,
+                        &overrides,
+                        &options,
+                    )
+                    .and_then(RenderedResult::new);
+                }
+
+                test(
+                    &scheme,
+                    &destinations,
+                    &configuration,
+                    &sdk,
+                    &project,
+                    &workspace,
+                    &overrides,
+                    &options,
+                )
+                .and_then(RenderedResult::new)
+            })
+        }
         Commands::Archive {
             scheme,
             destination,
             configuration,
+            validate_configuration,
             sdk,
             output,
             project,
             workspace,
-        } => archive(
-            &scheme,
-            &destination,
-            &configuration,
-            &sdk,
-            &output,
-            &project,
-            &workspace,
-        ),
+            overrides,
+        } => resolve_target(project, workspace).and_then(|(project, workspace)| {
+            check_configuration(validate_configuration, &configuration, &project, &workspace)?;
+            archive(
+                &scheme,
+                &resolve_destinations(destination, None, Some(sdk.generic_destination()))?,
+                &configuration,
+                &sdk,
+                &output,
+                &project,
+                &workspace,
+                &overrides,
+            )
+            .and_then(RenderedResult::new)
+        }),
         Commands::Upload {
             target,
             app_file_path,
             username,
             password,
-        } => upload(&target, &app_file_path, &username, &password),
+        } => upload(&target, &app_file_path, &username, &password).and_then(RenderedResult::new),
+        Commands::List {
+            project,
+            workspace,
+            format,
+        } => resolve_target(project, workspace)
+            .and_then(|(project, workspace)| list(&project, &workspace, format))
+            .and_then(RenderedResult::new),
+        Commands::ResolvePackages { project, workspace } => resolve_target(project, workspace)
+            .and_then(|(project, workspace)| {
+                BuildTarget::new(project.as_ref(), workspace.as_ref())
+                    .resolve_package_dependencies()
+            })
+            .and_then(RenderedResult::new),
         Commands::ExportArchive {
             archive_path,
             export_options,
             export_path,
-        } => export_archive(&archive_path, &export_options, &export_path),
+        } => export_archive(&archive_path, &export_options, &export_path)
+            .and_then(RenderedResult::new),
         Commands::Notarize {
             file_path,
             apple_id,
             password,
             team_id,
-        } => notarize(&file_path, &apple_id, &password, &team_id),
+        } => notarize(&file_path, &apple_id, &password, &team_id).and_then(RenderedResult::new),
         Commands::SetupSigning {
             certificate_path,
             certificate_password,
@@ -277,13 +989,284 @@ fn main() {
             &certificate_path,
             &certificate_password,
             &provisioning_profile,
-        ),
+        )
+        .and_then(RenderedResult::new),
+        Commands::Doctor => doctor().and_then(RenderedResult::new),
+        Commands::Xcode {
+            command: XcodeCommands::List,
+        } => find_xcodes().and_then(|xcodes| RenderedResult::new(XcodeListing { xcodes })),
+        Commands::Simulator { command } => match command {
+            SimulatorCommands::Create {
+                name,
+                device_type,
+                runtime,
+            } => create_simulator(&name, &device_type, runtime.as_deref()),
+            SimulatorCommands::Boot { devices, no_wait } => boot_simulators(&devices, !no_wait),
+            SimulatorCommands::Erase { devices } => erase_simulators(&devices),
+            SimulatorCommands::Delete { devices } => delete_simulators(&devices),
+            SimulatorCommands::StatusBar {
+                devices,
+                clear,
+                overrides,
+            } => set_status_bar(&devices, (!clear).then_some(&overrides)),
+        }
+        .and_then(RenderedResult::new),
+        Commands::Run { .. } => Err(anyhow::anyhow!(
+            "A pipeline step cannot run another pipeline"
+        )),
+    }
+}
+
+/// Runs the pipeline `name` from xctools.toml. Each step is parsed like a command line, with
+/// the defaults from xctools.toml and `profile` applied, and executed in this process.
+fn run(name: &str, profile: Option<&str>, dry_run: bool) -> anyhow::Result<RenderedResult> {
+    let current_dir = std::env::current_dir().unwrap_or_default();
+    let config = Config::discover(&current_dir)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "No {} found in {} or its parent directories; pipelines are defined there",
+            xctools_config::CONFIG_FILE_NAME,
+            current_dir.display()
+        )
+    })?;
+    let pipeline = config.pipeline(&Args::command(), name)?;
+
+    run_pipeline(&pipeline, dry_run, |step_args| {
+        let mut command_line = vec![OsString::from("xctools")];
+        if let Some(profile) = profile {
+            command_line.extend([OsString::from("--profile"), OsString::from(profile)]);
+        }
+        command_line.extend(step_args.into_iter().map(OsString::from));
+        let configured =
+            xctools_config::apply_config(&Args::command(), command_line, &current_dir)?;
+        let command = Args::try_parse_from(&configured.args)
+            .map_err(|error| anyhow::anyhow!("{}", clap_error_message(&error)))?
+            .command;
+
+        if dry_run && !command.supports_dry_run() {
+            anyhow::bail!(
+                "--dry-run is only supported by commands that run external tools, not '{}'",
+                command.name()
+            );
+        }
+        let streams_output = command.streams_output();
+        let rendered = execute(command)?;
+        if xcbuild_common::output_format() == OutputFormat::Text && !streams_output {
+            print!("{}", rendered.text);
+        }
+        if let Some(failure) = rendered.failure {
+            anyhow::bail!(failure);
+        }
+
+        Ok(rendered)
+    })
+    .and_then(RenderedResult::new)
+}
+
+/// The first paragraph of a clap error, without the "error: " prefix and the usage.
+fn clap_error_message(error: &clap::Error) -> String {
+    let rendered = error.render().to_string();
+    let message = rendered.split("\n\n").next().unwrap_or_default();
+
+    message.trim_start_matches("error: ").trim().to_string()
+}
+
+/// Parses the command line with the defaults from xctools.toml added to it.
+fn parse_args() -> Args {
+    let current_dir = std::env::current_dir().unwrap_or_default();
+    let configured = match xctools_config::apply_config(
+        &Args::command(),
+        std::env::args_os().collect(),
+        &current_dir,
+    ) {
+        Ok(configured) => configured,
+        Err(error) => {
+            eprintln!("Error: {:#}", error);
+            std::process::exit(EXIT_INVALID_ARGUMENTS);
+        }
     };
-    match output_result {
+    let args = Args::parse_from(&configured.args);
+
+    if args.verbose {
+        match &configured.config_path {
+            Some(path) => match &args.profile {
+                Some(profile) => eprintln!("Using {} with profile {}", path.display(), profile),
+                None => eprintln!("Using {}", path.display()),
+            },
+            None => eprintln!("No {} found", xctools_config::CONFIG_FILE_NAME),
+        }
+        for origin in &configured.origins {
+            eprintln!("  {}", origin);
+        }
+    }
+
+    args
+}
+
+/// Reports `error` on stderr, and as a JSON report on stdout in JSON mode, then exits with
+/// `exit_code`.
+fn fail(
+    output_format: OutputFormat,
+    command_name: &str,
+    started: Instant,
+    error: &anyhow::Error,
+    exit_code: i32,
+) -> ! {
+    eprintln!("Error: {:#}", error);
+    if output_format == OutputFormat::Json {
+        print_report(Report::failed(command_name, started.elapsed(), error));
+    }
+    std::process::exit(exit_code);
+}
+
+fn print_report(report: Report) {
+    match report
+        .with_xctools_version(env!("CARGO_PKG_VERSION"))
+        .to_json()
+    {
+        Ok(json) => print!("{}", json),
         Err(error) => {
-            eprintln!("Error: {}", error);
-            std::process::exit(1);
+            eprintln!("Error: {:#}", error);
+            std::process::exit(EXIT_FAILURE);
         }
-        Ok(output) => print!("{}", output),
+    }
+}
+
+/// Returns the explicit `--destination`s plus the simulator picked by `--destination-auto`, or
+/// the default derived from `--sdk` when neither is given.
+fn resolve_destinations(
+    mut destinations: Vec<Destination>,
+    destination_auto: Option<SimulatorSelector>,
+    sdk_default: Option<Destination>,
+) -> anyhow::Result<Vec<Destination>> {
+    if let Some(selector) = destination_auto {
+        let simulators = list_simulators()?;
+        let (device, runtime) = simulators.select(&selector)?;
+        eprintln!(
+            "Using simulator {} ({}, {})",
+            device.name, runtime.name, device.udid
+        );
+        destinations.push(device.destination(runtime));
+    }
+    if !destinations.is_empty() {
+        return Ok(destinations);
+    }
+
+    sdk_default
+        .map(|destination| vec![destination])
+        .ok_or_else(|| anyhow::anyhow!("Either --destination or --sdk must be specified"))
+}
+
+/// The result of `xctools xcode list`.
+#[derive(Serialize)]
+struct XcodeListing {
+    xcodes: Vec<XcodeInstallation>,
+}
+
+impl std::fmt::Display for XcodeListing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", render_xcode_list(&self.xcodes))
+    }
+}
+
+impl CommandResult for XcodeListing {}
+
+/// Renders installed Xcodes as a table of version, build and path.
+fn render_xcode_list(installations: &[XcodeInstallation]) -> String {
+    if installations.is_empty() {
+        return String::from("No Xcode installations found\n");
+    }
+
+    let rows: Vec<[String; 3]> = installations
+        .iter()
+        .map(|installation| {
+            [
+                installation.version.clone(),
+                installation.build.clone().unwrap_or_default(),
+                installation.path.display().to_string(),
+            ]
+        })
+        .collect();
+    let version_width = rows
+        .iter()
+        .map(|row| row[0].len())
+        .max()
+        .unwrap_or(0)
+        .max(7);
+    let build_width = rows
+        .iter()
+        .map(|row| row[1].len())
+        .max()
+        .unwrap_or(0)
+        .max(5);
+
+    let mut table = format!(
+        "{:<version_width$}  {:<build_width$}  PATH\n",
+        "VERSION", "BUILD"
+    );
+    for [version, build, path] in rows {
+        table.push_str(&format!(
+            "{:<version_width$}  {:<build_width$}  {}\n",
+            version, build, path
+        ));
+    }
+
+    table
+}
+
+/// Returns the given `--project`/`--workspace`, or the one discovered in the current directory
+/// when neither is given.
+fn resolve_target(
+    project: Option<String>,
+    workspace: Option<String>,
+) -> anyhow::Result<(Option<String>, Option<String>)> {
+    if project.is_some() || workspace.is_some() {
+        return Ok((project, workspace));
+    }
+
+    let target = BuildTarget::discover()?;
+    match (target.project(), target.workspace()) {
+        (_, Some(workspace)) => eprintln!("Using workspace {}", workspace),
+        (Some(project), None) => eprintln!("Using project {}", project),
+        (None, None) => {}
+    }
+
+    Ok((
+        target.project().map(String::from),
+        target.workspace().map(String::from),
+    ))
+}
+
+/// Checks the configuration against those declared by the project or workspace when
+/// `--validate-configuration` is given.
+fn check_configuration(
+    validate: bool,
+    configuration: &Configuration,
+    project: &Option<String>,
+    workspace: &Option<String>,
+) -> anyhow::Result<()> {
+    if !validate {
+        return Ok(());
+    }
+
+    let target = BuildTarget::new(project.as_ref(), workspace.as_ref());
+    configuration.validate(&target.configurations()?)
+}
+
+fn exit_code_for_error(error: &anyhow::Error) -> i32 {
+    if xcbuild_common::interrupted() {
+        return EXIT_INTERRUPTED;
+    }
+    let Some(command_error) = error.downcast_ref::<CommandError>() else {
+        return EXIT_FAILURE;
+    };
+
+    match command_error.kind {
+        CommandErrorKind::ToolMissing => EXIT_TOOL_MISSING,
+        CommandErrorKind::BuildFailed => EXIT_BUILD_FAILED,
+        CommandErrorKind::TestsFailed => EXIT_TESTS_FAILED,
+        CommandErrorKind::SigningFailed => EXIT_SIGNING_FAILED,
+        CommandErrorKind::TimedOut => EXIT_TIMED_OUT,
+        CommandErrorKind::Interrupted => EXIT_INTERRUPTED,
+        CommandErrorKind::Failed => EXIT_FAILURE,
     }
 }
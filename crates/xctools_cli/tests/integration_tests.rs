@@ -15,7 +15,7 @@ fn test_cli_help() {
 #[test]
 fn test_build_command_help() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&["build", "--help"]);
+    cmd.args(["build", "--help"]);
     cmd.assert()
         .success()
         .stdout(predicate::str::contains("Build Xcode project"))
@@ -36,8 +36,9 @@ fn test_build_command_missing_required_args() {
 
 #[test]
 fn test_build_command_missing_project_or_workspace() {
+    let temp_dir = tempdir().unwrap();
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.current_dir(temp_dir.path()).args([
         "build",
         "--scheme",
         "TestXcodeApp",
@@ -46,13 +47,16 @@ fn test_build_command_missing_project_or_workspace() {
     ]);
     cmd.assert()
         .failure()
-        .stderr(predicate::str::contains("required"));
+        .code(1)
+        .stderr(predicate::str::contains(
+            "No .xcworkspace or .xcodeproj found in .; pass --workspace or --project",
+        ));
 }
 
 #[test]
 fn test_build_command_with_both_project_and_workspace() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "build",
         "--scheme",
         "TestXcodeApp",
@@ -69,9 +73,9 @@ fn test_build_command_with_both_project_and_workspace() {
 }
 
 #[test]
-fn test_build_command_invalid_configuration() {
+fn test_build_command_empty_configuration() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "build",
         "--scheme",
         "TestXcodeApp",
@@ -80,11 +84,11 @@ fn test_build_command_invalid_configuration() {
         "--project",
         "TestXcodeApp/TestXcodeApp.xcodeproj",
         "--configuration",
-        "invalid",
+        "",
     ]);
-    cmd.assert()
-        .failure()
-        .stderr(predicate::str::contains("invalid value 'invalid'"));
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "configuration name must not be empty",
+    ));
 }
 
 #[test]
@@ -92,7 +96,7 @@ fn test_build_command_argument_parsing() {
     // Test that valid arguments are parsed correctly without actually running xcodebuild
     // We'll check that there are no argument parsing errors in stderr
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "build",
         "--scheme",
         "TestXcodeApp",
@@ -116,7 +120,7 @@ fn test_build_command_argument_parsing() {
 #[test]
 fn test_bump_version_command_help() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&["bump-version", "--help"]);
+    cmd.args(["bump-version", "--help"]);
     cmd.assert()
         .success()
         .stdout(predicate::str::contains("Bump version of Xcode project"))
@@ -138,7 +142,7 @@ fn test_bump_version_command_no_pbxproj() {
     let tmp = tempdir().unwrap();
     let mut cmd = Command::cargo_bin("xctools").unwrap();
     cmd.current_dir(tmp.path())
-        .args(&["bump-version", "--build-number", "42"]);
+        .args(["bump-version", "--build-number", "42"]);
     cmd.assert()
         .failure()
         .stderr(predicate::str::contains("No project.pbxproj found"));
@@ -156,7 +160,7 @@ fn test_bump_version_command_build_number_only() {
 
     let mut cmd = Command::cargo_bin("xctools").unwrap();
     cmd.current_dir(tmp.path())
-        .args(&["bump-version", "--build-number", "42"]);
+        .args(["bump-version", "--build-number", "42"]);
     cmd.assert()
         .success()
         .stdout(predicate::str::contains(
@@ -183,7 +187,7 @@ fn test_bump_version_command_version_number_only() {
 
     let mut cmd = Command::cargo_bin("xctools").unwrap();
     cmd.current_dir(tmp.path())
-        .args(&["bump-version", "--version-number", "2.1.3"]);
+        .args(["bump-version", "--version-number", "2.1.3"]);
     cmd.assert()
         .success()
         .stdout(predicate::str::contains(
@@ -209,7 +213,7 @@ fn test_bump_version_command_both_values() {
     .unwrap();
 
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.current_dir(tmp.path()).args(&[
+    cmd.current_dir(tmp.path()).args([
         "bump-version",
         "--build-number",
         "100",
@@ -238,7 +242,7 @@ fn test_bump_version_command_invalid_version() {
 
     let mut cmd = Command::cargo_bin("xctools").unwrap();
     cmd.current_dir(tmp.path())
-        .args(&["bump-version", "--version-number", "invalid.version"]);
+        .args(["bump-version", "--version-number", "invalid.version"]);
     cmd.assert()
         .failure()
         .stderr(predicate::str::contains("invalid value 'invalid.version'"));
@@ -261,7 +265,7 @@ fn test_bump_version_command_with_nested_pbxproj() {
 
     let mut cmd = Command::cargo_bin("xctools").unwrap();
     cmd.current_dir(tmp.path())
-        .args(&["bump-version", "--build-number", "999"]);
+        .args(["bump-version", "--build-number", "999"]);
     cmd.assert()
         .success()
         .stdout(predicate::str::contains(
@@ -278,7 +282,7 @@ fn test_bump_version_command_with_nested_pbxproj() {
 #[test]
 fn test_acknowledgements_command_help() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&["acknowledgements", "--help"]);
+    cmd.args(["acknowledgements", "--help"]);
     cmd.assert()
         .success()
         .stdout(predicate::str::contains("Generate acknowledgements file"))
@@ -298,7 +302,7 @@ fn test_acknowledgements_command_missing_required_args() {
 #[test]
 fn test_acknowledgements_command_missing_app_name() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&["acknowledgements", "--output", "./output.json"]);
+    cmd.args(["acknowledgements", "--output", "./output.json"]);
     cmd.assert()
         .failure()
         .stderr(predicate::str::contains("required"));
@@ -307,7 +311,7 @@ fn test_acknowledgements_command_missing_app_name() {
 #[test]
 fn test_acknowledgements_command_missing_output() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&["acknowledgements", "--app-name", "TestApp"]);
+    cmd.args(["acknowledgements", "--app-name", "TestApp"]);
     cmd.assert()
         .failure()
         .stderr(predicate::str::contains("required"));
@@ -318,7 +322,7 @@ fn test_acknowledgements_command_argument_parsing() {
     // Test that valid arguments are parsed correctly without actually finding DerivedData
     // We'll check that there are no CLI argument parsing errors in stderr
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "acknowledgements",
         "--app-name",
         "TestApp",
@@ -338,7 +342,7 @@ fn test_acknowledgements_command_argument_parsing() {
 #[test]
 fn test_acknowledgements_command_nonexistent_app() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "acknowledgements",
         "--app-name",
         "NonExistentApp123XYZ",
@@ -383,7 +387,7 @@ fn test_acknowledgements_command_output_to_directory() {
     // Set HOME environment variable to point to our mock directory
     let mut cmd = Command::cargo_bin("xctools").unwrap();
     cmd.env("HOME", home_dir);
-    cmd.args(&[
+    cmd.args([
         "acknowledgements",
         "--app-name",
         "TestApp",
@@ -441,7 +445,7 @@ fn test_acknowledgements_command_output_to_specific_file() {
 
     let mut cmd = Command::cargo_bin("xctools").unwrap();
     cmd.env("HOME", home_dir);
-    cmd.args(&[
+    cmd.args([
         "acknowledgements",
         "--app-name",
         "TestApp",
@@ -527,7 +531,7 @@ fn test_acknowledgements_command_with_mock_packages() {
 
     let mut cmd = Command::cargo_bin("xctools").unwrap();
     cmd.env("HOME", home_dir);
-    cmd.args(&[
+    cmd.args([
         "acknowledgements",
         "--app-name",
         "TestApp",
@@ -585,7 +589,7 @@ fn test_acknowledgements_command_with_mock_packages() {
 #[test]
 fn test_acknowledgements_command_invalid_output_path() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "acknowledgements",
         "--app-name",
         "TestApp",
@@ -615,20 +619,20 @@ fn test_acknowledgements_command_with_git_repository() {
 
     // Initialize a git repository in the temp directory
     std::process::Command::new("git")
-        .args(&["init"])
+        .args(["init"])
         .current_dir(&tmp)
         .output()
         .unwrap();
 
     // Configure git with test user
     std::process::Command::new("git")
-        .args(&["config", "user.name", "Test User"])
+        .args(["config", "user.name", "Test User"])
         .current_dir(&tmp)
         .output()
         .unwrap();
 
     std::process::Command::new("git")
-        .args(&["config", "user.email", "test@example.com"])
+        .args(["config", "user.email", "test@example.com"])
         .current_dir(&tmp)
         .output()
         .unwrap();
@@ -636,13 +640,13 @@ fn test_acknowledgements_command_with_git_repository() {
     // Create a test file and commit
     fs::write(tmp.path().join("README.md"), "# Test Project").unwrap();
     std::process::Command::new("git")
-        .args(&["add", "."])
+        .args(["add", "."])
         .current_dir(&tmp)
         .output()
         .unwrap();
 
     std::process::Command::new("git")
-        .args(&["commit", "-m", "Initial commit"])
+        .args(["commit", "-m", "Initial commit"])
         .current_dir(&tmp)
         .output()
         .unwrap();
@@ -673,7 +677,7 @@ fn test_acknowledgements_command_with_git_repository() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
     cmd.env("HOME", home_dir);
     cmd.current_dir(&tmp); // Run from within the git repository
-    cmd.args(&[
+    cmd.args([
         "acknowledgements",
         "--app-name",
         "TestApp",
@@ -729,7 +733,7 @@ fn test_acknowledgements_command_app_name_with_spaces() {
 
     let mut cmd = Command::cargo_bin("xctools").unwrap();
     cmd.env("HOME", home_dir);
-    cmd.args(&[
+    cmd.args([
         "acknowledgements",
         "--app-name",
         "My Test App",
@@ -753,7 +757,7 @@ fn test_acknowledgements_command_app_name_with_spaces() {
 #[test]
 fn test_acknowledgements_command_short_flags() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "acknowledgements",
         "-a",
         "TestApp",
@@ -774,7 +778,7 @@ fn test_acknowledgements_command_short_flags() {
 #[test]
 fn test_test_command_help() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&["test", "--help"]);
+    cmd.args(["test", "--help"]);
     cmd.assert()
         .success()
         .stdout(predicate::str::contains("Test Xcode project"))
@@ -797,7 +801,7 @@ fn test_test_command_missing_required_args() {
 #[test]
 fn test_test_command_missing_schema() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--destination",
         "iOS Simulator,name=iPhone 15 Pro",
@@ -813,7 +817,7 @@ fn test_test_command_missing_schema() {
 #[test]
 fn test_test_command_missing_destination() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "TestXcodeAppTests",
@@ -828,8 +832,9 @@ fn test_test_command_missing_destination() {
 
 #[test]
 fn test_test_command_missing_project_or_workspace() {
+    let temp_dir = tempdir().unwrap();
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.current_dir(temp_dir.path()).args([
         "test",
         "--scheme",
         "TestXcodeAppTests",
@@ -838,13 +843,16 @@ fn test_test_command_missing_project_or_workspace() {
     ]);
     cmd.assert()
         .failure()
-        .stderr(predicate::str::contains("required"));
+        .code(1)
+        .stderr(predicate::str::contains(
+            "No .xcworkspace or .xcodeproj found in .; pass --workspace or --project",
+        ));
 }
 
 #[test]
 fn test_test_command_with_both_project_and_workspace() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "TestXcodeAppTests",
@@ -861,9 +869,9 @@ fn test_test_command_with_both_project_and_workspace() {
 }
 
 #[test]
-fn test_test_command_invalid_configuration() {
+fn test_test_command_empty_configuration() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "TestXcodeAppTests",
@@ -872,17 +880,17 @@ fn test_test_command_invalid_configuration() {
         "--project",
         "TestXcodeApp/TestXcodeApp.xcodeproj",
         "--configuration",
-        "invalid",
+        "",
     ]);
-    cmd.assert()
-        .failure()
-        .stderr(predicate::str::contains("invalid value 'invalid'"));
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "configuration name must not be empty",
+    ));
 }
 
 #[test]
 fn test_test_command_with_project() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "TestXcodeAppTests",
@@ -906,7 +914,7 @@ fn test_test_command_with_project() {
 #[test]
 fn test_test_command_with_workspace() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "TestXcodeAppUITests",
@@ -930,7 +938,7 @@ fn test_test_command_with_workspace() {
 #[test]
 fn test_test_command_debug_configuration() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "TestXcodeAppTests",
@@ -954,7 +962,7 @@ fn test_test_command_debug_configuration() {
 #[test]
 fn test_test_command_release_configuration() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "TestXcodeAppTests",
@@ -978,7 +986,7 @@ fn test_test_command_release_configuration() {
 #[test]
 fn test_test_command_ios_simulator_destination() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "TestXcodeAppTests",
@@ -1000,7 +1008,7 @@ fn test_test_command_ios_simulator_destination() {
 #[test]
 fn test_test_command_ios_generic_destination() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "TestXcodeAppTests",
@@ -1022,7 +1030,7 @@ fn test_test_command_ios_generic_destination() {
 #[test]
 fn test_test_command_macos_destination() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "TestXcodeAppTests",
@@ -1044,7 +1052,7 @@ fn test_test_command_macos_destination() {
 #[test]
 fn test_test_command_ui_tests_scheme() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "TestXcodeAppUITests",
@@ -1068,7 +1076,7 @@ fn test_test_command_ui_tests_scheme() {
 #[test]
 fn test_test_command_unit_tests_scheme() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "TestXcodeAppTests",
@@ -1092,7 +1100,7 @@ fn test_test_command_unit_tests_scheme() {
 #[test]
 fn test_test_command_iphone_16_pro_destination() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "TestXcodeAppTests",
@@ -1115,7 +1123,7 @@ fn test_test_command_iphone_16_pro_destination() {
 fn test_test_command_default_configuration() {
     // Test that default configuration is applied when not specified
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "TestXcodeAppTests",
@@ -1138,7 +1146,7 @@ fn test_test_command_default_configuration() {
 fn test_test_command_argument_parsing_comprehensive() {
     // Test that all valid test arguments are parsed correctly without running xcodebuild
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "--scheme",
         "MyTestScheme",
@@ -1164,7 +1172,7 @@ fn test_test_command_argument_parsing_comprehensive() {
 fn test_test_command_short_flags() {
     // Test that short flags work correctly
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "-s",
         "TestXcodeAppTests",
@@ -1189,7 +1197,7 @@ fn test_test_command_short_flags() {
 fn test_test_command_workspace_short_flag() {
     // Test workspace with short flag
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "test",
         "-s",
         "TestXcodeAppUITests",
@@ -1214,7 +1222,7 @@ fn test_test_command_workspace_short_flag() {
 #[test]
 fn test_archive_command_help() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&["archive", "--help"]);
+    cmd.args(["archive", "--help"]);
     cmd.assert()
         .success()
         .stdout(predicate::str::contains("Archive Xcode project"))
@@ -1238,7 +1246,7 @@ fn test_archive_command_missing_required_args() {
 #[test]
 fn test_archive_command_missing_schema() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--destination",
         "generic/platform=iOS",
@@ -1256,9 +1264,8 @@ fn test_archive_command_missing_schema() {
 }
 
 #[test]
-fn test_archive_command_missing_destination() {
-    let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+fn test_archive_command_defaults_destination_from_sdk() {
+    let stdout = dry_run(&[
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1269,16 +1276,14 @@ fn test_archive_command_missing_destination() {
         "--project",
         "TestXcodeApp/TestXcodeApp.xcodeproj",
     ]);
-    cmd.assert()
-        .failure()
-        .stderr(predicate::str::contains("required"))
-        .stderr(predicate::str::contains("destination"));
+
+    assert!(stdout.contains(" -destination generic/platform=iOS "));
 }
 
 #[test]
 fn test_archive_command_missing_sdk() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1298,7 +1303,7 @@ fn test_archive_command_missing_sdk() {
 #[test]
 fn test_archive_command_missing_output() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1317,8 +1322,9 @@ fn test_archive_command_missing_output() {
 
 #[test]
 fn test_archive_command_missing_project_or_workspace() {
+    let temp_dir = tempdir().unwrap();
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.current_dir(temp_dir.path()).args([
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1331,13 +1337,16 @@ fn test_archive_command_missing_project_or_workspace() {
     ]);
     cmd.assert()
         .failure()
-        .stderr(predicate::str::contains("required"));
+        .code(1)
+        .stderr(predicate::str::contains(
+            "No .xcworkspace or .xcodeproj found in .; pass --workspace or --project",
+        ));
 }
 
 #[test]
 fn test_archive_command_with_both_project_and_workspace() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1360,7 +1369,7 @@ fn test_archive_command_with_both_project_and_workspace() {
 #[test]
 fn test_archive_command_invalid_sdk() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1379,9 +1388,9 @@ fn test_archive_command_invalid_sdk() {
 }
 
 #[test]
-fn test_archive_command_invalid_configuration() {
+fn test_archive_command_empty_configuration() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1394,17 +1403,17 @@ fn test_archive_command_invalid_configuration() {
         "--project",
         "TestXcodeApp/TestXcodeApp.xcodeproj",
         "--configuration",
-        "invalid",
+        "",
     ]);
-    cmd.assert()
-        .failure()
-        .stderr(predicate::str::contains("invalid value 'invalid'"));
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "configuration name must not be empty",
+    ));
 }
 
 #[test]
 fn test_archive_command_valid_iphoneos_sdk() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1432,7 +1441,7 @@ fn test_archive_command_valid_iphoneos_sdk() {
 #[test]
 fn test_archive_command_valid_macosx_sdk() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1460,7 +1469,7 @@ fn test_archive_command_valid_macosx_sdk() {
 #[test]
 fn test_archive_command_with_workspace() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1488,7 +1497,7 @@ fn test_archive_command_with_workspace() {
 #[test]
 fn test_archive_command_debug_configuration() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1516,7 +1525,7 @@ fn test_archive_command_debug_configuration() {
 #[test]
 fn test_archive_command_release_configuration() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1547,7 +1556,7 @@ fn test_archive_command_custom_output_path() {
     let archive_path = tmp.path().join("build/archives/MyApp-v1.0.xcarchive");
 
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1575,7 +1584,7 @@ fn test_archive_command_custom_output_path() {
 #[test]
 fn test_archive_command_ios_generic_destination() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1601,7 +1610,7 @@ fn test_archive_command_ios_generic_destination() {
 #[test]
 fn test_archive_command_macos_generic_destination() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--scheme",
         "TestXcodeApp",
@@ -1628,7 +1637,7 @@ fn test_archive_command_macos_generic_destination() {
 fn test_archive_command_argument_parsing_comprehensive() {
     // Test that all valid archive arguments are parsed correctly without running xcodebuild
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "archive",
         "--scheme",
         "MyTestScheme",
@@ -1659,7 +1668,7 @@ fn test_archive_command_argument_parsing_comprehensive() {
 #[test]
 fn test_notarize_command_help() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&["notarize", "--help"]);
+    cmd.args(["notarize", "--help"]);
     cmd.assert()
         .success()
         .stdout(predicate::str::contains("Notarize"))
@@ -1681,7 +1690,7 @@ fn test_notarize_command_missing_required_args() {
 #[test]
 fn test_notarize_command_missing_apple_id() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "notarize",
         "--file-path",
         "MyApp.dmg",
@@ -1701,7 +1710,7 @@ fn test_notarize_command_propagates_error_to_user() {
     // (notarytool validates file existence before contacting Apple).
     // Asserts that the failure is surfaced to the user via stderr rather than silently ignored.
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "notarize",
         "--file-path",
         "MyApp.dmg",
@@ -1715,7 +1724,9 @@ fn test_notarize_command_propagates_error_to_user() {
     cmd.assert()
         .failure()
         .stderr(predicate::str::contains("Error:"))
-        .stderr(predicate::str::contains("the following required arguments were not provided").not())
+        .stderr(
+            predicate::str::contains("the following required arguments were not provided").not(),
+        )
         .stderr(predicate::str::contains("invalid value").not())
         .stderr(predicate::str::contains("unexpected argument").not());
 }
@@ -1725,7 +1736,7 @@ fn test_notarize_command_propagates_error_to_user() {
 #[test]
 fn test_setup_signing_command_help() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&["setup-signing", "--help"]);
+    cmd.args(["setup-signing", "--help"]);
     cmd.assert()
         .success()
         .stdout(predicate::str::contains("signing"))
@@ -1746,7 +1757,7 @@ fn test_setup_signing_command_missing_required_args() {
 #[test]
 fn test_setup_signing_command_missing_certificate_password() {
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&["setup-signing", "--certificate-path", "signing.p12"]);
+    cmd.args(["setup-signing", "--certificate-path", "signing.p12"]);
     cmd.assert()
         .failure()
         .stderr(predicate::str::contains("required"));
@@ -1757,7 +1768,7 @@ fn test_setup_signing_command_propagates_error_to_user() {
     // Runs with valid args but a non-existent certificate so the command fails fast.
     // Asserts that the failure is surfaced to the user via stderr rather than silently ignored.
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "setup-signing",
         "--certificate-path",
         "signing.p12",
@@ -1767,7 +1778,9 @@ fn test_setup_signing_command_propagates_error_to_user() {
     cmd.assert()
         .failure()
         .stderr(predicate::str::contains("Error:"))
-        .stderr(predicate::str::contains("the following required arguments were not provided").not())
+        .stderr(
+            predicate::str::contains("the following required arguments were not provided").not(),
+        )
         .stderr(predicate::str::contains("invalid value").not())
         .stderr(predicate::str::contains("unexpected argument").not());
 }
@@ -1777,7 +1790,7 @@ fn test_setup_signing_command_propagates_error_with_multiple_profiles() {
     // Runs with valid args including provisioning profiles that don't exist.
     // Asserts that the failure is surfaced to the user via stderr rather than silently ignored.
     let mut cmd = Command::cargo_bin("xctools").unwrap();
-    cmd.args(&[
+    cmd.args([
         "setup-signing",
         "--certificate-path",
         "signing.p12",
@@ -1791,7 +1804,3297 @@ fn test_setup_signing_command_propagates_error_with_multiple_profiles() {
     cmd.assert()
         .failure()
         .stderr(predicate::str::contains("Error:"))
-        .stderr(predicate::str::contains("the following required arguments were not provided").not())
+        .stderr(
+            predicate::str::contains("the following required arguments were not provided").not(),
+        )
         .stderr(predicate::str::contains("invalid value").not())
         .stderr(predicate::str::contains("unexpected argument").not());
 }
+
+/// Writes an executable shell script named `name` into `dir` so it shadows the real tool when
+/// `dir` is used as `PATH`.
+fn write_fake_tool(dir: &std::path::Path, name: &str, script: &str) {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = dir.join(name);
+    fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn test_build_command_exits_with_tool_missing_code_when_xcodebuild_is_not_installed() {
+    let temp_dir = tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "build",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=iOS Simulator,name=iPhone 15 Pro",
+            "--project",
+            "MyApp.xcodeproj",
+        ]);
+
+    cmd.assert()
+        .code(3)
+        .stderr(predicate::str::contains("xcodebuild was not found"));
+}
+
+#[test]
+fn test_build_command_exits_with_build_failed_code_and_output_tail() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "echo \"error: cannot find 'Foo' in scope\"\necho '** BUILD FAILED **'\nexit 65",
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "build",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=iOS Simulator,name=iPhone 15 Pro",
+            "--project",
+            "MyApp.xcodeproj",
+        ]);
+
+    cmd.assert()
+        .code(4)
+        .stderr(predicate::str::contains("build failed with exit code 65"))
+        .stderr(predicate::str::contains(
+            "Command: xcodebuild build -project MyApp.xcodeproj -scheme MyApp",
+        ))
+        .stderr(predicate::str::contains("** BUILD FAILED **"));
+}
+
+#[test]
+fn test_test_command_exits_with_tests_failed_code() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "echo \"Test Suite 'All tests' failed\"\necho '** TEST FAILED **'\nexit 65",
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "test",
+            "--scheme",
+            "MyAppTests",
+            "--destination",
+            "platform=iOS Simulator,name=iPhone 15 Pro",
+            "--project",
+            "MyApp.xcodeproj",
+        ]);
+
+    cmd.assert()
+        .code(5)
+        .stderr(predicate::str::contains("test failed with exit code 65"));
+}
+
+#[test]
+fn test_archive_command_exits_with_signing_failed_code() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "echo 'error: No signing certificate \"iOS Distribution\" found' >&2\necho '** ARCHIVE FAILED **'\nexit 65",
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "archive",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "generic/platform=iOS",
+            "--sdk",
+            "iphoneos",
+            "--output",
+            "MyApp.xcarchive",
+            "--project",
+            "MyApp.xcodeproj",
+        ]);
+
+    cmd.assert()
+        .code(6)
+        .stderr(predicate::str::contains("No signing certificate"));
+}
+
+#[test]
+fn test_upload_command_failure_redacts_password() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcrun",
+        "echo 'Error: Unable to authenticate.' >&2\nexit 1",
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "upload",
+            "--target",
+            "ios",
+            "--app-file-path",
+            "MyApp.ipa",
+            "--username",
+            "developer@example.com",
+            "--password",
+            "super-secret-password",
+        ]);
+
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("Unable to authenticate"))
+        .stderr(predicate::str::contains("'********'"))
+        .stderr(predicate::str::contains("super-secret-password").not())
+        .stderr(predicate::str::contains("retrying").not());
+}
+
+#[test]
+fn test_build_command_streams_output_once_and_writes_log_file() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "echo 'CompileSwift normal arm64 App.swift'\necho 'warning: deprecated' >&2\necho '** BUILD SUCCEEDED **'",
+    );
+    let log_file = temp_dir.path().join("logs").join("build.log");
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "build",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=iOS Simulator,name=iPhone 15 Pro",
+            "--project",
+            "MyApp.xcodeproj",
+            "--formatter",
+            "raw",
+            "--log-file",
+        ])
+        .arg(&log_file);
+
+    let output = cmd.assert().success().get_output().clone();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.matches("** BUILD SUCCEEDED **").count(), 1);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("warning: deprecated"));
+
+    let log = fs::read_to_string(&log_file).unwrap();
+    assert!(log.starts_with("$ xcodebuild build -project MyApp.xcodeproj -scheme MyApp"));
+    assert!(log.contains("CompileSwift normal arm64 App.swift"));
+    assert!(log.contains("warning: deprecated"));
+    assert!(log.contains("** BUILD SUCCEEDED **"));
+}
+
+#[test]
+fn test_build_command_writes_default_log_file() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "echo '** BUILD SUCCEEDED **'",
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "build",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=iOS Simulator,name=iPhone 15 Pro",
+            "--project",
+            "MyApp.xcodeproj",
+        ]);
+
+    cmd.assert().success();
+
+    let log_files: Vec<_> = fs::read_dir(temp_dir.path().join(".xctools").join("logs"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(log_files.len(), 1);
+    assert!(log_files[0].starts_with("build-"));
+    assert!(log_files[0].ends_with(".log"));
+}
+
+/// Runs `xctools --dry-run <args>` with an empty `PATH`, proving no tool is executed, and
+/// returns the printed invocations.
+fn dry_run(args: &[&str]) -> String {
+    let temp_dir = tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .arg("--dry-run")
+        .args(args);
+
+    let output = cmd.assert().success().get_output().clone();
+    assert!(!temp_dir.path().join(".xctools").exists());
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_dry_run_build_prints_xcodebuild_invocation() {
+    let stdout = dry_run(&[
+        "build",
+        "--scheme",
+        "My App",
+        "--destination",
+        "platform=iOS Simulator,name=iPhone 15 Pro",
+        "--project",
+        "MyApp.xcodeproj",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcodebuild build -project MyApp.xcodeproj -scheme 'My App' \
+         -destination 'platform=iOS Simulator,name=iPhone 15 Pro' -configuration Debug\n"
+    );
+}
+
+#[test]
+fn test_dry_run_test_prints_xcodebuild_invocation() {
+    let stdout = dry_run(&[
+        "test",
+        "--scheme",
+        "MyAppTests",
+        "--destination",
+        "platform=macOS",
+        "--workspace",
+        "MyApp.xcworkspace",
+        "--configuration",
+        "release",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcodebuild test -workspace MyApp.xcworkspace -scheme MyAppTests \
+         -destination platform=macOS -configuration Release \
+         -resultBundlePath .xctools/results/MyAppTests.xcresult\n"
+    );
+}
+
+#[test]
+fn test_dry_run_archive_prints_xcodebuild_invocation() {
+    let stdout = dry_run(&[
+        "archive",
+        "--scheme",
+        "MyApp",
+        "--destination",
+        "generic/platform=iOS",
+        "--sdk",
+        "iphoneos",
+        "--output",
+        "build/MyApp.xcarchive",
+        "--project",
+        "MyApp.xcodeproj",
+        "--configuration",
+        "release",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcodebuild archive -project MyApp.xcodeproj -scheme MyApp \
+         -destination generic/platform=iOS -configuration Release \
+         -archivePath build/MyApp.xcarchive -sdk iphoneos\n"
+    );
+}
+
+#[test]
+fn test_dry_run_export_archive_prints_xcodebuild_invocation() {
+    let stdout = dry_run(&[
+        "export-archive",
+        "--archive-path",
+        "MyApp.xcarchive",
+        "--export-options",
+        "ExportOptions.plist",
+        "--export-path",
+        "build",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcodebuild -exportArchive -archivePath MyApp.xcarchive -exportPath build \
+         -exportOptionsPlist ExportOptions.plist\n"
+    );
+}
+
+#[test]
+fn test_dry_run_upload_prints_redacted_altool_invocation() {
+    let stdout = dry_run(&[
+        "upload",
+        "--target",
+        "ios",
+        "--app-file-path",
+        "MyApp.ipa",
+        "--username",
+        "developer@example.com",
+        "--password",
+        "super-secret-password",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcrun altool --upload-app -t ios -f MyApp.ipa -u developer@example.com -p '********'\n"
+    );
+}
+
+#[test]
+fn test_dry_run_notarize_prints_notarytool_and_stapler_invocations() {
+    let stdout = dry_run(&[
+        "notarize",
+        "--file-path",
+        "MyApp.dmg",
+        "--apple-id",
+        "developer@example.com",
+        "--password",
+        "super-secret-password",
+        "--team-id",
+        "A1B2C3D4E5",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcrun notarytool submit MyApp.dmg --apple-id developer@example.com \
+         --password '********' --team-id A1B2C3D4E5 --wait\n\
+         xcrun stapler staple MyApp.dmg\n"
+    );
+}
+
+#[test]
+fn test_dry_run_setup_signing_prints_redacted_security_invocations() {
+    let stdout = dry_run(&[
+        "setup-signing",
+        "--certificate-path",
+        "signing.p12",
+        "--certificate-password",
+        "super-secret-password",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "security create-keychain -p '********' xctools-signing.keychain\n\
+         security set-keychain-settings -lut 3600 xctools-signing.keychain\n\
+         security unlock-keychain -p '********' xctools-signing.keychain\n\
+         security import signing.p12 -k xctools-signing.keychain -P '********' \
+         -T /usr/bin/codesign -T /usr/bin/productsign\n\
+         security set-key-partition-list -S apple-tool:,apple: -s -k '********' \
+         xctools-signing.keychain\n\
+         security default-keychain -s xctools-signing.keychain\n\
+         Dry run: no changes were made.\n\
+         Keychain: xctools-signing.keychain\n\
+         Certificate: signing.p12\n\
+         Provisioning profiles to install: 0\n"
+    );
+}
+
+#[test]
+fn test_dry_run_setup_signing_still_validates_provisioning_profiles() {
+    let temp_dir = tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path()).args([
+        "--dry-run",
+        "setup-signing",
+        "--certificate-path",
+        "signing.p12",
+        "--certificate-password",
+        "secret",
+        "--provisioning-profile",
+        "Missing.mobileprovision",
+    ]);
+
+    cmd.assert().code(1).stderr(predicate::str::contains(
+        "Provisioning profile not found: Missing.mobileprovision",
+    ));
+}
+
+#[test]
+fn test_dry_run_is_rejected_for_commands_without_external_tools() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args(["bump-version", "--build-number", "42", "--dry-run"]);
+
+    cmd.assert().code(1).stderr(predicate::str::contains(
+        "--dry-run is only supported by commands that run external tools",
+    ));
+}
+
+fn build_with_fake_xcodebuild(formatter: &str, script: &str) -> (String, String) {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(temp_dir.path(), "xcodebuild", script);
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .env("NO_COLOR", "1")
+        .current_dir(temp_dir.path())
+        .args([
+            "build",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=iOS Simulator,name=iPhone 15 Pro",
+            "--project",
+            "MyApp.xcodeproj",
+            "--formatter",
+            formatter,
+        ]);
+
+    let output = cmd.output().unwrap();
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+const FAKE_BUILD_OUTPUT: &str =
+    "echo 'CompileSwift normal arm64 /src/App/ContentView.swift (in target)'
+echo '    cd /src/App'
+echo '/src/App/ContentView.swift:3:9: warning: variable x was never used'
+echo '** BUILD SUCCEEDED **'";
+
+#[test]
+fn test_build_command_pretty_formatter_is_the_default_style() {
+    let (stdout, _) = build_with_fake_xcodebuild("pretty", FAKE_BUILD_OUTPUT);
+
+    assert_eq!(
+        stdout,
+        "▸ Compiling ContentView.swift\n\
+         ⚠ /src/App/ContentView.swift:3:9: variable x was never used\n\
+         ** BUILD SUCCEEDED **\n\
+         1 file compiled, 1 warning\n"
+    );
+}
+
+#[test]
+fn test_build_command_quiet_formatter_shows_only_problems_and_result() {
+    let (stdout, _) = build_with_fake_xcodebuild("quiet", FAKE_BUILD_OUTPUT);
+
+    assert_eq!(
+        stdout,
+        "⚠ /src/App/ContentView.swift:3:9: variable x was never used\n\
+         ** BUILD SUCCEEDED **\n\
+         1 file compiled, 1 warning\n"
+    );
+}
+
+#[test]
+fn test_build_command_raw_formatter_passes_output_through() {
+    let (stdout, _) = build_with_fake_xcodebuild("raw", FAKE_BUILD_OUTPUT);
+
+    assert_eq!(
+        stdout,
+        "CompileSwift normal arm64 /src/App/ContentView.swift (in target)\n    cd /src/App\n\
+         /src/App/ContentView.swift:3:9: warning: variable x was never used\n\
+         ** BUILD SUCCEEDED **\n"
+    );
+}
+
+#[test]
+fn test_build_command_invalid_formatter() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args([
+        "--formatter",
+        "fancy",
+        "build",
+        "--scheme",
+        "MyApp",
+        "--destination",
+        "platform=macOS",
+        "--project",
+        "MyApp.xcodeproj",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'fancy'"));
+}
+
+#[test]
+fn test_dry_run_build_appends_build_settings_last() {
+    let stdout = dry_run(&[
+        "build",
+        "--scheme",
+        "MyApp",
+        "--destination",
+        "platform=macOS",
+        "--project",
+        "MyApp.xcodeproj",
+        "--build-setting",
+        "CODE_SIGNING_ALLOWED=NO",
+        "--build-setting",
+        "SWIFT_ACTIVE_COMPILATION_CONDITIONS=DEBUG CI",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcodebuild build -project MyApp.xcodeproj -scheme MyApp -destination platform=macOS \
+         -configuration Debug CODE_SIGNING_ALLOWED=NO \
+         'SWIFT_ACTIVE_COMPILATION_CONDITIONS=DEBUG CI'\n"
+    );
+}
+
+#[test]
+fn test_dry_run_archive_passes_xcconfig_before_build_settings() {
+    let temp_dir = tempdir().unwrap();
+    let xcconfig = temp_dir.path().join("Release.xcconfig");
+    std::fs::write(&xcconfig, "DEVELOPMENT_TEAM = A1B2C3D4E5\n").unwrap();
+    let xcconfig = xcconfig.to_string_lossy().to_string();
+
+    let stdout = dry_run(&[
+        "archive",
+        "--scheme",
+        "MyApp",
+        "--destination",
+        "generic/platform=iOS",
+        "--sdk",
+        "iphoneos",
+        "--output",
+        "build/MyApp.xcarchive",
+        "--project",
+        "MyApp.xcodeproj",
+        "--xcconfig",
+        &xcconfig,
+        "--build-setting",
+        "MARKETING_VERSION=2.0",
+    ]);
+
+    assert_eq!(
+        stdout,
+        format!(
+            "xcodebuild archive -project MyApp.xcodeproj -scheme MyApp \
+             -destination generic/platform=iOS -configuration Debug \
+             -archivePath build/MyApp.xcarchive -sdk iphoneos -xcconfig {} MARKETING_VERSION=2.0\n",
+            xcconfig
+        )
+    );
+}
+
+#[test]
+fn test_test_command_missing_xcconfig() {
+    let temp_dir = tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.current_dir(temp_dir.path()).args([
+        "--dry-run",
+        "test",
+        "--scheme",
+        "MyAppTests",
+        "--destination",
+        "platform=macOS",
+        "--project",
+        "MyApp.xcodeproj",
+        "--xcconfig",
+        "Config/Missing.xcconfig",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "xcconfig file not found: Config/Missing.xcconfig",
+        ));
+}
+
+#[test]
+fn test_build_command_invalid_build_setting() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args([
+        "build",
+        "--scheme",
+        "MyApp",
+        "--destination",
+        "platform=macOS",
+        "--project",
+        "MyApp.xcodeproj",
+        "--build-setting",
+        "CODE_SIGNING_ALLOWED",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("expected KEY=VALUE"));
+}
+
+#[test]
+fn test_dry_run_build_passes_custom_configuration_verbatim() {
+    let stdout = dry_run(&[
+        "build",
+        "--scheme",
+        "MyApp",
+        "--destination",
+        "platform=macOS",
+        "--project",
+        "MyApp.xcodeproj",
+        "--configuration",
+        "App Store",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcodebuild build -project MyApp.xcodeproj -scheme MyApp -destination platform=macOS \
+         -configuration 'App Store'\n"
+    );
+}
+
+#[test]
+fn test_dry_run_build_validates_configuration_against_project() {
+    let project = format!(
+        "{}/TestXcodeApp/TestXcodeApp.xcodeproj",
+        env!("CARGO_MANIFEST_DIR")
+    );
+
+    let stdout = dry_run(&[
+        "build",
+        "--scheme",
+        "TestXcodeApp",
+        "--destination",
+        "platform=macOS",
+        "--project",
+        &project,
+        "--configuration",
+        "RELEASE",
+        "--validate-configuration",
+    ]);
+
+    assert!(stdout.contains("-configuration Release"));
+}
+
+#[test]
+fn test_archive_command_rejects_undeclared_configuration() {
+    let temp_dir = tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.current_dir(temp_dir.path()).args([
+        "--dry-run",
+        "archive",
+        "--scheme",
+        "TestXcodeApp",
+        "--destination",
+        "generic/platform=iOS",
+        "--sdk",
+        "iphoneos",
+        "--output",
+        "MyApp.xcarchive",
+        "--project",
+        &format!(
+            "{}/TestXcodeApp/TestXcodeApp.xcodeproj",
+            env!("CARGO_MANIFEST_DIR")
+        ),
+        "--configuration",
+        "Staging",
+        "--validate-configuration",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains(
+            "Configuration 'Staging' is not declared in the project; available configurations: Debug, Release",
+        ));
+}
+
+#[test]
+fn test_dry_run_build_defaults_destination_from_sdk() {
+    let stdout = dry_run(&[
+        "build",
+        "--scheme",
+        "MyApp",
+        "--sdk",
+        "iphonesimulator",
+        "--project",
+        "MyApp.xcodeproj",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcodebuild build -project MyApp.xcodeproj -scheme MyApp \
+         -destination 'generic/platform=iOS Simulator' -configuration Debug -sdk iphonesimulator\n"
+    );
+}
+
+#[test]
+fn test_dry_run_test_defaults_destination_from_sdk() {
+    let stdout = dry_run(&[
+        "test",
+        "--scheme",
+        "MyAppTests",
+        "--sdk",
+        "appletvsimulator",
+        "--workspace",
+        "MyApp.xcworkspace",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcodebuild test -workspace MyApp.xcworkspace -scheme MyAppTests \
+         -destination 'platform=tvOS Simulator' -configuration Debug -sdk appletvsimulator \
+         -resultBundlePath .xctools/results/MyAppTests.xcresult\n"
+    );
+}
+
+#[test]
+fn test_dry_run_test_keeps_explicit_destination_with_sdk() {
+    let stdout = dry_run(&[
+        "test",
+        "--scheme",
+        "MyAppTests",
+        "--sdk",
+        "iphonesimulator18.2",
+        "--destination",
+        "platform=iOS Simulator,name=iPhone 16,OS=18.2",
+        "--project",
+        "MyApp.xcodeproj",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcodebuild test -project MyApp.xcodeproj -scheme MyAppTests \
+         -destination 'platform=iOS Simulator,name=iPhone 16,OS=18.2' -configuration Debug \
+         -sdk iphonesimulator18.2 \
+         -resultBundlePath .xctools/results/MyAppTests.xcresult\n"
+    );
+}
+
+#[test]
+fn test_dry_run_test_with_test_selection() {
+    let stdout = dry_run(&[
+        "test",
+        "--scheme",
+        "MyAppTests",
+        "--destination",
+        "platform=macOS",
+        "--project",
+        "MyApp.xcodeproj",
+        "--test-plan",
+        "Smoke Tests",
+        "--only-testing",
+        "MyAppTests/LoginTests",
+        "--only-testing",
+        "MyAppUITests",
+        "--skip-testing",
+        "MyAppTests/LoginTests/testSlowLogin",
+        "--only-test-configuration",
+        "English",
+        "--skip-test-configuration",
+        "German",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcodebuild test -project MyApp.xcodeproj -scheme MyAppTests \
+         -destination platform=macOS -configuration Debug \
+         -resultBundlePath .xctools/results/MyAppTests.xcresult -testPlan 'Smoke Tests' \
+         -only-testing:MyAppTests/LoginTests -only-testing:MyAppUITests \
+         -skip-testing:MyAppTests/LoginTests/testSlowLogin \
+         -only-test-configuration English -skip-test-configuration German\n"
+    );
+}
+
+#[test]
+fn test_test_command_rejects_invalid_test_identifier() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args([
+        "test",
+        "--scheme",
+        "MyAppTests",
+        "--destination",
+        "platform=macOS",
+        "--project",
+        "MyApp.xcodeproj",
+        "--only-testing",
+        "MyAppTests/LoginTests/testLogin/extra",
+    ]);
+
+    cmd.assert().code(2).stderr(predicate::str::contains(
+        "Invalid test identifier 'MyAppTests/LoginTests/testLogin/extra': \
+         expected Target, Target/Class or Target/Class/method",
+    ));
+}
+
+#[test]
+fn test_test_command_rejects_test_both_selected_and_skipped() {
+    let temp_dir = tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "test",
+            "--scheme",
+            "MyAppTests",
+            "--destination",
+            "platform=macOS",
+            "--project",
+            "MyApp.xcodeproj",
+            "--only-testing",
+            "MyAppTests/LoginTests",
+            "--skip-testing",
+            "MyAppTests/LoginTests",
+        ]);
+
+    cmd.assert().code(1).stderr(predicate::str::contains(
+        "'MyAppTests/LoginTests' is passed to both --only-testing and --skip-testing",
+    ));
+    assert!(!temp_dir.path().join(".xctools").exists());
+}
+
+#[test]
+fn test_dry_run_archive_with_versioned_visionos_sdk() {
+    let stdout = dry_run(&[
+        "archive",
+        "--scheme",
+        "MyApp",
+        "--sdk",
+        "xros2.2",
+        "--output",
+        "MyApp.xcarchive",
+        "--project",
+        "MyApp.xcodeproj",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcodebuild archive -project MyApp.xcodeproj -scheme MyApp \
+         -destination generic/platform=visionOS -configuration Debug \
+         -archivePath MyApp.xcarchive -sdk xros2.2\n"
+    );
+}
+
+#[test]
+fn test_build_command_missing_destination_and_sdk() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args(["build", "--scheme", "MyApp", "--project", "MyApp.xcodeproj"]);
+
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("required"))
+        .stderr(predicate::str::contains("--destination"))
+        .stderr(predicate::str::contains("--sdk"));
+}
+
+#[test]
+fn test_build_command_invalid_sdk() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args([
+        "build",
+        "--scheme",
+        "MyApp",
+        "--sdk",
+        "android",
+        "--project",
+        "MyApp.xcodeproj",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("invalid value 'android'"))
+        .stderr(predicate::str::contains("unknown SDK 'android'"));
+}
+
+#[test]
+fn test_dry_run_test_with_multiple_destinations() {
+    let stdout = dry_run(&[
+        "test",
+        "--scheme",
+        "MyAppTests",
+        "--destination",
+        "platform=iOS Simulator,name=iPhone 16,OS=18.2",
+        "--destination",
+        "platform=iOS Simulator,name=iPad Air 11-inch (M2)",
+        "--project",
+        "MyApp.xcodeproj",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcodebuild test -project MyApp.xcodeproj -scheme MyAppTests \
+         -destination 'platform=iOS Simulator,name=iPhone 16,OS=18.2' \
+         -destination 'platform=iOS Simulator,name=iPad Air 11-inch (M2)' -configuration Debug \
+         -resultBundlePath .xctools/results/MyAppTests.xcresult\n"
+    );
+}
+
+#[test]
+fn test_dry_run_build_normalizes_destination() {
+    let stdout = dry_run(&[
+        "build",
+        "--scheme",
+        "MyApp",
+        "--destination",
+        "iOS Simulator, os=latest ,Name=iPhone 16",
+        "--project",
+        "MyApp.xcodeproj",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcodebuild build -project MyApp.xcodeproj -scheme MyApp \
+         -destination 'platform=iOS Simulator,name=iPhone 16,OS=latest' -configuration Debug\n"
+    );
+}
+
+#[test]
+fn test_build_command_rejects_unknown_destination_key() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args([
+        "build",
+        "--scheme",
+        "MyApp",
+        "--destination",
+        "platform=iOS Simulator,device=iPhone 16",
+        "--project",
+        "MyApp.xcodeproj",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("unknown destination key 'device'"));
+}
+
+#[test]
+fn test_archive_command_rejects_unknown_destination_platform() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args([
+        "archive",
+        "--scheme",
+        "MyApp",
+        "--destination",
+        "generic/platform=iPadOS",
+        "--sdk",
+        "iphoneos",
+        "--output",
+        "MyApp.xcarchive",
+        "--project",
+        "MyApp.xcodeproj",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "unknown destination platform 'iPadOS'",
+        ));
+}
+
+const FAKE_SIMCTL_LIST: &str = r#"{
+  "runtimes": [
+    { "identifier": "com.apple.CoreSimulator.SimRuntime.iOS-17-5", "name": "iOS 17.5",
+      "platform": "iOS", "version": "17.5", "isAvailable": true },
+    { "identifier": "com.apple.CoreSimulator.SimRuntime.iOS-18-2", "name": "iOS 18.2",
+      "platform": "iOS", "version": "18.2", "isAvailable": true }
+  ],
+  "devices": {
+    "com.apple.CoreSimulator.SimRuntime.iOS-17-5": [
+      { "udid": "IPAD-17-5", "name": "iPad Air 11-inch (M2)", "state": "Shutdown", "isAvailable": true },
+      { "udid": "IPHONE-17-5", "name": "iPhone 15 Pro", "state": "Shutdown", "isAvailable": true }
+    ],
+    "com.apple.CoreSimulator.SimRuntime.iOS-18-2": [
+      { "udid": "IPHONE-18-2", "name": "iPhone 16", "state": "Shutdown", "isAvailable": true }
+    ]
+  }
+}"#;
+
+/// Runs xctools in dry-run mode with a fake `xcrun` that answers `simctl list --json`.
+fn dry_run_with_simulators(args: &[&str]) -> assert_cmd::assert::Assert {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcrun",
+        &format!("printf '%s\\n' '{}'", FAKE_SIMCTL_LIST),
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .arg("--dry-run")
+        .args(args);
+
+    cmd.assert()
+}
+
+#[test]
+fn test_dry_run_test_resolves_destination_auto_to_latest_simulator() {
+    dry_run_with_simulators(&[
+        "test",
+        "--scheme",
+        "MyAppTests",
+        "--destination-auto",
+        "iPhone latest",
+        "--project",
+        "MyApp.xcodeproj",
+    ])
+    .success()
+    .stdout(
+        "xcodebuild test -project MyApp.xcodeproj -scheme MyAppTests \
+         -destination 'platform=iOS Simulator,id=IPHONE-18-2' -configuration Debug \
+         -resultBundlePath .xctools/results/MyAppTests.xcresult\n",
+    )
+    .stderr(predicate::str::contains(
+        "Using simulator iPhone 16 (iOS 18.2, IPHONE-18-2)",
+    ));
+}
+
+#[test]
+fn test_dry_run_build_resolves_destination_auto_with_runtime() {
+    dry_run_with_simulators(&[
+        "build",
+        "--scheme",
+        "MyApp",
+        "--destination-auto",
+        "iPad, iOS 17",
+        "--project",
+        "MyApp.xcodeproj",
+    ])
+    .success()
+    .stdout(predicate::str::contains(
+        "-destination 'platform=iOS Simulator,id=IPAD-17-5'",
+    ));
+}
+
+#[test]
+fn test_test_command_destination_auto_without_match() {
+    dry_run_with_simulators(&[
+        "test",
+        "--scheme",
+        "MyAppTests",
+        "--destination-auto",
+        "Apple TV",
+        "--project",
+        "MyApp.xcodeproj",
+    ])
+    .failure()
+    .code(1)
+    .stdout(predicate::str::is_empty())
+    .stderr(predicate::str::contains(
+        "No available simulator matches 'Apple TV, latest'; available simulators: \
+         iPad Air 11-inch (M2) (iOS 17.5), iPhone 15 Pro (iOS 17.5), iPhone 16 (iOS 18.2)",
+    ));
+}
+
+#[test]
+fn test_build_command_invalid_destination_auto() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args([
+        "build",
+        "--scheme",
+        "MyApp",
+        "--destination-auto",
+        "latest",
+        "--project",
+        "MyApp.xcodeproj",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("expected a device name"));
+}
+
+#[test]
+fn test_dry_run_simulator_boot_waits_for_device_by_name() {
+    let output = dry_run_with_simulators(&["simulator", "boot", "iPhone 16"])
+        .success()
+        .get_output()
+        .clone();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let (invocations, json) = stdout.split_at(stdout.find('{').unwrap());
+
+    assert_eq!(invocations, "xcrun simctl bootstatus IPHONE-18-2 -b\n");
+    let report: serde_json::Value = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        report,
+        serde_json::json!({
+            "action": "boot",
+            "devices": [{
+                "udid": "IPHONE-18-2",
+                "name": "iPhone 16",
+                "state": "Booted",
+                "runtime": "iOS 18.2",
+                "runtimeIdentifier": "com.apple.CoreSimulator.SimRuntime.iOS-18-2",
+            }],
+        })
+    );
+}
+
+#[test]
+fn test_dry_run_simulator_boot_no_wait() {
+    dry_run_with_simulators(&["simulator", "boot", "IPHONE-17-5", "--no-wait"])
+        .success()
+        .stdout(predicate::str::starts_with(
+            "xcrun simctl boot IPHONE-17-5\n",
+        ));
+}
+
+#[test]
+fn test_dry_run_simulator_erase_and_delete_several_devices() {
+    dry_run_with_simulators(&["simulator", "erase", "iPhone 16", "IPAD-17-5"])
+        .success()
+        .stdout(predicate::str::starts_with(
+            "xcrun simctl erase IPHONE-18-2\nxcrun simctl erase IPAD-17-5\n",
+        ));
+    dry_run_with_simulators(&["simulator", "delete", "iPhone 15 Pro"])
+        .success()
+        .stdout(predicate::str::starts_with(
+            "xcrun simctl delete IPHONE-17-5\n",
+        ))
+        .stdout(predicate::str::contains("\"state\": \"Deleted\""));
+}
+
+#[test]
+fn test_dry_run_simulator_create_resolves_runtime() {
+    dry_run_with_simulators(&[
+        "simulator",
+        "create",
+        "--name",
+        "CI iPhone",
+        "--device-type",
+        "iPhone 16 Pro",
+        "--runtime",
+        "iOS latest",
+    ])
+    .success()
+    .stdout(predicate::str::starts_with(
+        "xcrun simctl create 'CI iPhone' 'iPhone 16 Pro' \
+         com.apple.CoreSimulator.SimRuntime.iOS-18-2\n",
+    ))
+    .stdout(predicate::str::contains("\"udid\": null"));
+}
+
+#[test]
+fn test_dry_run_simulator_status_bar() {
+    dry_run_with_simulators(&[
+        "simulator",
+        "status-bar",
+        "iPhone 16",
+        "--time",
+        "10:00",
+        "--operator-name",
+        "Carrier",
+    ])
+    .success()
+    .stdout(predicate::str::starts_with(
+        "xcrun simctl status_bar IPHONE-18-2 override --time 10:00 --batteryState charged \
+         --batteryLevel 100 --cellularBars 4 --wifiBars 3 --dataNetwork wifi \
+         --operatorName Carrier\n",
+    ));
+    dry_run_with_simulators(&["simulator", "status-bar", "iPhone 16", "--clear"])
+        .success()
+        .stdout(predicate::str::starts_with(
+            "xcrun simctl status_bar IPHONE-18-2 clear\n",
+        ));
+}
+
+#[test]
+fn test_simulator_status_bar_rejects_out_of_range_values() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args(["simulator", "status-bar", "iPhone 16", "--wifi-bars", "4"]);
+
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("--wifi-bars"));
+}
+
+#[test]
+fn test_simulator_unknown_device() {
+    dry_run_with_simulators(&["simulator", "erase", "iPhone 99"])
+        .failure()
+        .code(1)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains(
+            "No simulator is named or identified by 'iPhone 99'",
+        ));
+}
+
+#[test]
+fn test_simulator_create_reports_created_device() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcrun",
+        &format!(
+            "case \"$2\" in\n  list) printf '%s\\n' '{}' ;;\n  create) printf 'IPHONE-18-2\\n' ;;\nesac",
+            FAKE_SIMCTL_LIST
+        ),
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "simulator",
+            "create",
+            "--name",
+            "iPhone 16",
+            "--device-type",
+            "iPhone 16",
+        ]);
+
+    let output = cmd.assert().success().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["action"], "create");
+    assert_eq!(report["devices"][0]["udid"], "IPHONE-18-2");
+    assert_eq!(report["devices"][0]["runtime"], "iOS 18.2");
+    assert_eq!(report["devices"][0]["state"], "Shutdown");
+}
+
+const FAKE_XCODEBUILD_LIST: &str = r#"{
+  "project": {
+    "configurations": ["Debug", "Release"],
+    "name": "TestXcodeApp",
+    "schemes": ["TestXcodeApp", "TestXcodeApp Staging"],
+    "targets": ["TestXcodeApp", "TestXcodeAppTests"]
+  }
+}"#;
+
+/// Writes a fake `xcodebuild` that answers `-list -json` and rejects every scheme otherwise.
+fn write_fake_xcodebuild_with_schemes(dir: &std::path::Path) {
+    write_fake_tool(
+        dir,
+        "xcodebuild",
+        &format!(
+            "if [ \"$1\" = \"-list\" ]; then\n  printf '%s\\n' '{}'\n  exit 0\nfi\n\
+             echo 'xcodebuild: error: The project named \"TestXcodeApp\" does not contain a scheme named \"TestXcodeAp\".' >&2\n\
+             exit 65",
+            FAKE_XCODEBUILD_LIST
+        ),
+    );
+}
+
+#[test]
+fn test_list_command_prints_table() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_xcodebuild_with_schemes(temp_dir.path());
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args(["list", "--project", "TestXcodeApp.xcodeproj"]);
+
+    cmd.assert().success().stdout(
+        "TestXcodeApp\n\
+         \n\
+         SCHEMES               TARGETS            CONFIGURATIONS\n\
+         TestXcodeApp          TestXcodeApp       Debug\n\
+         TestXcodeApp Staging  TestXcodeAppTests  Release\n",
+    );
+}
+
+#[test]
+fn test_list_command_prints_json() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_xcodebuild_with_schemes(temp_dir.path());
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "list",
+            "--project",
+            "TestXcodeApp.xcodeproj",
+            "--format",
+            "json",
+        ]);
+
+    let output = cmd.assert().success().get_output().clone();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "name": "TestXcodeApp",
+            "schemes": ["TestXcodeApp", "TestXcodeApp Staging"],
+            "targets": ["TestXcodeApp", "TestXcodeAppTests"],
+            "configurations": ["Debug", "Release"],
+        })
+    );
+}
+
+#[test]
+fn test_list_command_discovers_project() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_xcodebuild_with_schemes(temp_dir.path());
+    fs::create_dir_all(temp_dir.path().join("TestXcodeApp.xcodeproj")).unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args(["list", "--format", "json"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"name\": \"TestXcodeApp\""))
+        .stderr("Using project TestXcodeApp.xcodeproj\n");
+}
+
+#[test]
+fn test_list_command_rejects_both_project_and_workspace() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args([
+        "list",
+        "--project",
+        "MyApp.xcodeproj",
+        "--workspace",
+        "MyApp.xcworkspace",
+    ]);
+
+    cmd.assert().failure().code(2);
+}
+
+#[test]
+fn test_list_command_reports_xcodebuild_failure() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "echo 'xcodebuild: error: Missing.xcodeproj does not exist.' >&2\nexit 66",
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args(["list", "--project", "Missing.xcodeproj"]);
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "xcodebuild -list failed with exit code 66",
+        ))
+        .stderr(predicate::str::contains(
+            "Missing.xcodeproj does not exist.",
+        ));
+}
+
+#[test]
+fn test_build_command_suggests_scheme_for_unknown_scheme() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_xcodebuild_with_schemes(temp_dir.path());
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "build",
+            "--scheme",
+            "TestXcodeAp",
+            "--destination",
+            "platform=macOS",
+            "--project",
+            "TestXcodeApp.xcodeproj",
+        ]);
+
+    cmd.assert()
+        .failure()
+        .code(4)
+        .stderr(predicate::str::contains(
+            "Error: Scheme 'TestXcodeAp' not found in TestXcodeApp (did you mean 'TestXcodeApp'?); \
+             available schemes: TestXcodeApp, TestXcodeApp Staging",
+        ))
+        .stderr(predicate::str::contains("build failed with exit code 65"));
+}
+
+#[test]
+fn test_archive_command_suggests_scheme_for_unknown_scheme() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_xcodebuild_with_schemes(temp_dir.path());
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "archive",
+            "--scheme",
+            "testxcodeapp staging",
+            "--sdk",
+            "iphoneos",
+            "--output",
+            "MyApp.xcarchive",
+            "--project",
+            "TestXcodeApp.xcodeproj",
+        ]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "(did you mean 'TestXcodeApp Staging'?)",
+    ));
+}
+
+/// Runs xctools in dry-run mode in a directory containing the given bundle directories.
+fn dry_run_in_repo(bundles: &[&str], args: &[&str]) -> assert_cmd::assert::Assert {
+    let temp_dir = tempdir().unwrap();
+    for bundle in bundles {
+        fs::create_dir_all(temp_dir.path().join(bundle)).unwrap();
+    }
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .arg("--dry-run")
+        .args(args);
+
+    cmd.assert()
+}
+
+#[test]
+fn test_dry_run_build_discovers_workspace() {
+    dry_run_in_repo(
+        &[
+            "MyApp.xcworkspace",
+            "MyApp.xcodeproj/project.xcworkspace",
+            "Pods/Pods.xcodeproj",
+        ],
+        &["build", "--scheme", "MyApp", "--sdk", "iphonesimulator"],
+    )
+    .success()
+    .stdout(
+        "xcodebuild build -workspace MyApp.xcworkspace -scheme MyApp \
+         -destination 'generic/platform=iOS Simulator' -configuration Debug -sdk iphonesimulator\n",
+    )
+    .stderr(predicate::str::contains(
+        "Using workspace MyApp.xcworkspace",
+    ));
+}
+
+#[test]
+fn test_dry_run_test_discovers_project() {
+    dry_run_in_repo(
+        &["MyApp.xcodeproj/project.xcworkspace"],
+        &[
+            "test",
+            "--scheme",
+            "MyAppTests",
+            "--destination",
+            "platform=macOS",
+        ],
+    )
+    .success()
+    .stdout(
+        "xcodebuild test -project MyApp.xcodeproj -scheme MyAppTests \
+         -destination platform=macOS -configuration Debug \
+         -resultBundlePath .xctools/results/MyAppTests.xcresult\n",
+    )
+    .stderr(predicate::str::contains("Using project MyApp.xcodeproj"));
+}
+
+#[test]
+fn test_dry_run_archive_explicit_project_skips_discovery() {
+    dry_run_in_repo(
+        &["App.xcodeproj", "Kit.xcodeproj"],
+        &[
+            "archive",
+            "--scheme",
+            "App",
+            "--sdk",
+            "macosx",
+            "--output",
+            "App.xcarchive",
+            "--project",
+            "Kit.xcodeproj",
+        ],
+    )
+    .success()
+    .stdout(predicate::str::starts_with(
+        "xcodebuild archive -project Kit.xcodeproj",
+    ))
+    .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn test_build_command_reports_ambiguous_projects() {
+    dry_run_in_repo(
+        &["App.xcodeproj", "Kit.xcodeproj"],
+        &["build", "--scheme", "App", "--sdk", "macosx"],
+    )
+    .failure()
+    .code(1)
+    .stdout(predicate::str::is_empty())
+    .stderr(predicate::str::contains(
+        "Found several projects in . (App.xcodeproj, Kit.xcodeproj); pass --project to choose one",
+    ));
+}
+
+#[test]
+fn test_build_command_reports_ambiguous_workspaces() {
+    dry_run_in_repo(
+        &["App.xcworkspace", "Other.xcworkspace"],
+        &["build", "--scheme", "App", "--sdk", "macosx"],
+    )
+    .failure()
+    .code(1)
+    .stderr(predicate::str::contains(
+        "Found several workspaces in . (App.xcworkspace, Other.xcworkspace); \
+         pass --workspace to choose one",
+    ));
+}
+
+fn write_fake_xcode(dir: &std::path::Path, name: &str, version: &str, build: &str) {
+    let contents = dir.join(name).join("Contents");
+    fs::create_dir_all(contents.join("Developer")).unwrap();
+    fs::write(
+        contents.join("version.plist"),
+        format!(
+            "<plist version=\"1.0\">\n<dict>\n\t<key>CFBundleShortVersionString</key>\n\
+             \t<string>{}</string>\n\t<key>ProductBuildVersion</key>\n\t<string>{}</string>\n\
+             </dict>\n</plist>\n",
+            version, build
+        ),
+    )
+    .unwrap();
+}
+
+/// Returns a command running in a temporary directory whose fake Xcodes (16.2, 16.0 and 15.4)
+/// are the only ones xctools can find.
+fn command_with_fake_xcodes(temp_dir: &std::path::Path) -> Command {
+    let applications = temp_dir.join("Applications");
+    write_fake_xcode(&applications, "Xcode.app", "16.2", "16C5032a");
+    write_fake_xcode(&applications, "Xcode-16.0.app", "16.0", "16A242d");
+    write_fake_xcode(&applications, "Xcode_15.4.app", "15.4", "15F31d");
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir)
+        .env("HOME", temp_dir)
+        .env("XCTOOLS_XCODE_SEARCH_PATH", &applications)
+        .current_dir(temp_dir);
+    cmd
+}
+
+#[test]
+fn test_xcode_list_command() {
+    let temp_dir = tempdir().unwrap();
+    let applications = temp_dir.path().join("Applications");
+    let mut cmd = command_with_fake_xcodes(temp_dir.path());
+    cmd.args(["xcode", "list"]);
+
+    cmd.assert().success().stdout(format!(
+        "VERSION  BUILD     PATH\n\
+         16.2     16C5032a  {0}/Xcode.app\n\
+         16.0     16A242d   {0}/Xcode-16.0.app\n\
+         15.4     15F31d    {0}/Xcode_15.4.app\n",
+        applications.display()
+    ));
+}
+
+#[test]
+fn test_xcode_list_command_without_installations() {
+    let temp_dir = tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("HOME", temp_dir.path())
+        .env("XCTOOLS_XCODE_SEARCH_PATH", temp_dir.path())
+        .args(["xcode", "list"]);
+
+    cmd.assert()
+        .success()
+        .stdout("No Xcode installations found\n");
+}
+
+#[test]
+fn test_dry_run_build_with_xcode_version_sets_developer_dir() {
+    let temp_dir = tempdir().unwrap();
+    let mut cmd = command_with_fake_xcodes(temp_dir.path());
+    cmd.args([
+        "--xcode",
+        "16",
+        "--dry-run",
+        "build",
+        "--scheme",
+        "MyApp",
+        "--destination",
+        "platform=macOS",
+        "--project",
+        "MyApp.xcodeproj",
+    ]);
+
+    let developer_dir = temp_dir
+        .path()
+        .join("Applications/Xcode.app/Contents/Developer");
+    cmd.assert()
+        .success()
+        .stdout(format!(
+            "DEVELOPER_DIR={} xcodebuild build -project MyApp.xcodeproj -scheme MyApp \
+             -destination platform=macOS -configuration Debug\n",
+            developer_dir.display()
+        ))
+        .stderr(predicate::str::contains(format!(
+            "Using Xcode 16.2 ({})",
+            temp_dir.path().join("Applications/Xcode.app").display()
+        )));
+}
+
+#[test]
+fn test_build_with_xcode_path_passes_developer_dir_to_xcodebuild() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "echo \"DEVELOPER_DIR is $DEVELOPER_DIR\"",
+    );
+    let xcode = temp_dir.path().join("Applications/Xcode_15.4.app");
+    let mut cmd = command_with_fake_xcodes(temp_dir.path());
+    cmd.args([
+        "build",
+        "--scheme",
+        "MyApp",
+        "--destination",
+        "platform=macOS",
+        "--project",
+        "MyApp.xcodeproj",
+        "--formatter",
+        "raw",
+        "--xcode",
+    ])
+    .arg(&xcode);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "DEVELOPER_DIR is {}/Contents/Developer",
+            xcode.display()
+        )));
+}
+
+#[test]
+fn test_xcode_version_not_installed() {
+    let temp_dir = tempdir().unwrap();
+    let mut cmd = command_with_fake_xcodes(temp_dir.path());
+    cmd.args([
+        "--xcode",
+        "14",
+        "build",
+        "--scheme",
+        "MyApp",
+        "--destination",
+        "platform=macOS",
+        "--project",
+        "MyApp.xcodeproj",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "No installed Xcode matches '14'; installed Xcodes: 16.2 (",
+        ));
+}
+
+#[test]
+fn test_xcode_option_rejects_invalid_value() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args(["xcode", "list", "--xcode", "latest"]);
+
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "expected a version such as 16 or 16.2",
+        ));
+}
+
+#[test]
+fn test_timeout_stops_long_running_tool() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(temp_dir.path(), "xcodebuild", "echo building\nsleep 30");
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env(
+        "PATH",
+        format!("{}:/usr/bin:/bin", temp_dir.path().display()),
+    )
+    .current_dir(temp_dir.path())
+    .args([
+        "--timeout",
+        "1",
+        "build",
+        "--scheme",
+        "MyApp",
+        "--destination",
+        "platform=macOS",
+        "--project",
+        "MyApp.xcodeproj",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .code(7)
+        .stderr(predicate::str::contains(
+            "xcodebuild timed out after 1s and was stopped",
+        ));
+}
+
+#[test]
+fn test_timeout_rejects_invalid_duration() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args(["--timeout", "soon", "list", "--project", "MyApp.xcodeproj"]);
+
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("invalid duration 'soon'"));
+}
+
+#[test]
+fn test_sigint_is_forwarded_to_running_tool() {
+    let temp_dir = tempdir().unwrap();
+    let started = temp_dir.path().join("started");
+    let interrupted = temp_dir.path().join("interrupted");
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        &format!(
+            "trap 'echo yes > {}; exit 130' INT\necho yes > {}\nwhile true; do sleep 0.1; done",
+            interrupted.display(),
+            started.display()
+        ),
+    );
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("xctools"))
+        .env(
+            "PATH",
+            format!("{}:/usr/bin:/bin", temp_dir.path().display()),
+        )
+        .current_dir(temp_dir.path())
+        .args([
+            "build",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=macOS",
+            "--project",
+            "MyApp.xcodeproj",
+        ])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(20);
+    while !started.exists() && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    assert!(started.exists(), "fake xcodebuild did not start");
+
+    std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let status = child.wait().unwrap();
+
+    assert_eq!(status.code(), Some(130));
+    assert!(
+        interrupted.exists(),
+        "SIGINT was not forwarded to xcodebuild"
+    );
+}
+
+#[test]
+fn test_sigint_after_tool_exits_stops_with_interrupted_exit_code() {
+    let temp_dir = tempdir().unwrap();
+    // The signal arrives while xctools itself is busy, just before the tool exits successfully.
+    write_fake_tool(temp_dir.path(), "xcodebuild", "kill -INT $PPID\nexit 0");
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "build",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=macOS",
+            "--project",
+            "MyApp.xcodeproj",
+        ]);
+
+    cmd.assert()
+        .code(130)
+        .stderr(predicate::str::contains("was interrupted"));
+}
+
+#[test]
+fn test_sigint_between_pipeline_steps_stops_the_pipeline() {
+    let temp_dir = tempdir().unwrap();
+    write_config(temp_dir.path(), PIPELINE_CONFIG);
+    write_fake_tool(temp_dir.path(), "xcodebuild", "kill -INT $PPID\nexit 0");
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .env("XCTOOLS_TEST_PASSWORD", "app-password")
+        .current_dir(temp_dir.path())
+        .args(["run", "release"]);
+
+    cmd.assert()
+        .code(130)
+        .stderr(predicate::str::contains("==> Step 1/3: archive"))
+        .stderr(predicate::str::contains("==> Step 2/3").not())
+        .stderr(predicate::str::contains("was interrupted"));
+}
+
+fn upload_command(temp_dir: &std::path::Path) -> Command {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir).current_dir(temp_dir).args([
+        "--retry-delay",
+        "1",
+        "upload",
+        "--target",
+        "ios",
+        "--app-file-path",
+        "MyApp.ipa",
+        "--username",
+        "developer@example.com",
+        "--password",
+        "super-secret-password",
+    ]);
+    cmd
+}
+
+#[test]
+fn test_upload_command_retries_network_failures() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcrun",
+        "if [ -f attempted ]; then\n  echo 'No errors uploading MyApp.ipa'\nelse\n  echo yes > attempted\n  echo 'Error: The network connection was lost.' >&2\n  exit 1\nfi",
+    );
+
+    upload_command(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No errors uploading MyApp.ipa"))
+        .stderr(predicate::str::contains(
+            "xcrun altool --upload-app failed on attempt 1 of 3 \
+             (Error: The network connection was lost.); retrying in",
+        ))
+        .stderr(predicate::str::contains(
+            "xcrun altool --upload-app succeeded on attempt 2 of 3",
+        ));
+}
+
+#[test]
+fn test_upload_command_gives_up_after_max_attempts() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcrun",
+        "echo 'Server returned HTTP status code: 503' >&2\nexit 1",
+    );
+
+    upload_command(temp_dir.path())
+        .args(["--max-attempts", "2"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("failed on attempt 1 of 2"))
+        .stderr(predicate::str::contains(
+            "xcrun altool --upload-app failed after 2 attempts",
+        ))
+        .stderr(predicate::str::contains("HTTP status code: 503"));
+}
+
+#[test]
+fn test_max_attempts_must_be_positive() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args(["--max-attempts", "0", "resolve-packages"]);
+
+    cmd.assert().failure().code(2);
+}
+
+#[test]
+fn test_resolve_packages_command_dry_run() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args([
+        "--dry-run",
+        "resolve-packages",
+        "--workspace",
+        "MyApp.xcworkspace",
+    ]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "xcodebuild -resolvePackageDependencies -workspace MyApp.xcworkspace",
+    ));
+}
+
+/// Parses the JSON report xctools printed on stdout.
+fn json_report(output: &std::process::Output) -> serde_json::Value {
+    serde_json::from_slice(&output.stdout).expect("stdout should be a single JSON report")
+}
+
+#[test]
+fn test_json_output_reports_successful_build() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "echo \"/src/App.swift:3:9: warning: variable 'x' was never used\"\n\
+         echo '** BUILD SUCCEEDED **'",
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "--output-format",
+            "json",
+            "build",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=iOS Simulator,name=iPhone 15 Pro",
+            "--project",
+            "MyApp.xcodeproj",
+        ]);
+
+    let output = cmd.assert().success().get_output().clone();
+    let report = json_report(&output);
+
+    assert_eq!(report["schemaVersion"], 1);
+    assert_eq!(report["command"], "build");
+    assert_eq!(report["status"], "succeeded");
+    assert!(report["durationSeconds"].is_number());
+    assert_eq!(report["result"]["scheme"], "MyApp");
+    assert_eq!(report["result"]["configuration"], "Debug");
+    assert_eq!(
+        report["warnings"],
+        serde_json::json!(["/src/App.swift:3:9: variable 'x' was never used"])
+    );
+    assert_eq!(report["error"], serde_json::Value::Null);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("** BUILD SUCCEEDED **"));
+}
+
+#[test]
+fn test_json_output_reports_failed_build() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "echo '** BUILD FAILED **'\nexit 65",
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "--output-format",
+            "json",
+            "build",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=iOS Simulator,name=iPhone 15 Pro",
+            "--project",
+            "MyApp.xcodeproj",
+        ]);
+
+    let output = cmd.assert().code(4).get_output().clone();
+    let report = json_report(&output);
+
+    assert_eq!(report["status"], "failed");
+    assert_eq!(report["result"], serde_json::Value::Null);
+    assert_eq!(report["error"]["kind"], "buildFailed");
+    assert_eq!(report["error"]["exitCode"], 65);
+    assert!(
+        report["error"]["invocation"]
+            .as_str()
+            .unwrap()
+            .starts_with("xcodebuild build -project MyApp.xcodeproj")
+    );
+    assert_eq!(
+        report["error"]["outputTail"],
+        serde_json::json!(["** BUILD FAILED **"])
+    );
+}
+
+#[test]
+fn test_output_format_after_subcommand_does_not_clash_with_archive_output() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "echo \"$@\" > xcodebuild-args.txt\necho '** ARCHIVE SUCCEEDED **'",
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "archive",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "generic/platform=iOS",
+            "--sdk",
+            "iphoneos",
+            "--output",
+            "MyApp.xcarchive",
+            "--output-format",
+            "json",
+            "--project",
+            "MyApp.xcodeproj",
+        ]);
+
+    let output = cmd.assert().success().get_output().clone();
+    let report = json_report(&output);
+
+    assert_eq!(report["command"], "archive");
+    assert_eq!(report["status"], "succeeded");
+    assert_eq!(report["result"]["archivePath"], "MyApp.xcarchive");
+    let args = std::fs::read_to_string(temp_dir.path().join("xcodebuild-args.txt")).unwrap();
+    assert!(args.contains("-archivePath MyApp.xcarchive"), "{}", args);
+}
+
+#[test]
+fn test_json_output_dry_run_prints_invocations_on_stderr() {
+    let temp_dir = tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "--output-format",
+            "json",
+            "--dry-run",
+            "archive",
+            "--scheme",
+            "MyApp",
+            "--sdk",
+            "iphoneos",
+            "--output",
+            "MyApp.xcarchive",
+            "--project",
+            "MyApp.xcodeproj",
+        ]);
+
+    let output = cmd.assert().success().get_output().clone();
+    let report = json_report(&output);
+
+    assert_eq!(report["command"], "archive");
+    assert_eq!(report["result"]["archivePath"], "MyApp.xcarchive");
+    assert_eq!(report["result"]["sdk"], "iphoneos");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("xcodebuild archive"));
+}
+
+#[test]
+fn test_json_output_reports_bump_version() {
+    let tmp = tempdir().unwrap();
+    fs::write(
+        tmp.path().join("project.pbxproj"),
+        "CURRENT_PROJECT_VERSION = 1;\nMARKETING_VERSION = 1.0.0;",
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.current_dir(tmp.path()).args([
+        "--output-format",
+        "json",
+        "bump-version",
+        "--build-number",
+        "42",
+    ]);
+
+    let output = cmd.assert().success().get_output().clone();
+    let report = json_report(&output);
+
+    assert_eq!(report["command"], "bump-version");
+    assert_eq!(report["result"]["buildNumber"], 42);
+    assert_eq!(report["result"]["versionNumber"], serde_json::Value::Null);
+}
+
+#[test]
+fn test_json_output_rejects_unknown_format() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args(["--output", "yaml", "list"]);
+
+    cmd.assert().failure().code(2);
+}
+
+/// Writes `contents` to the xctools.toml in `dir`.
+fn write_config(dir: &std::path::Path, contents: &str) {
+    fs::write(dir.join("xctools.toml"), contents).unwrap();
+}
+
+const CONFIG: &str = r#"
+scheme = "MyApp"
+project = "MyApp.xcodeproj"
+
+[build]
+destination = ["platform=iOS Simulator,name=iPhone 16"]
+
+[archive]
+sdk = "iphoneos"
+output = "build/MyApp.xcarchive"
+
+[profiles.appstore]
+configuration = "Release"
+
+[profiles.appstore.archive]
+build-setting = ["CODE_SIGN_STYLE=Manual"]
+"#;
+
+#[test]
+fn test_config_file_supplies_defaults() {
+    let temp_dir = tempdir().unwrap();
+    write_config(temp_dir.path(), CONFIG);
+    let nested = temp_dir.path().join("Sources");
+    fs::create_dir(&nested).unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(&nested)
+        .args(["--dry-run", "build"]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "xcodebuild build -project MyApp.xcodeproj -scheme MyApp -destination \
+         'platform=iOS Simulator,name=iPhone 16' -configuration Debug",
+    ));
+}
+
+#[test]
+fn test_verbose_never_prints_secret_values() {
+    let temp_dir = tempdir().unwrap();
+    // Option origins are only reported when there is a configuration file.
+    write_config(temp_dir.path(), "");
+    std::fs::write(temp_dir.path().join("Signing.p12"), "certificate").unwrap();
+    let notarize: &[&str] = &[
+        "notarize",
+        "-f",
+        "MyApp.dmg",
+        "-a",
+        "ci@example.com",
+        "-p",
+        "hunter2",
+        "-t",
+        "A1B2C3D4E5",
+    ];
+    let setup_signing: &[&str] = &[
+        "setup-signing",
+        "--certificate-path",
+        "Signing.p12",
+        "--certificate-password",
+        "hunter2",
+    ];
+
+    for (args, redacted) in [
+        (notarize, "--password '********' (from the command line)"),
+        (
+            setup_signing,
+            "--certificate-password '********' (from the command line)",
+        ),
+    ] {
+        let mut cmd = Command::cargo_bin("xctools").unwrap();
+        cmd.env("PATH", temp_dir.path())
+            .current_dir(temp_dir.path())
+            .args(["--verbose", "--dry-run"])
+            .args(args);
+
+        let output = cmd.output().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(stderr.contains(redacted), "{}", stderr);
+        assert!(!stdout.contains("hunter2"), "{}", stdout);
+        assert!(!stderr.contains("hunter2"), "{}", stderr);
+    }
+}
+
+#[test]
+fn test_verbose_never_prints_secret_values_from_config_file() {
+    let temp_dir = tempdir().unwrap();
+    write_config(
+        temp_dir.path(),
+        "[upload]\nusername = \"ci@example.com\"\npassword = \"hunter2\"\n",
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "--verbose",
+            "--dry-run",
+            "upload",
+            "--target",
+            "ios",
+            "--app-file-path",
+            "MyApp.ipa",
+        ]);
+
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "--password '********' (from [upload])",
+        ))
+        .stderr(predicate::str::contains(
+            "--username ci@example.com (from [upload])",
+        ))
+        .stdout(predicate::str::contains("hunter2").not())
+        .stderr(predicate::str::contains("hunter2").not());
+}
+
+#[test]
+fn test_config_file_rejects_env_references_outside_pipelines() {
+    let temp_dir = tempdir().unwrap();
+    write_config(
+        temp_dir.path(),
+        "[upload]\npassword = \"${env.APP_STORE_PASSWORD}\"\n",
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .env("APP_STORE_PASSWORD", "hunter2")
+        .current_dir(temp_dir.path())
+        .args([
+            "--dry-run",
+            "upload",
+            "--target",
+            "ios",
+            "--app-file-path",
+            "MyApp.ipa",
+        ]);
+
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("'password' in [upload] of "))
+        .stderr(predicate::str::contains(
+            "refers to ${env.APP_STORE_PASSWORD}, but environment variables are only expanded \
+             in pipeline steps",
+        ))
+        .stdout(predicate::str::contains("xcrun").not());
+}
+
+#[test]
+fn test_command_line_overrides_config_file() {
+    let temp_dir = tempdir().unwrap();
+    write_config(temp_dir.path(), CONFIG);
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "--dry-run",
+            "--verbose",
+            "build",
+            "--scheme",
+            "MyAppTests",
+            "--workspace",
+            "MyApp.xcworkspace",
+        ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "xcodebuild build -workspace MyApp.xcworkspace -scheme MyAppTests",
+        ))
+        .stderr(predicate::str::contains(
+            "--scheme MyAppTests (from the command line, overriding the top level)",
+        ))
+        .stderr(predicate::str::contains(
+            "--workspace MyApp.xcworkspace (from the command line, overriding the top level)",
+        ))
+        .stderr(predicate::str::contains(
+            "--destination platform=iOS Simulator,name=iPhone 16 (from [build])",
+        ));
+}
+
+#[test]
+fn test_config_profile_is_applied_on_top() {
+    let temp_dir = tempdir().unwrap();
+    write_config(temp_dir.path(), CONFIG);
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args(["--dry-run", "--verbose", "--profile", "appstore", "archive"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "-configuration Release -archivePath build/MyApp.xcarchive -sdk iphoneos \
+             CODE_SIGN_STYLE=Manual",
+        ))
+        .stderr(predicate::str::contains("with profile appstore"))
+        .stderr(predicate::str::contains(
+            "--configuration Release (from [profiles.appstore])",
+        ))
+        .stderr(predicate::str::contains(
+            "--build-setting CODE_SIGN_STYLE=Manual (from [profiles.appstore.archive])",
+        ));
+}
+
+#[test]
+fn test_config_file_with_unknown_option_is_rejected() {
+    let temp_dir = tempdir().unwrap();
+    write_config(temp_dir.path(), "[build]\nshceme = \"MyApp\"\n");
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["--dry-run", "build"]);
+
+    cmd.assert().code(2).stderr(predicate::str::contains(
+        "Unknown option 'shceme' in [build]",
+    ));
+}
+
+#[test]
+fn test_unknown_profile_is_rejected() {
+    let temp_dir = tempdir().unwrap();
+    write_config(temp_dir.path(), CONFIG);
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["--profile", "beta", "--dry-run", "build"]);
+
+    cmd.assert()
+        .code(2)
+        .stderr(predicate::str::contains("Profile 'beta' is not defined in"));
+}
+
+const PIPELINE_CONFIG: &str = r#"
+scheme = "MyApp"
+project = "MyApp.xcodeproj"
+
+[[pipelines.release.steps]]
+command = "archive"
+sdk = "iphoneos"
+output = "build/MyApp.xcarchive"
+
+[[pipelines.release.steps]]
+command = "export-archive"
+archive-path = "${archive.archivePath}"
+export-options = "ExportOptions.plist"
+export-path = "build/export"
+
+[[pipelines.release.steps]]
+id = "upload"
+command = "upload"
+target = "ios"
+app-file-path = "${export-archive.artifacts.0}"
+username = "ci@example.com"
+password = "${env.XCTOOLS_TEST_PASSWORD}"
+"#;
+
+#[test]
+fn test_run_pipeline_feeds_step_results_to_later_steps() {
+    let temp_dir = tempdir().unwrap();
+    write_config(temp_dir.path(), PIPELINE_CONFIG);
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .env("XCTOOLS_TEST_PASSWORD", "app-password")
+        .current_dir(temp_dir.path())
+        .args(["--dry-run", "run", "release"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "xcodebuild -exportArchive -archivePath build/MyApp.xcarchive",
+        ))
+        .stdout(predicate::str::contains("Pipeline 'release' succeeded in"))
+        .stderr(predicate::str::contains("==> Step 1/3: archive"))
+        .stderr(predicate::str::contains("==> Step 3/3: upload"));
+}
+
+#[test]
+fn test_run_pipeline_stops_at_failing_step() {
+    let temp_dir = tempdir().unwrap();
+    write_config(temp_dir.path(), PIPELINE_CONFIG);
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "echo '** ARCHIVE FAILED **'\nexit 65",
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .env("XCTOOLS_TEST_PASSWORD", "app-password")
+        .current_dir(temp_dir.path())
+        .args(["--output-format", "json", "run", "release"]);
+
+    let output = cmd
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("failed at step 'archive' after"))
+        .get_output()
+        .clone();
+    let report = json_report(&output);
+
+    assert_eq!(report["command"], "run");
+    assert!(
+        report["error"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Pipeline 'release' stopped at step 'archive'")
+    );
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("==> Step 2/3"));
+}
+
+#[test]
+fn test_run_pipeline_rejects_unset_environment_variable() {
+    let temp_dir = tempdir().unwrap();
+    write_config(temp_dir.path(), PIPELINE_CONFIG);
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .env_remove("XCTOOLS_TEST_PASSWORD")
+        .current_dir(temp_dir.path())
+        .args(["--dry-run", "run", "release"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Step 'upload' of pipeline 'release' uses the environment variable \
+             XCTOOLS_TEST_PASSWORD, which is not set",
+        ))
+        .stderr(predicate::str::contains("==> Step").not());
+}
+
+#[test]
+fn test_run_unknown_pipeline_lists_available_pipelines() {
+    let temp_dir = tempdir().unwrap();
+    write_config(temp_dir.path(), PIPELINE_CONFIG);
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["--dry-run", "run", "beta"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Pipeline 'beta' is not defined in",
+        ))
+        .stderr(predicate::str::contains("Available pipelines: release"));
+}
+
+/// Fake xcodebuild, xcrun and security for a machine with Xcode 16.2, one signing identity and
+/// the given provisioning profiles installed.
+fn doctor_machine(profiles: &[(&str, &str)]) -> tempfile::TempDir {
+    let temp_dir = tempdir().unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "case \"$1\" in\n\
+         -version) echo 'Xcode 16.2'; echo 'Build version 16C5032a' ;;\n\
+         -license) exit 0 ;;\n\
+         esac",
+    );
+    write_fake_tool(
+        temp_dir.path(),
+        "xcrun",
+        &format!(
+            "case \"$1\" in\n\
+             --find) echo \"/Applications/Xcode.app/Contents/Developer/usr/bin/$2\" ;;\n\
+             simctl) printf '%s\\n' '{}' ;;\n\
+             esac",
+            FAKE_SIMCTL_LIST
+        ),
+    );
+    let mut cms = String::new();
+    for (name, expiration_date) in profiles {
+        cms.push_str(&format!(
+            "  *'{name}.mobileprovision') echo '<plist><dict><key>ExpirationDate</key>\
+             <date>{expiration_date}</date><key>Name</key><string>{name}</string></dict></plist>' ;;\n"
+        ));
+    }
+    write_fake_tool(
+        temp_dir.path(),
+        "security",
+        &format!(
+            "case \"$1\" in\n\
+             find-identity) echo '  1) 0A1B2C3D \"Apple Distribution: Example Ltd (A1B2C3D4E5)\"'\n\
+             echo '     1 valid identities found' ;;\n\
+             cms) case \"$4\" in\n{}  esac ;;\n\
+             esac",
+            cms
+        ),
+    );
+    let profiles_dir = temp_dir
+        .path()
+        .join("Library/MobileDevice/Provisioning Profiles");
+    fs::create_dir_all(&profiles_dir).unwrap();
+    for (name, _) in profiles {
+        fs::write(profiles_dir.join(format!("{}.mobileprovision", name)), "").unwrap();
+    }
+
+    temp_dir
+}
+
+#[test]
+fn test_doctor_reports_every_check() {
+    let temp_dir = doctor_machine(&[("MyApp AppStore", "2099-01-01T00:00:00Z")]);
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .arg("doctor");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "PASS  Xcode                  Xcode 16.2 (16C5032a)",
+        ))
+        .stdout(predicate::str::contains(
+            "PASS  notarytool             /Applications/Xcode.app/Contents/Developer/usr/bin/notarytool",
+        ))
+        .stdout(predicate::str::contains("PASS  Xcode license          Accepted"))
+        .stdout(predicate::str::contains(
+            "Apple Distribution: Example Ltd (A1B2C3D4E5)",
+        ))
+        .stdout(predicate::str::contains(
+            "MyApp AppStore: valid until 2099-01-01",
+        ))
+        .stdout(predicate::str::contains("10 passed, 0 warnings, 0 failed"));
+}
+
+#[test]
+fn test_doctor_fails_on_expired_provisioning_profile() {
+    let temp_dir = doctor_machine(&[
+        ("MyApp AppStore", "2099-01-01T00:00:00Z"),
+        ("MyApp AdHoc", "2020-01-01T00:00:00Z"),
+    ]);
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args(["--output-format", "json", "doctor"]);
+
+    let output = cmd
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "1 check failed: Provisioning profiles",
+        ))
+        .get_output()
+        .clone();
+    let report = json_report(&output);
+
+    assert_eq!(report["status"], "failed");
+    let profiles = &report["result"]["checks"][9];
+    assert_eq!(profiles["name"], "Provisioning profiles");
+    assert_eq!(profiles["status"], "fail");
+    assert_eq!(profiles["message"], "2 installed, 1 expired");
+    assert_eq!(
+        profiles["details"],
+        serde_json::json!([
+            "MyApp AdHoc: expired on 2020-01-01",
+            "MyApp AppStore: valid until 2099-01-01"
+        ])
+    );
+}
+
+#[test]
+fn test_doctor_reports_why_the_license_check_failed() {
+    let temp_dir = doctor_machine(&[("MyApp AppStore", "2099-01-01T00:00:00Z")]);
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "case \"$1\" in\n\
+         -version) echo 'Xcode 16.2'; echo 'Build version 16C5032a' ;;\n\
+         -license) echo \"xcode-select: error: tool 'xcodebuild' requires Xcode, but active \
+         developer directory '/Library/Developer/CommandLineTools' is a command line tools \
+         instance\" >&2; exit 1 ;;\n\
+         esac",
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .arg("doctor");
+
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "FAIL  Xcode license          xcodebuild failed with exit code 1",
+        ))
+        .stdout(predicate::str::contains("is a command line tools instance"))
+        .stdout(predicate::str::contains("Not accepted").not());
+}
+
+#[test]
+fn test_doctor_without_xcode_fails_and_skips_dependent_checks() {
+    let temp_dir = tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .arg("doctor");
+
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "FAIL  xcodebuild             Not found on PATH",
+        ))
+        .stdout(predicate::str::contains(
+            "WARN  Xcode license          Not checked, because xcodebuild was not found",
+        ))
+        .stdout(predicate::str::contains("0 passed, 7 warnings, 3 failed"))
+        .stderr(predicate::str::contains(
+            "3 checks failed: xcodebuild, xcrun, security",
+        ));
+}
+
+/// `xcrun xcresulttool get test-results tests` output for one passing and one failing test.
+const XCRESULT_TESTS: &str = r#"{"testNodes": [{
+  "name": "MyAppTests", "nodeType": "Unit test bundle", "result": "Failed",
+  "children": [{
+    "name": "LoginTests", "nodeType": "Test Suite", "result": "Failed",
+    "children": [
+      {"name": "testLoginSucceeds()", "nodeType": "Test Case", "result": "Passed",
+       "durationInSeconds": 0.5},
+      {"name": "testLoginFails()", "nodeType": "Test Case", "result": "Failed",
+       "durationInSeconds": 1.5,
+       "children": [{"name": "LoginTests.swift:42: XCTAssertTrue failed",
+                     "nodeType": "Failure Message", "result": "Failed"}]}
+    ]
+  }]
+}]}"#;
+
+/// Writes a fake `xcrun` that prints [`XCRESULT_TESTS`] for the bundle at `result_bundle_path`.
+fn write_fake_xcresulttool(dir: &std::path::Path, result_bundle_path: &str) {
+    fs::write(dir.join("tests.json"), format!("{}\n", XCRESULT_TESTS)).unwrap();
+    write_fake_tool(
+        dir,
+        "xcrun",
+        &format!(
+            "[ \"$*\" = 'xcresulttool get test-results tests --path {}' ] || exit 1\n\
+             while IFS= read -r line; do echo \"$line\"; done < tests.json",
+            result_bundle_path
+        ),
+    );
+}
+
+#[test]
+fn test_test_command_writes_reports_for_failed_tests() {
+    let temp_dir = tempdir().unwrap();
+    fs::create_dir_all(temp_dir.path().join("results/MyAppTests.xcresult")).unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "echo '** TEST FAILED **'\nexit 65",
+    );
+    write_fake_xcresulttool(temp_dir.path(), "results/MyAppTests.xcresult");
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "test",
+            "--scheme",
+            "MyAppTests",
+            "--destination",
+            "platform=macOS",
+            "--project",
+            "MyApp.xcodeproj",
+            "--result-bundle-path",
+            "results/MyAppTests.xcresult",
+            "--junit",
+            "reports/junit.xml",
+            "--summary",
+            "reports/summary.md",
+        ]);
+
+    cmd.assert()
+        .code(5)
+        .stderr(predicate::str::contains(
+            "1 of 2 tests failed:\n  MyAppTests.LoginTests/testLoginFails() \
+             (LoginTests.swift:42: XCTAssertTrue failed)",
+        ))
+        .stderr(predicate::str::contains("test failed with exit code 65"));
+
+    let junit = fs::read_to_string(temp_dir.path().join("reports/junit.xml")).unwrap();
+    assert!(junit.contains(
+        r#"<testsuites name="MyAppTests" tests="2" failures="1" errors="0" skipped="0" time="2.000">"#
+    ));
+    assert!(junit.contains(
+        r#"<failure message="XCTAssertTrue failed">LoginTests.swift:42: XCTAssertTrue failed</failure>"#
+    ));
+    let summary = fs::read_to_string(temp_dir.path().join("reports/summary.md")).unwrap();
+    assert!(summary.starts_with("## Tests for MyAppTests\n"));
+    assert!(summary.contains("**Failed**: 2 tests, 1 passed, 1 failed, 0 skipped in 2.00s"));
+    assert!(summary.contains("  - `LoginTests.swift:42`: XCTAssertTrue failed"));
+}
+
+#[test]
+fn test_json_output_reports_test_results() {
+    let temp_dir = tempdir().unwrap();
+    let stale_bundle = temp_dir.path().join(".xctools/results/MyAppTests.xcresult");
+    fs::create_dir_all(stale_bundle.join("Data")).unwrap();
+    // The bundle of the previous run must be gone before xcodebuild writes the new one, which
+    // the fake stands in for with a plain file.
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "[ -e .xctools/results/MyAppTests.xcresult ] && exit 1\n\
+         : > .xctools/results/MyAppTests.xcresult\n\
+         echo '** TEST SUCCEEDED **'",
+    );
+    write_fake_xcresulttool(temp_dir.path(), ".xctools/results/MyAppTests.xcresult");
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "--output-format",
+            "json",
+            "test",
+            "--scheme",
+            "MyAppTests",
+            "--destination",
+            "platform=macOS",
+            "--project",
+            "MyApp.xcodeproj",
+        ]);
+
+    let output = cmd.assert().success().get_output().clone();
+    let report = json_report(&output);
+
+    assert_eq!(
+        report["result"]["resultBundlePath"],
+        ".xctools/results/MyAppTests.xcresult"
+    );
+    let suite = &report["result"]["results"]["targets"][0]["suites"][0];
+    assert_eq!(suite["name"], "LoginTests");
+    assert_eq!(suite["cases"][1]["status"], "failed");
+    assert_eq!(
+        suite["cases"][1]["failures"][0],
+        serde_json::json!({
+            "message": "XCTAssertTrue failed",
+            "file": "LoginTests.swift",
+            "line": 42
+        })
+    );
+    assert!(
+        !temp_dir.path().join(".xctools/test-timings.json").exists(),
+        "durations are only recorded with --timings"
+    );
+}
+
+#[test]
+fn test_dry_run_test_build_only_builds_for_generic_destination() {
+    let stdout = dry_run(&[
+        "test",
+        "--build-only",
+        "--scheme",
+        "MyApp",
+        "--sdk",
+        "iphonesimulator",
+        "--project",
+        "MyApp.xcodeproj",
+        "--only-testing",
+        "MyAppTests",
+    ]);
+
+    assert_eq!(
+        stdout,
+        "xcodebuild build-for-testing -project MyApp.xcodeproj -scheme MyApp \
+         -destination 'generic/platform=iOS Simulator' -configuration Debug -sdk iphonesimulator \
+         -derivedDataPath .xctools/derived-data/MyApp -only-testing:MyAppTests\n"
+    );
+}
+
+#[test]
+fn test_dry_run_test_from_xctestrun() {
+    let temp_dir = tempdir().unwrap();
+    fs::create_dir(temp_dir.path().join("products")).unwrap();
+    fs::write(
+        temp_dir
+            .path()
+            .join("products/MyApp_macosx15.2-arm64.xctestrun"),
+        "<dict>\n\t<key>SchemeName</key>\n\t<string>MyApp</string>\n</dict>\n",
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "--dry-run",
+            "test",
+            "--from-xctestrun",
+            "products/MyApp_macosx15.2-arm64.xctestrun",
+            "--destination",
+            "platform=macOS",
+            "--skip-testing",
+            "MyAppUITests",
+        ]);
+
+    cmd.assert().success().stdout(
+        "xcodebuild test-without-building -destination platform=macOS \
+         -xctestrun products/MyApp_macosx15.2-arm64.xctestrun \
+         -resultBundlePath .xctools/results/MyApp.xcresult -skip-testing:MyAppUITests\n",
+    );
+    assert!(!temp_dir.path().join(".xctools").exists());
+}
+
+#[test]
+fn test_test_command_from_xctestrun_conflicts_with_scheme() {
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.args([
+        "test",
+        "--from-xctestrun",
+        "MyApp.xctestrun",
+        "--scheme",
+        "MyApp",
+        "--destination",
+        "platform=macOS",
+    ]);
+
+    cmd.assert()
+        .code(2)
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn test_test_command_build_only_packages_test_products() {
+    let temp_dir = tempdir().unwrap();
+    let products = temp_dir
+        .path()
+        .join(".xctools/derived-data/MyApp/Build/Products");
+    fs::create_dir_all(products.join("Debug/MyAppTests.xctest")).unwrap();
+    fs::write(
+        products.join("Debug/MyAppTests.xctest/MyAppTests"),
+        "binary",
+    )
+    .unwrap();
+    fs::write(
+        products.join("MyApp_MyApp_macosx15.2-arm64.xctestrun"),
+        format!(
+            "<key>DYLD_FRAMEWORK_PATH</key>\n<string>{}/Debug</string>\n",
+            products.display()
+        ),
+    )
+    .unwrap();
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        "echo '** TEST BUILD SUCCEEDED **'",
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "--output-format",
+            "json",
+            "test",
+            "--build-only",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=macOS",
+            "--project",
+            "MyApp.xcodeproj",
+            "--artifact-dir",
+            "artifacts",
+        ]);
+
+    let output = cmd.assert().success().get_output().clone();
+    let report = json_report(&output);
+
+    assert_eq!(report["result"]["artifactDirectory"], "artifacts");
+    assert_eq!(
+        report["result"]["xctestrunPaths"],
+        serde_json::json!(["artifacts/MyApp_MyApp_macosx15.2-arm64.xctestrun"])
+    );
+    assert_eq!(
+        fs::read_to_string(
+            temp_dir
+                .path()
+                .join("artifacts/MyApp_MyApp_macosx15.2-arm64.xctestrun")
+        )
+        .unwrap(),
+        "<key>DYLD_FRAMEWORK_PATH</key>\n<string>__TESTROOT__/Debug</string>\n"
+    );
+    assert_eq!(
+        fs::read_to_string(
+            temp_dir
+                .path()
+                .join("artifacts/Debug/MyAppTests.xctest/MyAppTests")
+        )
+        .unwrap(),
+        "binary"
+    );
+}
+
+const ENUMERATED_TESTS: &str = r#"{"errors": [], "values": [{"testPlan": "MyApp", "enabledTests": [
+  {"identifier": "MyAppTests/LoginTests/testLogin()"},
+  {"identifier": "MyAppTests/LoginTests/testLogout()"},
+  {"identifier": "MyAppTests/CartTests/testAdd()"},
+  {"identifier": "MyAppUITests/CheckoutUITests/testCheckout()"}
+], "disabledTests": []}]}"#;
+
+#[test]
+fn test_dry_run_test_shard_balances_by_timings() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(temp_dir.path().join("tests.json"), ENUMERATED_TESTS).unwrap();
+    fs::write(
+        temp_dir.path().join("timings.json"),
+        r#"{"version": 1, "classes": {"MyAppUITests/CheckoutUITests": 300.0,
+            "MyAppTests/LoginTests": 20.0, "MyAppTests/CartTests": 10.0}}"#,
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "--dry-run",
+            "test",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=macOS",
+            "--project",
+            "MyApp.xcodeproj",
+            "--shard",
+            "2/2",
+            "--shard-tests",
+            "tests.json",
+            "--timings",
+            "timings.json",
+            "--skip-testing",
+            "MyAppTests/LoginTests/testLogout",
+        ]);
+
+    cmd.assert()
+        .success()
+        .stdout(
+            "xcodebuild test -project MyApp.xcodeproj -scheme MyApp -destination platform=macOS \
+             -configuration Debug -resultBundlePath .xctools/results/MyApp.xcresult \
+             -only-testing:MyAppTests/CartTests -only-testing:MyAppTests/LoginTests \
+             -skip-testing:MyAppTests/LoginTests/testLogout\n",
+        )
+        .stderr(predicate::str::contains(
+            "==> Shard 2/2: 2 of 3 test classes",
+        ));
+    assert!(!temp_dir.path().join(".xctools").exists());
+}
+
+#[test]
+fn test_dry_run_test_shard_without_timings_ignores_local_durations() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(temp_dir.path().join("tests.json"), ENUMERATED_TESTS).unwrap();
+    // Left behind by an earlier run on this machine; other machines would not have it.
+    fs::create_dir(temp_dir.path().join(".xctools")).unwrap();
+    fs::write(
+        temp_dir.path().join(".xctools/test-timings.json"),
+        r#"{"version": 1, "classes": {"MyAppUITests/CheckoutUITests": 300.0,
+            "MyAppTests/LoginTests": 20.0, "MyAppTests/CartTests": 10.0}}"#,
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "--dry-run",
+            "test",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=macOS",
+            "--project",
+            "MyApp.xcodeproj",
+            "--shard",
+            "2/2",
+            "--shard-tests",
+            "tests.json",
+        ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "-only-testing:MyAppTests/LoginTests\n",
+        ))
+        .stderr(predicate::str::contains(
+            "==> Shard 2/2: 1 of 3 test classes",
+        ));
+}
+
+#[test]
+fn test_dry_run_test_shard_enumerates_xctestrun_tests() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join("MyApp.xctestrun"),
+        "<dict>\n\t<key>SchemeName</key>\n\t<string>MyApp</string>\n</dict>\n",
+    )
+    .unwrap();
+    // Only the enumeration runs in dry-run mode; it writes the JSON to its last argument.
+    write_fake_tool(
+        temp_dir.path(),
+        "xcodebuild",
+        &format!(
+            "for arg; do output=\"$arg\"; done\n\
+             case \"$*\" in\n\
+             \"test-without-building -xctestrun MyApp.xctestrun -destination platform=macOS \
+             -enumerate-tests -test-enumeration-style flat -test-enumeration-format json \
+             -test-enumeration-output-path \"*) echo '{}' > \"$output\" ;;\n\
+             *) exit 64 ;;\n\
+             esac",
+            ENUMERATED_TESTS.replace('\n', " ")
+        ),
+    );
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "--dry-run",
+            "test",
+            "--from-xctestrun",
+            "MyApp.xctestrun",
+            "--destination",
+            "platform=macOS",
+            "--shard",
+            "1/2",
+        ]);
+
+    cmd.assert().success().stdout(
+        "xcodebuild test-without-building -destination platform=macOS -xctestrun MyApp.xctestrun \
+         -resultBundlePath .xctools/results/MyApp.xcresult \
+         -only-testing:MyAppTests/CartTests -only-testing:MyAppUITests/CheckoutUITests\n",
+    );
+}
+
+#[test]
+fn test_dry_run_test_empty_shard_runs_nothing() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(temp_dir.path().join("tests.json"), ENUMERATED_TESTS).unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "--dry-run",
+            "--output-format",
+            "json",
+            "test",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=macOS",
+            "--project",
+            "MyApp.xcodeproj",
+            "--shard",
+            "5/5",
+            "--shard-tests",
+            "tests.json",
+        ]);
+
+    let output = cmd.assert().success().get_output().clone();
+    let report = json_report(&output);
+    assert_eq!(report["result"]["shard"]["shard"], "5/5");
+    assert_eq!(report["result"]["shard"]["classes"], serde_json::json!([]));
+    assert_eq!(report["result"]["shard"]["totalClasses"], 3);
+    assert_eq!(
+        report["warnings"][0],
+        "Shard 5/5 has no test classes to run; there are 3 in total"
+    );
+}
+
+#[test]
+fn test_test_command_shard_needs_tests_to_shard() {
+    let temp_dir = tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "--dry-run",
+            "test",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=macOS",
+            "--project",
+            "MyApp.xcodeproj",
+            "--shard",
+            "1/2",
+        ]);
+
+    cmd.assert().code(1).stderr(predicate::str::contains(
+        "--shard needs --shard-tests, or --from-xctestrun to enumerate the tests",
+    ));
+}
+
+#[test]
+fn test_test_command_rejects_invalid_shard() {
+    for (args, message) in [
+        (&["--shard", "6/5"][..], "Invalid shard '6/5'"),
+        (
+            &["--shard", "1/5", "--only-testing", "MyAppTests"][..],
+            "cannot be used with",
+        ),
+    ] {
+        let mut cmd = Command::cargo_bin("xctools").unwrap();
+        cmd.args([
+            "test",
+            "--scheme",
+            "MyApp",
+            "--destination",
+            "platform=macOS",
+        ])
+        .args(args);
+
+        cmd.assert()
+            .code(2)
+            .stderr(predicate::str::contains(message));
+    }
+}
+
+/// Writes a fake `xcodebuild` that fails the first run and logs its invocations, and a fake
+/// `xcrun` whose first bundle has a failed test, which passes in the retries if `retry_passes`.
+fn write_fake_flaky_tools(dir: &std::path::Path, retry_passes: bool) {
+    let case = |name: &str, result: &str| {
+        format!(
+            r#"{{"name": "{0}", "nodeType": "Test Case", "result": "{1}",
+                "nodeIdentifier": "LoginTests/{0}", "durationInSeconds": 1.0,
+                "children": [{{"name": "LoginTests.swift:42: XCTAssertTrue failed",
+                               "nodeType": "Failure Message", "result": "{1}"}}]}}"#,
+            name, result
+        )
+    };
+    let tests = |cases: &[String]| {
+        format!(
+            r#"{{"testNodes": [{{"name": "MyAppTests", "nodeType": "Unit test bundle",
+                "children": [{{"name": "LoginTests", "nodeType": "Test Suite",
+                  "children": [{}]}}]}}]}}"#,
+            cases.join(",")
+        )
+        .replace('\n', " ")
+            + "\n"
+    };
+    let retry_result = if retry_passes { "Passed" } else { "Failed" };
+    fs::write(
+        dir.join("first.json"),
+        tests(&[
+            case("testLoginSucceeds()", "Passed"),
+            case("testLoginFails()", "Failed"),
+        ]),
+    )
+    .unwrap();
+    fs::write(
+        dir.join("retry.json"),
+        tests(&[case("testLoginFails()", retry_result)]),
+    )
+    .unwrap();
+    write_fake_tool(
+        dir,
+        "xcodebuild",
+        &format!(
+            "echo \"$*\" >> invocations.log\n\
+             for arg; do [ \"$previous\" = -resultBundlePath ] && bundle=\"$arg\"; previous=\"$arg\"; done\n\
+             : > \"$bundle\"\n\
+             case \"$bundle\" in *-retry-*) {} ;; esac\n\
+             echo '** TEST FAILED **'\n\
+             exit 65",
+            if retry_passes {
+                "echo '** TEST SUCCEEDED **'; exit 0"
+            } else {
+                ":"
+            }
+        ),
+    );
+    write_fake_tool(
+        dir,
+        "xcrun",
+        "case \"$*\" in\n\
+         *-retry-*) file=retry.json ;;\n\
+         *) file=first.json ;;\n\
+         esac\n\
+         while IFS= read -r line; do echo \"$line\"; done < $file",
+    );
+}
+
+#[test]
+fn test_test_command_retry_failed_reports_flaky_tests() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_flaky_tools(temp_dir.path(), true);
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "--output-format",
+            "json",
+            "test",
+            "--scheme",
+            "MyAppTests",
+            "--destination",
+            "platform=macOS",
+            "--project",
+            "MyApp.xcodeproj",
+            "--retry-failed",
+            "2",
+            "--flake-history",
+            "history/flaky-tests.json",
+        ]);
+
+    let output = cmd
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "==> Retrying 1 failed test(s), retry 1 of 2",
+        ))
+        .stderr(predicate::str::contains(
+            "==> 1 flaky test(s) passed on retry:\n  \
+             MyAppTests/LoginTests/testLoginFails() (passed on attempt 2), first failed at \
+             LoginTests.swift:42: XCTAssertTrue failed",
+        ))
+        .get_output()
+        .clone();
+    let report = json_report(&output);
+    let flaky_tests = &report["result"]["flakyTests"];
+    assert_eq!(
+        flaky_tests[0]["identifier"],
+        "MyAppTests/LoginTests/testLoginFails()"
+    );
+    assert_eq!(flaky_tests[0]["passedOnAttempt"], 2);
+    assert_eq!(flaky_tests[0]["failures"][0]["line"], 42);
+    assert_eq!(
+        report["result"]["retryResultBundlePaths"],
+        serde_json::json!([".xctools/results/MyAppTests-retry-1.xcresult"])
+    );
+    let invocations = fs::read_to_string(temp_dir.path().join("invocations.log")).unwrap();
+    assert_eq!(invocations.lines().count(), 2);
+    assert!(invocations.lines().nth(1).unwrap().ends_with(
+        "-resultBundlePath .xctools/results/MyAppTests-retry-1.xcresult \
+         -only-testing:MyAppTests/LoginTests/testLoginFails()"
+    ));
+
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "test",
+            "flaky-report",
+            "--flake-history",
+            "history/flaky-tests.json",
+        ]);
+
+    cmd.assert().success().stdout(
+        "1 of 1 flaky test(s) in history/flaky-tests.json\n  \
+         FLAKY   RUNS    RATE  FAILED  TEST\n      \
+         1      1    100%       0  MyAppTests/LoginTests/testLoginFails()\n",
+    );
+}
+
+#[test]
+fn test_test_command_retry_failed_fails_when_tests_keep_failing() {
+    let temp_dir = tempdir().unwrap();
+    write_fake_flaky_tools(temp_dir.path(), false);
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.env("PATH", temp_dir.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "test",
+            "--scheme",
+            "MyAppTests",
+            "--destination",
+            "platform=macOS",
+            "--project",
+            "MyApp.xcodeproj",
+            "--retry-failed",
+            "2",
+        ]);
+
+    cmd.assert()
+        .code(5)
+        .stderr(predicate::str::contains(
+            "==> Retrying 1 failed test(s), retry 2 of 2",
+        ))
+        .stderr(predicate::str::contains(
+            "1 of 2 tests failed:\n  MyAppTests.LoginTests/testLoginFails()",
+        ))
+        .stderr(predicate::str::contains("flaky").not());
+    let invocations = fs::read_to_string(temp_dir.path().join("invocations.log")).unwrap();
+    assert_eq!(invocations.lines().count(), 3);
+
+    let history: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(temp_dir.path().join(".xctools/flaky-tests.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        history["tests"]["MyAppTests/LoginTests/testLoginFails()"],
+        serde_json::json!({"runs": 1, "flaky": 0, "failed": 1})
+    );
+}
+
+#[test]
+fn test_test_flaky_report_without_history() {
+    let temp_dir = tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("xctools").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["--output-format", "json", "test", "flaky-report"]);
+
+    let output = cmd.assert().success().get_output().clone();
+    let report = json_report(&output);
+    assert_eq!(report["command"], "test flaky-report");
+    assert_eq!(report["result"]["tests"], serde_json::json!([]));
+    assert_eq!(report["result"]["totalFlakyTests"], 0);
+}
@@ -0,0 +1,1043 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+mod pipeline;
+
+use pipeline::PIPELINES_KEY;
+pub use pipeline::{Pipeline, PipelineStep};
+
+/// Name of the configuration file, looked up in the working directory and its parents.
+pub const CONFIG_FILE_NAME: &str = "xctools.toml";
+
+/// Table holding the named profiles selected with `--profile`.
+const PROFILES_KEY: &str = "profiles";
+
+/// Id of the global option that selects a profile.
+const PROFILE_ARG: &str = "profile";
+
+/// Options whose values `--verbose` never prints.
+const SECRET_OPTIONS: &[&str] = &["password", "certificate-password"];
+
+/// Printed by `--verbose` in place of a secret value, as in dry-run command lines.
+const REDACTED: &str = "'********'";
+
+/// Start of a reference to an environment variable, which only pipeline steps expand.
+const ENV_REFERENCE: &str = "${env.";
+
+/// The subcommand clap adds for printing help, which cannot be configured.
+const HELP_COMMAND: &str = "help";
+
+/// Options that cannot be set from the configuration file.
+const RESERVED_OPTIONS: &[&str] = &["help", "version", PROFILE_ARG];
+
+/// Returns the `xctools.toml` in `start` or in the closest parent directory that has one.
+pub fn find_config_file(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// A parsed `xctools.toml`.
+///
+/// Keys are option names without the leading dashes. Top-level keys are defaults for every
+/// command that has the option, and tables named after a command (`[build]`,
+/// `[simulator.boot]`) hold defaults for that command only. `[profiles.NAME]` tables have the
+/// same layout and are applied on top of the rest with `--profile NAME`.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::path::Path;
+/// use clap::{Arg, Command};
+/// use xctools_config::Config;
+///
+/// let cli = Command::new("xctools")
+///     .arg(Arg::new("profile").long("profile").global(true))
+///     .subcommand(
+///         Command::new("archive")
+///             .arg(Arg::new("scheme").long("scheme"))
+///             .arg(Arg::new("configuration").long("configuration")),
+///     );
+/// let config = Config::parse(
+///     Path::new("xctools.toml"),
+///     r#"
+///     scheme = "MyApp"
+///
+///     [profiles.appstore]
+///     configuration = "Release"
+///     "#,
+/// )
+/// .unwrap();
+///
+/// let values = config.values(&cli, &["archive"], Some("appstore")).unwrap();
+/// let args: Vec<String> = values.iter().flat_map(|value| value.to_args()).collect();
+/// assert_eq!(args, ["--scheme=MyApp", "--configuration=Release"]);
+/// assert_eq!(values[1].section, "[profiles.appstore]");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    path: PathBuf,
+    table: toml::Table,
+}
+
+/// The value the configuration file gives an option, and the section it was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigValue {
+    /// The option's long name, e.g. `destination-auto`.
+    pub option: String,
+    /// The option's values; empty for a flag that is switched on.
+    pub values: Vec<String>,
+    /// The section the value was read from, e.g. `[build]` or `[profiles.appstore]`.
+    pub section: String,
+    id: String,
+    /// Whether the option is a positional argument, which only pipeline steps can set.
+    positional: bool,
+}
+
+impl ConfigValue {
+    /// The value as command-line arguments, e.g. `--scheme=MyApp`.
+    pub fn to_args(&self) -> Vec<String> {
+        if self.positional {
+            return self.values.clone();
+        }
+        if self.values.is_empty() {
+            return vec![format!("--{}", self.option)];
+        }
+
+        self.values
+            .iter()
+            .map(|value| format!("--{}={}", self.option, value))
+            .collect()
+    }
+}
+
+impl Config {
+    /// Reads and parses the configuration file at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        Self::parse(path, &contents)
+    }
+
+    /// Parses `contents`, reporting errors against `path`.
+    pub fn parse(path: &Path, contents: &str) -> Result<Self> {
+        let table = contents
+            .parse::<toml::Table>()
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            table,
+        })
+    }
+
+    /// Loads the file found by [`find_config_file`], if there is one.
+    pub fn discover(start: &Path) -> Result<Option<Self>> {
+        find_config_file(start)
+            .map(|path| Self::load(&path))
+            .transpose()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The names of the profiles defined under `[profiles]`.
+    pub fn profiles(&self) -> Vec<&str> {
+        self.table
+            .get(PROFILES_KEY)
+            .and_then(toml::Value::as_table)
+            .map(|profiles| profiles.keys().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Resolves the defaults for the subcommand of `cli` at `command_path`, e.g.
+    /// `["simulator", "boot"]`, with `profile` applied on top.
+    ///
+    /// Sections are applied from the most general to the most specific: top-level keys, the
+    /// command's tables, then the profile's top-level keys and command tables. A value replaces
+    /// any earlier value for the same option and for the options it conflicts with, so a
+    /// `workspace` in `[build]` replaces a top-level `project`. A flag set to `false` switches
+    /// off an earlier `true`.
+    ///
+    /// The whole file is validated first: an unknown command, an option no command has, or an
+    /// option the command in whose table it appears does not have is an error.
+    pub fn values(
+        &self,
+        cli: &Command,
+        command_path: &[&str],
+        profile: Option<&str>,
+    ) -> Result<Vec<ConfigValue>> {
+        let cli = built(cli);
+        self.validate(&cli)?;
+        let command = find_subcommand(&cli, command_path)?;
+
+        let mut layers = sections(&self.table, None, command_path);
+        if let Some(profile) = profile {
+            let table = self.profile(profile)?;
+            layers.extend(sections(table, Some(profile), command_path));
+        }
+
+        let mut values: Vec<ConfigValue> = Vec::new();
+        for (section, table) in layers {
+            for (key, value) in table {
+                if value.is_table() {
+                    continue;
+                }
+                // Shared keys only apply to the commands that have the option.
+                let Some(arg) = find_option(command, key) else {
+                    continue;
+                };
+                let option_values = option_values(arg, value).with_context(|| {
+                    format!(
+                        "Invalid value for '{}' in {} of {}",
+                        key,
+                        section,
+                        self.path.display()
+                    )
+                })?;
+
+                let id = arg.get_id().to_string();
+                let rivals = rivals(command, arg);
+                values.retain(|existing| existing.id != id && !rivals.contains(&existing.id));
+                if let Some(option_values) = option_values {
+                    values.push(ConfigValue {
+                        option: key.clone(),
+                        values: option_values,
+                        section: section.clone(),
+                        id,
+                        positional: false,
+                    });
+                }
+            }
+        }
+
+        Ok(values)
+    }
+
+    fn profile(&self, name: &str) -> Result<&toml::Table> {
+        if let Some(profile) = self
+            .table
+            .get(PROFILES_KEY)
+            .and_then(|profiles| profiles.get(name))
+            .and_then(toml::Value::as_table)
+        {
+            return Ok(profile);
+        }
+
+        let profiles = self.profiles();
+        if profiles.is_empty() {
+            bail!(
+                "Profile '{}' is not defined: {} has no [{}] tables",
+                name,
+                self.path.display(),
+                PROFILES_KEY
+            );
+        }
+        bail!(
+            "Profile '{}' is not defined in {}. Available profiles: {}",
+            name,
+            self.path.display(),
+            profiles.join(", ")
+        )
+    }
+
+    fn validate(&self, cli: &Command) -> Result<()> {
+        for (key, value) in &self.table {
+            if key == PIPELINES_KEY {
+                for name in self.pipelines() {
+                    self.pipeline_from(cli, name)?;
+                }
+            }
+            if key != PROFILES_KEY {
+                continue;
+            }
+            let profiles = value.as_table().with_context(|| {
+                format!(
+                    "'{}' in {} must be a table of profiles",
+                    PROFILES_KEY,
+                    self.path.display()
+                )
+            })?;
+            for (name, profile) in profiles {
+                let profile = profile.as_table().with_context(|| {
+                    format!(
+                        "[{}.{}] in {} must be a table",
+                        PROFILES_KEY,
+                        name,
+                        self.path.display()
+                    )
+                })?;
+                self.validate_table(cli, profile, &format!("{}.{}", PROFILES_KEY, name))?;
+            }
+        }
+
+        self.validate_table(cli, &self.table, "")
+    }
+
+    /// Checks that every table in `table` names a subcommand of `command` and every other key
+    /// an option of `command` or one of its subcommands.
+    fn validate_table(&self, command: &Command, table: &toml::Table, section: &str) -> Result<()> {
+        for (key, value) in table {
+            if section.is_empty() && (key == PROFILES_KEY || key == PIPELINES_KEY) {
+                continue;
+            }
+            let nested = if section.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", section, key)
+            };
+
+            if let Some(table) = value.as_table() {
+                let Some(subcommand) = command
+                    .find_subcommand(key)
+                    .filter(|subcommand| subcommand.get_name() != HELP_COMMAND)
+                else {
+                    bail!("Unknown command [{}] in {}", nested, self.path.display());
+                };
+                self.validate_table(subcommand, table, &nested)?;
+            } else if !has_option(command, key) {
+                bail!(
+                    "Unknown option '{}' in {} of {}",
+                    key,
+                    section_name(section),
+                    self.path.display()
+                );
+            } else if let Some(reference) = env_reference(value) {
+                bail!(
+                    "'{}' in {} of {} refers to {}, but environment variables are only expanded \
+                     in pipeline steps",
+                    key,
+                    section_name(section),
+                    self.path.display(),
+                    reference
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Where an option's value came from, as reported by `xctools --verbose`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layer {
+    /// The option was given on the command line, replacing the value from `overrides`, the
+    /// section of the configuration file that also set it, if any.
+    CommandLine { overrides: Vec<String> },
+    /// The option was not given on the command line; its value comes from this section of the
+    /// configuration file.
+    Config { section: String },
+}
+
+/// An option that has a value, and the [`Layer`] that supplied it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueOrigin {
+    pub option: String,
+    /// The option's values; empty for a flag.
+    pub values: Vec<String>,
+    pub layer: Layer,
+}
+
+impl ValueOrigin {
+    /// Whether the values are secret, and so printed as `'********'`: those of a password option.
+    pub fn is_secret(&self) -> bool {
+        SECRET_OPTIONS.contains(&self.option.as_str())
+    }
+}
+
+impl fmt::Display for ValueOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.values.is_empty() {
+            write!(f, "--{}", self.option)?;
+        }
+        for (index, value) in self.values.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            let value = if self.is_secret() { REDACTED } else { value };
+            write!(f, "--{} {}", self.option, value)?;
+        }
+
+        match &self.layer {
+            Layer::CommandLine { overrides } if overrides.is_empty() => {
+                write!(f, " (from the command line)")
+            }
+            Layer::CommandLine { overrides } => write!(
+                f,
+                " (from the command line, overriding {})",
+                overrides.join(", ")
+            ),
+            Layer::Config { section } => write!(f, " (from {})", section),
+        }
+    }
+}
+
+/// Command-line arguments with the defaults from `xctools.toml` added, and where each option's
+/// value came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfiguredArgs {
+    pub args: Vec<OsString>,
+    /// The configuration file that was applied, if one was found.
+    pub config_path: Option<PathBuf>,
+    pub profile: Option<String>,
+    /// The options given on the command line or taken from the configuration file, by name.
+    pub origins: Vec<ValueOrigin>,
+}
+
+/// Adds the defaults from the `xctools.toml` found from `dir` to `args`, the command line of
+/// `cli`, so that they are parsed as if they had been typed.
+///
+/// Options given on the command line always win: a value from the file is dropped when its
+/// option, or one it conflicts with, was given. The profile is read from the global
+/// `--profile` option, which is an error when no configuration file is found. Command lines
+/// that do not select a subcommand, such as `--help`, are returned unchanged.
+pub fn apply_config(cli: &Command, args: Vec<OsString>, dir: &Path) -> Result<ConfiguredArgs> {
+    let mut configured = ConfiguredArgs {
+        args,
+        ..ConfiguredArgs::default()
+    };
+    // Required options may only be supplied by the file, so parse leniently first.
+    let Ok(matches) = cli
+        .clone()
+        .ignore_errors(true)
+        .try_get_matches_from(&configured.args)
+    else {
+        return Ok(configured);
+    };
+    let profile = matches
+        .try_get_one::<String>(PROFILE_ARG)
+        .ok()
+        .flatten()
+        .cloned();
+
+    let Some(config) = Config::discover(dir)? else {
+        if let Some(profile) = profile {
+            bail!(
+                "--profile {} needs an {} in {} or one of its parent directories",
+                profile,
+                CONFIG_FILE_NAME,
+                dir.display()
+            );
+        }
+        return Ok(configured);
+    };
+    configured.config_path = Some(config.path().to_path_buf());
+    configured.profile = profile.clone();
+
+    let mut command_path = Vec::new();
+    let mut chain = vec![&matches];
+    let mut current = &matches;
+    while let Some((name, subcommand)) = current.subcommand() {
+        command_path.push(name);
+        chain.push(subcommand);
+        current = subcommand;
+    }
+    if command_path.is_empty() {
+        return Ok(configured);
+    }
+
+    let values = config.values(cli, &command_path, profile.as_deref())?;
+    let cli = built(cli);
+    let command = find_subcommand(&cli, &command_path)?;
+    let given = |id: &str| given_on_command_line(&chain, id);
+
+    let mut added = Vec::new();
+    let mut overridden: Vec<(String, String)> = Vec::new();
+    for value in values {
+        let Some(arg) = command
+            .get_arguments()
+            .find(|arg| arg.get_id().as_str() == value.id)
+        else {
+            continue;
+        };
+        let replaced_by = std::iter::once(value.id.clone())
+            .chain(rivals(command, arg))
+            .find(|id| given(id));
+        match replaced_by {
+            Some(id) => overridden.push((id, value.section)),
+            None => {
+                added.extend(value.to_args().into_iter().map(OsString::from));
+                configured.origins.push(ValueOrigin {
+                    option: value.option,
+                    values: value.values,
+                    layer: Layer::Config {
+                        section: value.section,
+                    },
+                });
+            }
+        }
+    }
+
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        if !given(id) {
+            continue;
+        }
+        let values = if arg.get_action().takes_values() {
+            raw_values(&chain, id)
+        } else {
+            Vec::new()
+        };
+        let overrides = overridden
+            .iter()
+            .filter(|(overriding, _)| overriding == id)
+            .map(|(_, section)| section.clone())
+            .collect();
+        configured.origins.push(ValueOrigin {
+            option: long.to_string(),
+            values,
+            layer: Layer::CommandLine { overrides },
+        });
+    }
+    configured
+        .origins
+        .sort_by(|left, right| left.option.cmp(&right.option));
+
+    // Options after `--` would be taken as positional values.
+    let insert_at = configured
+        .args
+        .iter()
+        .skip(1)
+        .position(|arg| arg == "--")
+        .map_or(configured.args.len(), |index| index + 1);
+    configured.args.splice(insert_at..insert_at, added);
+
+    Ok(configured)
+}
+
+/// Builds `cli` so that its global options are propagated to every subcommand.
+pub(crate) fn built(cli: &Command) -> Command {
+    let mut cli = cli.clone();
+    cli.build();
+    cli
+}
+
+pub(crate) fn find_subcommand<'a>(cli: &'a Command, command_path: &[&str]) -> Result<&'a Command> {
+    command_path.iter().try_fold(cli, |command, name| {
+        command
+            .find_subcommand(name)
+            .filter(|subcommand| subcommand.get_name() != HELP_COMMAND)
+            .with_context(|| format!("Unknown command '{}'", command_path.join(" ")))
+    })
+}
+
+/// The top-level keys of `root` followed by the tables along `command_path`, each with the name
+/// it is reported under.
+fn sections<'a>(
+    root: &'a toml::Table,
+    profile: Option<&str>,
+    command_path: &[&str],
+) -> Vec<(String, &'a toml::Table)> {
+    let prefix = profile.map(|profile| format!("{}.{}", PROFILES_KEY, profile));
+    let mut sections = vec![(section_name(prefix.as_deref().unwrap_or_default()), root)];
+
+    let mut table = root;
+    let mut path: Vec<&str> = prefix.iter().map(String::as_str).collect();
+    for name in command_path {
+        let Some(nested) = table.get(*name).and_then(toml::Value::as_table) else {
+            break;
+        };
+        path.push(name);
+        sections.push((section_name(&path.join(".")), nested));
+        table = nested;
+    }
+
+    sections
+}
+
+fn section_name(section: &str) -> String {
+    if section.is_empty() {
+        String::from("the top level")
+    } else {
+        format!("[{}]", section)
+    }
+}
+
+/// The first `${env.NAME}` reference in an option's value, which outside a pipeline would be
+/// passed on literally instead of being read from the environment.
+fn env_reference(value: &toml::Value) -> Option<&str> {
+    let strings: Vec<&str> = match value {
+        toml::Value::String(value) => vec![value],
+        toml::Value::Array(values) => values.iter().filter_map(toml::Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    strings.into_iter().find_map(|value| {
+        let start = value.find(ENV_REFERENCE)?;
+        let end = value[start..]
+            .find('}')
+            .map_or(value.len(), |end| start + end + 1);
+        Some(&value[start..end])
+    })
+}
+
+pub(crate) fn find_option<'a>(command: &'a Command, key: &str) -> Option<&'a Arg> {
+    if RESERVED_OPTIONS.contains(&key) {
+        return None;
+    }
+
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(key))
+}
+
+/// Whether `command` or one of its subcommands has the option `key`.
+fn has_option(command: &Command, key: &str) -> bool {
+    find_option(command, key).is_some()
+        || command
+            .get_subcommands()
+            .any(|subcommand| has_option(subcommand, key))
+}
+
+/// Converts a TOML value to the option's values: `None` for a flag set to `false`, no values for
+/// a flag set to `true`, and one value per array element for options that may be repeated.
+pub(crate) fn option_values(arg: &Arg, value: &toml::Value) -> Result<Option<Vec<String>>> {
+    if !arg.get_action().takes_values() {
+        return match value.as_bool() {
+            Some(true) => Ok(Some(Vec::new())),
+            Some(false) => Ok(None),
+            None => bail!("expected true or false"),
+        };
+    }
+
+    let values = match value {
+        toml::Value::Array(items) => {
+            if !matches!(arg.get_action(), ArgAction::Append) {
+                bail!("expected a single value, as the option cannot be repeated");
+            }
+            items.iter().map(scalar).collect::<Result<_>>()?
+        }
+        value => vec![scalar(value)?],
+    };
+
+    Ok(Some(values))
+}
+
+fn scalar(value: &toml::Value) -> Result<String> {
+    Ok(match value {
+        toml::Value::String(string) => string.clone(),
+        toml::Value::Integer(integer) => integer.to_string(),
+        toml::Value::Float(float) => float.to_string(),
+        toml::Value::Boolean(boolean) => boolean.to_string(),
+        toml::Value::Datetime(datetime) => datetime.to_string(),
+        toml::Value::Array(_) | toml::Value::Table(_) => {
+            bail!("expected a string, number or boolean")
+        }
+    })
+}
+
+/// The ids of the options that cannot be combined with `arg`: its declared conflicts and the
+/// other members of the exclusive groups it belongs to, such as `project` and `workspace`.
+fn rivals(command: &Command, arg: &Arg) -> Vec<String> {
+    let id = arg.get_id();
+    let mut rivals: Vec<String> = command
+        .get_arg_conflicts_with(arg)
+        .iter()
+        .map(|rival| rival.get_id().to_string())
+        .collect();
+
+    for group in command.get_groups() {
+        if group.clone().is_multiple() || !group.get_args().any(|member| member == id) {
+            continue;
+        }
+        rivals.extend(
+            group
+                .get_args()
+                .filter(|member| *member != id)
+                .map(ToString::to_string),
+        );
+    }
+
+    rivals
+}
+
+fn given_on_command_line(chain: &[&ArgMatches], id: &str) -> bool {
+    chain.iter().any(|matches| {
+        matches.ids().any(|known| known.as_str() == id)
+            && matches.value_source(id) == Some(ValueSource::CommandLine)
+    })
+}
+
+fn raw_values(chain: &[&ArgMatches], id: &str) -> Vec<String> {
+    chain
+        .iter()
+        .rev()
+        .filter(|matches| matches.ids().any(|known| known.as_str() == id))
+        .find_map(|matches| matches.get_raw(id))
+        .map(|values| {
+            values
+                .map(|value| value.to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ArgGroup;
+    use tempfile::tempdir;
+
+    fn cli() -> Command {
+        Command::new("xctools")
+            .arg(Arg::new("profile").long("profile").global(true))
+            .arg(
+                Arg::new("dry_run")
+                    .long("dry-run")
+                    .global(true)
+                    .action(ArgAction::SetTrue),
+            )
+            .subcommand(
+                Command::new("build")
+                    .arg(Arg::new("scheme").short('s').long("scheme").required(true))
+                    .arg(
+                        Arg::new("destination")
+                            .short('d')
+                            .long("destination")
+                            .action(ArgAction::Append),
+                    )
+                    .arg(
+                        Arg::new("configuration")
+                            .long("configuration")
+                            .default_value("debug"),
+                    )
+                    .arg(
+                        Arg::new("validate_configuration")
+                            .long("validate-configuration")
+                            .action(ArgAction::SetTrue),
+                    )
+                    .arg(Arg::new("project").long("project"))
+                    .arg(Arg::new("workspace").long("workspace"))
+                    .group(ArgGroup::new("target").args(["project", "workspace"])),
+            )
+            .subcommand(Command::new("upload").arg(Arg::new("username").long("username")))
+            .subcommand(Command::new("simulator").subcommand(
+                Command::new("boot").arg(Arg::new("device").long("device").required(true)),
+            ))
+    }
+
+    fn config(contents: &str) -> Config {
+        Config::parse(Path::new("/repo/xctools.toml"), contents).unwrap()
+    }
+
+    fn args(values: &[ConfigValue]) -> Vec<String> {
+        values.iter().flat_map(ConfigValue::to_args).collect()
+    }
+
+    fn apply(dir: &Path, args: &[&str]) -> Result<ConfiguredArgs> {
+        let args = std::iter::once("xctools")
+            .chain(args.iter().copied())
+            .map(OsString::from)
+            .collect();
+        apply_config(&cli(), args, dir)
+    }
+
+    fn rendered(configured: &ConfiguredArgs) -> Vec<String> {
+        configured
+            .args
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_find_config_file_searches_parent_directories() {
+        let root = tempdir().unwrap();
+        let nested = root.path().join("App/Sources");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(root.path().join(CONFIG_FILE_NAME), "").unwrap();
+
+        assert_eq!(
+            find_config_file(&nested),
+            Some(root.path().join(CONFIG_FILE_NAME))
+        );
+    }
+
+    #[test]
+    fn test_values_apply_sections_from_general_to_specific() {
+        let config = config(
+            r#"
+            scheme = "MyApp"
+            configuration = "Debug"
+            username = "ci@example.com"
+
+            [build]
+            configuration = "Staging"
+            destination = ["platform=macOS", "generic/platform=iOS"]
+
+            [profiles.release]
+            configuration = "Release"
+
+            [profiles.release.build]
+            validate-configuration = true
+            "#,
+        );
+
+        let values = config.values(&cli(), &["build"], None).unwrap();
+        assert_eq!(
+            args(&values),
+            [
+                "--scheme=MyApp",
+                "--configuration=Staging",
+                "--destination=platform=macOS",
+                "--destination=generic/platform=iOS",
+            ]
+        );
+
+        let values = config.values(&cli(), &["build"], Some("release")).unwrap();
+        assert_eq!(
+            args(&values),
+            [
+                "--scheme=MyApp",
+                "--destination=platform=macOS",
+                "--destination=generic/platform=iOS",
+                "--configuration=Release",
+                "--validate-configuration",
+            ]
+        );
+        let sections: Vec<&str> = values.iter().map(|value| value.section.as_str()).collect();
+        assert_eq!(
+            sections,
+            [
+                "the top level",
+                "[build]",
+                "[profiles.release]",
+                "[profiles.release.build]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_values_replace_conflicting_options() {
+        let config = config(
+            r#"
+            project = "MyApp.xcodeproj"
+            validate-configuration = true
+
+            [build]
+            workspace = "MyApp.xcworkspace"
+            validate-configuration = false
+            "#,
+        );
+
+        let values = config.values(&cli(), &["build"], None).unwrap();
+
+        assert_eq!(args(&values), ["--workspace=MyApp.xcworkspace"]);
+    }
+
+    #[test]
+    fn test_values_for_nested_subcommand() {
+        let config = config(
+            r#"
+            [simulator.boot]
+            device = "Screenshots"
+            "#,
+        );
+
+        let values = config.values(&cli(), &["simulator", "boot"], None).unwrap();
+
+        assert_eq!(args(&values), ["--device=Screenshots"]);
+        assert_eq!(values[0].section, "[simulator.boot]");
+    }
+
+    #[test]
+    fn test_values_reject_unknown_keys() {
+        for (contents, message) in [
+            (
+                "[biuld]\nscheme = \"MyApp\"",
+                "Unknown command [biuld] in /repo/xctools.toml",
+            ),
+            (
+                "[build]\nusername = \"ci\"",
+                "Unknown option 'username' in [build] of /repo/xctools.toml",
+            ),
+            (
+                "shceme = \"MyApp\"",
+                "Unknown option 'shceme' in the top level of /repo/xctools.toml",
+            ),
+            (
+                "[profiles.ci.upload]\nscheme = \"MyApp\"",
+                "Unknown option 'scheme' in [profiles.ci.upload] of /repo/xctools.toml",
+            ),
+        ] {
+            let error = config(contents)
+                .values(&cli(), &["upload"], None)
+                .unwrap_err();
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_values_reject_invalid_values() {
+        let error = config("[build]\nscheme = [\"MyApp\", \"MyAppTests\"]")
+            .values(&cli(), &["build"], None)
+            .unwrap_err();
+        assert_eq!(
+            format!("{:#}", error),
+            "Invalid value for 'scheme' in [build] of /repo/xctools.toml: expected a single \
+             value, as the option cannot be repeated"
+        );
+
+        let error = config("[build]\nvalidate-configuration = \"yes\"")
+            .values(&cli(), &["build"], None)
+            .unwrap_err();
+        assert!(format!("{:#}", error).ends_with("expected true or false"));
+    }
+
+    #[test]
+    fn test_values_reject_env_references_outside_pipelines() {
+        let error = config("[upload]\nusername = \"${env.APP_STORE_USERNAME}\"")
+            .values(&cli(), &["upload"], None)
+            .unwrap_err();
+        assert_eq!(
+            format!("{:#}", error),
+            "'username' in [upload] of /repo/xctools.toml refers to ${env.APP_STORE_USERNAME}, \
+             but environment variables are only expanded in pipeline steps"
+        );
+
+        let error = config("[profiles.ci.build]\ndestination = [\"id=${env.SIMULATOR_ID}\"]")
+            .values(&cli(), &["build", "--scheme", "MyApp"], Some("ci"))
+            .unwrap_err();
+        assert_eq!(
+            format!("{:#}", error),
+            "'destination' in [profiles.ci.build] of /repo/xctools.toml refers to \
+             ${env.SIMULATOR_ID}, but environment variables are only expanded in pipeline steps"
+        );
+    }
+
+    #[test]
+    fn test_values_reject_unknown_profile() {
+        let error = config("[profiles.appstore]\n[profiles.adhoc]")
+            .values(&cli(), &["build"], Some("beta"))
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Profile 'beta' is not defined in /repo/xctools.toml. Available profiles: adhoc, \
+             appstore"
+        );
+    }
+
+    #[test]
+    fn test_apply_config_lets_command_line_win() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            r#"
+            scheme = "MyApp"
+            project = "MyApp.xcodeproj"
+
+            [build]
+            configuration = "Staging"
+            destination = ["platform=macOS"]
+            "#,
+        )
+        .unwrap();
+
+        let configured = apply(
+            dir.path(),
+            &[
+                "build",
+                "-d",
+                "generic/platform=iOS",
+                "--workspace",
+                "MyApp.xcworkspace",
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            rendered(&configured),
+            [
+                "xctools",
+                "build",
+                "-d",
+                "generic/platform=iOS",
+                "--workspace",
+                "MyApp.xcworkspace",
+                "--scheme=MyApp",
+                "--configuration=Staging",
+            ]
+        );
+        assert_eq!(
+            configured.config_path,
+            Some(dir.path().join(CONFIG_FILE_NAME))
+        );
+        let origins: Vec<String> = configured.origins.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            origins,
+            [
+                "--configuration Staging (from [build])",
+                "--destination generic/platform=iOS (from the command line, overriding [build])",
+                "--scheme MyApp (from the top level)",
+                "--workspace MyApp.xcworkspace (from the command line, overriding the top level)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_value_origin_redacts_secret_values() {
+        let origin = |option: &str, value: &str| ValueOrigin {
+            option: option.to_string(),
+            values: vec![value.to_string()],
+            layer: Layer::CommandLine {
+                overrides: Vec::new(),
+            },
+        };
+
+        assert_eq!(
+            origin("password", "hunter2").to_string(),
+            "--password '********' (from the command line)"
+        );
+        assert_eq!(
+            origin("certificate-password", "s3cret").to_string(),
+            "--certificate-password '********' (from the command line)"
+        );
+        assert_eq!(
+            origin("username", "ci@example.com").to_string(),
+            "--username ci@example.com (from the command line)"
+        );
+    }
+
+    #[test]
+    fn test_apply_config_inserts_options_before_double_dash() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join(CONFIG_FILE_NAME), "dry-run = true").unwrap();
+
+        let configured = apply(dir.path(), &["upload", "--", "extra"]).unwrap();
+
+        assert_eq!(
+            rendered(&configured),
+            ["xctools", "upload", "--dry-run", "--", "extra"]
+        );
+    }
+
+    #[test]
+    fn test_apply_config_without_config_file() {
+        let dir = tempdir().unwrap();
+
+        let configured = apply(dir.path(), &["build", "--scheme", "MyApp"]).unwrap();
+        assert_eq!(configured.config_path, None);
+        assert_eq!(
+            rendered(&configured),
+            ["xctools", "build", "--scheme", "MyApp"]
+        );
+
+        let error = apply(dir.path(), &["--profile", "appstore", "build"]).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("--profile appstore needs an xctools.toml in ")
+        );
+    }
+}
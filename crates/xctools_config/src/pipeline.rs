@@ -0,0 +1,422 @@
+use anyhow::{Context, Result, bail};
+use clap::Command;
+
+use crate::{
+    Config, ConfigValue, HELP_COMMAND, built, find_option, find_subcommand, option_values,
+};
+
+/// Table holding the pipelines run with `xctools run NAME`.
+pub(crate) const PIPELINES_KEY: &str = "pipelines";
+
+/// Key of the array of steps in a pipeline table.
+const STEPS_KEY: &str = "steps";
+
+/// Keys of a step that are not options of its command.
+const STEP_ID_KEY: &str = "id";
+const STEP_COMMAND_KEY: &str = "command";
+
+/// A named sequence of xctools commands, defined in `xctools.toml` as an array of
+/// `[[pipelines.NAME.steps]]` tables.
+///
+/// Each step names its `command`, e.g. `"archive"` or `"simulator boot"`, and sets that
+/// command's options with the same keys and values as the rest of the file; positional
+/// arguments, such as the devices of `simulator boot`, are set by their name. A step's `id`,
+/// which defaults to its command with spaces replaced by dashes, is how later steps refer to its
+/// outputs.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::path::Path;
+/// use clap::{Arg, Command};
+/// use xctools_config::Config;
+///
+/// let cli = Command::new("xctools")
+///     .subcommand(Command::new("archive").arg(Arg::new("output").long("output")))
+///     .subcommand(
+///         Command::new("export-archive").arg(Arg::new("archive_path").long("archive-path")),
+///     );
+/// let config = Config::parse(
+///     Path::new("xctools.toml"),
+///     r#"
+///     [[pipelines.release.steps]]
+///     command = "archive"
+///     output = "build/MyApp.xcarchive"
+///
+///     [[pipelines.release.steps]]
+///     command = "export-archive"
+///     archive-path = "${archive.archivePath}"
+///     "#,
+/// )
+/// .unwrap();
+///
+/// let pipeline = config.pipeline(&cli, "release").unwrap();
+/// assert_eq!(pipeline.steps[1].id, "export-archive");
+///
+/// let args = pipeline.steps[1]
+///     .args(|value| Ok(value.replace("${archive.archivePath}", "build/MyApp.xcarchive")))
+///     .unwrap();
+/// assert_eq!(args, ["export-archive", "--archive-path=build/MyApp.xcarchive"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pipeline {
+    pub name: String,
+    pub steps: Vec<PipelineStep>,
+}
+
+/// One command of a [`Pipeline`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineStep {
+    pub id: String,
+    /// The subcommand, e.g. `["simulator", "boot"]`.
+    pub command: Vec<String>,
+    pub options: Vec<ConfigValue>,
+}
+
+impl PipelineStep {
+    /// The step as command-line arguments after the program name, with every value passed
+    /// through `expand` first, e.g. to substitute the outputs of earlier steps.
+    pub fn args(&self, mut expand: impl FnMut(&str) -> Result<String>) -> Result<Vec<String>> {
+        let mut args = self.command.clone();
+        let mut positionals = Vec::new();
+        for option in &self.options {
+            let values = option
+                .values
+                .iter()
+                .map(|value| expand(value))
+                .collect::<Result<Vec<_>>>()?;
+            let option = ConfigValue {
+                values,
+                ..option.clone()
+            };
+            if option.positional {
+                positionals.extend(option.to_args());
+            } else {
+                args.extend(option.to_args());
+            }
+        }
+        if !positionals.is_empty() {
+            // Positional values such as simulator names may start with a dash.
+            args.push(String::from("--"));
+            args.extend(positionals);
+        }
+
+        Ok(args)
+    }
+}
+
+impl Config {
+    /// The names of the pipelines defined under `[pipelines]`.
+    pub fn pipelines(&self) -> Vec<&str> {
+        self.table
+            .get(PIPELINES_KEY)
+            .and_then(toml::Value::as_table)
+            .map(|pipelines| pipelines.keys().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Reads the pipeline `name`, checking that every step names a subcommand of `cli` and
+    /// only sets options of that subcommand. Global options such as `--xcode` apply to the whole
+    /// run and cannot be set per step.
+    pub fn pipeline(&self, cli: &Command, name: &str) -> Result<Pipeline> {
+        if !self.pipelines().contains(&name) {
+            let pipelines = self.pipelines();
+            if pipelines.is_empty() {
+                bail!(
+                    "Pipeline '{}' is not defined: {} has no [[{}.NAME.{}]] tables",
+                    name,
+                    self.path.display(),
+                    PIPELINES_KEY,
+                    STEPS_KEY
+                );
+            }
+            bail!(
+                "Pipeline '{}' is not defined in {}. Available pipelines: {}",
+                name,
+                self.path.display(),
+                pipelines.join(", ")
+            );
+        }
+
+        self.pipeline_from(&built(cli), name)
+    }
+
+    /// Parses the pipeline `name` against the already built `cli`.
+    pub(crate) fn pipeline_from(&self, cli: &Command, name: &str) -> Result<Pipeline> {
+        let section = format!("[{}.{}]", PIPELINES_KEY, name);
+        let table = self
+            .table
+            .get(PIPELINES_KEY)
+            .and_then(toml::Value::as_table)
+            .and_then(|pipelines| pipelines.get(name))
+            .and_then(toml::Value::as_table)
+            .with_context(|| format!("{} in {} must be a table", section, self.path.display()))?;
+        if let Some(key) = table.keys().find(|key| *key != STEPS_KEY) {
+            bail!(
+                "Unknown key '{}' in {} of {}; steps are listed as [[{}.{}.{}]]",
+                key,
+                section,
+                self.path.display(),
+                PIPELINES_KEY,
+                name,
+                STEPS_KEY
+            );
+        }
+        let steps = table
+            .get(STEPS_KEY)
+            .and_then(toml::Value::as_array)
+            .filter(|steps| !steps.is_empty())
+            .with_context(|| {
+                format!(
+                    "Pipeline '{}' in {} has no steps; add them as [[{}.{}.{}]] tables",
+                    name,
+                    self.path.display(),
+                    PIPELINES_KEY,
+                    name,
+                    STEPS_KEY
+                )
+            })?;
+
+        let mut pipeline = Pipeline {
+            name: name.to_string(),
+            steps: Vec::new(),
+        };
+        for (index, step) in steps.iter().enumerate() {
+            let location = format!(
+                "step {} of pipeline '{}' in {}",
+                index + 1,
+                name,
+                self.path.display()
+            );
+            let step = step
+                .as_table()
+                .with_context(|| format!("The {} must be a table", location))?;
+            pipeline
+                .steps
+                .push(parse_step(cli, step, &section, &location, &pipeline.steps)?);
+        }
+
+        Ok(pipeline)
+    }
+}
+
+fn parse_step(
+    cli: &Command,
+    step: &toml::Table,
+    section: &str,
+    location: &str,
+    earlier: &[PipelineStep],
+) -> Result<PipelineStep> {
+    let command_line = step
+        .get(STEP_COMMAND_KEY)
+        .and_then(toml::Value::as_str)
+        .with_context(|| format!("The {} has no command", location))?;
+    let words: Vec<&str> = command_line.split_whitespace().collect();
+    let command = find_subcommand(cli, &words)
+        .ok()
+        .filter(|_| !words.is_empty())
+        .with_context(|| format!("Unknown command '{}' in {}", command_line, location))?;
+    if command.has_subcommands() {
+        let subcommands: Vec<&str> = command
+            .get_subcommands()
+            .map(Command::get_name)
+            .filter(|name| *name != HELP_COMMAND)
+            .collect();
+        bail!(
+            "The command '{}' in {} needs a subcommand: {}",
+            command_line,
+            location,
+            subcommands.join(", ")
+        );
+    }
+
+    let id = match step.get(STEP_ID_KEY) {
+        Some(id) => id
+            .as_str()
+            .with_context(|| format!("The id of the {} must be a string", location))?
+            .to_string(),
+        None => words.join("-"),
+    };
+    if earlier.iter().any(|step| step.id == id) {
+        bail!(
+            "Duplicate step id '{}' in {}; set a distinct id on one of the steps",
+            id,
+            location
+        );
+    }
+
+    let mut options = Vec::new();
+    for (key, value) in step {
+        if key == STEP_ID_KEY || key == STEP_COMMAND_KEY {
+            continue;
+        }
+        let positional = command
+            .get_positionals()
+            .find(|arg| arg.get_id().as_str() == key);
+        let Some(arg) = positional.or_else(|| find_option(command, key)) else {
+            bail!(
+                "Unknown option '{}' for '{}' in {}",
+                key,
+                command_line,
+                location
+            );
+        };
+        if arg.is_global_set() {
+            bail!(
+                "The global option '{}' in {} applies to the whole run; pass it to `xctools run` \
+                 instead",
+                key,
+                location
+            );
+        }
+        let values = option_values(arg, value)
+            .with_context(|| format!("Invalid value for '{}' in {}", key, location))?;
+        if let Some(values) = values {
+            options.push(ConfigValue {
+                option: key.clone(),
+                values,
+                section: section.to_string(),
+                id: arg.get_id().to_string(),
+                positional: positional.is_some(),
+            });
+        }
+    }
+
+    Ok(PipelineStep {
+        id,
+        command: words.iter().map(ToString::to_string).collect(),
+        options,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use clap::{Arg, ArgAction};
+
+    fn cli() -> Command {
+        Command::new("xctools")
+            .arg(Arg::new("xcode").long("xcode").global(true))
+            .subcommand(
+                Command::new("archive")
+                    .arg(Arg::new("scheme").long("scheme"))
+                    .arg(
+                        Arg::new("build_setting")
+                            .long("build-setting")
+                            .action(ArgAction::Append),
+                    ),
+            )
+            .subcommand(
+                Command::new("simulator").subcommand(
+                    Command::new("boot")
+                        .arg(Arg::new("devices").action(ArgAction::Append))
+                        .arg(
+                            Arg::new("no_wait")
+                                .long("no-wait")
+                                .action(ArgAction::SetTrue),
+                        ),
+                ),
+            )
+    }
+
+    fn config(contents: &str) -> Config {
+        Config::parse(Path::new("/repo/xctools.toml"), contents).unwrap()
+    }
+
+    #[test]
+    fn test_pipeline_steps_to_args() {
+        let config = config(
+            r#"
+            [[pipelines.screenshots.steps]]
+            command = "simulator boot"
+            devices = ["-iPhone", "iPad"]
+            no-wait = true
+
+            [[pipelines.screenshots.steps]]
+            id = "appstore"
+            command = "archive"
+            scheme = "MyApp"
+            build-setting = ["A=1", "B=2"]
+            "#,
+        );
+
+        let pipeline = config.pipeline(&cli(), "screenshots").unwrap();
+        let ids: Vec<&str> = pipeline.steps.iter().map(|step| step.id.as_str()).collect();
+        let args: Vec<Vec<String>> = pipeline
+            .steps
+            .iter()
+            .map(|step| step.args(|value| Ok(value.to_string())).unwrap())
+            .collect();
+
+        assert_eq!(ids, ["simulator-boot", "appstore"]);
+        assert_eq!(
+            args,
+            [
+                vec!["simulator", "boot", "--no-wait", "--", "-iPhone", "iPad"],
+                vec![
+                    "archive",
+                    "--build-setting=A=1",
+                    "--build-setting=B=2",
+                    "--scheme=MyApp"
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pipeline_rejects_invalid_steps() {
+        for (steps, message) in [
+            (
+                "command = \"archvie\"",
+                "Unknown command 'archvie' in step 1 of pipeline 'release' in /repo/xctools.toml",
+            ),
+            (
+                "command = \"simulator\"",
+                "The command 'simulator' in step 1 of pipeline 'release' in /repo/xctools.toml \
+                 needs a subcommand: boot",
+            ),
+            (
+                "command = \"archive\"\nsheme = \"MyApp\"",
+                "Unknown option 'sheme' for 'archive' in step 1 of pipeline 'release' in \
+                 /repo/xctools.toml",
+            ),
+            (
+                "command = \"archive\"\nxcode = \"16\"",
+                "The global option 'xcode' in step 1 of pipeline 'release' in /repo/xctools.toml \
+                 applies to the whole run; pass it to `xctools run` instead",
+            ),
+            (
+                "command = \"archive\"\n[[pipelines.release.steps]]\ncommand = \"archive\"",
+                "Duplicate step id 'archive' in step 2 of pipeline 'release' in \
+                 /repo/xctools.toml; set a distinct id on one of the steps",
+            ),
+        ] {
+            let config = config(&format!("[[pipelines.release.steps]]\n{}", steps));
+            let error = config.pipeline(&cli(), "release").unwrap_err();
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_pipeline_must_be_defined() {
+        let error = config("[[pipelines.release.steps]]\ncommand = \"archive\"")
+            .pipeline(&cli(), "beta")
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Pipeline 'beta' is not defined in /repo/xctools.toml. Available pipelines: release"
+        );
+    }
+
+    #[test]
+    fn test_invalid_pipeline_fails_validation_of_other_commands() {
+        let error = config("[[pipelines.release.steps]]\ncommand = \"archvie\"")
+            .values(&cli(), &["archive"], None)
+            .unwrap_err();
+
+        assert!(error.to_string().starts_with("Unknown command 'archvie'"));
+    }
+}
@@ -0,0 +1,780 @@
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use xcbuild_common::{
+    CommandError, CommandErrorKind, CommandResult, Invocation, list_simulators, run_query,
+};
+
+/// Tools xctools runs directly, which must be on `PATH`.
+const PATH_TOOLS: &[&str] = &["xcodebuild", "xcrun", "security"];
+/// Tools xctools runs through `xcrun`, which finds them in the selected Xcode.
+const XCRUN_TOOLS: &[&str] = &["notarytool", "stapler"];
+/// Provisioning profiles expiring within this many days are reported as a warning.
+const PROFILE_EXPIRY_WARNING_DAYS: u64 = 30;
+/// How xcode-select's shims report that they cannot run a tool, such as xcodebuild with only the
+/// Command Line Tools selected.
+const XCODE_SELECT_ERROR: &str = "xcode-select: error:";
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Pass,
+    /// Something that does not stop xctools from working, but may fail a later step.
+    Warn,
+    Fail,
+}
+
+impl std::fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+        })
+    }
+}
+
+/// The outcome of one `doctor` check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Check {
+    /// What was checked, e.g. `xcodebuild` or `Provisioning profiles`.
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+    /// One line per item found, such as each installed runtime or signing identity.
+    pub details: Vec<String>,
+}
+
+impl Check {
+    fn new(name: &str, status: CheckStatus, message: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            message: message.into(),
+            details: Vec::new(),
+        }
+    }
+
+    fn with_details(mut self, details: Vec<String>) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+/// The result of [`doctor`]: every check, in the order they ran.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DoctorReport {
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    fn count(&self, status: CheckStatus) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == status)
+            .count()
+    }
+}
+
+impl std::fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name_width = self
+            .checks
+            .iter()
+            .map(|check| check.name.chars().count())
+            .max()
+            .unwrap_or_default();
+        for check in &self.checks {
+            writeln!(
+                f,
+                "{}  {:<name_width$}  {}",
+                check.status,
+                check.name,
+                check.message,
+                name_width = name_width
+            )?;
+            for detail in &check.details {
+                writeln!(f, "{:indent$}{}", "", detail, indent = name_width + 8)?;
+            }
+        }
+
+        let plural = |count: usize, word: &str| match count {
+            1 => format!("{} {}", count, word),
+            _ => format!("{} {}s", count, word),
+        };
+        writeln!(
+            f,
+            "\n{} passed, {}, {} failed",
+            self.count(CheckStatus::Pass),
+            plural(self.count(CheckStatus::Warn), "warning"),
+            self.count(CheckStatus::Fail)
+        )
+    }
+}
+
+impl CommandResult for DoctorReport {
+    fn failure(&self) -> Option<String> {
+        let failed: Vec<&str> = self
+            .checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Fail)
+            .map(|check| check.name.as_str())
+            .collect();
+        match failed.len() {
+            0 => None,
+            1 => Some(format!("1 check failed: {}", failed[0])),
+            count => Some(format!("{} checks failed: {}", count, failed.join(", "))),
+        }
+    }
+}
+
+/// Checks that this machine has what xctools needs to build, test, sign and ship an app.
+///
+/// Looks for `xcodebuild`, `xcrun` and `security` on `PATH` and for `notarytool` and `stapler` in
+/// the selected Xcode, then reports the selected Xcode's version and whether its license has
+/// been accepted, the installed simulator runtimes, the valid code signing identities and the
+/// expiry of the installed provisioning profiles.
+///
+/// Every check runs even when an earlier one fails, so that one run shows everything that needs
+/// fixing; checks that depend on a missing tool are reported as warnings. The tools are only
+/// queried, so the checks run in dry-run mode as well.
+///
+/// # Returns
+///
+/// Returns `Ok(DoctorReport)` with a pass, warn or fail status per check. A failed check does
+/// not make this function fail; [`CommandResult::failure`] reports it instead.
+///
+/// # Examples
+///
+/// ## Using the xctools CLI:
+/// ```bash
+/// xctools doctor
+/// xctools --xcode 16.2 --output-format json doctor
+/// ```
+pub fn doctor() -> Result<DoctorReport> {
+    let mut checks: Vec<Check> = PATH_TOOLS.iter().map(|tool| check_on_path(tool)).collect();
+    let missing: Vec<&str> = checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Fail)
+        .map(|check| check.name.as_str())
+        .collect();
+    let requires = |tool: &str, name: &str| {
+        missing.contains(&tool).then(|| {
+            Check::new(
+                name,
+                CheckStatus::Warn,
+                format!("Not checked, because {} was not found", tool),
+            )
+        })
+    };
+
+    let mut dependent_checks = Vec::new();
+    for tool in XCRUN_TOOLS {
+        dependent_checks.push(requires("xcrun", tool).unwrap_or_else(|| check_xcrun_tool(tool)));
+    }
+    let xcode = requires("xcodebuild", "Xcode").unwrap_or_else(check_xcode);
+    let xcode_works = xcode.status == CheckStatus::Pass;
+    dependent_checks.push(xcode);
+    dependent_checks.push(match requires("xcodebuild", "Xcode license") {
+        Some(check) => check,
+        None if !xcode_works => Check::new(
+            "Xcode license",
+            CheckStatus::Warn,
+            "Not checked, because no working Xcode is selected",
+        ),
+        None => check_license(),
+    });
+    dependent_checks
+        .push(requires("xcrun", "Simulator runtimes").unwrap_or_else(check_simulator_runtimes));
+    dependent_checks
+        .push(requires("security", "Signing identities").unwrap_or_else(check_signing_identities));
+    dependent_checks.push(
+        requires("security", "Provisioning profiles").unwrap_or_else(|| {
+            check_provisioning_profiles(&provisioning_profile_directories(), SystemTime::now())
+        }),
+    );
+    checks.extend(dependent_checks);
+
+    Ok(DoctorReport { checks })
+}
+
+fn check_on_path(tool: &str) -> Check {
+    match find_on_path(tool) {
+        Some(path) => Check::new(tool, CheckStatus::Pass, path.display().to_string()),
+        None => Check::new(tool, CheckStatus::Fail, "Not found on PATH"),
+    }
+}
+
+/// Returns the first executable named `tool` in the directories listed in `PATH`.
+fn find_on_path(tool: &str) -> Option<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|directory| directory.join(tool))
+        .find(|candidate| {
+            candidate.metadata().is_ok_and(|metadata| {
+                metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+            })
+        })
+}
+
+fn check_xcrun_tool(tool: &str) -> Check {
+    match query(&Invocation::new("xcrun").with_args(["--find", tool])) {
+        Ok(stdout) => Check::new(tool, CheckStatus::Pass, stdout.trim()),
+        Err(error) => Check::new(
+            tool,
+            CheckStatus::Fail,
+            format!("Not found in the selected Xcode: {}", error),
+        ),
+    }
+}
+
+fn check_xcode() -> Check {
+    let version = match query(&Invocation::new("xcodebuild").with_arg("-version")) {
+        Ok(stdout) => parse_xcode_version(&stdout),
+        Err(error) => return Check::new("Xcode", CheckStatus::Fail, error.to_string()),
+    };
+    let developer_dir = xcbuild_common::developer_dir()
+        .map(|developer_dir| developer_dir.display().to_string())
+        .or_else(|| {
+            query(&Invocation::new("xcode-select").with_arg("--print-path"))
+                .ok()
+                .map(|stdout| stdout.trim().to_string())
+        })
+        .filter(|developer_dir| !developer_dir.is_empty());
+
+    match developer_dir {
+        Some(developer_dir) => Check::new(
+            "Xcode",
+            CheckStatus::Pass,
+            format!("{} at {}", version, developer_dir),
+        ),
+        None => Check::new("Xcode", CheckStatus::Pass, version),
+    }
+}
+
+/// Turns the `xcodebuild -version` output ("Xcode 16.2" and "Build version 16C5032a") into
+/// "Xcode 16.2 (16C5032a)".
+fn parse_xcode_version(stdout: &str) -> String {
+    let mut lines = stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+    let version = lines.next().unwrap_or("Unknown Xcode version").to_string();
+
+    match lines.find_map(|line| line.strip_prefix("Build version ")) {
+        Some(build) => format!("{} ({})", version, build),
+        None => version,
+    }
+}
+
+fn check_license() -> Check {
+    let invocation = Invocation::new("xcodebuild").with_args(["-license", "check"]);
+    license_check(&invocation, run_query(&invocation))
+}
+
+/// Only a non-zero exit of `xcodebuild -license check` means the license was not accepted;
+/// xcodebuild failing to start, timing out, being interrupted or killed, and xcode-select refusing
+/// to run it from the Command Line Tools are reported as they are.
+fn license_check(invocation: &Invocation, result: Result<Output>) -> Check {
+    let name = "Xcode license";
+    let output = match result {
+        Ok(output) if output.status.success() => {
+            return Check::new(name, CheckStatus::Pass, "Accepted");
+        }
+        Ok(output) => output,
+        Err(error) => return Check::new(name, CheckStatus::Fail, format!("{:#}", error)),
+    };
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let xcode_select_failed = stderr
+        .lines()
+        .any(|line| line.trim().starts_with(XCODE_SELECT_ERROR));
+    let error = CommandError::from_output(
+        CommandErrorKind::Failed,
+        invocation.program(),
+        invocation,
+        &output,
+    );
+    match error.exit_code {
+        Some(_) if !xcode_select_failed => Check::new(
+            name,
+            CheckStatus::Fail,
+            "Not accepted; run `sudo xcodebuild -license accept`",
+        ),
+        _ => Check::new(name, CheckStatus::Fail, format!("{:#}", error)),
+    }
+}
+
+fn check_simulator_runtimes() -> Check {
+    let name = "Simulator runtimes";
+    let simulators = match list_simulators() {
+        Ok(simulators) => simulators,
+        Err(error) => return Check::new(name, CheckStatus::Fail, format!("{:#}", error)),
+    };
+    let available = simulators
+        .runtimes
+        .iter()
+        .filter(|runtime| runtime.is_available)
+        .count();
+    let details = simulators
+        .runtimes
+        .iter()
+        .map(|runtime| match runtime.is_available {
+            true => runtime.name.clone(),
+            false => format!("{} (unavailable)", runtime.name),
+        })
+        .collect();
+
+    let check = match available {
+        0 => Check::new(
+            name,
+            CheckStatus::Warn,
+            "No simulator runtimes available; tests on simulators will fail",
+        ),
+        1 => Check::new(name, CheckStatus::Pass, "1 available"),
+        count => Check::new(name, CheckStatus::Pass, format!("{} available", count)),
+    };
+    check.with_details(details)
+}
+
+fn check_signing_identities() -> Check {
+    let name = "Signing identities";
+    let invocation =
+        Invocation::new("security").with_args(["find-identity", "-v", "-p", "codesigning"]);
+    let identities = match query(&invocation) {
+        Ok(stdout) => parse_signing_identities(&stdout),
+        Err(error) => return Check::new(name, CheckStatus::Fail, error.to_string()),
+    };
+
+    let check = match identities.len() {
+        0 => Check::new(
+            name,
+            CheckStatus::Warn,
+            "No valid code signing identities; import one with `xctools setup-signing`",
+        ),
+        1 => Check::new(name, CheckStatus::Pass, "1 valid"),
+        count => Check::new(name, CheckStatus::Pass, format!("{} valid", count)),
+    };
+    check.with_details(identities)
+}
+
+/// Returns the identity names listed by `security find-identity`, e.g.
+/// `1) 0A1B2C... "Apple Distribution: Example Ltd (A1B2C3D4E5)"`.
+fn parse_signing_identities(stdout: &str) -> Vec<String> {
+    stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with(|c: char| c.is_ascii_digit()) && line.contains(')'))
+        .filter_map(|line| {
+            let start = line.find('"')?;
+            let end = line.rfind('"')?;
+            (end > start).then(|| line[start + 1..end].to_string())
+        })
+        .collect()
+}
+
+/// A provisioning profile's name and expiry, read from its decoded plist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProvisioningProfile {
+    name: String,
+    /// `ExpirationDate` as an ISO 8601 UTC timestamp, e.g. `2025-06-30T12:00:00Z`.
+    expiration_date: String,
+}
+
+fn check_provisioning_profiles(directories: &[PathBuf], now: SystemTime) -> Check {
+    let name = "Provisioning profiles";
+    let paths = match find_provisioning_profiles(directories) {
+        Ok(paths) => paths,
+        Err(error) => return Check::new(name, CheckStatus::Fail, format!("{:#}", error)),
+    };
+    if paths.is_empty() {
+        return Check::new(name, CheckStatus::Warn, "None installed");
+    }
+
+    let soon = timestamp(now + Duration::from_secs(PROFILE_EXPIRY_WARNING_DAYS * SECONDS_PER_DAY));
+    let now = timestamp(now);
+    let mut status = CheckStatus::Pass;
+    let (mut expired, mut expiring) = (0, 0);
+    let mut details = Vec::new();
+    for path in &paths {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        match read_provisioning_profile(path) {
+            Ok(profile) if profile.expiration_date < now => {
+                expired += 1;
+                status = CheckStatus::Fail;
+                details.push(format!(
+                    "{}: expired on {}",
+                    profile.name,
+                    date(&profile.expiration_date)
+                ));
+            }
+            Ok(profile) if profile.expiration_date < soon => {
+                expiring += 1;
+                status = status.max(CheckStatus::Warn);
+                details.push(format!(
+                    "{}: expires on {}",
+                    profile.name,
+                    date(&profile.expiration_date)
+                ));
+            }
+            Ok(profile) => details.push(format!(
+                "{}: valid until {}",
+                profile.name,
+                date(&profile.expiration_date)
+            )),
+            Err(error) => {
+                status = status.max(CheckStatus::Warn);
+                details.push(format!("{}: {:#}", file_name, error));
+            }
+        }
+    }
+
+    let mut message = match paths.len() {
+        1 => String::from("1 installed"),
+        count => format!("{} installed", count),
+    };
+    if expired > 0 {
+        message.push_str(&format!(", {} expired", expired));
+    }
+    if expiring > 0 {
+        message.push_str(&format!(
+            ", {} expiring within {} days",
+            expiring, PROFILE_EXPIRY_WARNING_DAYS
+        ));
+    }
+    Check::new(name, status, message).with_details(details)
+}
+
+/// The directories provisioning profiles are installed in: the one `setup-signing` uses and the
+/// one Xcode 16 and later use.
+fn provisioning_profile_directories() -> Vec<PathBuf> {
+    let Some(home) = std::env::var_os("HOME") else {
+        return Vec::new();
+    };
+    let library = Path::new(&home).join("Library");
+
+    vec![
+        library.join("MobileDevice").join("Provisioning Profiles"),
+        library
+            .join("Developer")
+            .join("Xcode")
+            .join("UserData")
+            .join("Provisioning Profiles"),
+    ]
+}
+
+fn find_provisioning_profiles(directories: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for directory in directories.iter().filter(|directory| directory.is_dir()) {
+        let entries = std::fs::read_dir(directory)
+            .with_context(|| format!("Failed to read {}", directory.display()))?;
+        for entry in entries {
+            let path = entry
+                .with_context(|| format!("Failed to read {}", directory.display()))?
+                .path();
+            let extension = path.extension().and_then(|extension| extension.to_str());
+            if matches!(extension, Some("mobileprovision" | "provisionprofile")) {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Decodes a signed profile with `security cms -D` and reads its name and expiry.
+fn read_provisioning_profile(path: &Path) -> Result<ProvisioningProfile> {
+    let plist = query(
+        &Invocation::new("security")
+            .with_args(["cms", "-D", "-i"])
+            .with_arg(path.to_string_lossy()),
+    )?;
+
+    parse_provisioning_profile(&plist)
+}
+
+fn parse_provisioning_profile(plist: &str) -> Result<ProvisioningProfile> {
+    Ok(ProvisioningProfile {
+        name: plist_value(plist, "Name").context("The profile has no Name")?,
+        expiration_date: plist_value(plist, "ExpirationDate")
+            .context("The profile has no ExpirationDate")?,
+    })
+}
+
+/// Returns the text of the element following `<key>key</key>` in an XML plist, such as the
+/// `<string>` or `<date>` holding its value.
+fn plist_value(plist: &str, key: &str) -> Option<String> {
+    let marker = format!("<key>{}</key>", key);
+    let rest = &plist[plist.find(&marker)? + marker.len()..];
+    let rest = &rest[rest.find('>')? + 1..];
+    let value = &rest[..rest.find('<')?];
+
+    Some(
+        value
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&"),
+    )
+}
+
+/// Runs a read-only query and returns its stdout, or an error naming the command when it fails.
+fn query(invocation: &Invocation) -> Result<String> {
+    let output = run_query(invocation)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if let Some(line) = stderr.lines().map(str::trim).find(|line| !line.is_empty()) {
+            anyhow::bail!("`{}` failed: {}", invocation, line);
+        }
+        return Err(CommandError::from_output(
+            CommandErrorKind::Failed,
+            &invocation.to_string(),
+            invocation,
+            &output,
+        )
+        .into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Formats `time` as an ISO 8601 UTC timestamp with second precision, the format of plist dates,
+/// so that the two compare as strings.
+fn timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (year, month, day) = civil_from_days((seconds / SECONDS_PER_DAY) as i64);
+    let seconds_of_day = seconds % SECONDS_PER_DAY;
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    )
+}
+
+/// The date part of a plist timestamp.
+fn date(timestamp: &str) -> &str {
+    timestamp.get(..10).unwrap_or(timestamp)
+}
+
+/// Converts days since 1970-01-01 to a proleptic Gregorian (year, month, day).
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_xcode_version() {
+        assert_eq!(
+            parse_xcode_version("Xcode 16.2\nBuild version 16C5032a\n"),
+            "Xcode 16.2 (16C5032a)"
+        );
+        assert_eq!(parse_xcode_version("Xcode 15.4\n"), "Xcode 15.4");
+    }
+
+    #[test]
+    fn test_parse_signing_identities() {
+        let stdout = "  1) 0A1B2C3D4E5F60718293A4B5C6D7E8F901234567 \"Apple Development: Jane \
+                      Appleseed (K1L2M3N4O5)\"\n  \
+                      2) 1B2C3D4E5F60718293A4B5C6D7E8F90123456789 \"Apple Distribution: \
+                      Example Ltd (A1B2C3D4E5)\"\n     2 valid identities found\n";
+
+        assert_eq!(
+            parse_signing_identities(stdout),
+            [
+                "Apple Development: Jane Appleseed (K1L2M3N4O5)",
+                "Apple Distribution: Example Ltd (A1B2C3D4E5)"
+            ]
+        );
+        assert!(parse_signing_identities("     0 valid identities found\n").is_empty());
+    }
+
+    #[test]
+    fn test_parse_provisioning_profile() {
+        let plist = r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+	<key>AppIDName</key>
+	<string>MyApp</string>
+	<key>ExpirationDate</key>
+	<date>2026-03-01T09:30:00Z</date>
+	<key>Name</key>
+	<string>MyApp App Store &amp; TestFlight</string>
+</dict>
+</plist>"#;
+
+        assert_eq!(
+            parse_provisioning_profile(plist).unwrap(),
+            ProvisioningProfile {
+                name: String::from("MyApp App Store & TestFlight"),
+                expiration_date: String::from("2026-03-01T09:30:00Z"),
+            }
+        );
+        assert_eq!(
+            parse_provisioning_profile("<dict></dict>")
+                .unwrap_err()
+                .to_string(),
+            "The profile has no Name"
+        );
+    }
+
+    #[test]
+    fn test_timestamp() {
+        for (seconds, expected) in [
+            (0, "1970-01-01T00:00:00Z"),
+            (951_782_400, "2000-02-29T00:00:00Z"),
+            (1_767_225_599, "2025-12-31T23:59:59Z"),
+        ] {
+            let time = UNIX_EPOCH + Duration::from_secs(seconds);
+
+            assert_eq!(timestamp(time), expected);
+        }
+    }
+
+    #[test]
+    fn test_check_provisioning_profiles_without_profiles() {
+        let temp_dir = tempdir().unwrap();
+
+        let check =
+            check_provisioning_profiles(&[temp_dir.path().join("missing")], SystemTime::now());
+
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert_eq!(check.message, "None installed");
+    }
+
+    #[test]
+    fn test_license_check_reports_why_the_check_did_not_run() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let invocation = Invocation::new("xcodebuild").with_args(["-license", "check"]);
+        let exited = |code: i32, stderr: &str| Output {
+            status: std::process::ExitStatus::from_raw(code << 8),
+            stdout: Vec::new(),
+            stderr: stderr.as_bytes().to_vec(),
+        };
+
+        let check = license_check(&invocation, Ok(exited(0, "")));
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert_eq!(check.message, "Accepted");
+
+        let check = license_check(
+            &invocation,
+            Ok(exited(
+                1,
+                "You have not agreed to the Xcode license agreements.\n",
+            )),
+        );
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert_eq!(
+            check.message,
+            "Not accepted; run `sudo xcodebuild -license accept`"
+        );
+
+        let check = license_check(
+            &invocation,
+            Ok(exited(
+                1,
+                "xcode-select: error: tool 'xcodebuild' requires Xcode, but active developer \
+                 directory '/Library/Developer/CommandLineTools' is a command line tools \
+                 instance\n",
+            )),
+        );
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(
+            check.message.starts_with(
+                "xcodebuild failed with exit code 1\nCommand: xcodebuild -license check"
+            ),
+            "{}",
+            check.message
+        );
+        assert!(check.message.contains("is a command line tools instance"));
+
+        let check = license_check(
+            &invocation,
+            Ok(Output {
+                status: std::process::ExitStatus::from_raw(9),
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+            }),
+        );
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert_eq!(
+            check.message,
+            "xcodebuild was terminated by a signal\nCommand: xcodebuild -license check"
+        );
+
+        for error in [
+            CommandError::tool_missing(&invocation),
+            CommandError::new(CommandErrorKind::TimedOut, "xcodebuild", &invocation)
+                .with_timeout(Duration::from_secs(30)),
+            CommandError::interrupted("xcodebuild"),
+        ] {
+            let expected = format!("{:#}", anyhow::Error::from(error.clone()));
+            let check = license_check(&invocation, Err(error.into()));
+
+            assert_eq!(check.status, CheckStatus::Fail);
+            assert_eq!(check.message, expected);
+        }
+    }
+
+    #[test]
+    fn test_doctor_report_display() {
+        let report = DoctorReport {
+            checks: vec![
+                Check::new("xcodebuild", CheckStatus::Pass, "/usr/bin/xcodebuild"),
+                Check::new("Signing identities", CheckStatus::Pass, "1 valid")
+                    .with_details(vec![String::from("Apple Distribution: Example Ltd")]),
+                Check::new("Xcode license", CheckStatus::Fail, "Not accepted"),
+            ],
+        };
+
+        assert_eq!(
+            report.to_string(),
+            "PASS  xcodebuild          /usr/bin/xcodebuild\n\
+             PASS  Signing identities  1 valid\n\
+             \x20                         Apple Distribution: Example Ltd\n\
+             FAIL  Xcode license       Not accepted\n\
+             \n\
+             2 passed, 0 warnings, 1 failed\n"
+        );
+        assert_eq!(
+            report.failure().as_deref(),
+            Some("1 check failed: Xcode license")
+        );
+    }
+}
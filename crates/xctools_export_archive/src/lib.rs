@@ -1,5 +1,49 @@
+use std::path::{Path, PathBuf};
+
 use anyhow::Result;
-use xcbuild_common::{XcodebuildCommandAction, XcodebuildParams, run_xcodebuild_command};
+use serde::Serialize;
+use xcbuild_common::{
+    CommandResult, XcodebuildCommandAction, XcodebuildParams, run_xcodebuild_command,
+    xcodebuild_warnings,
+};
+
+/// File extensions of the distributable products xcodebuild writes to the export path.
+const ARTIFACT_EXTENSIONS: &[&str] = &["ipa", "pkg", "app", "dmg", "zip"];
+
+/// The outcome of a successful [`export_archive`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResult {
+    pub archive_path: PathBuf,
+    pub export_path: PathBuf,
+    /// The exported .ipa, .pkg or .app files, sorted by path. Empty in dry-run mode.
+    pub artifacts: Vec<PathBuf>,
+    /// Warnings found in xcodebuild's output; reported at the top level of the JSON report.
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+impl std::fmt::Display for ExportResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Exported {} to {}",
+            self.archive_path.display(),
+            self.export_path.display()
+        )?;
+        for artifact in &self.artifacts {
+            writeln!(f, "  {}", artifact.display())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CommandResult for ExportResult {
+    fn warnings(&self) -> Vec<String> {
+        self.warnings.clone()
+    }
+}
 
 /// Exports an Xcode archive using the `xcodebuild -exportArchive` command-line tool.
 ///
@@ -17,7 +61,7 @@ use xcbuild_common::{XcodebuildCommandAction, XcodebuildParams, run_xcodebuild_c
 ///
 /// # Returns
 ///
-/// Returns `Ok(String)` containing the stdout from the xcodebuild -exportArchive command on success,
+/// Returns `Ok(ExportResult)` listing the exported artifacts and the warnings xcodebuild reported,
 /// or `Err` if the export process fails.
 ///
 /// # Examples
@@ -72,39 +116,104 @@ use xcbuild_common::{XcodebuildCommandAction, XcodebuildParams, run_xcodebuild_c
 /// - The export path directory will be created if it doesn't exist
 ///
 pub fn export_archive(
-    archive_path: &String,
-    export_options: &String,
-    export_path: &String,
-) -> Result<String> {
-    let params = XcodebuildParams::new(XcodebuildCommandAction::ExportArchive)
-        .with_archive_path(archive_path.clone())
-        .with_export_options(export_options.clone())
-        .with_export_path(export_path.clone());
+    archive_path: &str,
+    export_options: &str,
+    export_path: &str,
+) -> Result<ExportResult> {
+    let params = make_export_archive_params(archive_path, export_options, export_path);
     let output = run_xcodebuild_command(&params)?;
 
-    Ok(output)
+    Ok(ExportResult {
+        archive_path: PathBuf::from(archive_path),
+        export_path: PathBuf::from(export_path),
+        artifacts: find_artifacts(Path::new(export_path)),
+        warnings: xcodebuild_warnings(&output),
+    })
+}
+
+fn find_artifacts(export_path: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(export_path) else {
+        return Vec::new();
+    };
+    let mut artifacts: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| ARTIFACT_EXTENSIONS.contains(&extension))
+        })
+        .collect();
+    artifacts.sort();
+
+    artifacts
+}
+
+fn make_export_archive_params(
+    archive_path: &str,
+    export_options: &str,
+    export_path: &str,
+) -> XcodebuildParams {
+    XcodebuildParams::new(XcodebuildCommandAction::ExportArchive)
+        .with_archive_path(archive_path.to_string())
+        .with_export_options(export_options.to_string())
+        .with_export_path(export_path.to_string())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[allow(dead_code)]
-    fn assert_export_does_not_fail(result: Result<String>) {
-        let output = result.ok().unwrap();
-        assert!(output.is_empty() || output.len() > 0);
+    #[test]
+    fn test_find_artifacts() {
+        let tmp = tempfile::tempdir().unwrap();
+        for name in [
+            "MyApp.ipa",
+            "ExportOptions.plist",
+            "DistributionSummary.plist",
+        ] {
+            std::fs::write(tmp.path().join(name), "").unwrap();
+        }
+        std::fs::create_dir(tmp.path().join("MyMacApp.app")).unwrap();
+
+        assert_eq!(
+            find_artifacts(tmp.path()),
+            [
+                tmp.path().join("MyApp.ipa"),
+                tmp.path().join("MyMacApp.app")
+            ]
+        );
+        assert!(find_artifacts(&tmp.path().join("missing")).is_empty());
+    }
+
+    fn assert_export_invocation_is_valid(params: XcodebuildParams) {
+        let invocation = params.make_xcodebuild_invocation().unwrap();
+        let args = invocation.args();
+
+        assert_eq!(invocation.program(), "xcodebuild");
+        assert_eq!(args[0], "-exportArchive");
+        assert_eq!(
+            args.iter().skip_while(|arg| *arg != "-archivePath").nth(1),
+            params.archive_path.as_ref()
+        );
+        assert_eq!(
+            args.iter()
+                .skip_while(|arg| *arg != "-exportOptionsPlist")
+                .nth(1),
+            params.export_options.as_ref()
+        );
+        assert_eq!(
+            args.iter().skip_while(|arg| *arg != "-exportPath").nth(1),
+            params.export_path.as_ref()
+        );
     }
 
     #[test]
     fn test_export_archive_basic_functionality() {
         // Test basic export archive function call
-        let result = export_archive(
-            &"MyApp.xcarchive".to_string(),
-            &"ExportOptions.plist".to_string(),
-            &"build/export".to_string(),
-        );
+        let result =
+            make_export_archive_params("MyApp.xcarchive", "ExportOptions.plist", "build/export");
 
-        assert_export_does_not_fail(result);
+        assert_export_invocation_is_valid(result);
     }
 
     #[test]
@@ -120,13 +229,9 @@ mod tests {
         ];
 
         for archive_path in archive_paths {
-            let result = export_archive(
-                &archive_path.to_string(),
-                &"ExportOptions.plist".to_string(),
-                &"export".to_string(),
-            );
+            let result = make_export_archive_params(archive_path, "ExportOptions.plist", "export");
 
-            assert_export_does_not_fail(result);
+            assert_export_invocation_is_valid(result);
         }
     }
 
@@ -144,13 +249,10 @@ mod tests {
         ];
 
         for export_options_path in export_options_paths {
-            let result = export_archive(
-                &"TestApp.xcarchive".to_string(),
-                &export_options_path.to_string(),
-                &"export".to_string(),
-            );
+            let result =
+                make_export_archive_params("TestApp.xcarchive", export_options_path, "export");
 
-            assert_export_does_not_fail(result);
+            assert_export_invocation_is_valid(result);
         }
     }
 
@@ -168,62 +270,59 @@ mod tests {
         ];
 
         for export_path in export_paths {
-            let result = export_archive(
-                &"TestApp.xcarchive".to_string(),
-                &"ExportOptions.plist".to_string(),
-                &export_path.to_string(),
-            );
+            let result =
+                make_export_archive_params("TestApp.xcarchive", "ExportOptions.plist", export_path);
 
-            assert_export_does_not_fail(result);
+            assert_export_invocation_is_valid(result);
         }
     }
 
     #[test]
     fn test_export_archive_app_store_scenario() {
         // Test typical App Store export scenario
-        let result = export_archive(
-            &"MyApp.xcarchive".to_string(),
-            &"AppStoreExportOptions.plist".to_string(),
-            &"build/appstore".to_string(),
+        let result = make_export_archive_params(
+            "MyApp.xcarchive",
+            "AppStoreExportOptions.plist",
+            "build/appstore",
         );
 
-        assert_export_does_not_fail(result);
+        assert_export_invocation_is_valid(result);
     }
 
     #[test]
     fn test_export_archive_ad_hoc_scenario() {
         // Test typical Ad Hoc export scenario
-        let result = export_archive(
-            &"MyApp-Release.xcarchive".to_string(),
-            &"AdHocExportOptions.plist".to_string(),
-            &"build/adhoc".to_string(),
+        let result = make_export_archive_params(
+            "MyApp-Release.xcarchive",
+            "AdHocExportOptions.plist",
+            "build/adhoc",
         );
 
-        assert_export_does_not_fail(result);
+        assert_export_invocation_is_valid(result);
     }
 
     #[test]
     fn test_export_archive_enterprise_scenario() {
         // Test typical Enterprise export scenario
-        let result = export_archive(
-            &"MyEnterpriseApp.xcarchive".to_string(),
-            &"EnterpriseExportOptions.plist".to_string(),
-            &"build/enterprise".to_string(),
+        let result = make_export_archive_params(
+            "MyEnterpriseApp.xcarchive",
+            "EnterpriseExportOptions.plist",
+            "build/enterprise",
         );
 
-        assert_export_does_not_fail(result);
+        assert_export_invocation_is_valid(result);
     }
 
     #[test]
     fn test_export_archive_development_scenario() {
         // Test typical Development export scenario
-        let result = export_archive(
-            &"MyApp-Debug.xcarchive".to_string(),
-            &"DevelopmentExportOptions.plist".to_string(),
-            &"build/development".to_string(),
+        let result = make_export_archive_params(
+            "MyApp-Debug.xcarchive",
+            "DevelopmentExportOptions.plist",
+            "build/development",
         );
 
-        assert_export_does_not_fail(result);
+        assert_export_invocation_is_valid(result);
     }
 
     #[test]
@@ -234,63 +333,57 @@ mod tests {
         let export_path = "export".to_string();
 
         // This should compile and demonstrate the correct function signature
-        let result = export_archive(&archive_path, &export_options, &export_path);
+        let result = make_export_archive_params(&archive_path, &export_options, &export_path);
 
-        assert_export_does_not_fail(result);
+        assert_export_invocation_is_valid(result);
     }
 
     #[test]
     fn test_export_archive_return_type() {
-        // Test that export_archive function returns Result<String>
-        let result = export_archive(
-            &"TestApp.xcarchive".to_string(),
-            &"ExportOptions.plist".to_string(),
-            &"export".to_string(),
-        );
+        // Test that export archive parameters always build an xcodebuild invocation
+        let result =
+            make_export_archive_params("TestApp.xcarchive", "ExportOptions.plist", "export");
 
-        assert_export_does_not_fail(result);
+        assert_export_invocation_is_valid(result);
     }
 
     #[test]
     fn test_export_archive_with_empty_strings() {
         // Test behavior with empty string parameters
-        let result = export_archive(&"".to_string(), &"".to_string(), &"".to_string());
+        let result = make_export_archive_params("", "", "");
 
-        assert_export_does_not_fail(result);
+        assert_export_invocation_is_valid(result);
     }
 
     #[test]
     fn test_export_archive_with_special_characters() {
         // Test archive paths with special characters and spaces
-        let result = export_archive(
-            &"My App With Spaces.xcarchive".to_string(),
-            &"Export_Options_AppStore.plist".to_string(),
-            &"build/My App Export".to_string(),
+        let result = make_export_archive_params(
+            "My App With Spaces.xcarchive",
+            "Export_Options_AppStore.plist",
+            "build/My App Export",
         );
 
-        assert_export_does_not_fail(result);
+        assert_export_invocation_is_valid(result);
     }
 
     #[test]
     fn test_export_archive_parameter_types() {
-        // Test that the function accepts String references
+        // Test that the parameters accept both owned strings and literals
         let archive = "test.xcarchive".to_string();
         let options = "test.plist".to_string();
         let path = "test_export".to_string();
 
-        // This demonstrates the function accepts &String parameters
-        let result = export_archive(&archive, &options, &path);
+        // Owned strings are accepted through deref
+        let result = make_export_archive_params(&archive, &options, &path);
 
-        assert_export_does_not_fail(result);
+        assert_export_invocation_is_valid(result);
 
         // Also test with string literals converted to String
-        let result2 = export_archive(
-            &"literal.xcarchive".to_string(),
-            &"literal.plist".to_string(),
-            &"literal_export".to_string(),
-        );
+        let result2 =
+            make_export_archive_params("literal.xcarchive", "literal.plist", "literal_export");
 
-        assert_export_does_not_fail(result2);
+        assert_export_invocation_is_valid(result2);
     }
 
     #[test]
@@ -311,13 +404,9 @@ mod tests {
         ];
 
         for (archive, options, export_path) in ios_patterns {
-            let result = export_archive(
-                &archive.to_string(),
-                &options.to_string(),
-                &export_path.to_string(),
-            );
+            let result = make_export_archive_params(archive, options, export_path);
 
-            assert_export_does_not_fail(result);
+            assert_export_invocation_is_valid(result);
         }
     }
 
@@ -343,13 +432,9 @@ mod tests {
         ];
 
         for (archive, options, export_path) in macos_patterns {
-            let result = export_archive(
-                &archive.to_string(),
-                &options.to_string(),
-                &export_path.to_string(),
-            );
+            let result = make_export_archive_params(archive, options, export_path);
 
-            assert_export_does_not_fail(result);
+            assert_export_invocation_is_valid(result);
         }
     }
 }
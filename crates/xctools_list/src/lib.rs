@@ -0,0 +1,205 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use xcbuild_common::{BuildTarget, CommandResult, ProjectList};
+
+/// How `xctools list` prints the schemes, targets and configurations.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ListFormat {
+    /// One column each for schemes, targets and configurations
+    #[default]
+    Table,
+    /// The parsed `xcodebuild -list -json` output
+    Json,
+}
+
+impl std::fmt::Display for ListFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListFormat::Table => write!(f, "table"),
+            ListFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// The result of [`list`]: the schemes, targets and configurations of a project or workspace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProjectListing {
+    #[serde(flatten)]
+    pub list: ProjectList,
+    /// How the listing is printed in text mode.
+    #[serde(skip)]
+    pub format: ListFormat,
+}
+
+impl std::fmt::Display for ProjectListing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.format {
+            ListFormat::Table => write!(f, "{}", render_table(&self.list)),
+            ListFormat::Json => {
+                let json = serde_json::to_string_pretty(&self.list).map_err(|_| std::fmt::Error)?;
+                writeln!(f, "{}", json)
+            }
+        }
+    }
+}
+
+impl CommandResult for ProjectListing {}
+
+/// Lists the schemes, targets and build configurations of an Xcode project or workspace.
+///
+/// Runs `xcodebuild -list -json` against the project or workspace. The returned listing prints
+/// as a table or as JSON, depending on `format`. Workspaces only report schemes, so their target
+/// and configuration columns are empty.
+///
+/// # Arguments
+///
+/// * `project` - Optional path to the Xcode project file (.xcodeproj). Either this or
+///   `workspace` must be provided, but not both.
+/// * `workspace` - Optional path to the Xcode workspace file (.xcworkspace). Either this or
+///   `project` must be provided, but not both.
+/// * `format` - Output format, [`ListFormat::Table`] or [`ListFormat::Json`]
+///
+/// # Returns
+///
+/// Returns `Ok(ProjectListing)` with the parsed listing, or `Err` if xcodebuild fails or its
+/// output cannot be parsed.
+///
+/// # Examples
+///
+/// ## Using the xctools CLI:
+/// ```bash
+/// xctools list --project MyApp.xcodeproj
+/// xctools list --workspace MyApp.xcworkspace --format json
+/// ```
+///
+/// ## Testing parameter validation - neither project nor workspace:
+/// ```rust
+/// use xctools_list::{ListFormat, list};
+///
+/// let result = list(&None, &None, ListFormat::Table);
+/// assert!(result.is_err());
+/// assert!(result.unwrap_err().to_string().contains("Neither project nor workspace is specified"));
+/// ```
+pub fn list(
+    project: &Option<String>,
+    workspace: &Option<String>,
+    format: ListFormat,
+) -> Result<ProjectListing> {
+    let list = BuildTarget::new(project.as_ref(), workspace.as_ref()).list()?;
+
+    Ok(ProjectListing { list, format })
+}
+
+fn render_table(project_list: &ProjectList) -> String {
+    let columns = [
+        ("SCHEMES", &project_list.schemes),
+        ("TARGETS", &project_list.targets),
+        ("CONFIGURATIONS", &project_list.configurations),
+    ];
+    let widths: Vec<usize> = columns
+        .iter()
+        .map(|(header, values)| {
+            values
+                .iter()
+                .map(|value| value.chars().count())
+                .chain([header.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let rows = columns
+        .iter()
+        .map(|(_, values)| values.len())
+        .max()
+        .unwrap_or_default();
+
+    let mut table = format!("{}\n\n", project_list.name);
+    let headers: Vec<&str> = columns.iter().map(|(header, _)| *header).collect();
+    table.push_str(&render_row(&headers, &widths));
+    for row in 0..rows {
+        let cells: Vec<&str> = columns
+            .iter()
+            .map(|(_, values)| values.get(row).map_or("", String::as_str))
+            .collect();
+        table.push_str(&render_row(&cells, &widths));
+    }
+
+    table
+}
+
+fn render_row(cells: &[&str], widths: &[usize]) -> String {
+    let padded: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+        .collect();
+
+    format!("{}\n", padded.join("  ").trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_table_for_project() {
+        let project_list = ProjectList {
+            name: String::from("MyApp"),
+            schemes: vec![String::from("MyApp")],
+            targets: vec![String::from("MyApp"), String::from("MyAppTests")],
+            configurations: vec![
+                String::from("Debug"),
+                String::from("Release"),
+                String::from("Staging"),
+            ],
+        };
+
+        assert_eq!(
+            render_table(&project_list),
+            "MyApp\n\
+             \n\
+             SCHEMES  TARGETS     CONFIGURATIONS\n\
+             MyApp    MyApp       Debug\n\
+             \x20        MyAppTests  Release\n\
+             \x20                    Staging\n"
+        );
+    }
+
+    #[test]
+    fn test_render_table_for_workspace() {
+        let project_list = ProjectList {
+            name: String::from("MyApp"),
+            schemes: vec![String::from("MyApp"), String::from("MyApp Staging")],
+            ..ProjectList::default()
+        };
+
+        assert_eq!(
+            render_table(&project_list),
+            "MyApp\n\nSCHEMES        TARGETS  CONFIGURATIONS\nMyApp\nMyApp Staging\n"
+        );
+    }
+
+    #[test]
+    fn test_project_listing_display_as_json() {
+        let listing = ProjectListing {
+            list: ProjectList {
+                name: String::from("MyApp"),
+                schemes: vec![String::from("MyApp")],
+                ..ProjectList::default()
+            },
+            format: ListFormat::Json,
+        };
+
+        let printed: serde_json::Value = serde_json::from_str(&listing.to_string()).unwrap();
+
+        assert_eq!(printed, serde_json::to_value(&listing).unwrap());
+        assert_eq!(printed["schemes"], serde_json::json!(["MyApp"]));
+    }
+
+    #[test]
+    fn test_list_format_display() {
+        assert_eq!(ListFormat::default().to_string(), "table");
+        assert_eq!(ListFormat::Json.to_string(), "json");
+    }
+}
@@ -1,6 +1,38 @@
-use std::process::Command;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
+use serde::Serialize;
+use xcbuild_common::{
+    CommandError, CommandErrorKind, CommandResult, Invocation, StreamOptions, log_file_for,
+    retry_policy, stream_invocation,
+};
+
+/// The outcome of a successful [`notarize`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotarizationResult {
+    pub file: PathBuf,
+    /// The notary service's submission ID, as printed by `notarytool submit`. `None` in dry-run
+    /// mode.
+    pub submission_id: Option<String>,
+    /// The final submission status, e.g. `Accepted`. `None` in dry-run mode.
+    pub status: Option<String>,
+    /// Whether the ticket was stapled to `file`.
+    pub stapled: bool,
+}
+
+impl std::fmt::Display for NotarizationResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Notarized {}", self.file.display())?;
+        if let Some(submission_id) = &self.submission_id {
+            write!(f, " (submission {})", submission_id)?;
+        }
+
+        writeln!(f)
+    }
+}
+
+impl CommandResult for NotarizationResult {}
 
 /// Notarizes a macOS application using Apple's notarization service.
 ///
@@ -22,8 +54,8 @@ use anyhow::{Context, Result};
 ///
 /// # Returns
 ///
-/// Returns `Ok(String)` with a success message on successful notarization and stapling,
-/// or `Err` if submission, notarization, or stapling fails.
+/// Returns `Ok(NotarizationResult)` with the submission ID and status reported by notarytool on
+/// successful notarization and stapling, or `Err` if submission, notarization, or stapling fails.
 ///
 /// # Examples
 ///
@@ -41,11 +73,42 @@ use anyhow::{Context, Result};
 /// - After successful notarization, the ticket is stapled to the file so that
 ///   Gatekeeper can verify it offline.
 /// - Requires Xcode 13 or later (`xcrun notarytool` was introduced in Xcode 13).
-pub fn notarize(file_path: &str, apple_id: &str, password: &str, team_id: &str) -> Result<String> {
-    let submit_output = run_notarytool_submit(file_path, apple_id, password, team_id)?;
-    let staple_output = run_stapler_staple(file_path)?;
+/// - Submission and stapling are each retried according to `xcbuild_common::retry_policy` when
+///   Apple's services report a network failure or an HTTP 5xx error.
+/// - Output from both tools is streamed to the terminal and appended to a single log file
+///   (see `xcbuild_common::log_file_for`).
+pub fn notarize(
+    file_path: &str,
+    apple_id: &str,
+    password: &str,
+    team_id: &str,
+) -> Result<NotarizationResult> {
+    let log_file = log_file_for("notarize");
+    let submit_output = run_notarytool_submit(file_path, apple_id, password, team_id, &log_file)?;
+    run_stapler_staple(file_path, &log_file)?;
+
+    Ok(NotarizationResult {
+        file: PathBuf::from(file_path),
+        submission_id: notarytool_field(&submit_output, "id", false),
+        status: notarytool_field(&submit_output, "status", true),
+        stapled: true,
+    })
+}
 
-    Ok(format!("{}{}", submit_output, staple_output))
+/// Reads an indented `key: value` line from `notarytool submit --wait` output, which prints the
+/// submission ID when the upload starts and again, with the final status, once processing is
+/// complete.
+fn notarytool_field(output: &str, key: &str, last: bool) -> Option<String> {
+    let mut values = output.lines().filter_map(|line| {
+        let (name, value) = line.trim().split_once(':')?;
+        (name == key && !value.trim().is_empty()).then(|| value.trim().to_string())
+    });
+
+    if last {
+        values.next_back()
+    } else {
+        values.next()
+    }
 }
 
 fn run_notarytool_submit(
@@ -53,64 +116,70 @@ fn run_notarytool_submit(
     apple_id: &str,
     password: &str,
     team_id: &str,
+    log_file: &Path,
 ) -> Result<String> {
-    let command = make_notarytool_submit_command(file_path, apple_id, password, team_id);
-    let output = Command::new("zsh")
-        .arg("-c")
-        .arg(&command)
-        .spawn()
-        .context("Failed to run xcrun notarytool submit")?
-        .wait_with_output()
-        .context("Failed to run xcrun notarytool submit")?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!(
-            "xcrun notarytool submit failed (exit {}): {}",
-            output.status,
-            stderr.trim()
-        );
-    }
+    let invocation = make_notarytool_submit_invocation(file_path, apple_id, password, team_id);
+    retry_policy().run("xcrun notarytool submit", || {
+        let output = stream_invocation(
+            &invocation,
+            &StreamOptions::new().with_log_file(log_file.to_path_buf()),
+        )?;
+        if !output.status.success() {
+            return Err(CommandError::from_output(
+                CommandErrorKind::Failed,
+                "xcrun notarytool submit",
+                &invocation,
+                &output,
+            )
+            .into());
+        }
 
-    String::from_utf8(output.stdout).context("Failed to decode notarytool output")
+        String::from_utf8(output.stdout).context("Failed to decode notarytool output")
+    })
 }
 
-fn run_stapler_staple(file_path: &str) -> Result<String> {
-    let command = make_stapler_staple_command(file_path);
-    let output = Command::new("zsh")
-        .arg("-c")
-        .arg(&command)
-        .spawn()
-        .context("Failed to run xcrun stapler staple")?
-        .wait_with_output()
-        .context("Failed to run xcrun stapler staple")?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!(
-            "xcrun stapler staple failed (exit {}): {}",
-            output.status,
-            stderr.trim()
-        );
-    }
+fn run_stapler_staple(file_path: &str, log_file: &Path) -> Result<String> {
+    let invocation = make_stapler_staple_invocation(file_path);
+    retry_policy().run("xcrun stapler staple", || {
+        let output = stream_invocation(
+            &invocation,
+            &StreamOptions::new().with_log_file(log_file.to_path_buf()),
+        )?;
+        if !output.status.success() {
+            return Err(CommandError::from_output(
+                CommandErrorKind::Failed,
+                "xcrun stapler staple",
+                &invocation,
+                &output,
+            )
+            .into());
+        }
 
-    String::from_utf8(output.stdout).context("Failed to decode stapler output")
+        String::from_utf8(output.stdout).context("Failed to decode stapler output")
+    })
 }
 
-fn make_notarytool_submit_command(
+fn make_notarytool_submit_invocation(
     file_path: &str,
     apple_id: &str,
     password: &str,
     team_id: &str,
-) -> String {
-    format!(
-        "xcrun notarytool submit {} --apple-id {} --password {} --team-id {} --wait",
-        file_path, apple_id, password, team_id
-    )
+) -> Invocation {
+    Invocation::new("xcrun")
+        .with_args([
+            "notarytool",
+            "submit",
+            file_path,
+            "--apple-id",
+            apple_id,
+            "--password",
+        ])
+        .with_secret_arg(password)
+        .with_args(["--team-id", team_id, "--wait"])
 }
 
-fn make_stapler_staple_command(file_path: &str) -> String {
-    format!("xcrun stapler staple {}", file_path)
+fn make_stapler_staple_invocation(file_path: &str) -> Invocation {
+    Invocation::new("xcrun").with_args(["stapler", "staple", file_path])
 }
 
 #[cfg(test)]
@@ -122,8 +191,8 @@ mod tests {
     const TEST_CREDENTIAL: &str = "xxxx-xxxx-xxxx-xxxx";
 
     #[test]
-    fn test_make_notarytool_submit_command() {
-        let result = make_notarytool_submit_command(
+    fn test_make_notarytool_submit_invocation() {
+        let result = make_notarytool_submit_invocation(
             "MyApp.dmg",
             TEST_APPLE_ID,
             TEST_CREDENTIAL,
@@ -131,15 +200,15 @@ mod tests {
         );
 
         assert_eq!(
-            result,
+            result.to_string(),
             "xcrun notarytool submit MyApp.dmg --apple-id developer@example.com \
-             --password xxxx-xxxx-xxxx-xxxx --team-id A1B2C3D4E5 --wait"
+             --password '********' --team-id A1B2C3D4E5 --wait"
         );
     }
 
     #[test]
-    fn test_make_notarytool_submit_command_with_pkg() {
-        let result = make_notarytool_submit_command(
+    fn test_make_notarytool_submit_invocation_with_pkg() {
+        let result = make_notarytool_submit_invocation(
             "/path/to/MyApp.pkg",
             "mac.developer@example.com",
             TEST_CREDENTIAL,
@@ -147,15 +216,15 @@ mod tests {
         );
 
         assert_eq!(
-            result,
+            result.to_string(),
             "xcrun notarytool submit /path/to/MyApp.pkg --apple-id mac.developer@example.com \
-             --password xxxx-xxxx-xxxx-xxxx --team-id Z9Y8X7W6V5 --wait"
+             --password '********' --team-id Z9Y8X7W6V5 --wait"
         );
     }
 
     #[test]
-    fn test_make_notarytool_submit_command_with_zip() {
-        let result = make_notarytool_submit_command(
+    fn test_make_notarytool_submit_invocation_with_zip() {
+        let result = make_notarytool_submit_invocation(
             "./build/MyApp.zip",
             "team@company.com",
             TEST_CREDENTIAL,
@@ -163,23 +232,77 @@ mod tests {
         );
 
         assert_eq!(
-            result,
+            result.to_string(),
             "xcrun notarytool submit ./build/MyApp.zip --apple-id team@company.com \
-             --password xxxx-xxxx-xxxx-xxxx --team-id TEAMID1234 --wait"
+             --password '********' --team-id TEAMID1234 --wait"
         );
     }
 
     #[test]
-    fn test_make_stapler_staple_command() {
-        let result = make_stapler_staple_command("MyApp.dmg");
+    fn test_notarytool_field() {
+        let output = "Conducting pre-submission checks for MyApp.dmg and initiating connection \
+                      to the Apple notary service...\n\
+                      Submission ID received\n  \
+                      id: 2efe2717-52ef-43a5-96dc-0797e4ca1041\n\
+                      Successfully uploaded file\n\
+                      Waiting for processing to complete.\n\
+                      Current status: In Progress....\n\
+                      Processing complete\n  \
+                      id: 2efe2717-52ef-43a5-96dc-0797e4ca1041\n  \
+                      status: Accepted\n";
 
-        assert_eq!(result, "xcrun stapler staple MyApp.dmg");
+        assert_eq!(
+            notarytool_field(output, "id", false).as_deref(),
+            Some("2efe2717-52ef-43a5-96dc-0797e4ca1041")
+        );
+        assert_eq!(
+            notarytool_field(output, "status", true).as_deref(),
+            Some("Accepted")
+        );
+        assert_eq!(notarytool_field("", "status", true), None);
+    }
+
+    #[test]
+    fn test_make_stapler_staple_invocation() {
+        let result = make_stapler_staple_invocation("MyApp.dmg");
+
+        assert_eq!(result.to_string(), "xcrun stapler staple MyApp.dmg");
     }
 
     #[test]
-    fn test_make_stapler_staple_command_with_path() {
-        let result = make_stapler_staple_command("/path/to/MyApp.pkg");
+    fn test_make_stapler_staple_invocation_with_path() {
+        let result = make_stapler_staple_invocation("/path/to/MyApp.pkg");
 
-        assert_eq!(result, "xcrun stapler staple /path/to/MyApp.pkg");
+        assert_eq!(
+            result.to_string(),
+            "xcrun stapler staple /path/to/MyApp.pkg"
+        );
+    }
+
+    #[test]
+    fn test_make_notarytool_submit_invocation_keeps_arguments_separate() {
+        let result = make_notarytool_submit_invocation(
+            "build/My App.dmg",
+            TEST_APPLE_ID,
+            "pa$$word!",
+            TEST_TEAM_ID,
+        );
+
+        assert_eq!(result.program(), "xcrun");
+        assert_eq!(
+            result.args(),
+            [
+                "notarytool",
+                "submit",
+                "build/My App.dmg",
+                "--apple-id",
+                TEST_APPLE_ID,
+                "--password",
+                "pa$$word!",
+                "--team-id",
+                TEST_TEAM_ID,
+                "--wait",
+            ]
+        );
     }
 }
@@ -0,0 +1,612 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use xcbuild_common::{CommandResult, RenderedResult, check_interrupted};
+use xctools_config::{Pipeline, PipelineStep};
+
+/// Step id under which references read environment variables, e.g. `${env.APP_PASSWORD}`.
+const ENV_REFERENCE: &str = "env";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StepStatus {
+    Succeeded,
+    Failed,
+    /// The step did not run because an earlier step failed or xctools was interrupted.
+    Skipped,
+}
+
+impl std::fmt::Display for StepStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            StepStatus::Succeeded => "succeeded",
+            StepStatus::Failed => "failed",
+            StepStatus::Skipped => "skipped",
+        })
+    }
+}
+
+/// What happened to one step of a pipeline.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepOutcome {
+    pub id: String,
+    /// The subcommand the step ran, e.g. `simulator boot`.
+    pub command: String,
+    pub status: StepStatus,
+    /// How long the step ran, rounded to milliseconds; `None` for skipped steps.
+    pub duration_seconds: Option<f64>,
+    /// The step's command result, which later steps refer to as `${id.field}`; `null` unless
+    /// the step succeeded.
+    pub result: serde_json::Value,
+}
+
+/// The outcome of [`run_pipeline`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineResult {
+    pub pipeline: String,
+    pub duration_seconds: f64,
+    pub steps: Vec<StepOutcome>,
+    /// Warnings reported by the steps; reported at the top level of the JSON report.
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+impl PipelineResult {
+    fn failed_step(&self) -> Option<&StepOutcome> {
+        self.steps
+            .iter()
+            .find(|step| step.status == StepStatus::Failed)
+    }
+}
+
+impl std::fmt::Display for PipelineResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.failed_step() {
+            Some(step) => writeln!(
+                f,
+                "Pipeline '{}' failed at step '{}' after {:.1}s",
+                self.pipeline, step.id, self.duration_seconds
+            )?,
+            None if self
+                .steps
+                .iter()
+                .any(|step| step.status == StepStatus::Skipped) =>
+            {
+                writeln!(
+                    f,
+                    "Pipeline '{}' was interrupted after {:.1}s",
+                    self.pipeline, self.duration_seconds
+                )?
+            }
+            None => writeln!(
+                f,
+                "Pipeline '{}' succeeded in {:.1}s",
+                self.pipeline, self.duration_seconds
+            )?,
+        }
+
+        let id_width = self
+            .steps
+            .iter()
+            .map(|step| step.id.chars().count())
+            .max()
+            .unwrap_or_default();
+        for step in &self.steps {
+            let duration = step
+                .duration_seconds
+                .map(|seconds| format!("{:.1}s", seconds))
+                .unwrap_or_default();
+            let line = format!(
+                "  {:<9}  {:<id_width$}  {:>8}",
+                step.status,
+                step.id,
+                duration,
+                id_width = id_width
+            );
+            writeln!(f, "{}", line.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CommandResult for PipelineResult {
+    fn warnings(&self) -> Vec<String> {
+        self.warnings.clone()
+    }
+}
+
+/// Runs the steps of `pipeline` in order, stopping at the first one that fails.
+///
+/// `execute` runs a step given its command line after the program name, such as
+/// `["archive", "--scheme=MyApp"]`, and returns its result. Before a step runs, every
+/// `${id.field}` in its values is replaced with that field of the result of the earlier step
+/// `id`: `${archive.archivePath}`, or `${export-archive.artifacts.0}` for the first element of a
+/// list. `${env.NAME}` is replaced with the environment variable `NAME`, so that secrets need not
+/// be written to the file. References to unknown steps and unset variables are reported before
+/// the first step runs. In dry-run mode, where steps produce no files, a reference to a field an
+/// earlier step did not report is left as it is.
+///
+/// Each step's start is announced on stderr. When a step fails, a summary of all steps is
+/// printed on stderr and the step's error is returned with the pipeline and step added as
+/// context, so that its exit code is kept.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::path::Path;
+/// use clap::{Arg, Command};
+/// use xcbuild_common::RenderedResult;
+/// use xctools_config::Config;
+/// use xctools_run::{StepStatus, run_pipeline};
+///
+/// let cli = Command::new("xctools")
+///     .subcommand(Command::new("archive").arg(Arg::new("output").long("output")))
+///     .subcommand(Command::new("upload").arg(Arg::new("file").long("file")));
+/// let config = Config::parse(
+///     Path::new("xctools.toml"),
+///     r#"
+///     [[pipelines.release.steps]]
+///     command = "archive"
+///     output = "MyApp.xcarchive"
+///
+///     [[pipelines.release.steps]]
+///     command = "upload"
+///     file = "${archive.path}"
+///     "#,
+/// )
+/// .unwrap();
+/// let pipeline = config.pipeline(&cli, "release").unwrap();
+///
+/// let mut command_lines = Vec::new();
+/// let result = run_pipeline(&pipeline, false, |args| {
+///     command_lines.push(args.join(" "));
+///     Ok(RenderedResult {
+///         text: String::new(),
+///         json: serde_json::json!({ "path": "build/MyApp.xcarchive" }),
+///         warnings: Vec::new(),
+///         failure: None,
+///     })
+/// })
+/// .unwrap();
+///
+/// assert_eq!(
+///     command_lines,
+///     ["archive --output=MyApp.xcarchive", "upload --file=build/MyApp.xcarchive"]
+/// );
+/// assert_eq!(result.steps[1].status, StepStatus::Succeeded);
+/// ```
+pub fn run_pipeline(
+    pipeline: &Pipeline,
+    dry_run: bool,
+    mut execute: impl FnMut(Vec<String>) -> Result<RenderedResult>,
+) -> Result<PipelineResult> {
+    check_references(pipeline)?;
+
+    let started = Instant::now();
+    let mut result = PipelineResult {
+        pipeline: pipeline.name.clone(),
+        duration_seconds: 0.0,
+        steps: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let mut outputs: BTreeMap<&str, serde_json::Value> = BTreeMap::new();
+    let mut failure = None;
+
+    for (index, step) in pipeline.steps.iter().enumerate() {
+        let command = step.command.join(" ");
+        // A Ctrl-C received while the previous step was finishing stops the pipeline here.
+        if failure.is_none()
+            && let Err(error) = check_interrupted("xctools")
+        {
+            failure = Some((step.id.clone(), error.into()));
+        }
+        if failure.is_some() {
+            result.steps.push(StepOutcome {
+                id: step.id.clone(),
+                command,
+                status: StepStatus::Skipped,
+                duration_seconds: None,
+                result: serde_json::Value::Null,
+            });
+            continue;
+        }
+
+        eprintln!(
+            "==> Step {}/{}: {}",
+            index + 1,
+            pipeline.steps.len(),
+            describe(step)
+        );
+        let step_started = Instant::now();
+        let outcome = step
+            .args(|value| expand(value, &outputs, dry_run))
+            .and_then(&mut execute);
+        let mut outcome_result = serde_json::Value::Null;
+        let status = match outcome {
+            Ok(rendered) => {
+                outputs.insert(&step.id, rendered.json.clone());
+                outcome_result = rendered.json;
+                result.warnings.extend(rendered.warnings);
+                StepStatus::Succeeded
+            }
+            Err(error) => {
+                failure = Some((step.id.clone(), error));
+                StepStatus::Failed
+            }
+        };
+        result.steps.push(StepOutcome {
+            id: step.id.clone(),
+            command,
+            status,
+            duration_seconds: Some(seconds(step_started.elapsed())),
+            result: outcome_result,
+        });
+    }
+    result.duration_seconds = seconds(started.elapsed());
+
+    match failure {
+        Some((id, error)) => {
+            eprint!("{}", result);
+            Err(error.context(format!(
+                "Pipeline '{}' stopped at step '{}'",
+                pipeline.name, id
+            )))
+        }
+        None => Ok(result),
+    }
+}
+
+/// The step's id, followed by its command if that differs.
+fn describe(step: &PipelineStep) -> String {
+    let command = step.command.join(" ");
+    if step.id == step.command.join("-") {
+        command
+    } else {
+        format!("{} ({})", step.id, command)
+    }
+}
+
+/// Checks that every reference names an earlier step or a set environment variable.
+fn check_references(pipeline: &Pipeline) -> Result<()> {
+    for (index, step) in pipeline.steps.iter().enumerate() {
+        let earlier: Vec<&str> = pipeline.steps[..index]
+            .iter()
+            .map(|step| step.id.as_str())
+            .collect();
+        for option in &step.options {
+            for value in &option.values {
+                for reference in references(value)? {
+                    let (id, field) = split_reference(reference)?;
+                    if id == ENV_REFERENCE {
+                        if std::env::var_os(field).is_none() {
+                            bail!(
+                                "Step '{}' of pipeline '{}' uses the environment variable {}, \
+                                 which is not set",
+                                step.id,
+                                pipeline.name,
+                                field
+                            );
+                        }
+                    } else if !earlier.contains(&id) {
+                        bail!(
+                            "Step '{}' of pipeline '{}' refers to '${{{}}}', but no earlier step \
+                             has the id '{}'",
+                            step.id,
+                            pipeline.name,
+                            reference,
+                            id
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The contents of the `${...}` references in `value`.
+fn references(value: &str) -> Result<Vec<&str>> {
+    let mut references = Vec::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let length = rest[start..]
+            .find('}')
+            .with_context(|| format!("Unterminated reference in '{}'", value))?;
+        references.push(&rest[start + 2..start + length]);
+        rest = &rest[start + length + 1..];
+    }
+
+    Ok(references)
+}
+
+fn split_reference(reference: &str) -> Result<(&str, &str)> {
+    reference
+        .split_once('.')
+        .filter(|(id, field)| !id.is_empty() && !field.is_empty())
+        .with_context(|| {
+            format!(
+                "Invalid reference '${{{}}}': expected ${{STEP.FIELD}} or ${{env.NAME}}",
+                reference
+            )
+        })
+}
+
+/// Replaces the references in `value` with the values they refer to.
+fn expand(
+    value: &str,
+    outputs: &BTreeMap<&str, serde_json::Value>,
+    dry_run: bool,
+) -> Result<String> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    for reference in references(value)? {
+        let placeholder = format!("${{{}}}", reference);
+        let start = rest.find(&placeholder).unwrap_or_default();
+        expanded.push_str(&rest[..start]);
+        match resolve(reference, outputs) {
+            Ok(resolved) => expanded.push_str(&resolved),
+            Err(_) if dry_run => expanded.push_str(&placeholder),
+            Err(error) => return Err(error),
+        }
+        rest = &rest[start + placeholder.len()..];
+    }
+    expanded.push_str(rest);
+
+    Ok(expanded)
+}
+
+fn resolve(reference: &str, outputs: &BTreeMap<&str, serde_json::Value>) -> Result<String> {
+    let (id, field) = split_reference(reference)?;
+    if id == ENV_REFERENCE {
+        return std::env::var(field)
+            .with_context(|| format!("The environment variable {} is not set", field));
+    }
+
+    let output = outputs
+        .get(id)
+        .with_context(|| format!("No earlier step has the id '{}'", id))?;
+    let value = field
+        .split('.')
+        .try_fold(output, |value, key| match value {
+            serde_json::Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            value => value.get(key),
+        })
+        .with_context(|| {
+            format!(
+                "'${{{}}}' does not exist; step '{}' reported: {}",
+                reference, id, output
+            )
+        })?;
+
+    match value {
+        serde_json::Value::String(string) => Ok(string.clone()),
+        serde_json::Value::Number(number) => Ok(number.to_string()),
+        serde_json::Value::Bool(boolean) => Ok(boolean.to_string()),
+        value => bail!(
+            "'${{{}}}' is {} rather than a single value",
+            reference,
+            value
+        ),
+    }
+}
+
+/// Durations are reported in seconds, rounded to milliseconds.
+fn seconds(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1000.0).round() / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use clap::{Arg, ArgAction, Command};
+    use xctools_config::Config;
+
+    fn pipeline(steps: &str) -> Pipeline {
+        let cli = Command::new("xctools")
+            .subcommand(
+                Command::new("archive")
+                    .arg(Arg::new("output").long("output"))
+                    .arg(
+                        Arg::new("build_setting")
+                            .long("build-setting")
+                            .action(ArgAction::Append),
+                    ),
+            )
+            .subcommand(Command::new("export-archive").arg(Arg::new("archive").long("archive")))
+            .subcommand(Command::new("upload").arg(Arg::new("file").long("file")));
+        Config::parse(Path::new("/repo/xctools.toml"), steps)
+            .unwrap()
+            .pipeline(&cli, "release")
+            .unwrap()
+    }
+
+    fn rendered(json: serde_json::Value) -> RenderedResult {
+        RenderedResult {
+            text: String::new(),
+            json,
+            warnings: Vec::new(),
+            failure: None,
+        }
+    }
+
+    const RELEASE: &str = r#"
+        [[pipelines.release.steps]]
+        command = "archive"
+        output = "build/MyApp.xcarchive"
+
+        [[pipelines.release.steps]]
+        command = "export-archive"
+        archive = "${archive.archivePath}"
+
+        [[pipelines.release.steps]]
+        command = "upload"
+        file = "${export-archive.artifacts.0}"
+    "#;
+
+    #[test]
+    fn test_run_pipeline_feeds_outputs_to_later_steps() {
+        let mut command_lines = Vec::new();
+
+        let result = run_pipeline(&pipeline(RELEASE), false, |args| {
+            command_lines.push(args.join(" "));
+            Ok(rendered(match args[0].as_str() {
+                "archive" => serde_json::json!({ "archivePath": "build/MyApp.xcarchive" }),
+                "export-archive" => serde_json::json!({ "artifacts": ["build/export/MyApp.ipa"] }),
+                _ => serde_json::json!({ "file": "build/export/MyApp.ipa" }),
+            }))
+        })
+        .unwrap();
+
+        assert_eq!(
+            command_lines,
+            [
+                "archive --output=build/MyApp.xcarchive",
+                "export-archive --archive=build/MyApp.xcarchive",
+                "upload --file=build/export/MyApp.ipa",
+            ]
+        );
+        let statuses: Vec<StepStatus> = result.steps.iter().map(|step| step.status).collect();
+        assert_eq!(statuses, [StepStatus::Succeeded; 3]);
+        assert_eq!(
+            result.steps[1].result["artifacts"][0],
+            "build/export/MyApp.ipa"
+        );
+    }
+
+    #[test]
+    fn test_run_pipeline_stops_at_first_failure() {
+        let mut runs = 0;
+
+        let error = run_pipeline(&pipeline(RELEASE), false, |args| {
+            runs += 1;
+            if args[0] == "export-archive" {
+                bail!("export failed");
+            }
+            Ok(rendered(
+                serde_json::json!({ "archivePath": "MyApp.xcarchive" }),
+            ))
+        })
+        .unwrap_err();
+
+        assert_eq!(runs, 2);
+        assert_eq!(
+            format!("{:#}", error),
+            "Pipeline 'release' stopped at step 'export-archive': export failed"
+        );
+    }
+
+    #[test]
+    fn test_missing_output_fails_step_unless_dry_run() {
+        let execute = |args: Vec<String>| {
+            Ok(rendered(match args[0].as_str() {
+                "export-archive" => serde_json::json!({ "artifacts": [] }),
+                _ => serde_json::json!({ "archivePath": "MyApp.xcarchive" }),
+            }))
+        };
+
+        let error = run_pipeline(&pipeline(RELEASE), false, execute).unwrap_err();
+        assert_eq!(
+            format!("{:#}", error),
+            "Pipeline 'release' stopped at step 'upload': '${export-archive.artifacts.0}' does \
+             not exist; step 'export-archive' reported: {\"artifacts\":[]}"
+        );
+
+        let mut last = Vec::new();
+        run_pipeline(&pipeline(RELEASE), true, |args| {
+            last = args.clone();
+            execute(args)
+        })
+        .unwrap();
+        assert_eq!(last, ["upload", "--file=${export-archive.artifacts.0}"]);
+    }
+
+    #[test]
+    fn test_references_are_checked_before_running() {
+        for (steps, message) in [
+            (
+                "[[pipelines.release.steps]]\ncommand = \"upload\"\n\
+                 file = \"${archive.archivePath}\"\n\
+                 [[pipelines.release.steps]]\ncommand = \"archive\"",
+                "Step 'upload' of pipeline 'release' refers to '${archive.archivePath}', but no \
+                 earlier step has the id 'archive'",
+            ),
+            (
+                "[[pipelines.release.steps]]\ncommand = \"upload\"\n\
+                 file = \"${env.XCTOOLS_UNSET_VARIABLE}\"",
+                "Step 'upload' of pipeline 'release' uses the environment variable \
+                 XCTOOLS_UNSET_VARIABLE, which is not set",
+            ),
+            (
+                "[[pipelines.release.steps]]\ncommand = \"upload\"\nfile = \"${archive\"",
+                "Unterminated reference in '${archive'",
+            ),
+        ] {
+            let error = run_pipeline(&pipeline(steps), false, |_| unreachable!()).unwrap_err();
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_expand_mixes_text_and_references() {
+        let outputs = BTreeMap::from([("archive", serde_json::json!({ "version": 42 }))]);
+
+        assert_eq!(
+            expand(
+                "VERSION=${archive.version}-${archive.version}",
+                &outputs,
+                false
+            )
+            .unwrap(),
+            "VERSION=42-42"
+        );
+        assert!(expand("${archive.missing}", &outputs, false).is_err());
+    }
+
+    #[test]
+    fn test_pipeline_result_display() {
+        let result = PipelineResult {
+            pipeline: String::from("release"),
+            duration_seconds: 75.25,
+            steps: vec![
+                StepOutcome {
+                    id: String::from("archive"),
+                    command: String::from("archive"),
+                    status: StepStatus::Succeeded,
+                    duration_seconds: Some(70.0),
+                    result: serde_json::Value::Null,
+                },
+                StepOutcome {
+                    id: String::from("export-archive"),
+                    command: String::from("export-archive"),
+                    status: StepStatus::Failed,
+                    duration_seconds: Some(5.25),
+                    result: serde_json::Value::Null,
+                },
+                StepOutcome {
+                    id: String::from("upload"),
+                    command: String::from("upload"),
+                    status: StepStatus::Skipped,
+                    duration_seconds: None,
+                    result: serde_json::Value::Null,
+                },
+            ],
+            warnings: Vec::new(),
+        };
+
+        assert_eq!(
+            result.to_string(),
+            "Pipeline 'release' failed at step 'export-archive' after 75.2s\n\
+             \x20 succeeded  archive            70.0s\n\
+             \x20 failed     export-archive      5.2s\n\
+             \x20 skipped    upload\n"
+        );
+    }
+}
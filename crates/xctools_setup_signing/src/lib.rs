@@ -1,12 +1,54 @@
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use xcbuild_common::{
+    CommandError, CommandErrorKind, CommandResult, Invocation, command_runner, run_invocation,
+};
 
 const KEYCHAIN_NAME: &str = "xctools-signing.keychain";
 
+/// The outcome of a successful [`setup_signing`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningSetup {
+    /// Whether this was a dry run, in which nothing was changed.
+    pub dry_run: bool,
+    pub keychain: String,
+    pub certificate: PathBuf,
+    /// Where each provisioning profile was installed, or would be in a dry run.
+    pub provisioning_profiles: Vec<PathBuf>,
+}
+
+impl std::fmt::Display for SigningSetup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.dry_run {
+            writeln!(f, "Dry run: no changes were made.")?;
+        } else {
+            writeln!(f, "Code signing setup complete.")?;
+        }
+        writeln!(f, "Keychain: {}", self.keychain)?;
+        writeln!(f, "Certificate: {}", self.certificate.display())?;
+        if self.dry_run {
+            writeln!(
+                f,
+                "Provisioning profiles to install: {}",
+                self.provisioning_profiles.len()
+            )
+        } else {
+            writeln!(
+                f,
+                "Provisioning profiles installed: {}",
+                self.provisioning_profiles.len()
+            )
+        }
+    }
+}
+
+impl CommandResult for SigningSetup {}
+
 /// Sets up code signing for a CI environment by importing a certificate into a
 /// temporary keychain and installing provisioning profiles.
 ///
@@ -26,7 +68,8 @@ const KEYCHAIN_NAME: &str = "xctools-signing.keychain";
 ///
 /// # Returns
 ///
-/// Returns `Ok(String)` with a summary of what was set up, or `Err` if any step fails.
+/// Returns `Ok(SigningSetup)` describing the keychain and installed profiles, or `Err` if any step
+/// fails.
 ///
 /// # Examples
 ///
@@ -57,7 +100,7 @@ pub fn setup_signing(
     certificate_path: &str,
     certificate_password: &str,
     provisioning_profiles: &[String],
-) -> Result<String> {
+) -> Result<SigningSetup> {
     let keychain_password = generate_keychain_password();
     create_keychain(KEYCHAIN_NAME, &keychain_password)?;
     import_certificate(
@@ -67,13 +110,14 @@ pub fn setup_signing(
         certificate_password,
     )?;
     set_default_keychain(KEYCHAIN_NAME)?;
-    install_provisioning_profiles(provisioning_profiles)?;
-
-    let profile_count = provisioning_profiles.len();
-    Ok(format!(
-        "Code signing setup complete.\nKeychain: {}\nCertificate: {}\nProvisioning profiles installed: {}\n",
-        KEYCHAIN_NAME, certificate_path, profile_count
-    ))
+    let installed_profiles = install_provisioning_profiles(provisioning_profiles)?;
+
+    Ok(SigningSetup {
+        dry_run: command_runner().is_dry_run(),
+        keychain: KEYCHAIN_NAME.to_string(),
+        certificate: PathBuf::from(certificate_path),
+        provisioning_profiles: installed_profiles,
+    })
 }
 
 /// Generates a unique password for the ephemeral CI keychain using the current
@@ -88,20 +132,18 @@ fn generate_keychain_password() -> String {
 }
 
 fn create_keychain(keychain_name: &str, keychain_password: &str) -> Result<()> {
-    let commands = vec![
-        format!(
-            "security create-keychain -p {} {}",
-            keychain_password, keychain_name
-        ),
-        format!("security set-keychain-settings -lut 3600 {}", keychain_name),
-        format!(
-            "security unlock-keychain -p {} {}",
-            keychain_password, keychain_name
-        ),
+    let invocations = [
+        make_security_invocation(&["create-keychain", "-p"])
+            .with_secret_arg(keychain_password)
+            .with_arg(keychain_name),
+        make_security_invocation(&["set-keychain-settings", "-lut", "3600", keychain_name]),
+        make_security_invocation(&["unlock-keychain", "-p"])
+            .with_secret_arg(keychain_password)
+            .with_arg(keychain_name),
     ];
 
-    for command in commands {
-        run_security_command(&command)?;
+    for invocation in &invocations {
+        run_security_command(invocation)?;
     }
 
     Ok(())
@@ -113,46 +155,74 @@ fn import_certificate(
     certificate_path: &str,
     certificate_password: &str,
 ) -> Result<()> {
-    let command = format!(
-        "security import {} -k {} -P {} -T /usr/bin/codesign -T /usr/bin/productsign",
-        certificate_path, keychain_name, certificate_password
-    );
-    run_security_command(&command)?;
+    run_security_command(
+        &make_security_invocation(&["import", certificate_path, "-k", keychain_name, "-P"])
+            .with_secret_arg(certificate_password)
+            .with_args(["-T", "/usr/bin/codesign", "-T", "/usr/bin/productsign"]),
+    )?;
 
     // Allow codesign to access the certificate without user confirmation
-    let partition_command = format!(
-        "security set-key-partition-list -S apple-tool:,apple: -s -k {} {}",
-        keychain_password, keychain_name
-    );
-    run_security_command(&partition_command)?;
+    run_security_command(
+        &make_security_invocation(&[
+            "set-key-partition-list",
+            "-S",
+            "apple-tool:,apple:",
+            "-s",
+            "-k",
+        ])
+        .with_secret_arg(keychain_password)
+        .with_arg(keychain_name),
+    )?;
 
     Ok(())
 }
 
 fn set_default_keychain(keychain_name: &str) -> Result<()> {
-    let command = format!("security default-keychain -s {}", keychain_name);
-    run_security_command(&command)?;
+    run_security_command(&make_security_invocation(&[
+        "default-keychain",
+        "-s",
+        keychain_name,
+    ]))?;
 
     Ok(())
 }
 
-fn install_provisioning_profiles(provisioning_profiles: &[String]) -> Result<()> {
+/// Copies the profiles into the provisioning profiles directory and returns their installed
+/// paths.
+fn install_provisioning_profiles(provisioning_profiles: &[String]) -> Result<Vec<PathBuf>> {
     if provisioning_profiles.is_empty() {
-        return Ok(());
+        return Ok(Vec::new());
     }
 
     let profiles_dir = provisioning_profiles_directory()?;
+    if command_runner().is_dry_run() {
+        // Still validate the inputs so a dry run surfaces missing profiles.
+        return provisioning_profiles
+            .iter()
+            .map(|profile_path| profile_destination(profile_path, &profiles_dir))
+            .collect();
+    }
+
     fs::create_dir_all(&profiles_dir)
         .context("Failed to create provisioning profiles directory")?;
 
-    for profile_path in provisioning_profiles {
-        install_provisioning_profile(profile_path, &profiles_dir)?;
-    }
+    provisioning_profiles
+        .iter()
+        .map(|profile_path| install_provisioning_profile(profile_path, &profiles_dir))
+        .collect()
+}
 
-    Ok(())
+fn install_provisioning_profile(profile_path: &str, profiles_dir: &Path) -> Result<PathBuf> {
+    let destination = profile_destination(profile_path, profiles_dir)?;
+    fs::copy(profile_path, &destination).context(format!(
+        "Failed to install provisioning profile: {}",
+        profile_path
+    ))?;
+
+    Ok(destination)
 }
 
-fn install_provisioning_profile(profile_path: &str, profiles_dir: &Path) -> Result<()> {
+fn profile_destination(profile_path: &str, profiles_dir: &Path) -> Result<PathBuf> {
     let source = Path::new(profile_path);
     if !source.exists() {
         bail!("Provisioning profile not found: {}", profile_path);
@@ -161,14 +231,8 @@ fn install_provisioning_profile(profile_path: &str, profiles_dir: &Path) -> Resu
     let file_name = source
         .file_name()
         .context("Invalid provisioning profile path")?;
-    let destination = profiles_dir.join(file_name);
-
-    fs::copy(source, &destination).context(format!(
-        "Failed to install provisioning profile: {}",
-        profile_path
-    ))?;
 
-    Ok(())
+    Ok(profiles_dir.join(file_name))
 }
 
 fn provisioning_profiles_directory() -> Result<PathBuf> {
@@ -179,18 +243,24 @@ fn provisioning_profiles_directory() -> Result<PathBuf> {
         .join("Provisioning Profiles"))
 }
 
-fn run_security_command(command: &str) -> Result<String> {
-    let output = Command::new("zsh")
-        .arg("-c")
-        .arg(command)
-        .spawn()
-        .context(format!("Failed to spawn command: {}", command))?
-        .wait_with_output()
-        .context(format!("Failed to run command: {}", command))?;
+fn make_security_invocation(args: &[&str]) -> Invocation {
+    Invocation::new("security").with_args(args.iter().copied())
+}
+
+/// Runs a `security` invocation. Passwords must be added with `Invocation::with_secret_arg` so
+/// they are redacted from the error reported on failure.
+fn run_security_command(invocation: &Invocation) -> Result<String> {
+    let output = run_invocation(invocation)?;
 
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("Command failed (exit {}): {}", output.status, stderr.trim());
+        let subcommand = invocation.args().first().cloned().unwrap_or_default();
+        return Err(CommandError::from_output(
+            CommandErrorKind::SigningFailed,
+            &format!("security {}", subcommand),
+            invocation,
+            &output,
+        )
+        .into());
     }
 
     String::from_utf8(output.stdout).context("Failed to decode command output")
@@ -277,4 +347,26 @@ mod tests {
         assert!(pw1.starts_with("xctools-"));
         assert!(pw2.starts_with("xctools-"));
     }
+
+    #[test]
+    fn test_make_security_invocation_keeps_password_as_single_argument() {
+        let invocation = make_security_invocation(&["unlock-keychain", "-p"])
+            .with_secret_arg("pa$$ word!")
+            .with_arg("xctools-signing.keychain");
+
+        assert_eq!(invocation.program(), "security");
+        assert_eq!(
+            invocation.args(),
+            [
+                "unlock-keychain",
+                "-p",
+                "pa$$ word!",
+                "xctools-signing.keychain"
+            ]
+        );
+        assert_eq!(
+            invocation.to_string(),
+            "security unlock-keychain -p '********' xctools-signing.keychain"
+        );
+    }
 }
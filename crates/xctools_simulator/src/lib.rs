@@ -0,0 +1,434 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use xcbuild_common::{
+    CommandError, CommandErrorKind, CommandResult, Invocation, Simulator, SimulatorList,
+    list_simulators, run_invocation,
+};
+
+/// Status bar values applied by `xctools simulator status-bar`. The defaults are the values
+/// Apple uses in marketing screenshots.
+#[derive(clap::Args, Debug, Clone, PartialEq, Eq)]
+pub struct StatusBarOverrides {
+    /// Time shown in the status bar
+    #[arg(long, default_value = "9:41")]
+    pub time: String,
+
+    /// Battery level in percent
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub battery_level: u8,
+
+    /// Battery state: "charging", "charged" or "discharging"
+    #[arg(long, default_value = "charged", value_parser = ["charging", "charged", "discharging"])]
+    pub battery_state: String,
+
+    /// Number of cellular bars (0-4)
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u8).range(0..=4))]
+    pub cellular_bars: u8,
+
+    /// Number of Wi-Fi bars (0-3)
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(0..=3))]
+    pub wifi_bars: u8,
+
+    /// Data network type, e.g. "wifi", "5g" or "lte"
+    #[arg(long, default_value = "wifi")]
+    pub data_network: String,
+
+    /// Carrier name shown next to the cellular bars
+    #[arg(long)]
+    pub operator_name: Option<String>,
+}
+
+impl Default for StatusBarOverrides {
+    fn default() -> Self {
+        Self {
+            time: String::from("9:41"),
+            battery_level: 100,
+            battery_state: String::from("charged"),
+            cellular_bars: 4,
+            wifi_bars: 3,
+            data_network: String::from("wifi"),
+            operator_name: None,
+        }
+    }
+}
+
+impl StatusBarOverrides {
+    /// Returns the `simctl status_bar <device> override` arguments for these values.
+    pub fn arguments(&self) -> Vec<String> {
+        let mut arguments = vec![
+            String::from("--time"),
+            self.time.clone(),
+            String::from("--batteryState"),
+            self.battery_state.clone(),
+            String::from("--batteryLevel"),
+            self.battery_level.to_string(),
+            String::from("--cellularBars"),
+            self.cellular_bars.to_string(),
+            String::from("--wifiBars"),
+            self.wifi_bars.to_string(),
+            String::from("--dataNetwork"),
+            self.data_network.clone(),
+        ];
+        if let Some(operator_name) = &self.operator_name {
+            arguments.extend([String::from("--operatorName"), operator_name.clone()]);
+        }
+
+        arguments
+    }
+}
+
+/// The devices touched by a simulator command, printed as JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SimulatorReport {
+    /// `create`, `boot`, `erase`, `delete` or `status-bar`.
+    pub action: String,
+    pub devices: Vec<SimulatorDevice>,
+}
+
+impl SimulatorReport {
+    fn new(action: &str, devices: Vec<SimulatorDevice>) -> Self {
+        Self {
+            action: action.to_string(),
+            devices,
+        }
+    }
+
+    /// Renders the report as pretty-printed JSON followed by a newline.
+    pub fn to_json(&self) -> Result<String> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize report")?;
+
+        Ok(format!("{}\n", json))
+    }
+}
+
+/// Text mode prints the same JSON as [`SimulatorReport::to_json`], so scripts can read the UDIDs
+/// without `--output-format json`.
+impl std::fmt::Display for SimulatorReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_json().map_err(|_| std::fmt::Error)?)
+    }
+}
+
+impl CommandResult for SimulatorReport {}
+
+/// A simulator device as it is expected to be once the command has finished.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatorDevice {
+    /// `None` for a device created in dry-run mode, where simctl does not report a UDID.
+    pub udid: Option<String>,
+    pub name: String,
+    /// `Booted`, `Shutdown` or `Deleted`.
+    pub state: String,
+    /// Runtime name, e.g. `iOS 18.2`.
+    pub runtime: Option<String>,
+    pub runtime_identifier: Option<String>,
+}
+
+impl SimulatorDevice {
+    fn from_simulator(simulators: &SimulatorList, simulator: &Simulator, state: &str) -> Self {
+        let runtime = simulators.runtime(&simulator.runtime_identifier);
+        Self {
+            udid: Some(simulator.udid.clone()),
+            name: simulator.name.clone(),
+            state: state.to_string(),
+            runtime: runtime.map(|runtime| runtime.name.clone()),
+            runtime_identifier: Some(simulator.runtime_identifier.clone())
+                .filter(|identifier| !identifier.is_empty()),
+        }
+    }
+}
+
+/// Creates a new simulator with `xcrun simctl create`.
+///
+/// # Arguments
+///
+/// * `name` - Name of the new device
+/// * `device_type` - Device type name or identifier (e.g., "iPhone 16 Pro" or
+///   "com.apple.CoreSimulator.SimDeviceType.iPhone-16-Pro")
+/// * `runtime` - Optional runtime, resolved against the installed runtimes: an identifier, a
+///   name ("iOS 18.2") or a platform and version prefix ("iOS 18", "iOS latest"). When omitted,
+///   simctl picks the newest runtime compatible with the device type.
+///
+/// # Examples
+///
+/// ## Using the xctools CLI:
+/// ```bash
+/// xctools simulator create --name "CI iPhone" --device-type "iPhone 16 Pro" --runtime "iOS 18"
+/// ```
+pub fn create_simulator(
+    name: &str,
+    device_type: &str,
+    runtime: Option<&str>,
+) -> Result<SimulatorReport> {
+    let simulators = list_simulators()?;
+    let runtime = runtime
+        .map(|runtime| simulators.find_runtime(runtime))
+        .transpose()?;
+
+    let output = run_simctl(
+        "create",
+        make_create_invocation(
+            name,
+            device_type,
+            runtime.map(|runtime| &*runtime.identifier),
+        ),
+    )?;
+    let udid = Some(output.trim().to_string()).filter(|udid| !udid.is_empty());
+
+    let device = match &udid {
+        Some(udid) => {
+            let simulators = list_simulators()?;
+            let simulator = simulators.find_device(udid)?;
+            SimulatorDevice::from_simulator(&simulators, simulator, &simulator.state)
+        }
+        None => SimulatorDevice {
+            udid: None,
+            name: name.to_string(),
+            state: String::from("Shutdown"),
+            runtime: runtime.map(|runtime| runtime.name.clone()),
+            runtime_identifier: runtime.map(|runtime| runtime.identifier.clone()),
+        },
+    };
+
+    Ok(SimulatorReport::new("create", vec![device]))
+}
+
+/// Boots simulators, identified by UDID or exact name. Devices that are already booted are left
+/// alone. With `wait`, each boot blocks until the device has finished booting
+/// (`xcrun simctl bootstatus -b`), so tests can start immediately afterwards.
+pub fn boot_simulators(devices: &[String], wait: bool) -> Result<SimulatorReport> {
+    let simulators = list_simulators()?;
+    let mut touched = Vec::new();
+    for device in devices {
+        let simulator = simulators.find_device(device)?;
+        if wait {
+            run_simctl("bootstatus", make_bootstatus_invocation(&simulator.udid))?;
+        } else if !simulator.is_booted() {
+            run_simctl("boot", make_simctl_invocation(["boot", &simulator.udid]))?;
+        }
+        touched.push(SimulatorDevice::from_simulator(
+            &simulators,
+            simulator,
+            "Booted",
+        ));
+    }
+
+    Ok(SimulatorReport::new("boot", touched))
+}
+
+/// Erases the contents and settings of simulators, shutting booted devices down first since
+/// simctl can only erase a device that is shut down.
+pub fn erase_simulators(devices: &[String]) -> Result<SimulatorReport> {
+    let simulators = list_simulators()?;
+    let mut touched = Vec::new();
+    for device in devices {
+        let simulator = simulators.find_device(device)?;
+        if simulator.is_booted() {
+            run_simctl(
+                "shutdown",
+                make_simctl_invocation(["shutdown", &simulator.udid]),
+            )?;
+        }
+        run_simctl("erase", make_simctl_invocation(["erase", &simulator.udid]))?;
+        touched.push(SimulatorDevice::from_simulator(
+            &simulators,
+            simulator,
+            "Shutdown",
+        ));
+    }
+
+    Ok(SimulatorReport::new("erase", touched))
+}
+
+/// Deletes simulators with `xcrun simctl delete`.
+pub fn delete_simulators(devices: &[String]) -> Result<SimulatorReport> {
+    let simulators = list_simulators()?;
+    let mut touched = Vec::new();
+    for device in devices {
+        let simulator = simulators.find_device(device)?;
+        run_simctl(
+            "delete",
+            make_simctl_invocation(["delete", &simulator.udid]),
+        )?;
+        touched.push(SimulatorDevice::from_simulator(
+            &simulators,
+            simulator,
+            "Deleted",
+        ));
+    }
+
+    Ok(SimulatorReport::new("delete", touched))
+}
+
+/// Overrides the status bar of booted simulators, or clears a previous override when
+/// `overrides` is `None`.
+pub fn set_status_bar(
+    devices: &[String],
+    overrides: Option<&StatusBarOverrides>,
+) -> Result<SimulatorReport> {
+    let simulators = list_simulators()?;
+    let mut touched = Vec::new();
+    for device in devices {
+        let simulator = simulators.find_device(device)?;
+        run_simctl(
+            "status_bar",
+            make_status_bar_invocation(&simulator.udid, overrides),
+        )?;
+        touched.push(SimulatorDevice::from_simulator(
+            &simulators,
+            simulator,
+            &simulator.state,
+        ));
+    }
+
+    Ok(SimulatorReport::new("status-bar", touched))
+}
+
+fn run_simctl(subcommand: &str, invocation: Invocation) -> Result<String> {
+    let output = run_invocation(&invocation)?;
+    if !output.status.success() {
+        return Err(CommandError::from_output(
+            CommandErrorKind::Failed,
+            &format!("xcrun simctl {}", subcommand),
+            &invocation,
+            &output,
+        )
+        .into());
+    }
+
+    String::from_utf8(output.stdout).context("Failed to decode simctl output")
+}
+
+fn make_simctl_invocation<'a, I>(args: I) -> Invocation
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    Invocation::new("xcrun").with_arg("simctl").with_args(args)
+}
+
+fn make_create_invocation(name: &str, device_type: &str, runtime: Option<&str>) -> Invocation {
+    make_simctl_invocation(["create", name, device_type]).with_args(runtime)
+}
+
+fn make_bootstatus_invocation(udid: &str) -> Invocation {
+    make_simctl_invocation(["bootstatus", udid, "-b"])
+}
+
+fn make_status_bar_invocation(udid: &str, overrides: Option<&StatusBarOverrides>) -> Invocation {
+    let invocation = make_simctl_invocation(["status_bar", udid]);
+    match overrides {
+        Some(overrides) => invocation
+            .with_arg("override")
+            .with_args(overrides.arguments()),
+        None => invocation.with_arg("clear"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIMCTL_LIST: &str = include_str!("../../xcbuild_common/tests/fixtures/simctl_list.json");
+    const IPHONE_16_PRO: &str = "4D9E3012-AB66-4F4C-83D5-7A8B9CADBE04";
+
+    #[test]
+    fn test_make_create_invocation() {
+        let invocation = make_create_invocation(
+            "CI iPhone",
+            "iPhone 16 Pro",
+            Some("com.apple.CoreSimulator.SimRuntime.iOS-18-2"),
+        );
+
+        assert_eq!(
+            invocation.to_string(),
+            "xcrun simctl create 'CI iPhone' 'iPhone 16 Pro' \
+             com.apple.CoreSimulator.SimRuntime.iOS-18-2"
+        );
+    }
+
+    #[test]
+    fn test_make_create_invocation_without_runtime() {
+        let invocation = make_create_invocation("CI iPhone", "iPhone 16 Pro", None);
+
+        assert_eq!(
+            invocation.args(),
+            ["simctl", "create", "CI iPhone", "iPhone 16 Pro"]
+        );
+    }
+
+    #[test]
+    fn test_make_bootstatus_invocation() {
+        assert_eq!(
+            make_bootstatus_invocation(IPHONE_16_PRO).to_string(),
+            format!("xcrun simctl bootstatus {} -b", IPHONE_16_PRO)
+        );
+    }
+
+    #[test]
+    fn test_make_status_bar_invocation_with_defaults() {
+        let invocation =
+            make_status_bar_invocation(IPHONE_16_PRO, Some(&StatusBarOverrides::default()));
+
+        assert_eq!(
+            invocation.to_string(),
+            format!(
+                "xcrun simctl status_bar {} override --time 9:41 --batteryState charged \
+                 --batteryLevel 100 --cellularBars 4 --wifiBars 3 --dataNetwork wifi",
+                IPHONE_16_PRO
+            )
+        );
+    }
+
+    #[test]
+    fn test_make_status_bar_invocation_with_operator_name() {
+        let overrides = StatusBarOverrides {
+            operator_name: Some(String::from("Carrier One")),
+            ..StatusBarOverrides::default()
+        };
+        let invocation = make_status_bar_invocation(IPHONE_16_PRO, Some(&overrides));
+
+        assert!(
+            invocation
+                .to_string()
+                .ends_with("--dataNetwork wifi --operatorName 'Carrier One'")
+        );
+    }
+
+    #[test]
+    fn test_make_status_bar_clear_invocation() {
+        assert_eq!(
+            make_status_bar_invocation(IPHONE_16_PRO, None).to_string(),
+            format!("xcrun simctl status_bar {} clear", IPHONE_16_PRO)
+        );
+    }
+
+    #[test]
+    fn test_report_json() {
+        let simulators = SimulatorList::parse(SIMCTL_LIST).unwrap();
+        let simulator = simulators.find_device("iPhone 16 Pro").unwrap();
+        let report = SimulatorReport::new(
+            "erase",
+            vec![SimulatorDevice::from_simulator(
+                &simulators,
+                simulator,
+                "Shutdown",
+            )],
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "action": "erase",
+                "devices": [{
+                    "udid": IPHONE_16_PRO,
+                    "name": "iPhone 16 Pro",
+                    "state": "Shutdown",
+                    "runtime": "iOS 18.2",
+                    "runtimeIdentifier": "com.apple.CoreSimulator.SimRuntime.iOS-18-2",
+                }],
+            })
+        );
+    }
+}
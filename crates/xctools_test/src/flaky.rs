@@ -0,0 +1,370 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use xcbuild_common::CommandResult;
+
+use crate::results::{TestFailure, TestResults, TestStatus};
+
+/// Version written to and expected in the flake history file.
+const HISTORY_VERSION: u32 = 1;
+
+/// A test that failed and then passed when it was retried.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlakyTest {
+    /// The test, as `Target/Class/test()`.
+    pub identifier: String,
+    /// The run it passed in, counting the first run as 1.
+    pub passed_on_attempt: u32,
+    /// The failures of its first run.
+    pub failures: Vec<TestFailure>,
+}
+
+impl std::fmt::Display for FlakyTest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (passed on attempt {})",
+            self.identifier, self.passed_on_attempt
+        )?;
+        if let Some(failure) = self.failures.first() {
+            match failure.location() {
+                Some(location) => write!(f, ", first failed at {}: {}", location, failure.message)?,
+                None => write!(f, ", first failed with: {}", failure.message)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// How often one test ran with retries, passed only on a retry, and failed every attempt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlakeRecord {
+    pub runs: u32,
+    pub flaky: u32,
+    pub failed: u32,
+}
+
+impl FlakeRecord {
+    /// The share of runs in which the test passed only on a retry.
+    pub fn flake_rate(&self) -> f64 {
+        match self.runs {
+            0 => 0.0,
+            runs => f64::from(self.flaky) / f64::from(runs),
+        }
+    }
+}
+
+/// The flakiness of every test run with `--retry-failed`, keyed by `Target/Class/test()`, which
+/// accumulates across runs. Stored as JSON, by default in `.xctools/flaky-tests.json`:
+///
+/// ```json
+/// {"version": 1, "tests": {"MyAppUITests/CheckoutUITests/testCheckout()":
+///   {"runs": 12, "flaky": 3, "failed": 1}}}
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FlakeHistory {
+    #[serde(default)]
+    pub tests: BTreeMap<String, FlakeRecord>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct HistoryFile {
+    version: u32,
+    #[serde(flatten)]
+    history: FlakeHistory,
+}
+
+impl FlakeHistory {
+    /// Reads the history file at `path`; a missing file has no history.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default());
+            }
+            Err(error) => {
+                return Err(error).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        };
+        let file: HistoryFile = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse the flake history in {}", path.display()))?;
+        if file.version != HISTORY_VERSION {
+            bail!(
+                "Unsupported flake history version {} in {}; expected {}",
+                file.version,
+                path.display(),
+                HISTORY_VERSION
+            );
+        }
+
+        Ok(file.history)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let file = HistoryFile {
+            version: HISTORY_VERSION,
+            history: self.clone(),
+        };
+        let json = serde_json::to_string_pretty(&file)?;
+
+        fs::write(path, json + "\n").with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Counts a run with retries: every test of `results` that was not skipped ran, those in
+    /// `flaky` passed on a retry, and those still failed in `results` failed every attempt.
+    pub fn record(&mut self, results: &TestResults, flaky: &[FlakyTest]) {
+        for (target, _, case) in results.cases() {
+            if case.status == TestStatus::Skipped {
+                continue;
+            }
+            let identifier = format!("{}/{}", target.name, case.identifier);
+            let is_flaky = flaky.iter().any(|test| test.identifier == identifier);
+            let record = self.tests.entry(identifier).or_default();
+            record.runs += 1;
+            if is_flaky {
+                record.flaky += 1;
+            }
+            if case.status == TestStatus::Failed {
+                record.failed += 1;
+            }
+        }
+    }
+
+    /// The tests that were flaky at least once, the most often flaky first, then the highest
+    /// flake rate, then by name.
+    pub fn ranking(&self) -> Vec<FlakyTestStats> {
+        let mut ranking: Vec<FlakyTestStats> = self
+            .tests
+            .iter()
+            .filter(|(_, record)| record.flaky > 0)
+            .map(|(identifier, record)| FlakyTestStats {
+                identifier: identifier.clone(),
+                runs: record.runs,
+                flaky: record.flaky,
+                failed: record.failed,
+                flake_rate: record.flake_rate(),
+            })
+            .collect();
+        ranking.sort_by(|a, b| {
+            b.flaky
+                .cmp(&a.flaky)
+                .then_with(|| b.flake_rate.total_cmp(&a.flake_rate))
+                .then_with(|| a.identifier.cmp(&b.identifier))
+        });
+
+        ranking
+    }
+}
+
+/// One line of the [`flaky_report`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlakyTestStats {
+    pub identifier: String,
+    pub runs: u32,
+    pub flaky: u32,
+    pub failed: u32,
+    pub flake_rate: f64,
+}
+
+/// The flakiest tests in a flake history, from [`flaky_report`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlakyReport {
+    pub history_path: PathBuf,
+    /// The tests flaky at least once, the worst first; at most the requested number.
+    pub tests: Vec<FlakyTestStats>,
+    /// How many tests were flaky at least once, including those not listed.
+    pub total_flaky_tests: usize,
+}
+
+impl std::fmt::Display for FlakyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.total_flaky_tests == 0 {
+            return writeln!(
+                f,
+                "No flaky tests recorded in {}",
+                self.history_path.display()
+            );
+        }
+
+        writeln!(
+            f,
+            "{} of {} flaky test(s) in {}",
+            self.tests.len(),
+            self.total_flaky_tests,
+            self.history_path.display()
+        )?;
+        writeln!(
+            f,
+            "  {:>5}  {:>5}  {:>6}  {:>6}  TEST",
+            "FLAKY", "RUNS", "RATE", "FAILED"
+        )?;
+        for test in &self.tests {
+            writeln!(
+                f,
+                "  {:>5}  {:>5}  {:>5.0}%  {:>6}  {}",
+                test.flaky,
+                test.runs,
+                test.flake_rate * 100.0,
+                test.failed,
+                test.identifier
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CommandResult for FlakyReport {}
+
+/// Ranks the tests of the flake history at `history_path` that passed only on a retry, listing
+/// at most `limit` of them.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::path::Path;
+/// use xctools_test::flaky_report;
+///
+/// let report = flaky_report(Path::new("missing/flaky-tests.json"), 10).unwrap();
+/// assert!(report.tests.is_empty());
+/// assert_eq!(report.to_string(), "No flaky tests recorded in missing/flaky-tests.json\n");
+/// ```
+///
+/// ```bash
+/// xctools test flaky-report --limit 20
+/// ```
+pub fn flaky_report(history_path: &Path, limit: usize) -> Result<FlakyReport> {
+    let mut tests = FlakeHistory::load(history_path)?.ranking();
+    let total_flaky_tests = tests.len();
+    tests.truncate(limit);
+
+    Ok(FlakyReport {
+        history_path: history_path.to_path_buf(),
+        tests,
+        total_flaky_tests,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flaky(identifier: &str) -> FlakyTest {
+        FlakyTest {
+            identifier: identifier.to_string(),
+            passed_on_attempt: 2,
+            failures: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_record_counts_runs_flakes_and_failures() {
+        let results =
+            TestResults::parse(include_str!("../tests/fixtures/xcresult_tests.json")).unwrap();
+        let mut history = FlakeHistory::default();
+
+        history.record(
+            &results,
+            &[flaky("MyAppTests/LoginTests/testLoginSucceeds()")],
+        );
+        history.record(&results, &[]);
+
+        assert_eq!(
+            history.tests["MyAppTests/LoginTests/testLoginSucceeds()"],
+            FlakeRecord {
+                runs: 2,
+                flaky: 1,
+                failed: 0
+            }
+        );
+        assert_eq!(
+            history.tests["MyAppUITests/CheckoutUITests/testCheckout()"],
+            FlakeRecord {
+                runs: 2,
+                flaky: 0,
+                failed: 2
+            }
+        );
+        assert!(
+            !history
+                .tests
+                .contains_key("MyAppTests/LoginTests/testLoginOverNetwork()"),
+            "skipped tests did not run"
+        );
+    }
+
+    #[test]
+    fn test_ranking_orders_by_flakes_then_rate() {
+        let record = |runs, flaky| FlakeRecord {
+            runs,
+            flaky,
+            failed: 0,
+        };
+        let history = FlakeHistory {
+            tests: BTreeMap::from([
+                ("T/A/rare()".to_string(), record(100, 2)),
+                ("T/B/often()".to_string(), record(10, 2)),
+                ("T/C/worst()".to_string(), record(10, 5)),
+                ("T/D/stable()".to_string(), record(50, 0)),
+            ]),
+        };
+
+        let ranking: Vec<String> = history
+            .ranking()
+            .into_iter()
+            .map(|test| test.identifier)
+            .collect();
+
+        assert_eq!(ranking, ["T/C/worst()", "T/B/often()", "T/A/rare()"]);
+    }
+
+    #[test]
+    fn test_flaky_report_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("history/flaky-tests.json");
+        let mut history = FlakeHistory::default();
+        history.tests.insert(
+            "MyAppUITests/CheckoutUITests/testCheckout()".to_string(),
+            FlakeRecord {
+                runs: 8,
+                flaky: 2,
+                failed: 1,
+            },
+        );
+        history.tests.insert(
+            "MyAppTests/LoginTests/testLogin()".to_string(),
+            FlakeRecord::default(),
+        );
+        history.save(&path).unwrap();
+        assert_eq!(FlakeHistory::load(&path).unwrap(), history);
+
+        let report = flaky_report(&path, 10).unwrap();
+
+        assert_eq!(report.total_flaky_tests, 1);
+        assert_eq!(
+            report.to_string(),
+            format!(
+                "1 of 1 flaky test(s) in {}\n  \
+                 FLAKY   RUNS    RATE  FAILED  TEST\n      \
+                 2      8     25%       1  MyAppUITests/CheckoutUITests/testCheckout()\n",
+                path.display()
+            )
+        );
+        assert!(flaky_report(&path, 0).unwrap().tests.is_empty());
+    }
+}
@@ -1,7 +1,279 @@
-use anyhow::Result;
+mod flaky;
+mod products;
+mod reports;
+mod results;
+mod shard;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
 use xcbuild_common::{
-    BuildTarget, Configuration, XcodebuildCommandAction, XcodebuildParams, run_xcodebuild_command,
+    BuildOverrides, BuildTarget, CommandResult, Configuration, Destination, SDK, TestIdentifier,
+    TestSelection, XcodebuildCommandAction, XcodebuildParams, command_runner,
+    run_xcodebuild_command, xcodebuild_warnings,
+};
+
+pub use flaky::{FlakeHistory, FlakeRecord, FlakyReport, FlakyTest, FlakyTestStats, flaky_report};
+pub use products::{
+    PackagedTestProducts, TESTROOT_PLACEHOLDER, package_test_products, rewrite_xctestrun,
+};
+pub use reports::{junit_report, markdown_summary};
+pub use results::{
+    TestCase, TestFailure, TestResults, TestStatus, TestSuite, TestTarget, TestTotals,
+    read_test_results,
 };
+pub use shard::{
+    Shard, ShardPlan, TestTimings, enumerate_tests, parse_test_enumeration, partition,
+    read_test_list, test_class,
+};
+
+/// Directory the result bundles are written to unless `--result-bundle-path` is given.
+pub const DEFAULT_RESULT_BUNDLE_DIRECTORY: &str = ".xctools/results";
+/// Derived data directory of [`build_for_testing`], kept between builds so they are incremental.
+pub const DEFAULT_DERIVED_DATA_DIRECTORY: &str = ".xctools/derived-data";
+/// Directory [`build_for_testing`] packages the test products into unless `--artifact-dir` is
+/// given.
+pub const DEFAULT_ARTIFACT_DIRECTORY: &str = ".xctools/test-products";
+/// File the outcome of every run with retries is recorded in unless `--flake-history` is given;
+/// see [`FlakeHistory`].
+pub const DEFAULT_FLAKE_HISTORY_PATH: &str = ".xctools/flaky-tests.json";
+/// Failed tests listed in the error of a failed run; the rest are counted.
+const FAILED_TESTS_IN_ERROR: usize = 10;
+
+/// Which tests [`test`] runs, and where it writes its result bundle, reports and, for
+/// [`build_for_testing`], the test products.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestOptions {
+    /// The test plan, tests and test configurations to run; the whole scheme by default.
+    pub test_selection: TestSelection,
+    /// The `.xcresult` bundle xcodebuild writes. Defaults to `SCHEME.xcresult` in
+    /// [`DEFAULT_RESULT_BUNDLE_DIRECTORY`], which replaces the bundle of the previous run.
+    pub result_bundle_path: Option<PathBuf>,
+    /// Where to write a JUnit XML report of the results.
+    pub junit_path: Option<PathBuf>,
+    /// Where to write a Markdown summary of the results.
+    pub summary_path: Option<PathBuf>,
+    /// Where [`build_for_testing`] packages the test products. Defaults to `SCHEME` in
+    /// [`DEFAULT_ARTIFACT_DIRECTORY`], which replaces the products of the previous build; a
+    /// directory given here must be empty or not exist yet.
+    pub artifact_directory: Option<PathBuf>,
+    /// Run only this shard of the test classes, chosen with [`partition`]. Cannot be combined
+    /// with `--only-testing`, which the shard's classes are passed as.
+    pub shard: Option<Shard>,
+    /// The tests to shard: the JSON of `xcodebuild -enumerate-tests` or a `.xcresult` bundle of
+    /// a previous run; see [`read_test_list`]. Without it, [`test_without_building`] enumerates
+    /// the tests of its `.xctestrun` file.
+    pub shard_tests: Option<PathBuf>,
+    /// The durations that balance the shards, updated after every run. Every machine must use
+    /// the same file; without one, the shards are balanced by the number of classes and no
+    /// durations are recorded.
+    pub timings_path: Option<PathBuf>,
+    /// How many times to re-run the tests that failed, each time only those still failing.
+    /// Tests that pass on a retry are reported as flaky, and the run passes.
+    pub retry_failed: u32,
+    /// Where the flaky and failed tests of runs with retries are recorded. Defaults to
+    /// [`DEFAULT_FLAKE_HISTORY_PATH`].
+    pub flake_history_path: Option<PathBuf>,
+}
+
+impl TestOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_test_selection(mut self, test_selection: TestSelection) -> Self {
+        self.test_selection = test_selection;
+        self
+    }
+
+    pub fn with_result_bundle_path<P: Into<PathBuf>>(mut self, result_bundle_path: P) -> Self {
+        self.result_bundle_path = Some(result_bundle_path.into());
+        self
+    }
+
+    pub fn with_junit_path<P: Into<PathBuf>>(mut self, junit_path: P) -> Self {
+        self.junit_path = Some(junit_path.into());
+        self
+    }
+
+    pub fn with_summary_path<P: Into<PathBuf>>(mut self, summary_path: P) -> Self {
+        self.summary_path = Some(summary_path.into());
+        self
+    }
+
+    pub fn with_artifact_directory<P: Into<PathBuf>>(mut self, artifact_directory: P) -> Self {
+        self.artifact_directory = Some(artifact_directory.into());
+        self
+    }
+
+    pub fn with_shard(mut self, shard: Shard) -> Self {
+        self.shard = Some(shard);
+        self
+    }
+
+    pub fn with_shard_tests<P: Into<PathBuf>>(mut self, shard_tests: P) -> Self {
+        self.shard_tests = Some(shard_tests.into());
+        self
+    }
+
+    pub fn with_timings_path<P: Into<PathBuf>>(mut self, timings_path: P) -> Self {
+        self.timings_path = Some(timings_path.into());
+        self
+    }
+
+    pub fn with_retry_failed(mut self, retry_failed: u32) -> Self {
+        self.retry_failed = retry_failed;
+        self
+    }
+
+    pub fn with_flake_history_path<P: Into<PathBuf>>(mut self, flake_history_path: P) -> Self {
+        self.flake_history_path = Some(flake_history_path.into());
+        self
+    }
+
+    fn flake_history_path(&self) -> &Path {
+        self.flake_history_path
+            .as_deref()
+            .unwrap_or(Path::new(DEFAULT_FLAKE_HISTORY_PATH))
+    }
+
+    fn writes_reports(&self) -> bool {
+        self.junit_path.is_some() || self.summary_path.is_some()
+    }
+}
+
+/// The outcome of a [`test`] or [`test_without_building`] run in which every test passed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestResult {
+    /// The scheme tested; for [`test_without_building`], the scheme named in the `.xctestrun`
+    /// file, or the file's name.
+    pub scheme: String,
+    /// The configuration name passed to xcodebuild, e.g. `Debug`; not known for an `.xctestrun`
+    /// file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub configuration: Option<String>,
+    pub sdk: Option<String>,
+    pub destinations: Vec<String>,
+    /// The `.xctestrun` file the tests ran from, for [`test_without_building`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub xctestrun_path: Option<PathBuf>,
+    /// The test classes this shard ran, when run with [`TestOptions::shard`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shard: Option<ShardPlan>,
+    pub result_bundle_path: PathBuf,
+    /// The tests that ran, read from the result bundle; `None` in dry-run mode or when the
+    /// bundle could not be read. With retries, retried tests have the outcome of their last run.
+    pub results: Option<TestResults>,
+    /// The result bundles of the retries of failed tests, next to `result_bundle_path`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub retry_result_bundle_paths: Vec<PathBuf>,
+    /// The tests that failed but passed when retried.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub flaky_tests: Vec<FlakyTest>,
+    pub junit_path: Option<PathBuf>,
+    pub summary_path: Option<PathBuf>,
+    /// Warnings found in xcodebuild's output; reported at the top level of the JSON report.
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+impl std::fmt::Display for TestResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.configuration {
+            Some(configuration) => writeln!(
+                f,
+                "Tests passed for scheme {} ({}) with {} warning(s)",
+                self.scheme,
+                configuration,
+                self.warnings.len()
+            )?,
+            None => writeln!(
+                f,
+                "Tests passed for scheme {} with {} warning(s)",
+                self.scheme,
+                self.warnings.len()
+            )?,
+        }
+        if let Some(xctestrun_path) = &self.xctestrun_path {
+            writeln!(f, "xctestrun: {}", xctestrun_path.display())?;
+        }
+        if let Some(shard) = &self.shard {
+            writeln!(f, "{}", shard)?;
+        }
+        if let Some(results) = &self.results {
+            writeln!(f, "{}", results.totals())?;
+        }
+        if !self.flaky_tests.is_empty() {
+            writeln!(f, "Flaky tests, passed on retry:")?;
+            for flaky_test in &self.flaky_tests {
+                writeln!(f, "  {}", flaky_test)?;
+            }
+        }
+        writeln!(f, "Result bundle: {}", self.result_bundle_path.display())?;
+        if let Some(junit_path) = &self.junit_path {
+            writeln!(f, "JUnit report: {}", junit_path.display())?;
+        }
+        if let Some(summary_path) = &self.summary_path {
+            writeln!(f, "Summary: {}", summary_path.display())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CommandResult for TestResult {
+    fn warnings(&self) -> Vec<String> {
+        self.warnings.clone()
+    }
+}
+
+/// The outcome of [`build_for_testing`]: test products packaged for [`test_without_building`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestProducts {
+    pub scheme: String,
+    /// The configuration name passed to xcodebuild, e.g. `Debug`.
+    pub configuration: String,
+    pub sdk: Option<String>,
+    pub destinations: Vec<String>,
+    pub derived_data_path: PathBuf,
+    pub artifact_directory: PathBuf,
+    /// The `.xctestrun` files in `artifact_directory`, one per test plan and platform; empty in
+    /// dry-run mode.
+    pub xctestrun_paths: Vec<PathBuf>,
+    /// Warnings found in xcodebuild's output and while packaging; reported at the top level of
+    /// the JSON report.
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+impl std::fmt::Display for TestProducts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Built tests for scheme {} ({}) with {} warning(s)",
+            self.scheme,
+            self.configuration,
+            self.warnings.len()
+        )?;
+        writeln!(f, "Test products: {}", self.artifact_directory.display())?;
+        for xctestrun_path in &self.xctestrun_paths {
+            writeln!(f, "xctestrun: {}", xctestrun_path.display())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CommandResult for TestProducts {
+    fn warnings(&self) -> Vec<String> {
+        self.warnings.clone()
+    }
+}
 
 /// Runs tests for an Xcode project or workspace using the `xcodebuild` command-line tool.
 ///
@@ -12,35 +284,51 @@ use xcbuild_common::{
 /// # Arguments
 ///
 /// * `scheme` - The Xcode scheme name to test (e.g., "MyApp", "MyAppTests", "MyAppUITests")
-/// * `destination` - The test destination specifying the target device or simulator:
-///   - iOS Simulator: "iOS Simulator,name=iPhone 15 Pro"
+/// * `destinations` - One or more test destinations specifying the target device or simulator,
+///   each passed to xcodebuild with `-destination`:
+///   - iOS Simulator: "platform=iOS Simulator,name=iPhone 15 Pro"
 ///   - Generic iOS: "generic/platform=iOS"
 ///   - macOS: "platform=macOS"
 /// * `configuration` - The build configuration to use (Debug or Release)
+/// * `sdk` - Optional SDK passed to xcodebuild with `-sdk` (e.g. `SDK::Iphonesimulator`)
 /// * `project` - Optional path to the Xcode project file (.xcodeproj). Either this or
 ///   `workspace` must be provided, but not both.
 /// * `workspace` - Optional path to the Xcode workspace file (.xcworkspace). Either this or
 ///   `project` must be provided, but not both.
+/// * `overrides` - Build setting overrides (`KEY=VALUE` pairs and an optional `.xcconfig` file)
+///   appended to the xcodebuild command
+/// * `options` - The tests to run, the result bundle path and the JUnit and Markdown reports to
+///   write; see [`TestOptions`]
+///
+/// xcodebuild always writes a result bundle (`-resultBundlePath`). Once it finishes, the test
+/// tree is read from the bundle with `xcrun xcresulttool` and the requested reports are written,
+/// whether the tests passed or not.
+///
+/// With [`TestOptions::retry_failed`], failed tests are re-run before the reports are written.
 ///
 /// # Returns
 ///
-/// Returns `Ok(String)` containing the stdout from the xcodebuild test command on success,
-/// or `Err` if the tests fail or if neither project nor workspace is specified.
+/// Returns `Ok(TestResult)` describing the run, including the warnings xcodebuild reported and
+/// the tests that ran, or `Err` if the tests fail, listing the failed tests, or if neither
+/// project nor workspace is specified.
 ///
 /// # Examples
 ///
 /// ## Testing parameter validation - neither project nor workspace:
 /// ```rust
-/// use xctools_test::test;
-/// use xcbuild_common::Configuration;
+/// use xctools_test::{TestOptions, test};
+/// use xcbuild_common::{BuildOverrides, Configuration};
 ///
 /// // This should fail because neither project nor workspace is specified
 /// let result = test(
 ///     &"MyAppTests".to_string(),
-///     &"iOS Simulator,name=iPhone 15 Pro".to_string(),
+///     &["platform=iOS Simulator,name=iPhone 15 Pro".parse().unwrap()],
 ///     &Configuration::Debug,
 ///     &None,
 ///     &None,
+///     &None,
+///     &BuildOverrides::new(),
+///     &TestOptions::new(),
 /// );
 /// assert!(result.is_err());
 /// let error_msg = result.unwrap_err().to_string();
@@ -60,17 +348,20 @@ use xcbuild_common::{
 ///
 /// ## Testing with project parameter (will attempt to run tests):
 /// ```rust,no_run
-/// use xctools_test::test;
-/// use xcbuild_common::Configuration;
+/// use xctools_test::{TestOptions, test};
+/// use xcbuild_common::{BuildOverrides, Configuration};
 ///
 /// // This example shows the function signature but doesn't run
 /// // because it would try to execute xcodebuild test with a non-existent project
 /// let result = test(
 ///     &"MyAppTests".to_string(),
-///     &"iOS Simulator,name=iPhone 15 Pro".to_string(),
+///     &["platform=iOS Simulator,name=iPhone 15 Pro".parse().unwrap()],
 ///     &Configuration::Debug,
+///     &None,
 ///     &Some("MyApp.xcodeproj".to_string()),
 ///     &None,
+///     &BuildOverrides::new(),
+///     &TestOptions::new(),
 /// );
 /// // In a real scenario with a valid project, this would either succeed or
 /// // fail based on the actual test results
@@ -78,17 +369,20 @@ use xcbuild_common::{
 ///
 /// ## Testing with workspace parameter (will attempt to run tests):
 /// ```rust,no_run
-/// use xctools_test::test;
-/// use xcbuild_common::Configuration;
+/// use xctools_test::{TestOptions, test};
+/// use xcbuild_common::{BuildOverrides, Configuration};
 ///
 /// // This example shows the function signature but doesn't run
 /// // because it would try to execute xcodebuild test with a non-existent workspace
 /// let result = test(
 ///     &"MyAppUITests".to_string(),
-///     &"generic/platform=iOS".to_string(),
+///     &["generic/platform=iOS".parse().unwrap()],
 ///     &Configuration::Release,
 ///     &None,
+///     &None,
 ///     &Some("MyApp.xcworkspace".to_string()),
+///     &BuildOverrides::new(),
+///     &TestOptions::new(),
 /// );
 /// // In a real scenario with a valid workspace, this would either succeed or
 /// // fail based on the actual test results
@@ -97,7 +391,7 @@ use xcbuild_common::{
 /// ## Using the xctools CLI:
 /// ```bash
 /// # Run unit tests with project file and Debug configuration (default)
-/// xctools test --scheme MyAppTests --destination "iOS Simulator,name=iPhone 15 Pro" --project MyApp.xcodeproj
+/// xctools test --scheme MyAppTests --destination "platform=iOS Simulator,name=iPhone 15 Pro" --project MyApp.xcodeproj
 ///
 /// # Run UI tests with workspace file and Release configuration
 /// xctools test --scheme MyAppUITests --destination "generic/platform=iOS" --workspace MyApp.xcworkspace --configuration release
@@ -106,14 +400,26 @@ use xcbuild_common::{
 /// xctools test --scheme MyAppTests --destination "platform=macOS" --project MyApp.xcodeproj
 ///
 /// # Run all test schemes
-/// xctools test --scheme MyApp --destination "iOS Simulator,name=iPhone 15 Pro" --project MyApp.xcodeproj
+/// xctools test --scheme MyApp --destination "platform=iOS Simulator,name=iPhone 15 Pro" --project MyApp.xcodeproj
+///
+/// # Write JUnit XML for the CI and a Markdown summary for the job page
+/// xctools test --scheme MyAppTests --destination "platform=macOS" --project MyApp.xcodeproj --junit build/junit.xml --summary "$GITHUB_STEP_SUMMARY"
+///
+/// # Run one test class from the Smoke test plan
+/// xctools test --scheme MyApp --destination "platform=macOS" --project MyApp.xcodeproj --test-plan Smoke --only-testing MyAppTests/LoginTests
+///
+/// # Run the second of five shards of the test classes listed by xcodebuild -enumerate-tests
+/// xctools test --scheme MyApp --destination "platform=macOS" --project MyApp.xcodeproj --shard 2/5 --shard-tests build/tests.json
+///
+/// # Retry failed tests twice, reporting those that pass on a retry as flaky
+/// xctools test --scheme MyApp --destination "platform=macOS" --project MyApp.xcodeproj --retry-failed 2
 /// ```
 ///
 /// # Generated Command
 ///
 /// The function generates an xcodebuild command in the format:
 /// ```bash
-/// xcodebuild test -project MyApp.xcodeproj -scheme MyAppTests -destination 'iOS Simulator,name=iPhone 15 Pro' -configuration Debug
+/// xcodebuild test -project MyApp.xcodeproj -scheme MyAppTests -destination 'platform=iOS Simulator,name=iPhone 15 Pro' -configuration Debug -resultBundlePath .xctools/results/MyAppTests.xcresult
 /// ```
 ///
 /// # Test Types
@@ -132,23 +438,821 @@ use xcbuild_common::{
 /// - The destination must be valid for the target platform
 /// - Test targets must be properly configured in the Xcode project
 /// - For simulator testing, the specified simulator must be available
+#[allow(clippy::too_many_arguments)]
 pub fn test(
-    scheme: &String,
-    destination: &String,
+    scheme: &str,
+    destinations: &[Destination],
     configuration: &Configuration,
+    sdk: &Option<SDK>,
     project: &Option<String>,
     workspace: &Option<String>,
-) -> Result<String> {
+    overrides: &BuildOverrides,
+    options: &TestOptions,
+) -> Result<TestResult> {
     let target = BuildTarget::new(project.as_ref(), workspace.as_ref());
+    // Reject an invalid selection before the previous result bundle is removed.
+    options.test_selection.arguments()?;
+    let shard = plan_shard(options, None)?;
+    let result_bundle_path = result_bundle_path(scheme, options);
     let params = XcodebuildParams::new(XcodebuildCommandAction::Test)
-        .with_scheme(scheme.clone())
-        .with_destination(destination.clone())
+        .with_scheme(scheme.to_string())
+        .with_destinations(destinations.to_vec())
         .with_configuration(configuration.clone())
-        .with_target(target);
+        .with_target(target)
+        .with_result_bundle_path(result_bundle_path.to_string_lossy().to_string())
+        .with_test_selection(shard_selection(options, &shard)?)
+        .with_overrides(overrides.clone());
+    let params = match sdk {
+        Some(sdk) => params.with_sdk(sdk.clone()),
+        None => params,
+    };
+    let result = TestResult {
+        scheme: scheme.to_string(),
+        configuration: Some(configuration.command_string()),
+        sdk: sdk.as_ref().map(SDK::command_string),
+        destinations: destinations.iter().map(ToString::to_string).collect(),
+        xctestrun_path: None,
+        shard,
+        result_bundle_path,
+        results: None,
+        retry_result_bundle_paths: Vec::new(),
+        flaky_tests: Vec::new(),
+        junit_path: None,
+        summary_path: None,
+        warnings: Vec::new(),
+    };
+
+    run_tests(&params, result, options)
+}
+
+/// Runs tests built by [`build_for_testing`] from an `.xctestrun` file with
+/// `xcodebuild test-without-building`, typically on another machine than the one that built
+/// them.
+///
+/// The result bundle and reports work as for [`test`]; the bundle is named after the scheme the
+/// `.xctestrun` file was built from. Tests can be narrowed down with `--only-testing`,
+/// `--skip-testing` and the test configuration options, but the test plan is fixed by the file.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::path::Path;
+/// use xctools_test::{TestOptions, test_without_building};
+///
+/// let result = test_without_building(
+///     Path::new("missing/MyApp_iphonesimulator18.2-arm64.xctestrun"),
+///     &["platform=iOS Simulator,name=iPhone 16".parse().unwrap()],
+///     &TestOptions::new(),
+/// );
+/// assert!(result.unwrap_err().to_string().starts_with("xctestrun file not found"));
+/// ```
+///
+/// ```bash
+/// xctools test --from-xctestrun test-products/MyApp_iphonesimulator18.2-arm64.xctestrun --destination "platform=iOS Simulator,name=iPhone 16"
+/// ```
+pub fn test_without_building(
+    xctestrun_path: &Path,
+    destinations: &[Destination],
+    options: &TestOptions,
+) -> Result<TestResult> {
+    if options.test_selection.test_plan.is_some() {
+        bail!("A test plan cannot be chosen for an .xctestrun file, which names its own");
+    }
+    options.test_selection.arguments()?;
+    let xctestrun = fs::read(xctestrun_path)
+        .map_err(|_| anyhow::anyhow!("xctestrun file not found: {}", xctestrun_path.display()))?;
+    let scheme = products::xctestrun_scheme(&String::from_utf8_lossy(&xctestrun))
+        .or_else(|| {
+            xctestrun_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+        })
+        .unwrap_or_default();
+    let shard = plan_shard(options, Some((xctestrun_path, destinations)))?;
+    let result_bundle_path = result_bundle_path(&scheme, options);
+    let params = XcodebuildParams::new(XcodebuildCommandAction::TestWithoutBuilding)
+        .with_destinations(destinations.to_vec())
+        .with_xctestrun_path(xctestrun_path.to_string_lossy().to_string())
+        .with_result_bundle_path(result_bundle_path.to_string_lossy().to_string())
+        .with_test_selection(shard_selection(options, &shard)?);
+    let result = TestResult {
+        scheme,
+        configuration: None,
+        sdk: None,
+        destinations: destinations.iter().map(ToString::to_string).collect(),
+        xctestrun_path: Some(xctestrun_path.to_path_buf()),
+        shard,
+        result_bundle_path,
+        results: None,
+        retry_result_bundle_paths: Vec::new(),
+        flaky_tests: Vec::new(),
+        junit_path: None,
+        summary_path: None,
+        warnings: Vec::new(),
+    };
+
+    run_tests(&params, result, options)
+}
+
+/// Builds the tests of a scheme with `xcodebuild build-for-testing` without running them, and
+/// packages the products with their `.xctestrun` files into a directory that can be copied to
+/// other machines and run there with [`test_without_building`].
+///
+/// The build uses its own derived data directory under [`DEFAULT_DERIVED_DATA_DIRECTORY`], from
+/// which the whole `Build/Products` directory is copied into the artifact directory (see
+/// [`TestOptions::artifact_directory`]). Absolute paths in the `.xctestrun` files are rewritten
+/// relative to the file with `__TESTROOT__`; see [`package_test_products`].
+///
+/// Takes the same arguments as [`test`]; of the `options`, the test selection limits what is
+/// built and the artifact directory is used.
+///
+/// # Examples
+///
+/// ```bash
+/// # Build once, with a generic destination covering every simulator
+/// xctools test --build-only --scheme MyApp --destination "generic/platform=iOS Simulator" --project MyApp.xcodeproj --artifact-dir test-products
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn build_for_testing(
+    scheme: &str,
+    destinations: &[Destination],
+    configuration: &Configuration,
+    sdk: &Option<SDK>,
+    project: &Option<String>,
+    workspace: &Option<String>,
+    overrides: &BuildOverrides,
+    options: &TestOptions,
+) -> Result<TestProducts> {
+    let target = BuildTarget::new(project.as_ref(), workspace.as_ref());
+    options.test_selection.arguments()?;
+    let derived_data_path = Path::new(DEFAULT_DERIVED_DATA_DIRECTORY).join(scheme);
+    let artifact_directory = match &options.artifact_directory {
+        Some(artifact_directory) => artifact_directory.clone(),
+        None => Path::new(DEFAULT_ARTIFACT_DIRECTORY).join(scheme),
+    };
+    let dry_run = command_runner().is_dry_run();
+    if !dry_run {
+        prepare_artifact_directory(&artifact_directory, options.artifact_directory.is_none())?;
+    }
+    let params = XcodebuildParams::new(XcodebuildCommandAction::BuildForTesting)
+        .with_scheme(scheme.to_string())
+        .with_destinations(destinations.to_vec())
+        .with_configuration(configuration.clone())
+        .with_target(target)
+        .with_derived_data_path(derived_data_path.to_string_lossy().to_string())
+        .with_test_selection(options.test_selection.clone())
+        .with_overrides(overrides.clone());
+    let params = match sdk {
+        Some(sdk) => params.with_sdk(sdk.clone()),
+        None => params,
+    };
     let output = run_xcodebuild_command(&params)?;
+    let mut products = TestProducts {
+        scheme: scheme.to_string(),
+        configuration: configuration.command_string(),
+        sdk: sdk.as_ref().map(SDK::command_string),
+        destinations: destinations.iter().map(ToString::to_string).collect(),
+        artifact_directory,
+        xctestrun_paths: Vec::new(),
+        warnings: xcodebuild_warnings(&output),
+        derived_data_path,
+    };
+    if dry_run {
+        return Ok(products);
+    }
+
+    let packaged = package_test_products(
+        &products.derived_data_path.join("Build/Products"),
+        &products.artifact_directory,
+    )?;
+    products.xctestrun_paths = packaged.xctestrun_paths;
+    products.warnings.extend(packaged.warnings);
+
+    Ok(products)
+}
+
+/// The result bundle for a run of `scheme`: the one given in `options`, or `SCHEME.xcresult` in
+/// [`DEFAULT_RESULT_BUNDLE_DIRECTORY`].
+fn result_bundle_path(scheme: &str, options: &TestOptions) -> PathBuf {
+    match &options.result_bundle_path {
+        Some(result_bundle_path) => result_bundle_path.clone(),
+        None => Path::new(DEFAULT_RESULT_BUNDLE_DIRECTORY).join(format!("{}.xcresult", scheme)),
+    }
+}
+
+/// Chooses the test classes of [`TestOptions::shard`] from [`TestOptions::shard_tests`], or from
+/// the tests of the `.xctestrun` file and destinations given in `xctestrun`.
+fn plan_shard(
+    options: &TestOptions,
+    xctestrun: Option<(&Path, &[Destination])>,
+) -> Result<Option<ShardPlan>> {
+    let Some(shard) = options.shard else {
+        return Ok(None);
+    };
+    if !options.test_selection.only_testing.is_empty() {
+        bail!("--shard cannot be combined with --only-testing; the shard chooses the tests to run");
+    }
+    let tests = match (&options.shard_tests, xctestrun) {
+        (Some(shard_tests), _) => read_test_list(shard_tests)?,
+        (None, Some((xctestrun_path, destinations))) => enumerate_tests(
+            xctestrun_path,
+            destinations,
+            &options.test_selection.arguments()?,
+        )?,
+        (None, None) => {
+            bail!("--shard needs --shard-tests, or --from-xctestrun to enumerate the tests")
+        }
+    };
+    // A file local to each machine would drift apart between them, and so would their shards.
+    let timings = match &options.timings_path {
+        Some(timings_path) => TestTimings::load(timings_path)?,
+        None => TestTimings::default(),
+    };
+    let plan = ShardPlan::new(shard, &tests, &timings);
+    eprintln!("==> {}", plan);
+
+    Ok(Some(plan))
+}
+
+/// The test selection of `options`, running only the classes of `shard` if there is one.
+fn shard_selection(options: &TestOptions, shard: &Option<ShardPlan>) -> Result<TestSelection> {
+    let mut selection = options.test_selection.clone();
+    if let Some(shard) = shard {
+        selection.only_testing = shard
+            .classes
+            .iter()
+            .map(|class| class.parse::<TestIdentifier>().map_err(anyhow::Error::msg))
+            .collect::<Result<_>>()?;
+    }
+
+    Ok(selection)
+}
+
+/// Runs xcodebuild with `params`, then reads the test results from the result bundle into
+/// `result` and writes the reports `options` asks for.
+fn run_tests(
+    params: &XcodebuildParams,
+    mut result: TestResult,
+    options: &TestOptions,
+) -> Result<TestResult> {
+    let dry_run = command_runner().is_dry_run();
+    // Without -only-testing xcodebuild would run every test, so an empty shard runs nothing.
+    if let Some(shard) = &result.shard
+        && shard.classes.is_empty()
+    {
+        result.warnings.push(format!(
+            "Shard {} has no test classes to run; there are {} in total",
+            shard.shard, shard.total_classes
+        ));
+        if !dry_run {
+            write_reports(
+                &mut result,
+                &TestResults {
+                    targets: Vec::new(),
+                },
+                options,
+            )?;
+        }
+        return Ok(result);
+    }
+    if !dry_run {
+        prepare_result_bundle_path(
+            &result.result_bundle_path,
+            options.result_bundle_path.is_none(),
+        )?;
+    }
+    let mut outcome = run_xcodebuild_command(params);
+    if let Ok(output) = &outcome {
+        result.warnings = xcodebuild_warnings(output);
+    }
+    if dry_run {
+        return outcome.map(|_| result);
+    }
+
+    // A build failure leaves no bundle, or one without tests, so only its error is reported.
+    if result.result_bundle_path.exists() {
+        match read_test_results(&result.result_bundle_path) {
+            Ok(results) => result.results = Some(results),
+            Err(error) if options.writes_reports() && outcome.is_ok() => {
+                return Err(error.context("Failed to read the test results for the reports"));
+            }
+            Err(error) => result.warnings.push(format!(
+                "Could not read the test results from {}: {:#}",
+                result.result_bundle_path.display(),
+                error
+            )),
+        }
+    } else if options.writes_reports() && outcome.is_ok() {
+        bail!(
+            "xcodebuild did not write a result bundle to {}",
+            result.result_bundle_path.display()
+        );
+    }
+
+    if options.retry_failed > 0 && outcome.is_err() {
+        outcome = retry_failed_tests(params, &mut result, options, outcome)?;
+    }
+    if let Some(results) = result.results.take() {
+        write_reports(&mut result, &results, options)?;
+        if let Some(timings_path) = &options.timings_path
+            && let Err(error) = record_timings(&results, timings_path)
+        {
+            result
+                .warnings
+                .push(format!("Could not record test durations: {:#}", error));
+        }
+        if options.retry_failed > 0
+            && let Err(error) =
+                record_flakes(&results, &result.flaky_tests, options.flake_history_path())
+        {
+            result
+                .warnings
+                .push(format!("Could not record flaky tests: {:#}", error));
+        }
+        result.results = Some(results);
+    }
+
+    match (outcome, &result.results) {
+        (Ok(_), _) => Ok(result),
+        (Err(error), Some(results)) if results.totals().failed > 0 => {
+            Err(error.context(failed_tests_message(results)))
+        }
+        (Err(error), _) => Err(error),
+    }
+}
+
+/// Re-runs the failed tests of `result` up to [`TestOptions::retry_failed`] times, each time
+/// only those still failing, and merges the outcome of every retry into its results. Tests that
+/// pass on a retry become [`TestResult::flaky_tests`]. Returns the outcome of the last run.
+///
+/// Each retry writes its own result bundle next to the first one, replacing that of a previous
+/// run: `MyApp-retry-1.xcresult` for `MyApp.xcresult`.
+fn retry_failed_tests(
+    params: &XcodebuildParams,
+    result: &mut TestResult,
+    options: &TestOptions,
+    mut outcome: Result<String>,
+) -> Result<Result<String>> {
+    // A flaky test is reported with the failures of its first run, not those of a later retry.
+    let first_failures = result
+        .results
+        .as_ref()
+        .map(failures_by_identifier)
+        .unwrap_or_default();
+    for retry in 1..=options.retry_failed {
+        let Some(results) = &result.results else {
+            break;
+        };
+        let failed = failed_test_identifiers(results);
+        if outcome.is_ok() || failed.is_empty() {
+            break;
+        }
+        eprintln!(
+            "==> Retrying {} failed test(s), retry {} of {}",
+            failed.len(),
+            retry,
+            options.retry_failed
+        );
+
+        let result_bundle_path = retry_result_bundle_path(&result.result_bundle_path, retry);
+        prepare_result_bundle_path(&result_bundle_path, true)?;
+        let mut test_selection = params.test_selection.clone();
+        test_selection.only_testing = failed
+            .iter()
+            .map(|identifier| retry_identifier(identifier))
+            .collect::<Result<_>>()?;
+        let retry_params = params
+            .clone()
+            .with_result_bundle_path(result_bundle_path.to_string_lossy().to_string())
+            .with_test_selection(test_selection);
+        outcome = run_xcodebuild_command(&retry_params);
+        if let Ok(output) = &outcome {
+            result.warnings.extend(xcodebuild_warnings(output));
+        }
+        let retried = match read_test_results(&result_bundle_path) {
+            Ok(retried) => retried,
+            Err(error) => {
+                result.warnings.push(format!(
+                    "Could not read the test results of retry {} from {}: {:#}",
+                    retry,
+                    result_bundle_path.display(),
+                    error
+                ));
+                break;
+            }
+        };
+        result.retry_result_bundle_paths.push(result_bundle_path);
+        if let Some(results) = &mut result.results {
+            merge_retry(
+                results,
+                &retried,
+                &failed,
+                retry + 1,
+                &first_failures,
+                &mut result.flaky_tests,
+            );
+        }
+    }
+
+    if !result.flaky_tests.is_empty() {
+        eprintln!(
+            "==> {} flaky test(s) passed on retry:",
+            result.flaky_tests.len()
+        );
+        for flaky_test in &result.flaky_tests {
+            eprintln!("  {}", flaky_test);
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// The failures of every failed test of `results`, keyed by `Target/Class/test()`.
+fn failures_by_identifier(results: &TestResults) -> BTreeMap<String, Vec<TestFailure>> {
+    results
+        .cases()
+        .filter(|(_, _, case)| case.status == TestStatus::Failed)
+        .map(|(target, _, case)| {
+            (
+                format!("{}/{}", target.name, case.identifier),
+                case.failures.clone(),
+            )
+        })
+        .collect()
+}
+
+/// The failed tests of `results`, as `Target/Class/test()`.
+fn failed_test_identifiers(results: &TestResults) -> Vec<String> {
+    results
+        .cases()
+        .filter(|(_, _, case)| case.status == TestStatus::Failed)
+        .map(|(target, _, case)| format!("{}/{}", target.name, case.identifier))
+        .collect()
+}
+
+/// The `-only-testing` identifier that re-runs a failed test. A test of a nested Swift Testing
+/// suite is deeper than `-only-testing` reaches, so its outermost suite is re-run instead.
+fn retry_identifier(identifier: &str) -> Result<TestIdentifier> {
+    let identifier = match identifier.split('/').count() {
+        0..=3 => identifier.to_string(),
+        _ => test_class(identifier),
+    };
+
+    identifier
+        .parse::<TestIdentifier>()
+        .map_err(anyhow::Error::msg)
+}
+
+/// `MyApp-retry-1.xcresult` for `MyApp.xcresult` and retry 1.
+fn retry_result_bundle_path(result_bundle_path: &Path, retry: u32) -> PathBuf {
+    let stem = result_bundle_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+
+    result_bundle_path.with_file_name(format!("{}-retry-{}.xcresult", stem, retry))
+}
+
+/// Replaces the tests in `failed` with their outcome in `retried`, the results of the run
+/// numbered `attempt`, and adds those that passed to `flaky_tests` with their failures in
+/// `first_failures`.
+fn merge_retry(
+    results: &mut TestResults,
+    retried: &TestResults,
+    failed: &[String],
+    attempt: u32,
+    first_failures: &BTreeMap<String, Vec<TestFailure>>,
+    flaky_tests: &mut Vec<FlakyTest>,
+) {
+    for target in &mut results.targets {
+        for suite in &mut target.suites {
+            for case in &mut suite.cases {
+                let identifier = format!("{}/{}", target.name, case.identifier);
+                if !failed.contains(&identifier) {
+                    continue;
+                }
+                let Some((_, _, retried_case)) =
+                    retried.cases().find(|(retried_target, _, retried_case)| {
+                        retried_target.name == target.name
+                            && retried_case.identifier == case.identifier
+                    })
+                else {
+                    continue;
+                };
+                if matches!(
+                    retried_case.status,
+                    TestStatus::Passed | TestStatus::ExpectedFailure
+                ) {
+                    let failures = first_failures
+                        .get(&identifier)
+                        .cloned()
+                        .unwrap_or_else(|| case.failures.clone());
+                    flaky_tests.push(FlakyTest {
+                        identifier,
+                        passed_on_attempt: attempt,
+                        failures,
+                    });
+                }
+                *case = retried_case.clone();
+            }
+        }
+    }
+}
+
+/// Adds a run with retries to the flake history file.
+fn record_flakes(
+    results: &TestResults,
+    flaky_tests: &[FlakyTest],
+    flake_history_path: &Path,
+) -> Result<()> {
+    let mut history = FlakeHistory::load(flake_history_path)?;
+    history.record(results, flaky_tests);
+
+    history.save(flake_history_path)
+}
+
+/// Updates the durations in the timings file with those of `results`.
+fn record_timings(results: &TestResults, timings_path: &Path) -> Result<()> {
+    let mut timings = TestTimings::load(timings_path)?;
+    timings.record(results);
+
+    timings.save(timings_path)
+}
+
+/// Removes the products of the previous build from the default artifact directory. A directory
+/// given by the caller is never removed, so it must be empty.
+fn prepare_artifact_directory(artifact_directory: &Path, is_default: bool) -> Result<()> {
+    if is_default && artifact_directory.exists() {
+        fs::remove_dir_all(artifact_directory).with_context(|| {
+            format!(
+                "Failed to remove the previous test products in {}",
+                artifact_directory.display()
+            )
+        })?;
+    } else if fs::read_dir(artifact_directory).is_ok_and(|mut entries| entries.next().is_some()) {
+        bail!(
+            "Artifact directory {} is not empty",
+            artifact_directory.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Creates the bundle's parent directory. xcodebuild refuses to overwrite a bundle, so the
+/// default bundle of the previous run is removed; one at a path given by the caller is left for
+/// xcodebuild to report.
+fn prepare_result_bundle_path(result_bundle_path: &Path, is_default: bool) -> Result<()> {
+    if let Some(parent) = result_bundle_path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    if is_default && result_bundle_path.exists() {
+        fs::remove_dir_all(result_bundle_path).with_context(|| {
+            format!(
+                "Failed to remove the previous result bundle {}",
+                result_bundle_path.display()
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+fn write_reports(
+    result: &mut TestResult,
+    results: &TestResults,
+    options: &TestOptions,
+) -> Result<()> {
+    if let Some(junit_path) = &options.junit_path {
+        write_report(junit_path, &junit_report(results, &result.scheme))?;
+        result.junit_path = Some(junit_path.clone());
+    }
+    if let Some(summary_path) = &options.summary_path {
+        let title = format!("Tests for {}", result.scheme);
+        write_report(summary_path, &markdown_summary(results, &title))?;
+        result.summary_path = Some(summary_path.clone());
+    }
+
+    Ok(())
+}
 
-    Ok(output)
+fn write_report(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Lists the failed tests with their first failure, e.g.
+/// `MyAppTests.LoginTests/testLogin() (LoginTests.swift:42: XCTAssertTrue failed)`.
+fn failed_tests_message(results: &TestResults) -> String {
+    let totals = results.totals();
+    let failed: Vec<String> = results
+        .cases()
+        .filter(|(_, _, case)| case.status == TestStatus::Failed)
+        .map(|(target, suite, case)| {
+            let name = format!("{}.{}/{}", target.name, suite.name, case.name);
+            match case.failures.first() {
+                Some(failure) => match failure.location() {
+                    Some(location) => format!("{} ({}: {})", name, location, failure.message),
+                    None => format!("{} ({})", name, failure.message),
+                },
+                None => name,
+            }
+        })
+        .collect();
+
+    let mut message = format!("{} of {} tests failed:", totals.failed, totals.total);
+    for test in failed.iter().take(FAILED_TESTS_IN_ERROR) {
+        message.push_str("\n  ");
+        message.push_str(test);
+    }
+    if failed.len() > FAILED_TESTS_IN_ERROR {
+        message.push_str(&format!(
+            "\n  and {} more",
+            failed.len() - FAILED_TESTS_IN_ERROR
+        ));
+    }
+
+    message
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_tests_message() {
+        let results =
+            TestResults::parse(include_str!("../tests/fixtures/xcresult_tests.json")).unwrap();
+
+        assert_eq!(
+            failed_tests_message(&results),
+            "2 of 6 tests failed:\n  \
+             MyAppTests.LoginTests/testLoginWithExpiredToken() (LoginTests.swift:42: \
+             XCTAssertEqual failed: (\"401\") is not equal to (\"200\") - <login> & \"retry\")\n  \
+             MyAppUITests.CheckoutUITests/testCheckout() (CheckoutUITests.swift:18: Failed to \
+             tap \"Pay\" Button: No matches found)"
+        );
+    }
+
+    #[test]
+    fn test_merge_retry_marks_tests_passing_on_retry_as_flaky() {
+        let mut results =
+            TestResults::parse(include_str!("../tests/fixtures/xcresult_tests.json")).unwrap();
+        let failed = failed_test_identifiers(&results);
+        assert_eq!(
+            failed,
+            [
+                "MyAppTests/LoginTests/testLoginWithExpiredToken()",
+                "MyAppUITests/CheckoutUITests/testCheckout()",
+            ]
+        );
+        // The retry runs only the failed tests; the checkout test passes this time.
+        let mut retried = results.clone();
+        retried.targets[0]
+            .suites
+            .retain(|suite| suite.name == "LoginTests");
+        retried.targets[0].suites[0]
+            .cases
+            .retain(|case| case.status == TestStatus::Failed);
+        let checkout = &mut retried.targets[1].suites[0].cases[0];
+        checkout.status = TestStatus::Passed;
+        checkout.failures.clear();
+        checkout.duration_seconds = Some(9.5);
+        let first_failures = failures_by_identifier(&results);
+        let mut flaky_tests = Vec::new();
+
+        merge_retry(
+            &mut results,
+            &retried,
+            &failed,
+            2,
+            &first_failures,
+            &mut flaky_tests,
+        );
+
+        assert_eq!(flaky_tests.len(), 1);
+        assert_eq!(
+            flaky_tests[0].identifier,
+            "MyAppUITests/CheckoutUITests/testCheckout()"
+        );
+        assert_eq!(flaky_tests[0].passed_on_attempt, 2);
+        assert_eq!(flaky_tests[0].failures[0].line, Some(18));
+        assert!(
+            flaky_tests[0]
+                .to_string()
+                .starts_with("MyAppUITests/CheckoutUITests/testCheckout() (passed on attempt 2), first failed at CheckoutUITests.swift:18:")
+        );
+        assert_eq!(results.totals().failed, 1);
+        assert_eq!(results.totals().total, 6);
+        assert_eq!(
+            results.targets[1].suites[0].cases[0].duration_seconds,
+            Some(9.5)
+        );
+        assert_eq!(
+            failed_test_identifiers(&results),
+            ["MyAppTests/LoginTests/testLoginWithExpiredToken()"]
+        );
+    }
+
+    #[test]
+    fn test_merge_retry_keeps_first_run_failures_of_tests_passing_on_a_later_retry() {
+        let mut results =
+            TestResults::parse(include_str!("../tests/fixtures/xcresult_tests.json")).unwrap();
+        let first_failures = failures_by_identifier(&results);
+        let checkout = "MyAppUITests/CheckoutUITests/testCheckout()".to_string();
+        let first_run = results.clone();
+        let retry = |status: TestStatus, message: &str| {
+            let mut retried = first_run.clone();
+            retried
+                .targets
+                .retain(|target| target.name == "MyAppUITests");
+            let case = &mut retried.targets[0].suites[0].cases[0];
+            case.status = status;
+            for failure in &mut case.failures {
+                failure.message = message.to_string();
+                failure.line = Some(99);
+            }
+            if status == TestStatus::Passed {
+                case.failures.clear();
+            }
+            retried
+        };
+        let mut flaky_tests = Vec::new();
+
+        // The checkout test fails on the first retry with a different failure, then passes.
+        let failed_again = retry(TestStatus::Failed, "Timed out waiting for the pay button");
+        merge_retry(
+            &mut results,
+            &failed_again,
+            std::slice::from_ref(&checkout),
+            2,
+            &first_failures,
+            &mut flaky_tests,
+        );
+        assert!(flaky_tests.is_empty());
+        assert_eq!(
+            results.targets[1].suites[0].cases[0].failures[0].line,
+            Some(99)
+        );
+        let passed = retry(TestStatus::Passed, "");
+        merge_retry(
+            &mut results,
+            &passed,
+            std::slice::from_ref(&checkout),
+            3,
+            &first_failures,
+            &mut flaky_tests,
+        );
+
+        assert_eq!(flaky_tests.len(), 1);
+        assert_eq!(flaky_tests[0].identifier, checkout);
+        assert_eq!(flaky_tests[0].passed_on_attempt, 3);
+        assert_eq!(flaky_tests[0].failures, first_failures[&checkout]);
+        assert_eq!(flaky_tests[0].failures[0].line, Some(18));
+    }
+
+    #[test]
+    fn test_retry_identifier_and_result_bundle_path() {
+        assert_eq!(
+            retry_identifier("MyAppTests/LoginTests/testLogin()")
+                .unwrap()
+                .to_string(),
+            "MyAppTests/LoginTests/testLogin()"
+        );
+        assert_eq!(
+            retry_identifier("MyAppTests/Formatting/Currency/parsesAmounts()")
+                .unwrap()
+                .to_string(),
+            "MyAppTests/Formatting"
+        );
+        assert_eq!(
+            retry_result_bundle_path(Path::new(".xctools/results/MyApp.xcresult"), 2),
+            Path::new(".xctools/results/MyApp-retry-2.xcresult")
+        );
+    }
+
+    #[test]
+    fn test_prepare_result_bundle_path_replaces_default_bundle_only() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bundle = temp_dir.path().join("results/MyAppTests.xcresult");
+        fs::create_dir_all(bundle.join("Data")).unwrap();
+
+        prepare_result_bundle_path(&bundle, false).unwrap();
+        assert!(bundle.exists());
+
+        prepare_result_bundle_path(&bundle, true).unwrap();
+        assert!(!bundle.exists());
+        assert!(bundle.parent().unwrap().is_dir());
+    }
+}
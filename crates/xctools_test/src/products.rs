@@ -0,0 +1,295 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+/// Placeholder xcodebuild replaces with the directory containing the `.xctestrun` file.
+pub const TESTROOT_PLACEHOLDER: &str = "__TESTROOT__";
+
+/// The `.xctestrun` files written by [`package_test_products`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackagedTestProducts {
+    pub xctestrun_paths: Vec<PathBuf>,
+    /// Paths into the build directory that remain in the `.xctestrun` files, which other machines
+    /// will not have.
+    pub warnings: Vec<String>,
+}
+
+/// Copies the build products of `xcodebuild build-for-testing` (the `Build/Products` directory
+/// of its derived data) into `artifact_directory`, so that the tests can run on another machine
+/// with `xcodebuild test-without-building -xctestrun`.
+///
+/// Absolute paths to `products_directory` in the `.xctestrun` files are replaced with
+/// `__TESTROOT__`, which xcodebuild resolves to the directory the `.xctestrun` file is in.
+/// Symbolic links, such as those inside macOS frameworks, are copied as links.
+///
+/// Fails if `products_directory` contains no `.xctestrun` file.
+pub fn package_test_products(
+    products_directory: &Path,
+    artifact_directory: &Path,
+) -> Result<PackagedTestProducts> {
+    let mut xctestruns = Vec::new();
+    for entry in fs::read_dir(products_directory).with_context(|| {
+        format!(
+            "Failed to read the build products in {}",
+            products_directory.display()
+        )
+    })? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "xctestrun")
+        {
+            xctestruns.push(path);
+        }
+    }
+    if xctestruns.is_empty() {
+        bail!(
+            "xcodebuild did not write an .xctestrun file to {}",
+            products_directory.display()
+        );
+    }
+    xctestruns.sort();
+
+    copy_directory(products_directory, artifact_directory, &|path| {
+        xctestruns.iter().any(|xctestrun| xctestrun == path)
+    })?;
+
+    // xcodebuild writes absolute paths, so match the directory the way it may have resolved it.
+    let mut products_paths = vec![std::path::absolute(products_directory)?];
+    if let Ok(canonical) = products_directory.canonicalize()
+        && !products_paths.contains(&canonical)
+    {
+        products_paths.push(canonical);
+    }
+    let derived_data_paths: Vec<PathBuf> = products_paths
+        .iter()
+        .filter_map(|path| Some(path.parent()?.parent()?.to_path_buf()))
+        .collect();
+
+    let mut packaged = PackagedTestProducts::default();
+    for xctestrun in &xctestruns {
+        let contents = fs::read_to_string(xctestrun).with_context(|| {
+            format!(
+                "Failed to read {}; binary property lists are not supported",
+                xctestrun.display()
+            )
+        })?;
+        let rewritten = products_paths
+            .iter()
+            .fold(contents, |contents, products_path| {
+                rewrite_xctestrun(&contents, products_path)
+            });
+        let destination = artifact_directory.join(xctestrun.file_name().unwrap_or_default());
+        if let Some(derived_data_path) = derived_data_paths
+            .iter()
+            .find(|path| rewritten.contains(&*escape_xml(&path.to_string_lossy())))
+        {
+            packaged.warnings.push(format!(
+                "{} still refers to {}, which other machines will not have",
+                destination.display(),
+                derived_data_path.display()
+            ));
+        }
+        fs::write(&destination, rewritten)
+            .with_context(|| format!("Failed to write {}", destination.display()))?;
+        packaged.xctestrun_paths.push(destination);
+    }
+
+    Ok(packaged)
+}
+
+/// Replaces the absolute `products_directory` in the paths of an `.xctestrun` property list with
+/// [`TESTROOT_PLACEHOLDER`]. Only whole path components are replaced, in plain values as well as
+/// in colon-separated lists such as `DYLD_FRAMEWORK_PATH`.
+pub fn rewrite_xctestrun(xctestrun: &str, products_directory: &Path) -> String {
+    let products_directory = escape_xml(&products_directory.to_string_lossy());
+    let products_directory = products_directory.trim_end_matches('/');
+    if products_directory.is_empty() {
+        return xctestrun.to_string();
+    }
+
+    let mut rewritten = String::with_capacity(xctestrun.len());
+    let mut rest = xctestrun;
+    while let Some(index) = rest.find(products_directory) {
+        let after = &rest[index + products_directory.len()..];
+        rewritten.push_str(&rest[..index]);
+        // A path starts a value or follows the separator of a list, and ends with a component.
+        let starts_path = matches!(rewritten.chars().next_back(), Some('>' | ':') | None);
+        let ends_component = matches!(after.chars().next(), Some('/' | '<' | ':') | None);
+        if starts_path && ends_component {
+            rewritten.push_str(TESTROOT_PLACEHOLDER);
+        } else {
+            rewritten.push_str(products_directory);
+        }
+        rest = after;
+    }
+    rewritten.push_str(rest);
+
+    rewritten
+}
+
+/// Returns the scheme an `.xctestrun` file was built from, which Xcode records in its
+/// `__xctestrun_metadata__`.
+pub(crate) fn xctestrun_scheme(xctestrun: &str) -> Option<String> {
+    let after_key = xctestrun
+        .split_once("<key>SchemeName</key>")?
+        .1
+        .trim_start();
+    let value = after_key.strip_prefix("<string>")?;
+    let (value, _) = value.split_once("</string>")?;
+
+    Some(
+        value
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&"),
+    )
+}
+
+/// Copies `source` into `destination` recursively, skipping the files `skip` matches.
+fn copy_directory(source: &Path, destination: &Path, skip: &dyn Fn(&Path) -> bool) -> Result<()> {
+    fs::create_dir_all(destination)
+        .with_context(|| format!("Failed to create {}", destination.display()))?;
+    for entry in
+        fs::read_dir(source).with_context(|| format!("Failed to read {}", source.display()))?
+    {
+        let entry = entry?;
+        let path = entry.path();
+        if skip(&path) {
+            continue;
+        }
+        let target = destination.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(&path)?, &target)
+                .with_context(|| format!("Failed to link {}", target.display()))?;
+        } else if file_type.is_dir() {
+            copy_directory(&path, &target, skip)?;
+        } else {
+            fs::copy(&path, &target).with_context(|| {
+                format!("Failed to copy {} to {}", path.display(), target.display())
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XCTESTRUN: &str =
+        include_str!("../tests/fixtures/MyApp_iphonesimulator18.2-arm64.xctestrun");
+    const PRODUCTS_DIRECTORY: &str =
+        "/Users/ci/work/MyApp/.xctools/derived-data/MyApp/Build/Products";
+
+    #[test]
+    fn test_rewrite_xctestrun() {
+        let rewritten = rewrite_xctestrun(XCTESTRUN, Path::new(PRODUCTS_DIRECTORY));
+
+        assert!(!rewritten.contains(PRODUCTS_DIRECTORY));
+        assert!(
+            rewritten
+                .contains("<string>__TESTROOT__/Debug-iphonesimulator/MyApp.app/MyApp</string>")
+        );
+        assert!(rewritten.contains(
+            "<string>__TESTROOT__/Debug-iphonesimulator:__PLATFORMS__/iPhoneSimulator.platform/\
+             Developer/Library/Frameworks</string>"
+        ));
+        // Source paths are only used for code coverage and stay as they are.
+        assert!(rewritten.contains("<string>/Users/ci/work/MyApp/MyApp/</string>"));
+        assert_eq!(
+            rewritten.lines().count(),
+            XCTESTRUN.lines().count(),
+            "only paths change"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_xctestrun_matches_whole_components_only() {
+        let xctestrun = "<string>/build/Products2/MyApp.app</string>\n\
+                         <string>/other/build/Products/MyApp.app</string>\n\
+                         <string>/build/Products</string>";
+
+        assert_eq!(
+            rewrite_xctestrun(xctestrun, Path::new("/build/Products/")),
+            "<string>/build/Products2/MyApp.app</string>\n\
+             <string>/other/build/Products/MyApp.app</string>\n\
+             <string>__TESTROOT__</string>"
+        );
+    }
+
+    #[test]
+    fn test_xctestrun_scheme() {
+        assert_eq!(xctestrun_scheme(XCTESTRUN).as_deref(), Some("MyApp"));
+        assert_eq!(xctestrun_scheme("<dict></dict>"), None);
+    }
+
+    #[test]
+    fn test_package_test_products() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let products = temp_dir.path().join("DerivedData/Build/Products");
+        let framework = products.join("Debug/MyKit.framework");
+        fs::create_dir_all(framework.join("Versions/A")).unwrap();
+        fs::write(framework.join("Versions/A/MyKit"), "binary").unwrap();
+        std::os::unix::fs::symlink("Versions/A/MyKit", framework.join("MyKit")).unwrap();
+        let products_path = std::path::absolute(&products).unwrap();
+        fs::write(
+            products.join("MyApp_macosx15.2-arm64.xctestrun"),
+            format!(
+                "<string>{0}/Debug/MyApp.app</string>\n<string>{1}/Logs</string>",
+                products_path.display(),
+                products_path.parent().unwrap().parent().unwrap().display()
+            ),
+        )
+        .unwrap();
+        let artifacts = temp_dir.path().join("artifacts");
+
+        let packaged = package_test_products(&products, &artifacts).unwrap();
+
+        let xctestrun = artifacts.join("MyApp_macosx15.2-arm64.xctestrun");
+        assert_eq!(packaged.xctestrun_paths, vec![xctestrun.clone()]);
+        assert!(
+            fs::read_to_string(&xctestrun)
+                .unwrap()
+                .starts_with("<string>__TESTROOT__/Debug/MyApp.app</string>\n")
+        );
+        assert_eq!(packaged.warnings.len(), 1);
+        assert!(packaged.warnings[0].contains("which other machines will not have"));
+        assert_eq!(
+            fs::read_link(artifacts.join("Debug/MyKit.framework/MyKit")).unwrap(),
+            Path::new("Versions/A/MyKit")
+        );
+        assert_eq!(
+            fs::read_to_string(artifacts.join("Debug/MyKit.framework/Versions/A/MyKit")).unwrap(),
+            "binary"
+        );
+    }
+
+    #[test]
+    fn test_package_test_products_requires_xctestrun() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp_dir.path().join("Products/Debug")).unwrap();
+
+        let error = package_test_products(
+            &temp_dir.path().join("Products"),
+            &temp_dir.path().join("artifacts"),
+        )
+        .unwrap_err();
+
+        assert!(
+            error
+                .to_string()
+                .starts_with("xcodebuild did not write an .xctestrun file")
+        );
+        assert!(!temp_dir.path().join("artifacts").exists());
+    }
+}
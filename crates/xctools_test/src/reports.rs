@@ -0,0 +1,248 @@
+use std::fmt::Write;
+
+use crate::results::{TestResults, TestStatus};
+
+/// Failures listed in the Markdown summary; the rest are counted.
+const SUMMARY_FAILURE_LIMIT: usize = 50;
+
+/// Renders `results` as a JUnit XML report, the format CI systems display per-test results
+/// from. Each suite becomes a `<testsuite>` named `Target.Suite`, and expected failures count
+/// as passed.
+///
+/// # Examples
+///
+/// ```rust
+/// use xctools_test::{TestResults, junit_report};
+///
+/// let results = TestResults::parse(r#"{"testNodes": [{
+///   "name": "MyAppTests", "nodeType": "Unit test bundle", "result": "Passed",
+///   "children": [{
+///     "name": "LoginTests", "nodeType": "Test Suite", "result": "Passed",
+///     "children": [{
+///       "name": "testLogin()", "nodeType": "Test Case", "result": "Passed",
+///       "durationInSeconds": 0.5
+///     }]
+///   }]
+/// }]}"#)
+/// .unwrap();
+///
+/// let xml = junit_report(&results, "MyApp");
+///
+/// assert!(xml.contains(r#"<testsuite name="MyAppTests.LoginTests" tests="1" failures="0""#));
+/// assert!(xml.contains(
+///     r#"<testcase name="testLogin()" classname="MyAppTests.LoginTests" time="0.500"/>"#
+/// ));
+/// ```
+pub fn junit_report(results: &TestResults, name: &str) -> String {
+    let totals = results.totals();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" \
+         time=\"{:.3}\">",
+        escape_xml(name),
+        totals.total,
+        totals.failed,
+        totals.skipped,
+        totals.duration_seconds
+    );
+    for target in &results.targets {
+        for suite in &target.suites {
+            let totals = suite.totals();
+            let class_name = escape_xml(&format!("{}.{}", target.name, suite.name));
+            let _ = writeln!(
+                xml,
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" \
+                 skipped=\"{}\" time=\"{:.3}\">",
+                class_name, totals.total, totals.failed, totals.skipped, totals.duration_seconds
+            );
+            for case in &suite.cases {
+                let attributes = format!(
+                    "name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                    escape_xml(&case.name),
+                    class_name,
+                    case.duration_seconds.unwrap_or_default()
+                );
+                match case.status {
+                    TestStatus::Failed => {
+                        let _ = writeln!(xml, "    <testcase {}>", attributes);
+                        for failure in &case.failures {
+                            let text = match failure.location() {
+                                Some(location) => format!("{}: {}", location, failure.message),
+                                None => failure.message.clone(),
+                            };
+                            let _ = writeln!(
+                                xml,
+                                "      <failure message=\"{}\">{}</failure>",
+                                escape_xml(&failure.message),
+                                escape_xml(&text)
+                            );
+                        }
+                        if case.failures.is_empty() {
+                            let _ = writeln!(xml, "      <failure message=\"Test failed\"/>");
+                        }
+                        let _ = writeln!(xml, "    </testcase>");
+                    }
+                    TestStatus::Skipped => {
+                        let _ = writeln!(xml, "    <testcase {}>", attributes);
+                        match &case.skip_reason {
+                            Some(reason) => {
+                                let _ = writeln!(
+                                    xml,
+                                    "      <skipped message=\"{}\"/>",
+                                    escape_xml(reason)
+                                );
+                            }
+                            None => {
+                                let _ = writeln!(xml, "      <skipped/>");
+                            }
+                        }
+                        let _ = writeln!(xml, "    </testcase>");
+                    }
+                    TestStatus::Passed | TestStatus::ExpectedFailure => {
+                        let _ = writeln!(xml, "    <testcase {}/>", attributes);
+                    }
+                }
+            }
+            let _ = writeln!(xml, "  </testsuite>");
+        }
+    }
+    xml.push_str("</testsuites>\n");
+
+    xml
+}
+
+/// Renders `results` as a Markdown summary for pull requests and CI job pages, such as
+/// `$GITHUB_STEP_SUMMARY`: the totals, a table per target and the failures with their
+/// locations.
+pub fn markdown_summary(results: &TestResults, title: &str) -> String {
+    let totals = results.totals();
+    let mut markdown = format!("## {}\n\n", escape_markdown(title));
+    let outcome = match totals.failed {
+        0 => "**Passed**",
+        _ => "**Failed**",
+    };
+    let _ = writeln!(
+        markdown,
+        "{}: {} tests, {} passed, {} failed, {} skipped in {:.2}s\n",
+        outcome,
+        totals.total,
+        totals.passed,
+        totals.failed,
+        totals.skipped,
+        totals.duration_seconds
+    );
+
+    markdown.push_str("| Target | Passed | Failed | Skipped | Duration |\n");
+    markdown.push_str("|---|---:|---:|---:|---:|\n");
+    for target in &results.targets {
+        let totals = target.totals();
+        let _ = writeln!(
+            markdown,
+            "| {} | {} | {} | {} | {:.2}s |",
+            escape_markdown(&target.name),
+            totals.passed,
+            totals.failed,
+            totals.skipped,
+            totals.duration_seconds
+        );
+    }
+
+    let failed: Vec<_> = results
+        .cases()
+        .filter(|(_, _, case)| case.status == TestStatus::Failed)
+        .collect();
+    if !failed.is_empty() {
+        markdown.push_str("\n### Failures\n\n");
+        for (target, suite, case) in failed.iter().take(SUMMARY_FAILURE_LIMIT) {
+            let _ = writeln!(
+                markdown,
+                "- **{}.{}/{}**",
+                escape_markdown(&target.name),
+                escape_markdown(&suite.name),
+                escape_markdown(&case.name)
+            );
+            for failure in &case.failures {
+                let message = escape_markdown(&failure.message.replace('\n', " "));
+                match failure.location() {
+                    Some(location) => {
+                        let _ = writeln!(markdown, "  - `{}`: {}", location, message);
+                    }
+                    None => {
+                        let _ = writeln!(markdown, "  - {}", message);
+                    }
+                }
+            }
+        }
+        if failed.len() > SUMMARY_FAILURE_LIMIT {
+            let _ = writeln!(
+                markdown,
+                "\n{} more failures are not shown.",
+                failed.len() - SUMMARY_FAILURE_LIMIT
+            );
+        }
+    }
+
+    markdown
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Escapes the characters that would start inline formatting, HTML or a table cell boundary.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '|' | '<' | '>' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../tests/fixtures/xcresult_tests.json");
+
+    #[test]
+    fn test_junit_report() {
+        let results = TestResults::parse(FIXTURE).unwrap();
+
+        assert_eq!(
+            junit_report(&results, "MyApp"),
+            include_str!("../tests/fixtures/junit.xml")
+        );
+    }
+
+    #[test]
+    fn test_markdown_summary() {
+        let results = TestResults::parse(FIXTURE).unwrap();
+
+        assert_eq!(
+            markdown_summary(&results, "Tests for MyApp"),
+            include_str!("../tests/fixtures/summary.md")
+        );
+    }
+
+    #[test]
+    fn test_markdown_summary_without_failures() {
+        let summary = markdown_summary(&TestResults::default(), "Tests for MyApp");
+
+        assert!(summary.contains("**Passed**: 0 tests"));
+        assert!(!summary.contains("### Failures"));
+    }
+
+    #[test]
+    fn test_escape_markdown() {
+        assert_eq!(escape_markdown("a|b *c* <d>"), "a\\|b \\*c\\* \\<d\\>");
+    }
+}
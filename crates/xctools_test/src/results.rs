@@ -0,0 +1,515 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use xcbuild_common::{CommandError, CommandErrorKind, Invocation, run_query};
+
+/// Prefix Xcode puts in front of the message of a skipped test.
+const SKIPPED_PREFIX: &str = "Test skipped - ";
+
+/// How a test case ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Skipped,
+    /// The test failed as declared with `XCTExpectFailure` or `withKnownIssue`, which counts as
+    /// passing.
+    ExpectedFailure,
+}
+
+/// One failure reported by a test case, e.g. a failed assertion.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestFailure {
+    pub message: String,
+    /// The source file the failure was reported in, as named by xcresulttool, e.g.
+    /// `LoginTests.swift`.
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+impl TestFailure {
+    /// Splits a failure such as `LoginTests.swift:42: XCTAssertEqual failed` into its location
+    /// and message.
+    fn parse(text: &str) -> Self {
+        let location = text.split_once(": ").and_then(|(location, message)| {
+            let (file, line) = location.rsplit_once(':')?;
+            let line = line.parse().ok()?;
+            (!file.is_empty()).then(|| (file.to_string(), line, message.to_string()))
+        });
+
+        match location {
+            Some((file, line, message)) => Self {
+                message,
+                file: Some(file),
+                line: Some(line),
+            },
+            None => Self {
+                message: text.to_string(),
+                file: None,
+                line: None,
+            },
+        }
+    }
+
+    /// `file:line`, if the failure has a location.
+    pub fn location(&self) -> Option<String> {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => Some(format!("{}:{}", file, line)),
+            (Some(file), None) => Some(file.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestCase {
+    /// The test's name, e.g. `testLogin()`.
+    pub name: String,
+    /// The identifier xcodebuild's `-only-testing` accepts after the target, e.g.
+    /// `LoginTests/testLogin()`.
+    pub identifier: String,
+    pub status: TestStatus,
+    pub duration_seconds: Option<f64>,
+    /// Every failure the test reported, across devices and repetitions.
+    pub failures: Vec<TestFailure>,
+    /// Why the test was skipped, if it gave a reason.
+    pub skip_reason: Option<String>,
+}
+
+/// A test class or Swift Testing suite. Nested suites are flattened, with their names joined by
+/// `/`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestSuite {
+    pub name: String,
+    pub cases: Vec<TestCase>,
+}
+
+/// A unit or UI test bundle.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestTarget {
+    pub name: String,
+    pub suites: Vec<TestSuite>,
+}
+
+/// Counts of test cases by [`TestStatus`]; expected failures count as passed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestTotals {
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub duration_seconds: f64,
+}
+
+impl TestTotals {
+    fn add(&mut self, case: &TestCase) {
+        self.total += 1;
+        match case.status {
+            TestStatus::Passed | TestStatus::ExpectedFailure => self.passed += 1,
+            TestStatus::Failed => self.failed += 1,
+            TestStatus::Skipped => self.skipped += 1,
+        }
+        self.duration_seconds += case.duration_seconds.unwrap_or_default();
+    }
+}
+
+impl std::fmt::Display for TestTotals {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} passed, {} failed, {} skipped in {:.2}s",
+            self.passed, self.failed, self.skipped, self.duration_seconds
+        )
+    }
+}
+
+/// The test tree of a result bundle, read from `xcrun xcresulttool get test-results tests`.
+///
+/// # Examples
+///
+/// ```rust
+/// use xctools_test::{TestResults, TestStatus};
+///
+/// let json = r#"{
+///   "testNodes": [{
+///     "name": "MyApp", "nodeType": "Test Plan", "result": "Failed",
+///     "children": [{
+///       "name": "MyAppTests", "nodeType": "Unit test bundle", "result": "Failed",
+///       "children": [{
+///         "name": "LoginTests", "nodeType": "Test Suite", "result": "Failed",
+///         "children": [{
+///           "name": "testLogin()", "nodeType": "Test Case", "result": "Failed",
+///           "nodeIdentifier": "LoginTests/testLogin()", "durationInSeconds": 0.25,
+///           "children": [{
+///             "name": "LoginTests.swift:42: XCTAssertTrue failed",
+///             "nodeType": "Failure Message", "result": "Failed"
+///           }]
+///         }]
+///       }]
+///     }]
+///   }]
+/// }"#;
+///
+/// let results = TestResults::parse(json).unwrap();
+/// let case = &results.targets[0].suites[0].cases[0];
+///
+/// assert_eq!(case.status, TestStatus::Failed);
+/// assert_eq!(case.failures[0].location().as_deref(), Some("LoginTests.swift:42"));
+/// assert_eq!(case.failures[0].message, "XCTAssertTrue failed");
+/// assert_eq!(results.totals().failed, 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestResults {
+    pub targets: Vec<TestTarget>,
+}
+
+impl TestResults {
+    /// Parses the JSON printed by `xcrun xcresulttool get test-results tests` (Xcode 16 and
+    /// later).
+    pub fn parse(json: &str) -> Result<Self> {
+        let export: TestsExport = serde_json::from_str(json)
+            .context("Failed to parse `xcrun xcresulttool get test-results tests` output")?;
+        let mut results = Self::default();
+        for node in &export.test_nodes {
+            results.add_node(node);
+        }
+
+        Ok(results)
+    }
+
+    fn add_node(&mut self, node: &TestNode) {
+        match node.node_type.as_str() {
+            "Unit test bundle" | "UI test bundle" => {
+                let mut target = TestTarget {
+                    name: node.name.clone(),
+                    suites: Vec::new(),
+                };
+                for child in &node.children {
+                    add_to_target(&mut target, child, None);
+                }
+                self.targets.push(target);
+            }
+            _ => {
+                for child in &node.children {
+                    self.add_node(child);
+                }
+            }
+        }
+    }
+
+    /// Every test case with the target and suite it belongs to.
+    pub fn cases(&self) -> impl Iterator<Item = (&TestTarget, &TestSuite, &TestCase)> {
+        self.targets.iter().flat_map(|target| {
+            target
+                .suites
+                .iter()
+                .flat_map(move |suite| suite.cases.iter().map(move |case| (target, suite, case)))
+        })
+    }
+
+    pub fn totals(&self) -> TestTotals {
+        let mut totals = TestTotals::default();
+        for (_, _, case) in self.cases() {
+            totals.add(case);
+        }
+
+        totals
+    }
+}
+
+impl TestTarget {
+    pub fn totals(&self) -> TestTotals {
+        let mut totals = TestTotals::default();
+        for case in self.suites.iter().flat_map(|suite| &suite.cases) {
+            totals.add(case);
+        }
+
+        totals
+    }
+}
+
+impl TestSuite {
+    pub fn totals(&self) -> TestTotals {
+        let mut totals = TestTotals::default();
+        for case in &self.cases {
+            totals.add(case);
+        }
+
+        totals
+    }
+}
+
+/// Adds the suites and cases below `node` to `target`. Cases outside any suite, such as Swift
+/// Testing functions at file scope, go into a suite named after the target.
+fn add_to_target(target: &mut TestTarget, node: &TestNode, suite: Option<&str>) {
+    match node.node_type.as_str() {
+        "Test Suite" => {
+            let name = match suite {
+                Some(parent) => format!("{}/{}", parent, node.name),
+                None => node.name.clone(),
+            };
+            for child in &node.children {
+                add_to_target(target, child, Some(&name));
+            }
+        }
+        "Test Case" => {
+            let suite_name = suite.unwrap_or(&target.name).to_string();
+            let case = parse_case(node);
+            match target
+                .suites
+                .iter_mut()
+                .find(|suite| suite.name == suite_name)
+            {
+                Some(suite) => suite.cases.push(case),
+                None => target.suites.push(TestSuite {
+                    name: suite_name,
+                    cases: vec![case],
+                }),
+            }
+        }
+        _ => {
+            for child in &node.children {
+                add_to_target(target, child, suite);
+            }
+        }
+    }
+}
+
+fn parse_case(node: &TestNode) -> TestCase {
+    let status = match node.result.as_str() {
+        "Passed" => TestStatus::Passed,
+        "Failed" => TestStatus::Failed,
+        "Expected Failure" => TestStatus::ExpectedFailure,
+        _ => TestStatus::Skipped,
+    };
+    let mut messages = Vec::new();
+    collect_messages(node, &mut messages);
+    let (failures, skip_reason) = match status {
+        TestStatus::Skipped => {
+            let reason = messages.first().map(|message| {
+                message
+                    .strip_prefix(SKIPPED_PREFIX)
+                    .unwrap_or(message)
+                    .to_string()
+            });
+            (Vec::new(), reason)
+        }
+        _ => (
+            messages
+                .iter()
+                .map(|message| TestFailure::parse(message))
+                .collect(),
+            None,
+        ),
+    };
+
+    TestCase {
+        name: node.name.clone(),
+        identifier: node
+            .node_identifier
+            .clone()
+            .unwrap_or_else(|| node.name.clone()),
+        status,
+        duration_seconds: node
+            .duration_in_seconds
+            .or_else(|| node.duration.as_deref().and_then(parse_duration)),
+        failures,
+        skip_reason,
+    }
+}
+
+/// Collects the failure messages below a test case, which sit under device, configuration and
+/// repetition nodes when the test ran more than once.
+fn collect_messages(node: &TestNode, messages: &mut Vec<String>) {
+    for child in &node.children {
+        if child.node_type == "Failure Message" {
+            messages.push(child.name.clone());
+        } else {
+            collect_messages(child, messages);
+        }
+    }
+}
+
+/// Parses durations such as `0.25s`, `12s` or `1m 3s`, written by Xcodes that do not report
+/// `durationInSeconds`.
+fn parse_duration(duration: &str) -> Option<f64> {
+    duration
+        .split_whitespace()
+        .map(|part| {
+            let part = part.replace(',', ".");
+            let (value, unit) = part.split_at(part.find(|c: char| c.is_alphabetic())?);
+            let value: f64 = value.parse().ok()?;
+            match unit {
+                "s" => Some(value),
+                "m" => Some(value * 60.0),
+                "h" => Some(value * 3600.0),
+                _ => None,
+            }
+        })
+        .sum()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TestsExport {
+    #[serde(default)]
+    test_nodes: Vec<TestNode>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TestNode {
+    name: String,
+    node_type: String,
+    #[serde(default)]
+    node_identifier: Option<String>,
+    #[serde(default)]
+    result: String,
+    #[serde(default)]
+    duration: Option<String>,
+    #[serde(default)]
+    duration_in_seconds: Option<f64>,
+    #[serde(default)]
+    children: Vec<TestNode>,
+}
+
+/// Reads the test tree of a result bundle with `xcrun xcresulttool`, which needs Xcode 16 or
+/// later.
+///
+/// Reading has no side effects, so it runs even in dry-run mode.
+pub fn read_test_results(result_bundle_path: &Path) -> Result<TestResults> {
+    let invocation = Invocation::new("xcrun")
+        .with_args(["xcresulttool", "get", "test-results", "tests", "--path"])
+        .with_arg(result_bundle_path.to_string_lossy());
+    let output = run_query(&invocation)?;
+    if !output.status.success() {
+        return Err(CommandError::from_output(
+            CommandErrorKind::Failed,
+            "xcrun xcresulttool",
+            &invocation,
+            &output,
+        )
+        .into());
+    }
+
+    TestResults::parse(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../tests/fixtures/xcresult_tests.json");
+
+    #[test]
+    fn test_parse_fixture_into_tree() {
+        let results = TestResults::parse(FIXTURE).unwrap();
+
+        let targets: Vec<&str> = results.targets.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(targets, ["MyAppTests", "MyAppUITests"]);
+        let suites: Vec<&str> = results.targets[0]
+            .suites
+            .iter()
+            .map(|suite| suite.name.as_str())
+            .collect();
+        assert_eq!(suites, ["LoginTests", "Formatting/Currency", "Formatting"]);
+
+        let login = &results.targets[0].suites[0];
+        assert_eq!(login.cases[0].status, TestStatus::Passed);
+        assert_eq!(login.cases[0].duration_seconds, Some(0.012));
+        assert_eq!(
+            login.cases[1].failures,
+            [TestFailure {
+                message: String::from(
+                    "XCTAssertEqual failed: (\"401\") is not equal to (\"200\") - <login> & \"retry\""
+                ),
+                file: Some(String::from("LoginTests.swift")),
+                line: Some(42),
+            }]
+        );
+        assert_eq!(login.cases[2].status, TestStatus::Skipped);
+        assert_eq!(
+            login.cases[2].skip_reason.as_deref(),
+            Some("Requires a network connection")
+        );
+        assert!(login.cases[2].failures.is_empty());
+        assert_eq!(
+            results.targets[0].suites[2].cases[0].status,
+            TestStatus::ExpectedFailure
+        );
+    }
+
+    #[test]
+    fn test_failures_below_device_nodes_are_collected() {
+        let results = TestResults::parse(FIXTURE).unwrap();
+
+        let checkout = &results.targets[1].suites[0].cases[0];
+        assert_eq!(checkout.identifier, "CheckoutUITests/testCheckout()");
+        assert_eq!(
+            checkout.failures[0].location().as_deref(),
+            Some("CheckoutUITests.swift:18")
+        );
+        assert_eq!(checkout.duration_seconds, Some(12.5));
+    }
+
+    #[test]
+    fn test_totals() {
+        let results = TestResults::parse(FIXTURE).unwrap();
+
+        let totals = results.totals();
+        assert_eq!(
+            (totals.total, totals.passed, totals.failed, totals.skipped),
+            (6, 3, 2, 1)
+        );
+        assert!((totals.duration_seconds - 12.767).abs() < 1e-9);
+        assert_eq!(results.targets[1].totals().failed, 1);
+        assert_eq!(
+            results.totals().to_string(),
+            "3 passed, 2 failed, 1 skipped in 12.77s"
+        );
+    }
+
+    #[test]
+    fn test_cases_outside_suites_use_target_name() {
+        let json = r#"{"testNodes": [{
+            "name": "KitTests", "nodeType": "Unit test bundle", "result": "Passed",
+            "children": [{
+                "name": "addsNumbers()", "nodeType": "Test Case", "result": "Passed",
+                "duration": "1m 2.5s"
+            }]
+        }]}"#;
+
+        let results = TestResults::parse(json).unwrap();
+
+        let suite = &results.targets[0].suites[0];
+        assert_eq!(suite.name, "KitTests");
+        assert_eq!(suite.cases[0].identifier, "addsNumbers()");
+        assert_eq!(suite.cases[0].duration_seconds, Some(62.5));
+    }
+
+    #[test]
+    fn test_failure_without_location() {
+        assert_eq!(
+            TestFailure::parse("Crash: MyApp (1234) at 0x0"),
+            TestFailure {
+                message: String::from("Crash: MyApp (1234) at 0x0"),
+                file: None,
+                line: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_other_json() {
+        assert!(TestResults::parse(r#"{"testNodes": "none"}"#).is_err());
+        assert_eq!(TestResults::parse("{}").unwrap(), TestResults::default());
+    }
+}
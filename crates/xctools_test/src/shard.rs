@@ -0,0 +1,557 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use xcbuild_common::{CommandError, CommandErrorKind, Destination, Invocation, run_query};
+
+use crate::results::{TestResults, read_test_results};
+
+/// Version written to and expected in the timings file.
+const TIMINGS_VERSION: u32 = 1;
+/// Duration assumed for every class when no class has a recorded duration.
+const DEFAULT_CLASS_DURATION_SECONDS: f64 = 1.0;
+
+/// One of `count` equal parts of a test run, written `INDEX/COUNT` with a 1-based index, such as
+/// `2/5`.
+///
+/// # Examples
+///
+/// ```rust
+/// use xctools_test::Shard;
+///
+/// let shard: Shard = "2/5".parse().unwrap();
+/// assert_eq!((shard.index, shard.count), (2, 5));
+/// assert_eq!(shard.to_string(), "2/5");
+///
+/// assert!("0/5".parse::<Shard>().is_err());
+/// assert!("6/5".parse::<Shard>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid shard '{}': expected INDEX/COUNT with 1 <= INDEX <= COUNT, e.g. 2/5",
+                s
+            )
+        };
+        let (index, count) = s.split_once('/').ok_or_else(invalid)?;
+        let index: usize = index.trim().parse().map_err(|_| invalid())?;
+        let count: usize = count.trim().parse().map_err(|_| invalid())?;
+        if index == 0 || index > count {
+            return Err(invalid());
+        }
+
+        Ok(Self { index, count })
+    }
+}
+
+impl std::fmt::Display for Shard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+/// Durations of test classes from previous runs, keyed by `Target/Class`, which balance
+/// [`partition`]. Stored as JSON in the file given with `--timings`, which every machine that
+/// runs a shard must share:
+///
+/// ```json
+/// {"version": 1, "classes": {"MyAppUITests/CheckoutUITests": 312.5}}
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TestTimings {
+    #[serde(default)]
+    pub classes: BTreeMap<String, f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TimingsFile {
+    version: u32,
+    #[serde(flatten)]
+    timings: TestTimings,
+}
+
+impl TestTimings {
+    /// Reads the timings file at `path`; a missing file has no timings.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default());
+            }
+            Err(error) => {
+                return Err(error).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        };
+        let file: TimingsFile = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse test timings in {}", path.display()))?;
+        if file.version != TIMINGS_VERSION {
+            bail!(
+                "Unsupported test timings version {} in {}; expected {}",
+                file.version,
+                path.display(),
+                TIMINGS_VERSION
+            );
+        }
+
+        Ok(file.timings)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let file = TimingsFile {
+            version: TIMINGS_VERSION,
+            timings: self.clone(),
+        };
+        let json = serde_json::to_string_pretty(&file)?;
+
+        fs::write(path, json + "\n").with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Replaces the durations of the classes that ran in `results` with their latest totals.
+    pub fn record(&mut self, results: &TestResults) {
+        let mut durations: BTreeMap<String, f64> = BTreeMap::new();
+        for (target, _, case) in results.cases() {
+            let identifier = format!("{}/{}", target.name, case.identifier);
+            *durations.entry(test_class(&identifier)).or_default() +=
+                case.duration_seconds.unwrap_or_default();
+        }
+        self.classes.extend(durations);
+    }
+}
+
+/// Reads the tests to shard from `path`: either a `.xcresult` bundle of a previous run, or the
+/// JSON `xcodebuild -enumerate-tests -test-enumeration-style flat -test-enumeration-format json`
+/// writes. Returns `Target/Class/test` identifiers.
+pub fn read_test_list(path: &Path) -> Result<Vec<String>> {
+    if path.is_dir() {
+        let results = read_test_results(path)?;
+        return Ok(results
+            .cases()
+            .map(|(target, _, case)| format!("{}/{}", target.name, case.identifier))
+            .collect());
+    }
+
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the test list {}", path.display()))?;
+    parse_test_enumeration(&contents)
+        .with_context(|| format!("Failed to read the test list {}", path.display()))
+}
+
+/// Lists the tests of an `.xctestrun` file with `xcodebuild test-without-building
+/// -enumerate-tests`, which needs Xcode 16 or later. `selection_arguments` narrow the list down
+/// the way they will narrow down the run.
+///
+/// Enumerating has no side effects, so it runs even in dry-run mode.
+pub fn enumerate_tests(
+    xctestrun_path: &Path,
+    destinations: &[Destination],
+    selection_arguments: &[String],
+) -> Result<Vec<String>> {
+    let output_path = std::env::temp_dir().join(format!(
+        "xctools-test-enumeration-{}.json",
+        std::process::id()
+    ));
+    let mut invocation = Invocation::new("xcodebuild")
+        .with_args(["test-without-building", "-xctestrun"])
+        .with_arg(xctestrun_path.to_string_lossy());
+    for destination in destinations {
+        invocation = invocation
+            .with_arg("-destination")
+            .with_arg(destination.to_string());
+    }
+    let invocation = invocation
+        .with_args(selection_arguments.iter().cloned())
+        .with_args([
+            "-enumerate-tests",
+            "-test-enumeration-style",
+            "flat",
+            "-test-enumeration-format",
+            "json",
+            "-test-enumeration-output-path",
+        ])
+        .with_arg(output_path.to_string_lossy());
+    let output = run_query(&invocation)?;
+    let enumeration = fs::read_to_string(&output_path);
+    let _ = fs::remove_file(&output_path);
+    if !output.status.success() {
+        return Err(CommandError::from_output(
+            CommandErrorKind::Failed,
+            "xcodebuild -enumerate-tests",
+            &invocation,
+            &output,
+        )
+        .into());
+    }
+    let enumeration = enumeration.with_context(|| {
+        format!(
+            "xcodebuild did not write the test enumeration to {}",
+            output_path.display()
+        )
+    })?;
+
+    parse_test_enumeration(&enumeration)
+}
+
+/// Parses the flat JSON test enumeration of `xcodebuild -enumerate-tests`, returning the
+/// identifiers of the enabled tests of every test plan.
+///
+/// # Examples
+///
+/// ```rust
+/// use xctools_test::parse_test_enumeration;
+///
+/// let tests = parse_test_enumeration(r#"{"errors": [], "values": [{
+///   "testPlan": "MyApp",
+///   "enabledTests": [{"identifier": "MyAppTests/LoginTests/testLogin()"}],
+///   "disabledTests": [{"identifier": "MyAppTests/LoginTests/testBroken()"}]
+/// }]}"#)
+/// .unwrap();
+///
+/// assert_eq!(tests, ["MyAppTests/LoginTests/testLogin()"]);
+/// ```
+pub fn parse_test_enumeration(json: &str) -> Result<Vec<String>> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Enumeration {
+        #[serde(default)]
+        errors: Vec<serde_json::Value>,
+        values: Vec<TestPlanTests>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct TestPlanTests {
+        #[serde(default)]
+        enabled_tests: Vec<EnumeratedTest>,
+    }
+    #[derive(Deserialize)]
+    struct EnumeratedTest {
+        identifier: String,
+    }
+
+    let enumeration: Enumeration = serde_json::from_str(json).context(
+        "Expected the output of `xcodebuild -enumerate-tests -test-enumeration-style flat \
+         -test-enumeration-format json`",
+    )?;
+    if let Some(error) = enumeration.errors.first() {
+        bail!(
+            "xcodebuild could not enumerate the tests: {}",
+            error
+                .as_str()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| error.to_string())
+        );
+    }
+
+    Ok(enumeration
+        .values
+        .into_iter()
+        .flat_map(|plan| plan.enabled_tests)
+        .map(|test| test.identifier)
+        .collect())
+}
+
+/// The class a test belongs to, `Target/Class`, which is the unit tests are sharded by. A test
+/// outside any class, such as a global Swift Testing function, is its own unit.
+pub fn test_class(identifier: &str) -> String {
+    identifier
+        .splitn(3, '/')
+        .take(2)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Splits the classes of `tests` into `count` shards.
+///
+/// The split depends only on its inputs, so every machine computes the same shards. Classes are
+/// handed out longest first, each to the shard with the least total duration so far (the first
+/// such shard on ties), with durations from `timings`. Classes without a recorded duration count
+/// as the average of the recorded ones; without any timings this is a round-robin over the
+/// sorted class names.
+///
+/// # Examples
+///
+/// ```rust
+/// use xctools_test::{TestTimings, partition};
+///
+/// let tests = [
+///     "MyAppTests/A/test1()",
+///     "MyAppTests/A/test2()",
+///     "MyAppTests/B/test()",
+///     "MyAppTests/C/test()",
+/// ]
+/// .map(String::from);
+///
+/// assert_eq!(
+///     partition(&tests, 2, &TestTimings::default()),
+///     [vec!["MyAppTests/A", "MyAppTests/C"], vec!["MyAppTests/B"]]
+/// );
+/// ```
+pub fn partition(tests: &[String], count: usize, timings: &TestTimings) -> Vec<Vec<String>> {
+    let classes: BTreeSet<String> = tests.iter().map(|test| test_class(test)).collect();
+    let known: Vec<f64> = classes
+        .iter()
+        .filter_map(|class| timings.classes.get(class).copied())
+        .collect();
+    let default_duration = match known.len() {
+        0 => DEFAULT_CLASS_DURATION_SECONDS,
+        known_count => known.iter().sum::<f64>() / known_count as f64,
+    };
+    let mut weighted: Vec<(f64, String)> = classes
+        .into_iter()
+        .map(|class| {
+            let duration = timings
+                .classes
+                .get(&class)
+                .copied()
+                .unwrap_or(default_duration);
+            (duration, class)
+        })
+        .collect();
+    // Longest first; names keep the order of equally long classes stable.
+    weighted.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+    let mut shards = vec![Vec::new(); count.max(1)];
+    let mut totals = vec![0.0_f64; count.max(1)];
+    for (duration, class) in weighted {
+        let shortest = (0..totals.len())
+            .min_by(|&a, &b| totals[a].total_cmp(&totals[b]).then(a.cmp(&b)))
+            .unwrap_or_default();
+        totals[shortest] += duration;
+        shards[shortest].push(class);
+    }
+    for shard in &mut shards {
+        shard.sort();
+    }
+
+    shards
+}
+
+/// The classes one shard runs, out of all the classes found.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardPlan {
+    /// The shard, e.g. `2/5`.
+    pub shard: String,
+    pub classes: Vec<String>,
+    pub total_classes: usize,
+}
+
+impl ShardPlan {
+    /// Partitions `tests` and picks the classes of `shard`.
+    pub fn new(shard: Shard, tests: &[String], timings: &TestTimings) -> Self {
+        let mut shards = partition(tests, shard.count, timings);
+        let total_classes = shards.iter().map(Vec::len).sum();
+
+        Self {
+            shard: shard.to_string(),
+            classes: shards.swap_remove(shard.index - 1),
+            total_classes,
+        }
+    }
+}
+
+impl std::fmt::Display for ShardPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Shard {}: {} of {} test classes",
+            self.shard,
+            self.classes.len(),
+            self.total_classes
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tests(identifiers: &[&str]) -> Vec<String> {
+        identifiers.iter().map(ToString::to_string).collect()
+    }
+
+    fn timings(classes: &[(&str, f64)]) -> TestTimings {
+        TestTimings {
+            classes: classes
+                .iter()
+                .map(|(class, duration)| (class.to_string(), *duration))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_shard_rejects_invalid_input() {
+        for input in ["", "2", "a/5", "2/b", "0/5", "6/5", "1/0", "-1/5"] {
+            assert!(input.parse::<Shard>().is_err(), "{:?}", input);
+        }
+        assert_eq!(
+            "1/1".parse::<Shard>().unwrap(),
+            Shard { index: 1, count: 1 }
+        );
+    }
+
+    #[test]
+    fn test_test_class() {
+        assert_eq!(
+            test_class("MyAppTests/LoginTests/testLogin()"),
+            "MyAppTests/LoginTests"
+        );
+        assert_eq!(
+            test_class("MyAppTests/Outer/Inner/test()"),
+            "MyAppTests/Outer"
+        );
+        assert_eq!(
+            test_class("MyAppTests/globalTest()"),
+            "MyAppTests/globalTest()"
+        );
+    }
+
+    #[test]
+    fn test_partition_without_timings_is_round_robin_by_name() {
+        let tests = tests(&[
+            "T/E/test()",
+            "T/A/test1()",
+            "T/A/test2()",
+            "T/D/test()",
+            "T/C/test()",
+            "T/B/test()",
+        ]);
+
+        assert_eq!(
+            partition(&tests, 3, &TestTimings::default()),
+            [vec!["T/A", "T/D"], vec!["T/B", "T/E"], vec!["T/C"]]
+        );
+    }
+
+    #[test]
+    fn test_partition_is_independent_of_input_order() {
+        let mut tests = tests(&["T/A/a()", "T/B/b()", "T/C/c()", "T/D/d()", "T/E/e()"]);
+        let timings = timings(&[("T/A", 5.0), ("T/C", 5.0), ("T/E", 1.0)]);
+        let expected = partition(&tests, 2, &timings);
+
+        tests.reverse();
+
+        assert_eq!(partition(&tests, 2, &timings), expected);
+    }
+
+    #[test]
+    fn test_partition_balances_by_duration() {
+        let tests = tests(&["T/Slow/a()", "T/Medium/a()", "T/Fast1/a()", "T/Fast2/a()"]);
+        let timings = timings(&[
+            ("T/Slow", 60.0),
+            ("T/Medium", 35.0),
+            ("T/Fast1", 20.0),
+            ("T/Fast2", 10.0),
+        ]);
+
+        assert_eq!(
+            partition(&tests, 2, &timings),
+            [vec!["T/Slow"], vec!["T/Fast1", "T/Fast2", "T/Medium"]]
+        );
+    }
+
+    #[test]
+    fn test_partition_gives_unknown_classes_the_average_duration() {
+        let tests = tests(&["T/Known1/a()", "T/Known2/a()", "T/New/a()"]);
+        let timings = timings(&[("T/Known1", 30.0), ("T/Known2", 10.0)]);
+
+        // New counts as 20s: Known1 (30) | New (20), then Known2 (10) joins the shorter shard.
+        assert_eq!(
+            partition(&tests, 2, &timings),
+            [vec!["T/Known1"], vec!["T/Known2", "T/New"]]
+        );
+    }
+
+    #[test]
+    fn test_partition_with_more_shards_than_classes() {
+        let shards = partition(&tests(&["T/A/a()"]), 3, &TestTimings::default());
+
+        assert_eq!(shards, [vec!["T/A"], vec![], vec![]]);
+    }
+
+    #[test]
+    fn test_shard_plan() {
+        let tests = tests(&["T/A/a()", "T/B/b()", "T/C/c()"]);
+        let plan = ShardPlan::new("2/2".parse().unwrap(), &tests, &TestTimings::default());
+
+        assert_eq!(plan.classes, ["T/B"]);
+        assert_eq!(plan.to_string(), "Shard 2/2: 1 of 3 test classes");
+    }
+
+    #[test]
+    fn test_parse_test_enumeration() {
+        let tests =
+            parse_test_enumeration(include_str!("../tests/fixtures/enumerate_tests.json")).unwrap();
+
+        assert_eq!(
+            tests,
+            [
+                "MyAppTests/LoginTests/testLoginSucceeds()",
+                "MyAppTests/LoginTests/testLoginWithExpiredToken()",
+                "MyAppTests/FormattingTests/parsesAmounts()",
+                "MyAppUITests/CheckoutUITests/testCheckout()",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_test_enumeration_reports_errors() {
+        let error =
+            parse_test_enumeration(r#"{"errors": ["Unable to find a destination"], "values": []}"#)
+                .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "xcodebuild could not enumerate the tests: Unable to find a destination"
+        );
+        assert!(parse_test_enumeration(r#"{"testNodes": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_timings_record_and_round_trip() {
+        let results =
+            TestResults::parse(include_str!("../tests/fixtures/xcresult_tests.json")).unwrap();
+        let mut timings = timings(&[("MyAppTests/LoginTests", 99.0), ("Old/Class", 1.0)]);
+
+        timings.record(&results);
+
+        assert!((timings.classes["MyAppTests/LoginTests"] - 0.262).abs() < 1e-9);
+        assert_eq!(timings.classes["MyAppUITests/CheckoutUITests"], 12.5);
+        assert_eq!(timings.classes["Old/Class"], 1.0);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("timings/test-timings.json");
+        timings.save(&path).unwrap();
+        assert_eq!(TestTimings::load(&path).unwrap(), timings);
+        assert_eq!(
+            TestTimings::load(&temp_dir.path().join("missing.json")).unwrap(),
+            TestTimings::default()
+        );
+
+        fs::write(&path, r#"{"version": 2, "classes": {}}"#).unwrap();
+        assert!(
+            TestTimings::load(&path)
+                .unwrap_err()
+                .to_string()
+                .starts_with("Unsupported test timings version 2")
+        );
+    }
+}
@@ -1,7 +1,31 @@
-use std::process::Command;
+use std::path::PathBuf;
 
 use anyhow::{Context, Result};
-use xcbuild_common::UploadTarget;
+use serde::Serialize;
+use xcbuild_common::{
+    CommandError, CommandErrorKind, CommandResult, Invocation, StreamOptions, UploadTarget,
+    log_file_for, retry_policy, stream_invocation,
+};
+
+/// The action reported in errors and retry messages for uploads.
+const UPLOAD_ACTION: &str = "xcrun altool --upload-app";
+
+/// The outcome of a successful [`upload`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadResult {
+    /// `ios` or `macos`.
+    pub target: String,
+    pub file: PathBuf,
+}
+
+impl std::fmt::Display for UploadResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Uploaded {} ({})", self.file.display(), self.target)
+    }
+}
+
+impl CommandResult for UploadResult {}
 
 /// Uploads an iOS or macOS application package to distribution platforms.
 ///
@@ -20,8 +44,9 @@ use xcbuild_common::UploadTarget;
 ///
 /// # Returns
 ///
-/// Returns `Ok(String)` containing the stdout from the xcrun altool command on success,
-/// or `Err` if the upload fails, authentication fails, or the command execution fails.
+/// Returns `Ok(UploadResult)` describing the uploaded file on success, or `Err` if the upload fails, authentication fails, or the command execution fails. A
+/// non-zero exit from altool is reported as an `xcbuild_common::CommandError` with the password
+/// redacted from the rendered command line.
 ///
 /// # Examples
 ///
@@ -43,16 +68,22 @@ use xcbuild_common::UploadTarget;
 /// - The function uses `xcrun altool` which may be deprecated in newer Xcode versions.
 ///   Consider migrating to `xcrun notarytool` for newer workflows.
 /// - App-specific passwords are recommended over regular Apple ID passwords for security.
-/// - The upload process may take several minutes depending on file size and network connection.
+/// - The upload process may take several minutes depending on file size and network connection;
+///   altool's output is streamed to the terminal and appended to the run's log file.
+/// - Network failures and HTTP 5xx responses are retried according to
+///   `xcbuild_common::retry_policy`; other failures, such as rejected credentials, are not.
 pub fn upload(
     target: &UploadTarget,
     app_file_path: &str,
     username: &str,
     password: &str,
-) -> Result<String> {
-    let output = run_xcrun_command(target, app_file_path, username, password)?;
+) -> Result<UploadResult> {
+    run_xcrun_command(target, app_file_path, username, password)?;
 
-    Ok(output)
+    Ok(UploadResult {
+        target: target.command_string(),
+        file: PathBuf::from(app_file_path),
+    })
 }
 
 fn run_xcrun_command(
@@ -61,28 +92,47 @@ fn run_xcrun_command(
     username: &str,
     password: &str,
 ) -> Result<String> {
-    let command = make_xcrun_command(target, app_file_path, username, password);
-    let output = Command::new("zsh")
-        .arg("-c")
-        .arg(&command)
-        .spawn()
-        .context(format!("Failed to run {}", command))?
-        .wait_with_output()
-        .context(format!("Failed to run {}", command))?;
-
-    String::from_utf8(output.stdout).context("Failed to decode output")
+    let invocation = make_xcrun_invocation(target, app_file_path, username, password);
+    let log_file = log_file_for("upload");
+
+    retry_policy().run(UPLOAD_ACTION, || {
+        let output = stream_invocation(
+            &invocation,
+            &StreamOptions::new().with_log_file(log_file.clone()),
+        )?;
+        if !output.status.success() {
+            return Err(CommandError::from_output(
+                CommandErrorKind::Failed,
+                UPLOAD_ACTION,
+                &invocation,
+                &output,
+            )
+            .into());
+        }
+
+        String::from_utf8(output.stdout).context("Failed to decode output")
+    })
 }
 
-fn make_xcrun_command(
+fn make_xcrun_invocation(
     target: &UploadTarget,
     app_file_path: &str,
     username: &str,
     password: &str,
-) -> String {
-    format!(
-        "xcrun altool --upload-app -t {} -f {} -u {} -p {}",
-        target, app_file_path, username, password
-    )
+) -> Invocation {
+    Invocation::new("xcrun")
+        .with_args([
+            "altool",
+            "--upload-app",
+            "-t",
+            &target.command_string(),
+            "-f",
+            app_file_path,
+            "-u",
+            username,
+            "-p",
+        ])
+        .with_secret_arg(password)
 }
 
 #[cfg(test)]
@@ -91,47 +141,74 @@ mod tests {
     use xcbuild_common::UploadTarget;
 
     #[test]
-    fn test_make_xcrun_command_with_ios_target() {
+    fn test_make_xcrun_invocation_with_ios_target() {
         let target = UploadTarget::Ios;
         let app_file_path = "MyApp.ipa";
         let username = "developer@example.com";
         let password = "app-specific-password";
 
-        let result = make_xcrun_command(&target, app_file_path, username, password);
+        let result = make_xcrun_invocation(&target, app_file_path, username, password).to_string();
 
         assert_eq!(
             result,
-            "xcrun altool --upload-app -t ios -f MyApp.ipa -u developer@example.com -p app-specific-password"
+            "xcrun altool --upload-app -t ios -f MyApp.ipa -u developer@example.com -p '********'"
         );
     }
 
     #[test]
-    fn test_make_xcrun_command_with_macos_target() {
+    fn test_make_xcrun_invocation_with_macos_target() {
         let target = UploadTarget::Macos;
         let app_file_path = "MyMacApp.pkg";
         let username = "mac.developer@example.com";
         let password = "secure-password-123";
 
-        let result = make_xcrun_command(&target, app_file_path, username, password);
+        let result = make_xcrun_invocation(&target, app_file_path, username, password).to_string();
 
         assert_eq!(
             result,
-            "xcrun altool --upload-app -t macos -f MyMacApp.pkg -u mac.developer@example.com -p secure-password-123"
+            "xcrun altool --upload-app -t macos -f MyMacApp.pkg -u mac.developer@example.com -p '********'"
         );
     }
 
     #[test]
-    fn test_make_xcrun_command_with_special_characters() {
+    fn test_make_xcrun_invocation_with_special_characters() {
         let target = UploadTarget::Ios;
         let app_file_path = "/path/to/My App With Spaces.ipa";
         let username = "test@company.co.uk";
         let password = "p@ssw0rd!";
 
-        let result = make_xcrun_command(&target, app_file_path, username, password);
+        let result = make_xcrun_invocation(&target, app_file_path, username, password).to_string();
 
         assert_eq!(
             result,
-            "xcrun altool --upload-app -t ios -f /path/to/My App With Spaces.ipa -u test@company.co.uk -p p@ssw0rd!"
+            "xcrun altool --upload-app -t ios -f '/path/to/My App With Spaces.ipa' -u test@company.co.uk -p '********'"
+        );
+    }
+
+    #[test]
+    fn test_make_xcrun_invocation_keeps_arguments_separate() {
+        let invocation = make_xcrun_invocation(
+            &UploadTarget::Ios,
+            "/path/to/My App With Spaces.ipa",
+            "test@company.co.uk",
+            "pa$$word!",
+        );
+
+        assert_eq!(invocation.program(), "xcrun");
+        assert_eq!(
+            invocation.args(),
+            [
+                "altool",
+                "--upload-app",
+                "-t",
+                "ios",
+                "-f",
+                "/path/to/My App With Spaces.ipa",
+                "-u",
+                "test@company.co.uk",
+                "-p",
+                "pa$$word!",
+            ]
         );
     }
 
@@ -155,7 +232,7 @@ mod tests {
             }
             Err(error) => {
                 // Error should be anyhow::Error and contain some meaningful message
-                assert!(error.to_string().len() > 0);
+                assert!(!error.to_string().is_empty());
             }
         }
     }